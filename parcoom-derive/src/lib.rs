@@ -0,0 +1,290 @@
+//! `#[derive(Parse)]`, re-exported by `parcoom` with the `derive` feature.
+//!
+//! The input is read token by token, so that the crate doesn't need any
+//! dependencies, and the impl is written out as source text.
+
+use proc_macro::{Delimiter, TokenStream, TokenTree};
+
+/// Implements `parcoom::Parsable` for a struct or enum.
+///
+/// A struct parses as its fields in order, an enum as the first of its
+/// variants that matches. Fields parse with the parser of their type. A
+/// unit variant matches its name unless it has a `prefix`.
+///
+/// `#[parcoom(...)]` on the type, a variant or a field takes:
+/// - `prefix = "lit"`, a literal before it,
+/// - `suffix = "lit"`, a literal after it,
+/// - `padded`, skipping the junk around it and its literals, or around the
+///   items and separators of a `sep` field,
+/// - `sep = "lit"`, only on `Vec` fields, a separator between the items.
+#[proc_macro_derive(Parse, attributes(parcoom))]
+pub fn derive_parse(input: TokenStream) -> TokenStream {
+    let code = match derive(input) {
+        Ok(code) => code,
+        Err(e) => format!("::core::compile_error!({e:?});"),
+    };
+    code.parse().unwrap()
+}
+
+#[derive(Default)]
+struct Options {
+    prefix: Option<String>,
+    suffix: Option<String>,
+    padded: bool,
+    sep: Option<String>,
+}
+
+struct Field {
+    name: Option<String>,
+    ty: String,
+    options: Options,
+}
+
+enum Fields {
+    Named(Vec<Field>),
+    Unnamed(Vec<Field>),
+    Unit,
+}
+
+fn derive(input: TokenStream) -> Result<String, String> {
+    let mut tokens = input.into_iter().peekable();
+    let options = attributes(&mut tokens)?;
+    visibility(&mut tokens);
+    let kind = ident(tokens.next())?;
+    let name = ident(tokens.next())?;
+    if matches!(tokens.peek(), Some(TokenTree::Punct(p)) if p.as_char() == '<') {
+        return Err("#[derive(Parse)] does not support generics".to_string());
+    }
+    let body = match kind.as_str() {
+        "struct" => {
+            let fields = fields(tokens.next())?;
+            let sequence = sequence("Self", &fields)?;
+            wrap(sequence, &options)
+        }
+        "enum" => {
+            let variants = match tokens.next() {
+                Some(TokenTree::Group(g)) if g.delimiter() == Delimiter::Brace => g.stream(),
+                _ => return Err("expected the variants of the enum".to_string()),
+            };
+            let alternatives = variants_of(variants)?
+                .into_iter()
+                .map(|(variant, mut options, fields)| {
+                    if let (Fields::Unit, None) = (&fields, &options.prefix) {
+                        options.prefix = Some(format!("{variant:?}"));
+                    }
+                    let sequence = sequence(&format!("Self::{variant}"), &fields)?;
+                    Ok(wrap(sequence, &options))
+                })
+                .collect::<Result<Vec<_>, String>>()?;
+            if alternatives.is_empty() {
+                return Err("#[derive(Parse)] needs an enum with variants".to_string());
+            }
+            wrap(alternatives.join(" | "), &options)
+        }
+        _ => return Err("#[derive(Parse)] only supports structs and enums".to_string()),
+    };
+    Ok(format!(
+        "impl ::parcoom::Parsable for {name} {{
+            fn parser<'a>() -> ::parcoom::Parser<'a, Self> {{
+                {body}
+            }}
+        }}"
+    ))
+}
+
+/// Skips the attributes at the start of `tokens`, returning the options of
+/// the `parcoom` ones.
+fn attributes(
+    tokens: &mut core::iter::Peekable<impl Iterator<Item = TokenTree>>,
+) -> Result<Options, String> {
+    let mut options = Options::default();
+    while matches!(tokens.peek(), Some(TokenTree::Punct(p)) if p.as_char() == '#') {
+        tokens.next();
+        let attribute = match tokens.next() {
+            Some(TokenTree::Group(g)) if g.delimiter() == Delimiter::Bracket => g.stream(),
+            _ => return Err("expected an attribute".to_string()),
+        };
+        let mut attribute = attribute.into_iter();
+        match (attribute.next(), attribute.next()) {
+            (Some(TokenTree::Ident(i)), Some(TokenTree::Group(g)))
+                if i.to_string() == "parcoom" =>
+            {
+                parse_options(g.stream(), &mut options)?
+            }
+            _ => {}
+        }
+    }
+    Ok(options)
+}
+
+fn parse_options(stream: TokenStream, options: &mut Options) -> Result<(), String> {
+    let tokens: Vec<TokenTree> = stream.into_iter().collect();
+    for option in tokens.split(|t| matches!(t, TokenTree::Punct(p) if p.as_char() == ',')) {
+        match option {
+            [] => {}
+            [TokenTree::Ident(key)] if key.to_string() == "padded" => options.padded = true,
+            [TokenTree::Ident(key), TokenTree::Punct(eq), TokenTree::Literal(value)]
+                if eq.as_char() == '=' =>
+            {
+                let value = Some(value.to_string());
+                match key.to_string().as_str() {
+                    "prefix" => options.prefix = value,
+                    "suffix" => options.suffix = value,
+                    "sep" => options.sep = value,
+                    key => return Err(format!("unknown parcoom option {key}")),
+                }
+            }
+            _ => return Err("expected a parcoom option such as prefix = \"=\"".to_string()),
+        }
+    }
+    Ok(())
+}
+
+fn visibility(tokens: &mut core::iter::Peekable<impl Iterator<Item = TokenTree>>) {
+    if matches!(tokens.peek(), Some(TokenTree::Ident(i)) if i.to_string() == "pub") {
+        tokens.next();
+        if matches!(tokens.peek(), Some(TokenTree::Group(g)) if g.delimiter() == Delimiter::Parenthesis)
+        {
+            tokens.next();
+        }
+    }
+}
+
+fn ident(token: Option<TokenTree>) -> Result<String, String> {
+    match token {
+        Some(TokenTree::Ident(i)) => Ok(i.to_string()),
+        _ => Err("expected an identifier".to_string()),
+    }
+}
+
+fn fields(token: Option<TokenTree>) -> Result<Fields, String> {
+    let group = match token {
+        Some(TokenTree::Group(g)) => g,
+        _ => return Ok(Fields::Unit),
+    };
+    let named = match group.delimiter() {
+        Delimiter::Brace => true,
+        Delimiter::Parenthesis => false,
+        _ => return Err("expected fields".to_string()),
+    };
+    let mut fields = Vec::new();
+    let mut tokens = group.stream().into_iter().peekable();
+    while tokens.peek().is_some() {
+        let options = attributes(&mut tokens)?;
+        visibility(&mut tokens);
+        let name = match named {
+            true => {
+                let name = ident(tokens.next())?;
+                tokens.next();
+                Some(name)
+            }
+            false => None,
+        };
+        // The type goes on until a comma outside of angle brackets.
+        let mut ty = Vec::new();
+        let mut depth = 0;
+        for token in tokens.by_ref() {
+            match &token {
+                TokenTree::Punct(p) if p.as_char() == ',' && depth == 0 => break,
+                TokenTree::Punct(p) if p.as_char() == '<' => depth += 1,
+                TokenTree::Punct(p) if p.as_char() == '>' => depth -= 1,
+                _ => {}
+            }
+            ty.push(token);
+        }
+        let ty = ty.into_iter().collect::<TokenStream>().to_string();
+        fields.push(Field { name, ty, options });
+    }
+    Ok(match named {
+        true => Fields::Named(fields),
+        false => Fields::Unnamed(fields),
+    })
+}
+
+fn variants_of(stream: TokenStream) -> Result<Vec<(String, Options, Fields)>, String> {
+    let mut variants = Vec::new();
+    let mut tokens = stream.into_iter().peekable();
+    while tokens.peek().is_some() {
+        let options = attributes(&mut tokens)?;
+        let name = ident(tokens.next())?;
+        let fields = match tokens.peek() {
+            Some(TokenTree::Group(_)) => fields(tokens.next())?,
+            _ => Fields::Unit,
+        };
+        // Skips the discriminant, if any, and the comma.
+        for token in tokens.by_ref() {
+            if matches!(&token, TokenTree::Punct(p) if p.as_char() == ',') {
+                break;
+            }
+        }
+        variants.push((name, options, fields));
+    }
+    Ok(variants)
+}
+
+/// The fields one after the other, building `constructor` from them.
+fn sequence(constructor: &str, fields: &Fields) -> Result<String, String> {
+    let fields = match fields {
+        Fields::Unit => return Ok(format!("::parcoom::wrap(()).map(|_| {constructor})")),
+        Fields::Named(fields) | Fields::Unnamed(fields) => fields,
+    };
+    if fields.is_empty() {
+        return Ok(format!("::parcoom::wrap(()).map(|_| {constructor} {{}})"));
+    }
+    let mut parsers = Vec::new();
+    for field in fields {
+        let parser = match &field.options.sep {
+            Some(sep) => format!(
+                "<{} as ::parcoom::__private::Separated>::sep_by({sep}, {})",
+                field.ty, field.options.padded
+            ),
+            None => format!(
+                "::parcoom::__private::lazy(<{} as ::parcoom::Parsable>::parser)",
+                field.ty
+            ),
+        };
+        parsers.push(wrap(parser, &field.options));
+    }
+    let mut pattern = "__f0".to_string();
+    for i in 1..fields.len() {
+        pattern = format!("({pattern}, __f{i})");
+    }
+    let values = fields
+        .iter()
+        .enumerate()
+        .map(|(i, field)| match &field.name {
+            Some(name) => format!("{name}: __f{i}"),
+            None => format!("__f{i}"),
+        });
+    let values = values.collect::<Vec<_>>().join(", ");
+    let value = match fields[0].name {
+        Some(_) => format!("{constructor} {{ {values} }}"),
+        None => format!("{constructor}({values})"),
+    };
+    Ok(format!(
+        "({}).map(|{pattern}| {value})",
+        parsers.join(" + ")
+    ))
+}
+
+/// `parser` with the literals and padding of `options` around it.
+fn wrap(parser: String, options: &Options) -> String {
+    let pad = |p: String| match options.padded {
+        true => format!("::parcoom::padded({p})"),
+        false => p,
+    };
+    let mut parser = pad(format!("({parser})"));
+    if let Some(prefix) = &options.prefix {
+        parser = format!(
+            "({} >> {parser})",
+            pad(format!("::parcoom::prefix({prefix})"))
+        );
+    }
+    if let Some(suffix) = &options.suffix {
+        parser = format!(
+            "({parser} << {})",
+            pad(format!("::parcoom::prefix({suffix})"))
+        );
+    }
+    parser
+}
@@ -0,0 +1,112 @@
+//! Parsing into an arena, such as a `bumpalo::Bump`, instead of allocating
+//! each node and list of a syntax tree on its own.
+//!
+//! Nodes then borrow from the arena rather than owning their children, as
+//! in `enum Expr<'a> { Num(u32), List(&'a [Expr<'a>]) }`, and are all
+//! freed at once along with it. What alternatives that were backtracked out
+//! of allocated stays in the arena until then.
+
+use alloc::vec::Vec;
+
+use crate::{MaybeSync, Parser};
+
+/// What values are allocated in. The crate doesn't depend on any arena
+/// crate; a newtype around one implements this by forwarding to it, e.g.
+/// to `Bump::alloc` and `Bump::alloc_slice_fill_iter` for `bumpalo`.
+pub trait Arena {
+    /// Moves `value` into the arena.
+    fn alloc<T>(&self, value: T) -> &T;
+
+    /// Moves the items of `items` into the arena, in order.
+    fn alloc_slice<T>(&self, items: impl ExactSizeIterator<Item = T>) -> &[T];
+}
+
+impl<'a, T: 'a> Parser<'a, T> {
+    /// Moves the value into `arena`.
+    pub fn alloc_in<A: Arena + MaybeSync>(self, arena: &'a A) -> Parser<'a, &'a T> {
+        self.map(move |x| arena.alloc(x))
+    }
+
+    /// Like [`many`](Parser::many), but the items end up in `arena`. They
+    /// are gathered in a `Vec` first, which is freed right after.
+    pub fn many_in<A: Arena + MaybeSync>(self, arena: &'a A) -> Parser<'a, &'a [T]> {
+        self.many().map(move |xs| arena.alloc_slice(xs.into_iter()))
+    }
+
+    /// Like [`many1`](Parser::many1), but the items end up in `arena`.
+    pub fn many1_in<A: Arena + MaybeSync>(self, arena: &'a A) -> Parser<'a, &'a [T]> {
+        self.many1()
+            .map(move |xs| arena.alloc_slice(xs.into_iter()))
+    }
+
+    /// Like [`sep_by`](Parser::sep_by), but the items end up in `arena`.
+    pub fn sep_by_in<S: 'a, A: Arena + MaybeSync>(
+        self,
+        sep: Parser<'a, S>,
+        arena: &'a A,
+    ) -> Parser<'a, &'a [T]> {
+        self.sep_by(sep)
+            .map(move |xs: Vec<T>| arena.alloc_slice(xs.into_iter()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chars::digits1;
+    use crate::{prefix, recursive, run};
+    use alloc::boxed::Box;
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Never frees, which is enough for tests, and counts its allocations.
+    #[derive(Default)]
+    struct Leaking(AtomicUsize);
+
+    impl Arena for Leaking {
+        fn alloc<T>(&self, value: T) -> &T {
+            self.0.fetch_add(1, Ordering::Relaxed);
+            Box::leak(Box::new(value))
+        }
+
+        fn alloc_slice<T>(&self, items: impl ExactSizeIterator<Item = T>) -> &[T] {
+            self.0.fetch_add(1, Ordering::Relaxed);
+            Box::leak(items.collect())
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum Expr<'a> {
+        Num(u32),
+        List(&'a [Expr<'a>]),
+        Neg(&'a Expr<'a>),
+    }
+
+    #[test]
+    fn arena_test() {
+        let arena = Leaking::default();
+        let expr = recursive(|expr| {
+            let num = digits1().map(|n| Expr::Num(n.parse().unwrap()));
+            let list = expr.clone().sep_by_in(prefix(" "), &arena);
+            let list = list.delimited_by(prefix("("), prefix(")")).map(Expr::List);
+            let neg = (prefix("-") >> expr.alloc_in(&arena)).map(Expr::Neg);
+            num | list | neg
+        });
+        let parsed = run(expr, "(1 -(2 3) ())".to_string());
+        let inner = [Expr::Num(2), Expr::Num(3)];
+        let items = [
+            Expr::Num(1),
+            Expr::Neg(&Expr::List(&inner)),
+            Expr::List(&[]),
+        ];
+        assert_eq!(parsed, Ok(Expr::List(&items)));
+        // three lists and a negation
+        assert_eq!(arena.0.load(Ordering::Relaxed), 4);
+
+        let digits = prefix("1").many1_in(&arena) + prefix("2").many_in(&arena);
+        assert_eq!(
+            run(digits.clone(), "113".to_string()),
+            Ok((&["1"; 2][..], &[][..]))
+        );
+        assert!(run(digits, "2".to_string()).is_err());
+    }
+}
@@ -0,0 +1,189 @@
+//! The raw text between matching, possibly nested delimiters.
+//!
+//! ```
+//! use parcoom::balanced::{balanced, Balanced};
+//! use parcoom::{prefix, run};
+//!
+//! let call = prefix("f") >> balanced('(', ')');
+//! assert_eq!(run(call, "f(a, (b))".to_string()), Ok("a, (b)".to_string()));
+//!
+//! let block = Balanced::new('{', '}').escape('\\').quote('"').build();
+//! let parsed = run(block, r#"{ x = "}"; \{ }"#.to_string());
+//! assert_eq!(parsed, Ok(r#" x = "}"; \{ "#.to_string()));
+//! ```
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::limits::counted;
+use crate::{input_sub, Failure, Needed, Parser, Ptr};
+
+/// Builds a parser of text from an opening delimiter to the closing one
+/// that matches it, yielding the text in between as it is. Delimiters in
+/// between nest, unless escaped or quoted as configured.
+#[derive(Debug, Clone)]
+pub struct Balanced {
+    open: char,
+    close: char,
+    escape: Option<char>,
+    quotes: Vec<char>,
+}
+
+impl Balanced {
+    pub fn new(open: char, close: char) -> Self {
+        Balanced {
+            open,
+            close,
+            escape: None,
+            quotes: Vec::new(),
+        }
+    }
+
+    /// The char after `escape` is taken as it is, whether a delimiter, a
+    /// quote or `escape` itself.
+    pub fn escape(mut self, escape: char) -> Self {
+        self.escape = Some(escape);
+        self
+    }
+
+    /// Delimiters between two `quote`s, as in string literals, don't count.
+    pub fn quote(mut self, quote: char) -> Self {
+        self.quotes.push(quote);
+        self
+    }
+
+    /// The length of the text after the opening delimiter up to the closing
+    /// one, or how the scan failed: `None` when `text` ran out, the quote
+    /// otherwise left open.
+    fn scan(&self, text: &str) -> Result<usize, Option<char>> {
+        let mut depth = 0;
+        let mut quote = None;
+        let mut chars = text.char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                c if Some(c) == self.escape => {
+                    chars.next();
+                }
+                c if quote == Some(c) => quote = None,
+                _ if quote.is_some() => {}
+                c if c == self.close && depth == 0 => return Ok(i),
+                c if c == self.close => depth -= 1,
+                c if c == self.open => depth += 1,
+                c if self.quotes.contains(&c) => quote = Some(c),
+                _ => {}
+            }
+        }
+        Err(quote)
+    }
+
+    /// Once the opening delimiter matched, failures are not backtracked out
+    /// of, see [`cut`](crate::cut).
+    pub fn build<'a>(self) -> Parser<'a, String> {
+        counted(Parser {
+            run: Ptr::new(move |input| {
+                if !input.text().starts_with(self.open) {
+                    if input.text().is_empty() && input.partial {
+                        return (input, Err(Failure::incomplete(Needed::Size(1))));
+                    }
+                    let expected = format!("{:?}", self.open.to_string());
+                    let desc = format!("expected {}", self.open);
+                    return (input, Err(Failure::expecting(expected, desc)));
+                }
+                let at = self.open.len_utf8();
+                let text = &input.text()[at..];
+                let quote = match self.scan(text) {
+                    Ok(end) => {
+                        let inner = text[..end].to_string();
+                        return (
+                            input_sub(at + end + self.close.len_utf8(), &input),
+                            Ok(inner),
+                        );
+                    }
+                    Err(_) if input.partial => {
+                        return (input, Err(Failure::incomplete(Needed::Unknown)))
+                    }
+                    Err(quote) => quote,
+                };
+                let (closing, desc) = match quote {
+                    Some(quote) => (
+                        quote,
+                        format!("unterminated quote, expected closing {quote}"),
+                    ),
+                    None => (
+                        self.close,
+                        format!("missing closing delimiter: expected {}", self.close),
+                    ),
+                };
+                let e = Failure::unterminated(format!("{:?}", closing.to_string()), desc);
+                (input, Err(e))
+            }),
+            shape: None,
+        })
+    }
+}
+
+/// The text between `open` and the `close` matching it, such as `a, (b)` of
+/// `(a, (b))`. See [`Balanced`] for escapes and quotes.
+pub fn balanced<'a>(open: char, close: char) -> Parser<'a, String> {
+    Balanced::new(open, close).build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{prefix, run, run_streaming, StreamResult};
+
+    #[test]
+    fn balanced_test() {
+        let parser = balanced('[', ']') + balanced('[', ']');
+        let parsed = run(parser, "[[a]][]".to_string());
+        assert_eq!(parsed, Ok(("[a]".to_string(), String::new())));
+        let parsed = run(balanced('«', '»') << prefix("!"), "«a«b»»!".to_string());
+        assert_eq!(parsed, Ok("a«b»".to_string()));
+        let parsed = run(balanced('|', '|'), "|a|b|".to_string());
+        assert_eq!(parsed, Ok("a".to_string()));
+
+        let e = run(balanced('(', ')'), "x".to_string()).unwrap_err();
+        assert_eq!(e.expected, ["\"(\"".to_string()]);
+        // committed once opened
+        let e = run(
+            balanced('(', ')') | prefix("(").map(str::to_string),
+            "(()".to_string(),
+        );
+        let e = e.unwrap_err();
+        assert_eq!(e.desc, "missing closing delimiter: expected )");
+        assert_eq!(e.location.offset, 0);
+    }
+
+    #[test]
+    fn balanced_quotes_test() {
+        let parser = || {
+            Balanced::new('{', '}')
+                .escape('\\')
+                .quote('"')
+                .quote('\'')
+                .build()
+        };
+        let parsed = run(parser(), r#"{'"' "\"}" {}}"#.to_string());
+        assert_eq!(parsed, Ok(r#"'"' "\"}" {}"#.to_string()));
+        let parsed = run(parser(), r"{\}}".to_string());
+        assert_eq!(parsed, Ok(r"\}".to_string()));
+        let e = run(parser(), "{\"}".to_string()).unwrap_err();
+        assert_eq!(e.desc, "unterminated quote, expected closing \"");
+
+        // unless configured, quotes are text like any other
+        let parsed = run(balanced('{', '}'), "{\"}\"}".to_string());
+        assert_eq!(parsed, Ok("\"".to_string()));
+    }
+
+    #[test]
+    fn balanced_streaming_test() {
+        let streamed = run_streaming(balanced('(', ')'), "(a(b)".to_string());
+        assert_eq!(streamed, StreamResult::Incomplete(Needed::Unknown));
+        let streamed = run_streaming(balanced('(', ')'), "".to_string());
+        assert_eq!(streamed, StreamResult::Incomplete(Needed::Size(1)));
+        let streamed = run_streaming(balanced('(', ')'), "(a(b))c".to_string());
+        assert_eq!(streamed, StreamResult::Ok("a(b)".to_string(), 6));
+    }
+}
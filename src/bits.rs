@@ -0,0 +1,126 @@
+//! Parsers for packed bit fields, such as the header of an MP3 frame.
+//!
+//! Bits are read most significant first. Run them inside [`bits`], which
+//! skips the rest of a partly read byte afterwards so byte and text parsers
+//! can carry on.
+
+use alloc::format;
+
+use crate::{Failure, Needed, Parser, ParserInput, Ptr};
+
+/// Runs `p`, then moves on to the next whole byte.
+pub fn bits<'a, A: 'a>(p: Parser<'a, A>) -> Parser<'a, A> {
+    Parser {
+        run: Ptr::new(move |input| {
+            let (input_, result) = (p.run)(input);
+            match result {
+                Ok(x) if input_.bit != 0 => (advance(&input_, 8 - input_.bit as usize), Ok(x)),
+                result => (input_, result),
+            }
+        }),
+        shape: None,
+    }
+}
+
+fn advance(input: &ParserInput, n: usize) -> ParserInput {
+    let bits = input.bit as usize + n;
+    ParserInput {
+        pos: input.pos + bits / 8,
+        bit: (bits % 8) as u8,
+        ..input.clone()
+    }
+}
+
+/// The next `n` bits of the input, or how many more bytes that takes.
+fn read(input: &ParserInput, n: usize) -> Result<u64, usize> {
+    let bytes = input.bytes();
+    let start = input.bit as usize;
+    if start + n > bytes.len() * 8 {
+        return Err((start + n).div_ceil(8) - bytes.len());
+    }
+    let value = (start..start + n).fold(0, |value, i| {
+        let bit = bytes[i / 8] >> (7 - i % 8) & 1;
+        value << 1 | u64::from(bit)
+    });
+    Ok(value)
+}
+
+/// The next `n` bits as a number, `n` being at most 64.
+pub fn take_bits<'a>(n: usize) -> Parser<'a, u64> {
+    assert!(n <= 64, "take_bits: {n} bits do not fit into a u64");
+    Parser {
+        run: Ptr::new(move |input| match read(&input, n) {
+            Ok(value) => (advance(&input, n), Ok(value)),
+            Err(needed) if input.partial => (input, Err(Failure::incomplete(Needed::Size(needed)))),
+            Err(_) => {
+                let desc = format!("expected {n} bits, got end of input");
+                (input, Err(Failure::expecting(format!("{n} bits"), desc)))
+            }
+        }),
+        shape: None,
+    }
+}
+
+/// The next `n` bits, which must be `pattern`, e.g. the sync word of a frame.
+pub fn tag_bits<'a>(pattern: u64, n: usize) -> Parser<'a, u64> {
+    let expected = format!("{pattern:#0width$b}", width = n + 2);
+    Parser {
+        run: Ptr::new(move |input| {
+            let (input_, result) = (take_bits(n).run)(input.clone());
+            match result {
+                Ok(value) if value == pattern => (input_, Ok(value)),
+                Ok(value) => {
+                    let desc = format!("expected {expected}, got {value:#0width$b}", width = n + 2);
+                    (input, Err(Failure::expecting(expected.clone(), desc)))
+                }
+                Err(e) => (input_, Err(e)),
+            }
+        }),
+        shape: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bytes::u8;
+    use crate::{run_bytes, Location, ParserError};
+
+    #[test]
+    fn header_test() {
+        // sync word, version, layer, protection bit, then a whole byte
+        let header = tag_bits(0x7ff, 11) >> take_bits(2).then(take_bits(2)).then(take_bits(1));
+        let parser = bits(header).then(u8());
+        let parsed = run_bytes(parser, vec![0xff, 0xfb, 0x90, 0x42]);
+        assert_eq!(parsed, Ok((((3, 1), 1), 0x90)));
+    }
+
+    #[test]
+    fn wide_field_test() {
+        let parser = bits(take_bits(4) >> take_bits(12));
+        assert_eq!(run_bytes(parser, vec![0xab, 0xcd]), Ok(0xbcd));
+        let parser = bits(take_bits(3)).then(u8());
+        assert_eq!(run_bytes(parser, vec![0xff, 0x01]), Ok((7, 1)));
+    }
+
+    #[test]
+    fn tag_bits_test() {
+        let parsed = run_bytes(bits(tag_bits(0b101, 3)), vec![0b1000_0000]);
+        assert_eq!(
+            parsed,
+            Err(ParserError {
+                desc: "expected 0b101, got 0b100".to_string(),
+                location: Location {
+                    offset: 0,
+                    char_offset: 0,
+                    line: 1,
+                    column: 1,
+                },
+                context: vec![],
+                expected: vec!["0b101".to_string()],
+                cause: None,
+                at_end: false,
+            })
+        );
+    }
+}
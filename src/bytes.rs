@@ -0,0 +1,411 @@
+//! Parsers for binary data, run with [`run_bytes`].
+//!
+//! Integers and floats come in big-endian (`be_*`) and little-endian (`le_*`)
+//! flavors. They also work on text sources, where they read its UTF-8 bytes.
+
+use alloc::format;
+use alloc::string::ToString;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+use crate::limits::collect;
+use crate::streaming::stream;
+use crate::{
+    finish, input_from, input_sub, Failure, Needed, Parser, ParserError, ParserInput, Source,
+};
+use crate::{Parse, Ptr, StreamResult};
+
+/// Runs `p` on binary `input`, like [`run`](crate::run) does on text.
+pub fn run_bytes<A>(p: impl Parse<A>, input: Vec<u8>) -> Result<A, ParserError> {
+    finish(p.parse(input_from(Source::Bytes(Arc::from(input)))))
+}
+
+/// Runs `p` on binary `input` as the start of a stream that may have more
+/// data to come, like [`run_streaming`](crate::run_streaming) does on text.
+pub fn run_bytes_streaming<A>(p: Parser<A>, input: Vec<u8>) -> StreamResult<A> {
+    stream(&p, input_from(Source::Bytes(Arc::from(input))))
+}
+
+/// Exactly `n` bytes.
+pub fn take<'a>(n: usize) -> Parser<'a, Vec<u8>> {
+    Parser {
+        run: Ptr::new(move |input| {
+            let rest = input.bytes();
+            if rest.len() >= n {
+                let taken = rest[..n].to_vec();
+                (input_sub(n, &input), Ok(taken))
+            } else if input.partial {
+                let needed = Needed::Size(n - rest.len());
+                (input, Err(Failure::incomplete(needed)))
+            } else {
+                let desc = format!("expected {n} bytes, got {}", rest.len());
+                (input, Err(Failure::expecting(format!("{n} bytes"), desc)))
+            }
+        }),
+        shape: None,
+    }
+}
+
+/// The bytes of `tag`, e.g. a magic number like `b"\x89PNG"`.
+pub fn tag<'a>(tag: &'static [u8]) -> Parser<'a, &'static [u8]> {
+    Parser {
+        run: Ptr::new(move |input| {
+            let rest = input.bytes();
+            if rest.starts_with(tag) {
+                (input_sub(tag.len(), &input), Ok(tag))
+            } else if input.partial && tag.starts_with(rest) {
+                let needed = Needed::Size(tag.len() - rest.len());
+                (input, Err(Failure::incomplete(needed)))
+            } else {
+                let desc = format!("expected {tag:x?}");
+                (input, Err(Failure::expecting(format!("{tag:x?}"), desc)))
+            }
+        }),
+        shape: None,
+    }
+}
+
+/// The length `len` yields, as a `usize`.
+fn length<'a, L: TryInto<usize> + 'a>(len: Parser<'a, L>) -> Parser<'a, usize> {
+    Parser {
+        run: Ptr::new(move |input| match (len.run)(input.clone()) {
+            (input_, Ok(n)) => match n.try_into() {
+                Ok(n) => (input_, Ok(n)),
+                Err(_) => (input, Err(Failure::from("length too large".to_string()))),
+            },
+            (input_, Err(e)) => (input_, Err(e)),
+        }),
+        shape: None,
+    }
+}
+
+/// A field of the length `len` yields in bytes, parsed by `value`, as in
+/// type-length-value formats. `value` only sees the bytes of the field, and
+/// those it leaves are skipped. Fails after the length if the input is
+/// shorter than that.
+pub fn length_value<'a, L: TryInto<usize> + 'a, A: 'a>(
+    len: Parser<'a, L>,
+    value: Parser<'a, A>,
+) -> Parser<'a, A> {
+    let len = length(len);
+    Parser {
+        run: Ptr::new(move |input| {
+            let (input, n) = match (len.run)(input) {
+                (input_, Ok(n)) => (input_, n),
+                (input_, Err(e)) => return (input_, Err(e)),
+            };
+            let available = input.bytes().len();
+            if available < n {
+                if input.partial {
+                    let needed = Needed::Size(n - available);
+                    return (input, Err(Failure::incomplete(needed)));
+                }
+                let desc = format!("expected a field of {n} bytes, got {available}");
+                return (input, Err(Failure::expecting(format!("{n} bytes"), desc)));
+            }
+            let (end, partial) = (input.end, input.partial);
+            let field = ParserInput {
+                end: Some(input.pos + n),
+                partial: false,
+                ..input
+            };
+            let start = field.pos;
+            let (input_, result) = (value.run)(field);
+            let input_ = ParserInput {
+                end,
+                partial,
+                ..input_
+            };
+            match result {
+                Ok(x) => {
+                    let skipped = start + n - input_.pos;
+                    (input_sub(skipped, &input_), Ok(x))
+                }
+                Err(e) => (input_, Err(e)),
+            }
+        }),
+        shape: None,
+    }
+}
+
+/// As many `item`s as `len` yields.
+pub fn length_count<'a, L: TryInto<usize> + 'a, A: 'a>(
+    len: Parser<'a, L>,
+    item: Parser<'a, A>,
+) -> Parser<'a, Vec<A>> {
+    let len = length(len);
+    Parser {
+        run: Ptr::new(move |input| {
+            let (mut input, n) = match (len.run)(input) {
+                (input_, Ok(n)) => (input_, n),
+                (input_, Err(e)) => return (input_, Err(e)),
+            };
+            // a count read from the input can be anything
+            let mut xs = Vec::with_capacity(n.min(input.bytes().len()));
+            for _ in 0..n {
+                match (item.run)(input.clone()) {
+                    (input_, Ok(x)) => {
+                        if let Err(e) = collect::<A>(&input, xs.len() + 1) {
+                            return (input, Err(e));
+                        }
+                        xs.push(x);
+                        input = input_;
+                    }
+                    (input_, Err(e)) => return (input_, Err(e)),
+                }
+            }
+            (input, Ok(xs))
+        }),
+        shape: None,
+    }
+}
+
+/// The magic numbers binary formats start with, for [`magic_number`].
+pub mod magic {
+    pub const PNG: &[u8] = b"\x89PNG\r\n\x1a\n";
+    pub const ELF: &[u8] = b"\x7fELF";
+    pub const GZIP: &[u8] = b"\x1f\x8b";
+    pub const ZIP: &[u8] = b"PK\x03\x04";
+    pub const PDF: &[u8] = b"%PDF-";
+    pub const WASM: &[u8] = b"\0asm";
+}
+
+/// The magic number `magic` a file in the format `format` starts with,
+/// such as [`magic::PNG`]. Unlike with [`tag`] the error names the format.
+pub fn magic_number<'a>(format: &'static str, magic: &'static [u8]) -> Parser<'a, ()> {
+    let p = tag(magic);
+    Parser {
+        run: Ptr::new(move |input| match (p.run)(input) {
+            (input_, Ok(_)) => (input_, Ok(())),
+            (input_, Err(e)) if e.needed.is_some() => (input_, Err(e)),
+            (input_, Err(_)) => {
+                let desc = format!("expected the {format} magic number {magic:x?}");
+                let expected = format!("the {format} magic number");
+                (input_, Err(Failure::expecting(expected, desc)))
+            }
+        }),
+        shape: None,
+    }
+}
+
+fn array<'a, const N: usize>() -> Parser<'a, [u8; N]> {
+    take(N).map(|bytes| {
+        let mut array = [0; N];
+        array.copy_from_slice(&bytes);
+        array
+    })
+}
+
+macro_rules! numbers {
+    ($($be:ident $le:ident $t:ty;)*) => {$(
+        #[doc = concat!("A big-endian `", stringify!($t), "`.")]
+        pub fn $be<'a>() -> Parser<'a, $t> {
+            array().map(<$t>::from_be_bytes)
+        }
+
+        #[doc = concat!("A little-endian `", stringify!($t), "`.")]
+        pub fn $le<'a>() -> Parser<'a, $t> {
+            array().map(<$t>::from_le_bytes)
+        }
+    )*};
+}
+
+numbers! {
+    be_u16 le_u16 u16;
+    be_u32 le_u32 u32;
+    be_u64 le_u64 u64;
+    be_i16 le_i16 i16;
+    be_i32 le_i32 i32;
+    be_i64 le_i64 i64;
+    be_f32 le_f32 f32;
+    be_f64 le_f64 f64;
+}
+
+pub fn u8<'a>() -> Parser<'a, u8> {
+    array().map(|[b]| b)
+}
+
+pub fn i8<'a>() -> Parser<'a, i8> {
+    array().map(i8::from_be_bytes)
+}
+
+/// The 7-bit groups of a LEB128 number, least significant first, along with
+/// the last byte. Fails on bits past the 64th, ten bytes are enough: the
+/// tenth only holds one bit, its others must be zero, or copies of that
+/// one if `signed`.
+fn leb128<'a>(signed: bool) -> Parser<'a, (u64, u32, u8)> {
+    Parser {
+        run: Ptr::new(move |input| {
+            let mut value = 0u64;
+            for (i, &b) in input.bytes().iter().enumerate() {
+                let shift = 7 * i as u32;
+                let fits = match shift {
+                    0..=56 => true,
+                    63 if signed => matches!(b & 0x7f, 0 | 0x7f),
+                    63 => b & 0x7e == 0,
+                    _ => false,
+                };
+                if !fits {
+                    let desc = "varint does not fit into 64 bits".to_string();
+                    return (input_sub(i, &input), Err(Failure::from(desc)));
+                }
+                value |= u64::from(b & 0x7f) << shift;
+                if b & 0x80 == 0 {
+                    return (input_sub(i + 1, &input), Ok((value, shift + 7, b)));
+                }
+            }
+            if input.partial {
+                (input, Err(Failure::incomplete(Needed::Unknown)))
+            } else {
+                let desc = "expected a varint, got end of input".to_string();
+                (input, Err(Failure::expecting("a varint".to_string(), desc)))
+            }
+        }),
+        shape: None,
+    }
+}
+
+/// An unsigned LEB128 number, as used by protobuf and WebAssembly.
+pub fn uleb128<'a>() -> Parser<'a, u64> {
+    leb128(false).map(|(value, _, _)| value)
+}
+
+/// A signed LEB128 number.
+pub fn sleb128<'a>() -> Parser<'a, i64> {
+    leb128(true).map(|(value, bits, last)| {
+        if bits < 64 && last & 0x40 != 0 {
+            (value | (!0 << bits)) as i64
+        } else {
+            value as i64
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{prefix, run, run_streaming, Location};
+
+    #[test]
+    fn numbers_test() {
+        let parser = be_u16().then(le_u32()).then(u8()).then(i8());
+        let parsed = run_bytes(parser, vec![0x01, 0x02, 0x04, 0x03, 0x02, 0x01, 7, 0xff]);
+        assert_eq!(parsed, Ok((((0x0102, 0x01020304), 7), -1)));
+
+        let parsed = run_bytes(be_f64(), 1.5f64.to_be_bytes().to_vec());
+        assert_eq!(parsed, Ok(1.5));
+        let parsed = run_bytes(le_i16(), vec![0xfe, 0xff]);
+        assert_eq!(parsed, Ok(-2));
+    }
+
+    #[test]
+    fn too_short_test() {
+        let parsed = run_bytes(tag(b"\x89PNG") >> be_u32(), vec![0x89, b'P', b'N', b'G', 0]);
+        assert_eq!(
+            parsed,
+            Err(ParserError {
+                desc: "expected 4 bytes, got 1".to_string(),
+                location: Location {
+                    offset: 4,
+                    char_offset: 4,
+                    line: 1,
+                    column: 5,
+                },
+                context: vec![],
+                expected: vec!["4 bytes".to_string()],
+                cause: None,
+                at_end: false,
+            })
+        );
+    }
+
+    #[test]
+    fn magic_number_test() {
+        let header = magic_number("ELF", magic::ELF) >> u8();
+        assert_eq!(run_bytes(header.clone(), b"\x7fELF\x02".to_vec()), Ok(2));
+        let e = run_bytes(header, b"\x7fELG\x02".to_vec()).unwrap_err();
+        assert_eq!(e.desc, "expected the ELF magic number [7f, 45, 4c, 46]");
+        assert_eq!(e.expected, ["the ELF magic number".to_string()]);
+
+        let png = tag(&[0x89, b'P', b'N', b'G']) >> tag(b"\r\n\x1a\n");
+        assert!(run_bytes(png, magic::PNG.to_vec()).is_ok());
+        let png = magic_number("PNG", magic::PNG);
+        assert_eq!(run_bytes(png, magic::PNG.to_vec()), Ok(()));
+    }
+
+    #[test]
+    fn length_value_test() {
+        // a type byte, a length byte and that many bytes of value
+        let tlv = u8() + length_value(u8(), be_u16().many());
+        let parsed = run_bytes(tlv.clone().many(), vec![1, 4, 0, 1, 0, 2, 2, 0, 3, 1, 0xff]);
+        assert_eq!(parsed, Ok(vec![(1, vec![1, 2]), (2, vec![]), (3, vec![])]));
+        // the value can't read past the field, what it leaves is skipped
+        let parsed = run_bytes(length_value(u8(), u8()) + u8(), vec![2, 7, 8, 9]);
+        assert_eq!(parsed, Ok((7, 9)));
+        let e = run_bytes(length_value(u8(), be_u32()), vec![2, 0, 0, 0, 0]).unwrap_err();
+        assert_eq!(
+            (e.desc.as_str(), e.location.offset),
+            ("expected 4 bytes, got 2", 1)
+        );
+
+        let e = run_bytes(tlv, vec![1, 9, 0, 1]).unwrap_err();
+        assert_eq!(e.desc, "expected a field of 9 bytes, got 2");
+        assert_eq!(e.location.offset, 2);
+        let streamed = run_streaming(length_value(u8(), u8()), "\x03a".to_string());
+        assert_eq!(streamed, StreamResult::Incomplete(Needed::Size(2)));
+        let streamed = run_bytes_streaming(length_value(u8(), u8()), vec![3, 0xff]);
+        assert_eq!(streamed, StreamResult::Incomplete(Needed::Size(2)));
+        let streamed = run_bytes_streaming(length_value(u8(), u8()), vec![1, 0xff, 0]);
+        assert_eq!(streamed, StreamResult::Ok(0xff, 2));
+        let e = run_bytes(length_value(be_u64(), u8()), vec![0xff; 9]).unwrap_err();
+        assert!(e.desc.starts_with("expected a field of"));
+    }
+
+    #[test]
+    fn length_count_test() {
+        let parser = length_count(u8(), be_u16());
+        assert_eq!(
+            run_bytes(parser.clone(), vec![2, 0, 1, 0, 2]),
+            Ok(vec![1, 2])
+        );
+        let e = run_bytes(parser, vec![200, 0, 1]).unwrap_err();
+        assert_eq!(
+            (e.desc.as_str(), e.location.offset),
+            ("expected 2 bytes, got 0", 3)
+        );
+    }
+
+    #[test]
+    fn varint_test() {
+        assert_eq!(run_bytes(uleb128(), vec![0xe5, 0x8e, 0x26]), Ok(624485));
+        assert_eq!(run_bytes(sleb128(), vec![0xc0, 0xbb, 0x78]), Ok(-123456));
+        assert_eq!(run_bytes(sleb128(), vec![0x3f]), Ok(63));
+        let min = vec![0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x7f];
+        assert_eq!(run_bytes(sleb128(), min), Ok(i64::MIN));
+
+        let max = vec![0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01];
+        assert_eq!(run_bytes(uleb128(), max), Ok(u64::MAX));
+        let parsed = run_bytes(uleb128(), vec![0x80; 11]);
+        assert_eq!(
+            parsed.map_err(|e| e.desc),
+            Err("varint does not fit into 64 bits".to_string())
+        );
+
+        // bits past the 64th in the tenth byte
+        let mut too_big = vec![0xff; 9];
+        too_big.push(0x7f);
+        let e = run_bytes(uleb128(), too_big.clone()).unwrap_err();
+        assert_eq!(e.desc, "varint does not fit into 64 bits");
+        assert_eq!(e.location.offset, 9);
+        assert_eq!(run_bytes(sleb128(), too_big), Ok(-1));
+        let mut too_small = vec![0x80; 9];
+        too_small.push(0x3f);
+        assert!(run_bytes(sleb128(), too_small).is_err());
+    }
+
+    #[test]
+    fn text_source_test() {
+        let parser = prefix("ab") >> be_u16();
+        assert_eq!(run(parser, "abcd".to_string()), Ok(0x6364));
+    }
+}
@@ -0,0 +1,482 @@
+//! Ready-made character-class parsers.
+//!
+//! Classes are Unicode-aware and follow the `char::is_*` predicates of the
+//! standard library, except for the digits: `digit`, `digits1` and
+//! `hex_digit` only accept ASCII, `numeric` is the Unicode class.
+
+use alloc::format;
+use alloc::string::ToString;
+
+use crate::shape::Shape;
+use crate::{eof, input_sub, prefix, satisfy_or, Failure, MaybeSync, Needed, Parser, Ptr, Substr};
+
+/// What [`take_while`] and the other char-class parsers accept: a closure,
+/// or one of the [`CharClass`] constants, which skip a run of ASCII chars
+/// much faster than calling a closure on each char.
+pub trait CharPredicate: MaybeSync {
+    fn matches(&self, c: char) -> bool;
+
+    /// The length in bytes of the run of matching chars `text` starts with.
+    fn span(&self, text: &str) -> usize {
+        text.char_indices()
+            .find(|&(_, c)| !self.matches(c))
+            .map_or(text.len(), |(i, _)| i)
+    }
+}
+
+impl<F: Fn(char) -> bool + MaybeSync> CharPredicate for F {
+    fn matches(&self, c: char) -> bool {
+        self(c)
+    }
+}
+
+/// A class of chars with a fast path, see [`CharPredicate`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CharClass(Class);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Class {
+    Whitespace,
+    Space,
+    Numeric,
+    AsciiDigit,
+    HexDigit,
+    Alphabetic,
+    Alphanumeric,
+    Except(char),
+}
+
+/// `char::is_whitespace`.
+pub const WHITESPACE: CharClass = CharClass(Class::Whitespace);
+/// Whitespace but line breaks.
+pub const SPACE: CharClass = CharClass(Class::Space);
+/// `char::is_numeric`.
+pub const NUMERIC: CharClass = CharClass(Class::Numeric);
+/// `0` to `9`.
+pub const ASCII_DIGIT: CharClass = CharClass(Class::AsciiDigit);
+/// `char::is_ascii_hexdigit`.
+pub const HEX_DIGIT: CharClass = CharClass(Class::HexDigit);
+/// `char::is_alphabetic`.
+pub const ALPHABETIC: CharClass = CharClass(Class::Alphabetic);
+/// `char::is_alphanumeric`.
+pub const ALPHANUMERIC: CharClass = CharClass(Class::Alphanumeric);
+
+impl CharClass {
+    /// Every char but `c`, for skipping to the next `c`.
+    pub const fn except(c: char) -> CharClass {
+        CharClass(Class::Except(c))
+    }
+}
+
+impl CharPredicate for CharClass {
+    fn matches(&self, c: char) -> bool {
+        match self.0 {
+            Class::Whitespace => c.is_whitespace(),
+            Class::Space => is_space(c),
+            Class::Numeric => c.is_numeric(),
+            Class::AsciiDigit => c.is_ascii_digit(),
+            Class::HexDigit => c.is_ascii_hexdigit(),
+            Class::Alphabetic => c.is_alphabetic(),
+            Class::Alphanumeric => c.is_alphanumeric(),
+            Class::Except(x) => c != x,
+        }
+    }
+
+    fn span(&self, text: &str) -> usize {
+        let bytes = text.as_bytes();
+        let mut i = match self.0 {
+            Class::Except(c) if c.is_ascii() => {
+                return find_byte(bytes, c as u8).unwrap_or(text.len())
+            }
+            Class::Numeric | Class::AsciiDigit => ascii_digits(bytes),
+            _ => 0,
+        };
+        // Only decodes the chars that are not ASCII.
+        while let Some(&b) = bytes.get(i) {
+            let len = match b.is_ascii() {
+                true if self.matches(b as char) => 1,
+                true => break,
+                false => match text[i..].chars().next() {
+                    Some(c) if self.matches(c) => c.len_utf8(),
+                    _ => break,
+                },
+            };
+            i += len;
+        }
+        i
+    }
+}
+
+const ONES: u64 = u64::from_ne_bytes([0x01; 8]);
+const HIGHS: u64 = u64::from_ne_bytes([0x80; 8]);
+
+/// The index of the first `b` in `bytes`, comparing 8 bytes at a time.
+fn find_byte(bytes: &[u8], b: u8) -> Option<usize> {
+    let mut chunks = bytes.chunks_exact(8);
+    let mut i = 0;
+    for chunk in chunks.by_ref() {
+        // A byte of `x` is 0 where the chunk has `b`.
+        let x = u64::from_ne_bytes(chunk.try_into().unwrap()) ^ (ONES * b as u64);
+        if x.wrapping_sub(ONES) & !x & HIGHS != 0 {
+            break;
+        }
+        i += 8;
+    }
+    bytes[i..].iter().position(|&x| x == b).map(|j| i + j)
+}
+
+/// The length of the run of 8 ASCII digits at a time `bytes` starts with,
+/// leaving the rest of the run to the caller.
+fn ascii_digits(bytes: &[u8]) -> usize {
+    let threes = u64::from_ne_bytes([0x30; 8]);
+    let mut i = 0;
+    for chunk in bytes.chunks_exact(8) {
+        let x = u64::from_ne_bytes(chunk.try_into().unwrap());
+        // Digits are 0x30 to 0x39, which adding 6 keeps below 0x40.
+        let high = u64::from_ne_bytes([0xf0; 8]);
+        if x & high != threes || x.wrapping_add(ONES * 6) & high != threes {
+            break;
+        }
+        i += 8;
+    }
+    i
+}
+
+/// The run of chars `p` accepts at the start of the input, possibly empty.
+pub fn take_while<'a>(p: impl CharPredicate + 'a) -> Parser<'a, Substr> {
+    Parser {
+        run: Ptr::new(move |input| {
+            let i = p.span(input.text());
+            if i == input.text().len() && input.partial {
+                return (input, Err(Failure::incomplete(Needed::Size(1))));
+            }
+            (input_sub(i, &input), Ok(Substr::take(&input, i)))
+        }),
+        shape: None,
+    }
+}
+
+pub(crate) fn while1<'a>(expected: &'static str, p: impl CharPredicate + 'a) -> Parser<'a, Substr> {
+    Parser {
+        run: Ptr::new(move |input| {
+            let n = input.text().len();
+            let i = p.span(input.text());
+            if i == n && input.partial {
+                return (input, Err(Failure::incomplete(Needed::Size(1))));
+            }
+            if i == 0 {
+                let desc = format!("expected {expected}");
+                return (input, Err(Failure::expecting(expected.to_string(), desc)));
+            }
+            (input_sub(i, &input), Ok(Substr::take(&input, i)))
+        }),
+        shape: Some(Ptr::new(Shape::Described(expected.to_string()))),
+    }
+}
+
+/// The run of chars `p` accepts at the start of the input, at least one.
+pub fn take_while1<'a>(p: impl CharPredicate + 'a) -> Parser<'a, Substr> {
+    while1("matching chars", p)
+}
+
+/// Exactly `n` chars.
+pub fn take<'a>(n: usize) -> Parser<'a, Substr> {
+    Parser {
+        run: Ptr::new(move |input| {
+            let text = input.text();
+            let mut ends = text.char_indices().map(|(i, _)| i).chain([text.len()]);
+            match ends.nth(n) {
+                Some(end) => (input_sub(end, &input), Ok(Substr::take(&input, end))),
+                None if input.partial => {
+                    let needed = Needed::Size(n - text.chars().count());
+                    (input, Err(Failure::incomplete(needed)))
+                }
+                None => {
+                    let desc = format!("expected {n} chars, got {}", text.chars().count());
+                    (input, Err(Failure::expecting(format!("{n} chars"), desc)))
+                }
+            }
+        }),
+        shape: None,
+    }
+}
+
+/// Everything before the next `pattern`, which is left unconsumed, such as
+/// the body of a `<!-- ... -->` comment. Fails when `pattern` doesn't occur.
+pub fn take_until<'a>(pattern: &'static str) -> Parser<'a, Substr> {
+    Parser {
+        run: Ptr::new(move |input| match input.text().find(pattern) {
+            Some(end) => (input_sub(end, &input), Ok(Substr::take(&input, end))),
+            None if input.partial => (input, Err(Failure::incomplete(Needed::Unknown))),
+            None => {
+                let desc = format!("expected {pattern:?} before the end of input");
+                (input, Err(Failure::expecting(format!("{pattern:?}"), desc)))
+            }
+        }),
+        shape: None,
+    }
+}
+
+fn is_space(c: char) -> bool {
+    c.is_whitespace() && c != '\n' && c != '\r'
+}
+
+/// `0` to `9`. Unlike the other classes the digits are ASCII only, so that
+/// what they match goes through `str::parse` and the number parsers; see
+/// [`numeric`] for those of any script.
+pub fn digit<'a>() -> Parser<'a, char> {
+    satisfy_or("a digit".to_string(), |c| c.is_ascii_digit())
+}
+
+/// `0` to `9`, at least one.
+pub fn digits1<'a>() -> Parser<'a, Substr> {
+    while1("digits", ASCII_DIGIT)
+}
+
+/// A char of any script's digits or numerals, such as `٣` or `½`.
+pub fn numeric<'a>() -> Parser<'a, char> {
+    satisfy_or("a numeric char".to_string(), char::is_numeric)
+}
+
+pub fn alpha<'a>() -> Parser<'a, char> {
+    satisfy_or("a letter".to_string(), char::is_alphabetic)
+}
+
+pub fn alphanumeric<'a>() -> Parser<'a, char> {
+    satisfy_or("a letter or digit".to_string(), char::is_alphanumeric)
+}
+
+pub fn whitespace<'a>() -> Parser<'a, char> {
+    satisfy_or("whitespace".to_string(), char::is_whitespace)
+}
+
+pub fn hex_digit<'a>() -> Parser<'a, char> {
+    satisfy_or("a hex digit".to_string(), |c| c.is_ascii_hexdigit())
+}
+
+pub fn newline<'a>() -> Parser<'a, char> {
+    prefix("\n").map(|_| '\n')
+}
+
+/// `\n` or `\r\n`.
+pub fn line_ending<'a>() -> Parser<'a, &'static str> {
+    (prefix("\r\n") | prefix("\n")).label("a line ending")
+}
+
+/// Everything up to the next line ending or the end of the input, which is
+/// left unconsumed.
+pub fn rest_of_line<'a>() -> Parser<'a, Substr> {
+    Parser {
+        run: Ptr::new(|input| {
+            let text = input.text();
+            let end = text.find('\n').map(|i| match text[..i].strip_suffix('\r') {
+                Some(line) => line.len(),
+                None => i,
+            });
+            match end {
+                Some(end) => (input_sub(end, &input), Ok(Substr::take(&input, end))),
+                None if input.partial => (input, Err(Failure::incomplete(Needed::Unknown))),
+                None => (
+                    input_sub(text.len(), &input),
+                    Ok(Substr::take(&input, text.len())),
+                ),
+            }
+        }),
+        shape: None,
+    }
+}
+
+/// A line without its line ending, which is consumed. The last line needs
+/// none, but there is no line at the end of the input.
+pub fn line<'a>() -> Parser<'a, Substr> {
+    let line = rest_of_line() << (line_ending().ignored() | eof());
+    Parser {
+        run: Ptr::new(move |input| {
+            if input.text().is_empty() && !input.partial {
+                let desc = "expected a line, got end of input".to_string();
+                return (input, Err(Failure::expecting("a line".to_string(), desc)));
+            }
+            (line.run)(input)
+        }),
+        shape: None,
+    }
+}
+
+/// Spaces and tabs (any whitespace but line breaks), possibly none.
+pub fn space0<'a>() -> Parser<'a, Substr> {
+    take_while(SPACE)
+}
+
+/// Spaces and tabs (any whitespace but line breaks), at least one.
+pub fn space1<'a>() -> Parser<'a, Substr> {
+    while1("spaces", SPACE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{many, run, Location, ParserError};
+
+    #[test]
+    fn digit_test() {
+        let parser = many(digit());
+        let parsed = run(parser, "12٣a".to_string());
+        assert_eq!(parsed, Ok(vec!['1', '2']));
+        let parser = many(numeric());
+        let parsed = run(parser, "1٣½a".to_string());
+        assert_eq!(parsed, Ok(vec!['1', '٣', '½']));
+        let parsed = run(digits1() << eof(), "12٣".to_string());
+        assert!(parsed.is_err());
+
+        let parsed = run(digits1(), "x".to_string());
+        assert_eq!(
+            parsed,
+            Err(ParserError {
+                desc: "expected digits".to_string(),
+                location: Location {
+                    offset: 0,
+                    char_offset: 0,
+                    line: 1,
+                    column: 1,
+                },
+                context: vec![],
+                expected: vec!["digits".to_string()],
+                cause: None,
+                at_end: false,
+            })
+        );
+    }
+
+    #[test]
+    fn alpha_test() {
+        let parser = alpha() + alphanumeric() + hex_digit();
+        let parsed = run(parser, "é2f".to_string());
+        assert_eq!(parsed, Ok((('é', '2'), 'f')));
+
+        let parsed = run(hex_digit(), "g".to_string());
+        assert_eq!(
+            parsed,
+            Err(ParserError {
+                desc: "expected a hex digit, got 'g'".to_string(),
+                location: Location {
+                    offset: 0,
+                    char_offset: 0,
+                    line: 1,
+                    column: 1,
+                },
+                context: vec![],
+                expected: vec!["a hex digit".to_string()],
+                cause: None,
+                at_end: false,
+            })
+        );
+    }
+
+    #[test]
+    fn space_test() {
+        let parser = space0().to_owned() + newline() + whitespace() + space1().to_owned();
+        let parsed = run(parser, " \t\n\n \u{3000}x".to_string());
+        assert_eq!(
+            parsed,
+            Ok(((((" \t".to_string()), '\n'), '\n'), " \u{3000}".to_string()))
+        );
+
+        let parsed = run(space0(), "x".to_string());
+        assert_eq!(parsed.as_deref(), Ok(""));
+
+        assert!(run(space1(), "\nx".to_string()).is_err());
+    }
+
+    #[test]
+    fn line_test() {
+        let parsed = run(many(line()), "one\r\ntwo\n\nthree".to_string()).unwrap();
+        assert_eq!(parsed, ["one", "two", "", "three"]);
+
+        let parser = rest_of_line().to_owned() + line_ending();
+        let parsed = run(parser, "a\rb\r\n".to_string());
+        assert_eq!(parsed, Ok(("a\rb".to_string(), "\r\n")));
+
+        let parsed = run(rest_of_line() >> line_ending(), "ab".to_string());
+        assert_eq!(
+            parsed,
+            Err(ParserError {
+                desc: "expected a line ending".to_string(),
+                location: Location {
+                    offset: 2,
+                    char_offset: 2,
+                    line: 1,
+                    column: 3,
+                },
+                context: vec![],
+                expected: vec!["a line ending".to_string()],
+                cause: None,
+                at_end: true,
+            })
+        );
+    }
+
+    #[test]
+    fn take_while_test() {
+        let text = "0123456789012345x";
+        assert_eq!(ASCII_DIGIT.span(text), 16);
+        assert_eq!(NUMERIC.span("12345678٣٤x"), 12);
+        assert_eq!(CharClass::except('x').span(text), 16);
+        assert_eq!(CharClass::except('é').span("abcé"), 3);
+        assert_eq!(CharClass::except(';').span("no semicolon"), 12);
+        assert_eq!(ALPHABETIC.span("héllo wörld"), 6);
+        assert_eq!(WHITESPACE.span(" \t\u{3000}\n x"), 7);
+        // the fast paths agree with the closures
+        for text in ["", "a", "12 34", "٣3", "\u{3000}  a", "abcdefgh;ijk"] {
+            for class in [
+                WHITESPACE,
+                SPACE,
+                NUMERIC,
+                ALPHANUMERIC,
+                CharClass::except(';'),
+            ] {
+                assert_eq!(class.span(text), (|c| class.matches(c)).span(text));
+            }
+        }
+
+        let parser =
+            take_while(ASCII_DIGIT).to_owned() + take_while(|c: char| c != '\n').to_owned();
+        let parsed = run(parser, "12ab\n".to_string());
+        assert_eq!(parsed, Ok(("12".to_string(), "ab".to_string())));
+    }
+
+    #[test]
+    fn take_test() {
+        let parser = take(2).to_owned() + take_while1(ALPHABETIC).to_owned() + take(0).to_owned();
+        let parsed = run(parser, "éxyz1".to_string());
+        assert_eq!(
+            parsed,
+            Ok((("éx".to_string(), "yz".to_string()), String::new()))
+        );
+        let e = run(take(3), "ab".to_string()).unwrap_err();
+        assert_eq!(e.desc, "expected 3 chars, got 2");
+        assert_eq!(run(take(2), "ab".to_string()).as_deref(), Ok("ab"));
+
+        let e = run(take_while1(ASCII_DIGIT), "x1".to_string()).unwrap_err();
+        assert_eq!(e.expected, ["matching chars".to_string()]);
+
+        let comment = prefix("<!--") >> take_until("-->").to_owned() << prefix("-->");
+        let parsed = run(comment.clone(), "<!-- a -- b -->".to_string());
+        assert_eq!(parsed, Ok(" a -- b ".to_string()));
+        assert_eq!(run(take_until(";"), ";".to_string()).as_deref(), Ok(""));
+        let e = run(comment, "<!-- a".to_string()).unwrap_err();
+        assert_eq!(e.desc, "expected \"-->\" before the end of input");
+        assert_eq!(e.location.offset, 4);
+    }
+
+    #[test]
+    fn take_streaming_test() {
+        use crate::{run_streaming, StreamResult};
+
+        let streamed = run_streaming(take(3), "ab".to_string());
+        assert_eq!(streamed, StreamResult::Incomplete(Needed::Size(1)));
+        let streamed = run_streaming(take_until("-->"), "a --".to_string());
+        assert_eq!(streamed, StreamResult::Incomplete(Needed::Unknown));
+        let streamed = run_streaming(take_until("-->").to_owned(), "a -->".to_string());
+        assert_eq!(streamed, StreamResult::Ok("a ".to_string(), 2));
+    }
+}
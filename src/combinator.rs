@@ -0,0 +1,566 @@
+//! Statically typed parsers.
+//!
+//! Every [`Parser`] goes through an `Rc<dyn Fn>` (`Arc` with the `sync`
+//! feature), which is convenient but
+//! costs an indirection per combinator. The structs here implement [`Parse`]
+//! directly instead, so a grammar composed out of them with the [`Parse`]
+//! methods is a single concrete type the compiler can inline through. Call
+//! [`Parse::boxed`] to erase the type where it gets in the way, e.g. to
+//! store parsers in a `Vec` or to build a recursive grammar; [`BoxedParser`]
+//! is just another name for [`Parser`].
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+use core::ops::Deref;
+use core::result;
+
+use crate::limits::{collect, step};
+use crate::{
+    input_sub, no_progress, Failure, MaybeSync, Merge, Needed, NoProgress, Parser, ParserInput, Ptr,
+};
+
+pub type BoxedParser<'a, T> = Parser<'a, T>;
+
+pub trait Parse<T> {
+    /// Parses the start of `input`, returning the input after the match, or
+    /// where the failure happened, which is where the error is reported.
+    ///
+    /// A failure may leave the input past where the parser started. Every
+    /// combinator going on after a failure that isn't fatal, such as
+    /// `optional`, `|`, `choice` and the repetitions, goes on from the input
+    /// as it was before the attempt, with the diagnostics, state and events
+    /// it had then; hand-written ones can do so with
+    /// [`ParserInput::checkpoint`].
+    fn parse(&self, input: ParserInput) -> ParseResult<T>;
+
+    fn boxed<'a>(self) -> Parser<'a, T>
+    where
+        Self: Sized + MaybeSync + 'a,
+    {
+        Parser {
+            run: Ptr::new(move |input| self.parse(input).into()),
+            shape: None,
+        }
+    }
+
+    fn map<B, F: Fn(T) -> B>(self, f: F) -> Map<Self, F, T>
+    where
+        Self: Sized,
+    {
+        Map(self, f, PhantomData)
+    }
+
+    fn and_then<B, Q: Parse<B>, F: Fn(T) -> Q>(self, f: F) -> AndThen<Self, F, T>
+    where
+        Self: Sized,
+    {
+        AndThen(self, f, PhantomData)
+    }
+
+    fn then<B, Q: Parse<B>>(self, q: Q) -> Then<Self, Q>
+    where
+        Self: Sized,
+    {
+        Then(self, q)
+    }
+
+    fn then_ignore<B, Q: Parse<B>>(self, q: Q) -> ThenIgnore<Self, Q, B>
+    where
+        Self: Sized,
+    {
+        ThenIgnore(self, q, PhantomData)
+    }
+
+    fn ignore_then<B, Q: Parse<B>>(self, q: Q) -> IgnoreThen<Self, Q, T>
+    where
+        Self: Sized,
+    {
+        IgnoreThen(self, q, PhantomData)
+    }
+
+    fn or<Q: Parse<T>>(self, q: Q) -> Or<Self, Q>
+    where
+        Self: Sized,
+    {
+        Or(self, q)
+    }
+
+    fn optional(self) -> Optional<Self>
+    where
+        Self: Sized,
+    {
+        Optional(self)
+    }
+
+    fn many(self) -> Many<Self>
+    where
+        Self: Sized,
+    {
+        Many(self, NoProgress::Stop)
+    }
+}
+
+impl<T, P: Parse<T> + ?Sized> Parse<T> for &P {
+    fn parse(&self, input: ParserInput) -> ParseResult<T> {
+        (**self).parse(input)
+    }
+}
+
+impl<T> Parse<T> for Parser<'_, T> {
+    fn parse(&self, input: ParserInput) -> ParseResult<T> {
+        (self.run)(input).into()
+    }
+}
+
+/// What [`Parse::parse`] returns: the input after what the parser matched
+/// along with its value, or the input where it failed along with the
+/// failure. Derefs to the `Result`.
+///
+/// ```
+/// use parcoom::{run, Parse, ParseResult, ParserInput};
+///
+/// /// Two chars, the same twice.
+/// struct Double;
+///
+/// impl Parse<char> for Double {
+///     fn parse(&self, input: ParserInput) -> ParseResult<char> {
+///         let any = parcoom::any_char();
+///         any.parse(input).and_then(|c, input| {
+///             parcoom::satisfy(move |d| d == c).parse(input)
+///         })
+///     }
+/// }
+///
+/// assert_eq!(run(Double, "aa".to_string()), Ok('a'));
+/// assert!(run(Double, "ab".to_string()).is_err());
+/// ```
+#[derive(Debug)]
+pub struct ParseResult<T> {
+    pub input: ParserInput,
+    pub result: Result<T, Failure>,
+}
+
+impl<T> ParseResult<T> {
+    pub fn ok(input: ParserInput, value: T) -> Self {
+        ParseResult {
+            input,
+            result: Ok(value),
+        }
+    }
+
+    pub fn err(input: ParserInput, failure: Failure) -> Self {
+        ParseResult {
+            input,
+            result: Err(failure),
+        }
+    }
+
+    /// How far into the source the input is.
+    pub fn offset(&self) -> usize {
+        self.input.offset()
+    }
+
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> ParseResult<U> {
+        ParseResult {
+            input: self.input,
+            result: self.result.map(f),
+        }
+    }
+
+    /// Goes on with `f` from the input after the match, if there was one.
+    pub fn and_then<U>(self, f: impl FnOnce(T, ParserInput) -> ParseResult<U>) -> ParseResult<U> {
+        match self.result {
+            Ok(x) => f(x, self.input),
+            Err(e) => ParseResult::err(self.input, e),
+        }
+    }
+
+    /// Fails unless the match ends past `start`, the offset the parser
+    /// started at, as items repeated over and over must.
+    pub fn expect_consumed(self, start: usize) -> Self {
+        match self.result {
+            Ok(_) if self.input.offset() <= start => {
+                let desc = "expected to consume input".to_string();
+                ParseResult::err(self.input, Failure::from(desc))
+            }
+            _ => self,
+        }
+    }
+
+    pub fn into_result(self) -> Result<T, Failure> {
+        self.result
+    }
+}
+
+impl<T> Deref for ParseResult<T> {
+    type Target = Result<T, Failure>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.result
+    }
+}
+
+/// Yields the value, if any.
+impl<T> IntoIterator for ParseResult<T> {
+    type Item = T;
+    type IntoIter = result::IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.result.into_iter()
+    }
+}
+
+impl<T> From<(ParserInput, Result<T, Failure>)> for ParseResult<T> {
+    fn from((input, result): (ParserInput, Result<T, Failure>)) -> Self {
+        ParseResult { input, result }
+    }
+}
+
+impl<T> From<ParseResult<T>> for (ParserInput, Result<T, Failure>) {
+    fn from(output: ParseResult<T>) -> Self {
+        (output.input, output.result)
+    }
+}
+
+impl<T> From<ParseResult<T>> for Result<T, Failure> {
+    fn from(output: ParseResult<T>) -> Self {
+        output.result
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Prefix(pub &'static str);
+
+impl Parse<&'static str> for Prefix {
+    fn parse(&self, input: ParserInput) -> ParseResult<&'static str> {
+        let prefix_str = self.0;
+        if input.text().starts_with(prefix_str) {
+            ParseResult::ok(input_sub(prefix_str.len(), &input), prefix_str)
+        } else if input.partial && prefix_str.starts_with(input.text()) {
+            let needed = Needed::Size(prefix_str.len() - input.text().len());
+            ParseResult::err(input, Failure::incomplete(needed))
+        } else {
+            let desc = format!("expected {prefix_str}");
+            let e = Failure {
+                truncated: prefix_str.starts_with(input.text()),
+                ..Failure::expecting(format!("{prefix_str:?}"), desc)
+            };
+            ParseResult::err(input, e)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct AnyChar;
+
+impl Parse<char> for AnyChar {
+    fn parse(&self, input: ParserInput) -> ParseResult<char> {
+        let n = input.text().len();
+        if let Some(c) = input.text().chars().next() {
+            ParseResult::ok(input_sub(c.len_utf8(), &input), c)
+        } else if input.partial {
+            ParseResult::err(input, Failure::incomplete(Needed::Size(1)))
+        } else {
+            let desc = format!("expected any char, got none (input.len() = {n}");
+            ParseResult::err(input, Failure::expecting("any char".into(), desc))
+        }
+    }
+}
+
+/// One char accepted by `f`, `expected` describes which ones those are.
+#[derive(Debug, Clone)]
+pub struct Satisfy<F> {
+    pub expected: String,
+    pub f: F,
+}
+
+impl<F: Fn(char) -> bool> Parse<char> for Satisfy<F> {
+    fn parse(&self, input: ParserInput) -> ParseResult<char> {
+        let expected = &self.expected;
+        match input.text().chars().next() {
+            Some(c) if (self.f)(c) => ParseResult::ok(input_sub(c.len_utf8(), &input), c),
+            Some(c) => {
+                let desc = format!("expected {expected}, got {c:?}");
+                ParseResult::err(input, Failure::expecting(expected.clone(), desc))
+            }
+            None if input.partial => ParseResult::err(input, Failure::incomplete(Needed::Size(1))),
+            None => {
+                let desc = format!("expected {expected}, got end of input");
+                ParseResult::err(input, Failure::expecting(expected.clone(), desc))
+            }
+        }
+    }
+}
+
+pub struct Map<P, F, A>(P, F, PhantomData<fn() -> A>);
+
+impl<A, B, P: Parse<A>, F: Fn(A) -> B> Parse<B> for Map<P, F, A> {
+    fn parse(&self, input: ParserInput) -> ParseResult<B> {
+        self.0.parse(input).map(&self.1)
+    }
+}
+
+pub struct AndThen<P, F, A>(P, F, PhantomData<fn() -> A>);
+
+impl<A, B, P: Parse<A>, Q: Parse<B>, F: Fn(A) -> Q> Parse<B> for AndThen<P, F, A> {
+    fn parse(&self, input: ParserInput) -> ParseResult<B> {
+        self.0
+            .parse(input)
+            .and_then(|x, input| (self.1)(x).parse(input))
+    }
+}
+
+pub struct Then<P, Q>(P, Q);
+
+impl<A, B, P: Parse<A>, Q: Parse<B>> Parse<(A, B)> for Then<P, Q> {
+    fn parse(&self, input: ParserInput) -> ParseResult<(A, B)> {
+        self.0
+            .parse(input)
+            .and_then(|x, input| self.1.parse(input).map(|y| (x, y)))
+    }
+}
+
+pub struct ThenIgnore<P, Q, B>(P, Q, PhantomData<fn() -> B>);
+
+impl<A, B, P: Parse<A>, Q: Parse<B>> Parse<A> for ThenIgnore<P, Q, B> {
+    fn parse(&self, input: ParserInput) -> ParseResult<A> {
+        self.0
+            .parse(input)
+            .and_then(|x, input| self.1.parse(input).map(|_| x))
+    }
+}
+
+pub struct IgnoreThen<P, Q, A>(P, Q, PhantomData<fn() -> A>);
+
+impl<A, B, P: Parse<A>, Q: Parse<B>> Parse<B> for IgnoreThen<P, Q, A> {
+    fn parse(&self, input: ParserInput) -> ParseResult<B> {
+        self.0.parse(input).and_then(|_, input| self.1.parse(input))
+    }
+}
+
+pub struct Or<P, Q>(P, Q);
+
+impl<A, P: Parse<A>, Q: Parse<A>> Parse<A> for Or<P, Q> {
+    fn parse(&self, input: ParserInput) -> ParseResult<A> {
+        let ParseResult {
+            input: input_,
+            result,
+        } = self.0.parse(input.clone());
+        let e = match result {
+            Ok(x) => return ParseResult::ok(input_, x),
+            Err(e) if e.fatal => return ParseResult::err(input_, e),
+            Err(e) => e,
+        };
+        match self.1.parse(input) {
+            ParseResult {
+                input: input__,
+                result: Err(e_),
+            } => {
+                let (input, e) = Merge::Farthest.pick((input_, e), (input__, e_));
+                ParseResult::err(input, e)
+            }
+            output => output,
+        }
+    }
+}
+
+pub struct Optional<P>(P);
+
+impl<A, P: Parse<A>> Parse<Option<A>> for Optional<P> {
+    fn parse(&self, input: ParserInput) -> ParseResult<Option<A>> {
+        let checkpoint = input.checkpoint();
+        let ParseResult {
+            input: mut input_,
+            result,
+        } = self.0.parse(input);
+        match result {
+            Ok(x) => ParseResult::ok(input_, Some(x)),
+            Err(e) if e.fatal => ParseResult::err(input_, e),
+            Err(_) => {
+                input_.rewind(checkpoint);
+                ParseResult::ok(input_, None)
+            }
+        }
+    }
+}
+
+pub struct Many<P>(pub(crate) P, pub(crate) NoProgress);
+
+impl<A, P: Parse<A>> Parse<Vec<A>> for Many<P> {
+    fn parse(&self, input: ParserInput) -> ParseResult<Vec<A>> {
+        many_onto(&self.0, self.1, Vec::new(), input)
+    }
+}
+
+/// Pushes what `p` yields onto `xs` for as long as it matches, counting
+/// the items already in `xs` against the limits.
+pub(crate) fn many_onto<A>(
+    p: &impl Parse<A>,
+    on: NoProgress,
+    mut xs: Vec<A>,
+    input: ParserInput,
+) -> ParseResult<Vec<A>> {
+    let mut input_ = input;
+    loop {
+        if let Err(e) = step(&input_) {
+            return ParseResult::err(input_, e);
+        }
+        let pos = input_.pos;
+        let checkpoint = input_.checkpoint();
+        let ParseResult {
+            input: mut input__,
+            result,
+        } = p.parse(input_);
+        match result {
+            Ok(x) => {
+                if let Err(e) = collect::<A>(&input__, xs.len() + 1) {
+                    input__.rewind(checkpoint);
+                    return ParseResult::err(input__, e);
+                }
+                xs.push(x);
+                input_ = input__;
+                if input_.pos == pos {
+                    match on {
+                        NoProgress::Stop => break,
+                        NoProgress::Fail => return ParseResult::err(input_, no_progress(pos)),
+                    }
+                }
+            }
+            Err(e) if e.fatal => {
+                return ParseResult::err(input__, e);
+            }
+            // back to where the item started
+            Err(_) => {
+                input__.rewind(checkpoint);
+                input_ = input__;
+                break;
+            }
+        }
+    }
+    ParseResult::ok(input_, xs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{make_input, run, Location, ParserError};
+    use alloc::vec::Vec;
+
+    #[test]
+    fn typed_parser_test() {
+        let digit = Satisfy {
+            expected: "a digit".into(),
+            f: |c: char| c.is_ascii_digit(),
+        };
+        let number = digit.many().map(String::from_iter);
+        let parser = Prefix("#")
+            .ignore_then(number)
+            .then_ignore(Prefix(";"))
+            .then(AnyChar.optional());
+
+        let parsed = run(parser, "#42;x".to_string());
+        assert_eq!(parsed, Ok(("42".to_string(), Some('x'))));
+    }
+
+    #[test]
+    fn typed_or_test() {
+        let parser = Prefix("let").or(Prefix("fn")).and_then(|x| {
+            let len = x.len();
+            AnyChar.many().map(move |xs| xs.len() + len)
+        });
+        assert_eq!(run(&parser, "fn x".to_string()), Ok(4));
+
+        let parsed = run(parser, "x".to_string());
+        assert_eq!(
+            parsed,
+            Err(ParserError {
+                desc: "expected \"let\" or \"fn\"".to_string(),
+                location: Location {
+                    offset: 0,
+                    char_offset: 0,
+                    line: 1,
+                    column: 1,
+                },
+                context: vec![],
+                expected: vec!["\"let\"".to_string(), "\"fn\"".to_string()],
+                cause: None,
+                at_end: false,
+            })
+        );
+    }
+
+    /// Whichever of two parsers gets further, hand-written with checkpoints.
+    struct Longest<P, Q>(P, Q);
+
+    impl<T, P: Parse<T>, Q: Parse<T>> Parse<T> for Longest<P, Q> {
+        fn parse(&self, input: ParserInput) -> ParseResult<T> {
+            let checkpoint = input.checkpoint();
+            let ParseResult { mut input, result } = self.0.parse(input);
+            let (first, end) = match result {
+                Ok(x) => (Some(x), input.offset()),
+                Err(_) => (None, 0),
+            };
+            let after_first = input.checkpoint();
+            input.rewind(checkpoint);
+            let ParseResult { mut input, result } = self.1.parse(input);
+            match (first, result) {
+                (_, Ok(y)) if input.offset() > end => ParseResult::ok(input, y),
+                (Some(x), _) => {
+                    input.rewind(after_first);
+                    ParseResult::ok(input, x)
+                }
+                (None, result) => ParseResult { input, result },
+            }
+        }
+    }
+
+    #[test]
+    fn checkpoint_test() {
+        let parser = Longest(Prefix("<"), Prefix("<=")).then(Longest(Prefix("ab"), Prefix("a")));
+        assert_eq!(run(&parser, "<=ab".to_string()), Ok(("<=", "ab")));
+        assert_eq!(run(&parser, "<a".to_string()), Ok(("<", "a")));
+        let e = run(parser, "<=x".to_string()).unwrap_err();
+        assert_eq!(e.location.offset, 2);
+    }
+
+    #[test]
+    fn parse_result_test() {
+        let parsed = Prefix("ab").parse(make_input("abc".to_string()));
+        assert_eq!(parsed.offset(), 2);
+        assert_eq!(*parsed, Ok("ab"));
+        let parsed = parsed
+            .map(str::len)
+            .and_then(|n, input| AnyChar.parse(input).map(|c| (n, c)));
+        assert_eq!(parsed.offset(), 3);
+        assert_eq!(parsed.into_iter().collect::<Vec<_>>(), [(2, 'c')]);
+
+        let failed = Prefix("x")
+            .parse(make_input("ab".to_string()))
+            .and_then(|_, input| AnyChar.parse(input));
+        assert_eq!(failed.offset(), 0);
+        assert!(failed.is_err());
+        let (input, result) = failed.into();
+        assert!(result.is_err());
+        let mut restored = ParseResult::from((input, result));
+        restored = restored.expect_consumed(0);
+        assert!(restored.into_iter().next().is_none());
+
+        // empty matches
+        let empty = Optional(Prefix("x")).parse(make_input("ab".to_string()));
+        assert_eq!(
+            empty.expect_consumed(0).into_result().map(|_| ()),
+            Err(Failure::from("expected to consume input".to_string()))
+        );
+        let full = AnyChar.parse(make_input("ab".to_string()));
+        assert_eq!(full.expect_consumed(0).into_result(), Ok('a'));
+    }
+
+    #[test]
+    fn boxed_test() {
+        // boxed parsers mix with the operators of `Parser`
+        let parser = Prefix("a").boxed() >> Prefix("b").or(Prefix("c")).boxed();
+        assert_eq!(run(parser, "ac".to_string()), Ok("c"));
+    }
+}
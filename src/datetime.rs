@@ -0,0 +1,369 @@
+//! Dates, times and durations as RFC 3339 and ISO 8601 write them.
+//!
+//! Fields out of range, such as month 13, February 30 or a leap second that
+//! is not at the end of a UTC day, fail at the start of the field. The
+//! values are plain structs, to be converted to those of a date-time crate
+//! where needed.
+//!
+//! ```
+//! use parcoom::datetime::{rfc3339, Date};
+//! use parcoom::run;
+//!
+//! let parsed = run(rfc3339(), "2016-12-31T18:59:60.5-05:00".to_string()).unwrap();
+//! assert_eq!(parsed.date, Date { year: 2016, month: 12, day: 31 });
+//! assert_eq!((parsed.time.second, parsed.time.nanosecond), (60, 500_000_000));
+//! assert_eq!(parsed.offset, -300);
+//! ```
+
+use alloc::format;
+use alloc::string::ToString;
+use core::ops::RangeInclusive;
+
+use crate::chars::{while1, ASCII_DIGIT};
+use crate::number::unsigned;
+use crate::{
+    cut, input_sub, one_of, prefix, satisfy, wrap, Failure, Needed, Parser, ParserInput, Ptr,
+    Spanned,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Date {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Time {
+    pub hour: u8,
+    pub minute: u8,
+    /// 60 for a leap second.
+    pub second: u8,
+    pub nanosecond: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DateTime {
+    pub date: Date,
+    pub time: Time,
+    /// Minutes east of UTC.
+    pub offset: i16,
+}
+
+/// An ISO 8601 duration, its components as written: `PT90M` is 90 minutes,
+/// not an hour and a half.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Duration {
+    pub years: u32,
+    pub months: u32,
+    pub weeks: u32,
+    pub days: u32,
+    pub hours: u32,
+    pub minutes: u32,
+    pub seconds: u32,
+    pub nanoseconds: u32,
+}
+
+fn leap_year(year: u32) -> bool {
+    year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400))
+}
+
+fn days_in_month(year: u32, month: u32) -> u32 {
+    match month {
+        2 if leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Exactly `n` digits, the value of which is in `range`.
+fn field<'a>(name: &'static str, n: usize, range: RangeInclusive<u32>) -> Parser<'a, u32> {
+    Parser {
+        run: Ptr::new(move |input| {
+            let text = input.text();
+            let digits = text.bytes().take(n).take_while(u8::is_ascii_digit).count();
+            if digits == text.len() && digits < n && input.partial {
+                return (input, Err(Failure::incomplete(Needed::Size(n - digits))));
+            }
+            if digits < n {
+                let expected = format!("the {name}");
+                let desc = format!("expected the {name}, {n} digits");
+                return (input, Err(Failure::expecting(expected, desc)));
+            }
+            let value = text[..n].parse().unwrap();
+            if !range.contains(&value) {
+                let (min, max) = (range.start(), range.end());
+                let desc = format!("{name} {value} is out of range, expected {min} to {max}");
+                return (input, Err(Failure::from(desc)));
+            }
+            (input_sub(n, &input), Ok(value))
+        }),
+        shape: None,
+    }
+}
+
+/// `YYYY-MM-DD`
+pub fn date<'a>() -> Parser<'a, Date> {
+    let year_month =
+        (field("year", 4, 0..=9999) << prefix("-")) + (field("month", 2, 1..=12) << prefix("-"));
+    year_month.and_then(|(year, month)| {
+        let day = field("day", 2, 1..=days_in_month(year, month));
+        day.map(move |day| Date {
+            year: year as u16,
+            month: month as u8,
+            day: day as u8,
+        })
+    })
+}
+
+/// `.` and one or more digits, as nanoseconds. Digits past the ninth are
+/// dropped.
+fn fraction<'a>() -> Parser<'a, u32> {
+    let digits = prefix(".") >> cut(while1("a digit", ASCII_DIGIT));
+    digits.map(|digits| {
+        let nanos = format!("{digits:0<9}");
+        nanos[..9].parse().unwrap()
+    })
+}
+
+/// A time of day without an offset, `hh:mm:ss` and an optional fraction.
+/// Second 60, a leap second, is only taken in the last minute of an hour.
+pub fn time<'a>() -> Parser<'a, Time> {
+    let hour_minute =
+        (field("hour", 2, 0..=23) << prefix(":")) + (field("minute", 2, 0..=59) << prefix(":"));
+    hour_minute.and_then(|(hour, minute)| {
+        let max = if minute == 59 { 60 } else { 59 };
+        let second = field("second", 2, 0..=max) + fraction().or_default();
+        second.map(move |(second, nanosecond)| Time {
+            hour: hour as u8,
+            minute: minute as u8,
+            second: second as u8,
+            nanosecond,
+        })
+    })
+}
+
+/// `Z`, or the offset from UTC as `+hh:mm` or `-hh:mm`, in minutes.
+pub fn offset<'a>() -> Parser<'a, i16> {
+    let zulu = one_of("Zz").to(0);
+    let hours = one_of("+-") + (field("offset hour", 2, 0..=23) << prefix(":"));
+    let numeric = (hours + field("offset minute", 2, 0..=59)).map(|((sign, hours), minutes)| {
+        let offset = (hours * 60 + minutes) as i16;
+        if sign == '-' {
+            -offset
+        } else {
+            offset
+        }
+    });
+    zulu | numeric
+}
+
+/// An RFC 3339 date-time such as `1985-04-12T23:20:50.52Z`. The separator
+/// may be `T`, `t` or a space, which RFC 3339 allows too. A leap second must
+/// be at 23:59:60 in UTC.
+pub fn rfc3339<'a>() -> Parser<'a, DateTime> {
+    let datetime = (date() << one_of("Tt ")) + time().spanned() + offset();
+    Parser {
+        run: Ptr::new(move |input| match (datetime.run)(input) {
+            (input_, Ok(((date, time), offset))) => {
+                let Spanned { value: time, span } = time;
+                let utc = (i32::from(time.hour) * 60 + i32::from(time.minute) - i32::from(offset))
+                    .rem_euclid(24 * 60);
+                if time.second == 60 && utc != 24 * 60 - 1 {
+                    let desc = "leap second not at 23:59:60 UTC".to_string();
+                    let pos = span.start + 6;
+                    return (ParserInput { pos, ..input_ }, Err(Failure::from(desc)));
+                }
+                (input_, Ok(DateTime { date, time, offset }))
+            }
+            (input_, Err(e)) => (input_, Err(e)),
+        }),
+        shape: None,
+    }
+}
+
+/// Digits followed by `unit`, such as `3D`, or nothing if they are not
+/// there. With `fraction` they may have one, as nanoseconds.
+fn component<'a>(unit: char, fraction: bool) -> Parser<'a, Option<(u32, u32)>> {
+    let fraction = match fraction {
+        true => self::fraction().or_default(),
+        false => wrap(0),
+    };
+    let component = (unsigned() + fraction) << satisfy(move |c| c == unit);
+    Parser {
+        run: Ptr::new(move |input| match (component.run)(input.clone()) {
+            (input_, Ok(x)) => (input_, Ok(Some(x))),
+            (input_, Err(e)) if e.fatal || e.needed.is_some() => (input_, Err(e)),
+            (_, Err(_)) => (input, Ok(None)),
+        }),
+        shape: None,
+    }
+}
+
+/// An ISO 8601 duration such as `P1Y2M10DT2H30M` or `PT0.5S`, with at
+/// least one component. Only the seconds may have a fraction.
+pub fn duration<'a>() -> Parser<'a, Duration> {
+    let (number, seconds) = (|unit| component(unit, false), component('S', true));
+    let date = number('Y') + number('M') + number('W') + number('D');
+    let time = number('H') + number('M') + seconds;
+    let time = Parser {
+        run: Ptr::new(move |input| match (time.run)(input.clone()) {
+            (_, Ok(((None, None), None))) => {
+                let desc = "expected hours, minutes or seconds".to_string();
+                (input, Err(Failure::from(desc)))
+            }
+            output => output,
+        }),
+        shape: None,
+    };
+    let duration = prefix("P") >> cut(date + (prefix("T") >> cut(time)).optional());
+    Parser {
+        run: Ptr::new(move |input| match (duration.run)(input) {
+            (input_, Ok(((((None, None), None), None), None))) => {
+                let desc = "expected a duration component".to_string();
+                (input_, Err(Failure::from(desc)))
+            }
+            (input_, Ok(((((years, months), weeks), days), time))) => {
+                let ((hours, minutes), seconds) = time.unwrap_or_default();
+                let (seconds, nanoseconds) = seconds.unwrap_or_default();
+                let n = |component: Option<(u32, u32)>| component.unwrap_or_default().0;
+                let duration = Duration {
+                    years: n(years),
+                    months: n(months),
+                    weeks: n(weeks),
+                    days: n(days),
+                    hours: n(hours),
+                    minutes: n(minutes),
+                    seconds,
+                    nanoseconds,
+                };
+                (input_, Ok(duration))
+            }
+            (input_, Err(e)) => (input_, Err(e)),
+        }),
+        shape: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{run, Location, ParserError};
+    use alloc::string::String;
+    use alloc::vec;
+
+    fn error<A: core::fmt::Debug>(p: Parser<A>, text: &str) -> (usize, String) {
+        let e = run(p, text.to_string()).unwrap_err();
+        (e.location.offset, e.desc)
+    }
+
+    #[test]
+    fn date_test() {
+        let parsed = run(date(), "2024-02-29".to_string());
+        assert_eq!(
+            parsed,
+            Ok(Date {
+                year: 2024,
+                month: 2,
+                day: 29
+            })
+        );
+        let out_of_range = |desc: &str| (5, desc.to_string());
+        assert_eq!(
+            error(date(), "2023-13-01"),
+            out_of_range("month 13 is out of range, expected 1 to 12")
+        );
+        assert_eq!(
+            error(date(), "2023-02-29"),
+            (8, "day 29 is out of range, expected 1 to 28".to_string())
+        );
+        assert_eq!(error(date(), "1900-02-29").0, 8, "1900 is not a leap year");
+        assert!(run(date(), "2000-02-29".to_string()).is_ok());
+        assert_eq!(
+            run(date(), "2023-4-01".to_string()),
+            Err(ParserError {
+                desc: "expected the month, 2 digits".to_string(),
+                location: Location {
+                    offset: 5,
+                    char_offset: 5,
+                    line: 1,
+                    column: 6,
+                },
+                context: vec![],
+                expected: vec!["the month".to_string()],
+                cause: None,
+                at_end: false,
+            })
+        );
+    }
+
+    #[test]
+    fn time_test() {
+        let parsed = run(time(), "23:20:50.52".to_string());
+        let expected = Time {
+            hour: 23,
+            minute: 20,
+            second: 50,
+            nanosecond: 520_000_000,
+        };
+        assert_eq!(parsed, Ok(expected));
+        let parsed = run(time(), "00:00:00.1234567891".to_string());
+        assert_eq!(parsed.map(|t| t.nanosecond), Ok(123_456_789));
+        assert_eq!(
+            error(time(), "24:00:00"),
+            (0, "hour 24 is out of range, expected 0 to 23".to_string())
+        );
+        assert_eq!(error(time(), "12:30:60").0, 6);
+        assert!(run(time(), "12:59:60".to_string()).is_ok());
+
+        let offset = |text: &str| run(offset(), text.to_string());
+        assert_eq!(offset("Z"), Ok(0));
+        assert_eq!(offset("+05:30"), Ok(330));
+        assert_eq!(offset("-00:45"), Ok(-45));
+    }
+
+    #[test]
+    fn rfc3339_test() {
+        let parsed = run(rfc3339(), "1985-04-12t23:20:50Z".to_string()).unwrap();
+        assert_eq!(parsed.date.day, 12);
+        assert_eq!(parsed.time.minute, 20);
+        assert!(run(rfc3339(), "1990-12-31 23:59:60Z".to_string()).is_ok());
+        assert_eq!(
+            error(rfc3339(), "1990-12-31T23:59:60+01:00"),
+            (17, "leap second not at 23:59:60 UTC".to_string())
+        );
+        assert!(run(rfc3339(), "1990-12-31T23:59:59".to_string()).is_err());
+    }
+
+    #[test]
+    fn duration_test() {
+        let parsed = run(duration(), "P1Y2M3W4DT5H6M7.5S".to_string());
+        let expected = Duration {
+            years: 1,
+            months: 2,
+            weeks: 3,
+            days: 4,
+            hours: 5,
+            minutes: 6,
+            seconds: 7,
+            nanoseconds: 500_000_000,
+        };
+        assert_eq!(parsed, Ok(expected));
+        let parsed = run(duration(), "PT90M".to_string());
+        let expected = Duration {
+            minutes: 90,
+            ..Duration::default()
+        };
+        assert_eq!(parsed, Ok(expected));
+        assert_eq!(run(duration(), "P0D".to_string()), Ok(Duration::default()));
+        assert_eq!(
+            error(duration(), "P"),
+            (1, "expected a duration component".to_string())
+        );
+        assert_eq!(
+            error(duration(), "P1DT"),
+            (4, "expected hours, minutes or seconds".to_string())
+        );
+    }
+}
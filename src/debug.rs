@@ -0,0 +1,93 @@
+//! Tracing what parsers do, for debugging grammars.
+
+use crate::Parser;
+
+#[cfg(all(feature = "std", debug_assertions))]
+mod trace {
+    use alloc::string::String;
+    use core::cell::Cell;
+
+    use crate::{Parser, Ptr};
+
+    std::thread_local! {
+        static DEPTH: Cell<usize> = const { Cell::new(0) };
+    }
+
+    #[cfg(test)]
+    std::thread_local! {
+        pub(super) static LINES: core::cell::RefCell<alloc::vec::Vec<String>> =
+            const { core::cell::RefCell::new(alloc::vec::Vec::new()) };
+    }
+
+    fn log(depth: usize, line: String) {
+        let line = format!("{}{line}", "  ".repeat(depth));
+        #[cfg(test)]
+        LINES.with(|lines| lines.borrow_mut().push(line));
+        #[cfg(not(test))]
+        std::eprintln!("{line}");
+    }
+
+    pub(super) fn debug<'a, T: 'a>(name: String, p: Parser<'a, T>) -> Parser<'a, T> {
+        let shape = p.shape.clone();
+        Parser {
+            run: Ptr::new(move |input| {
+                let depth = DEPTH.get();
+                let start = input.location();
+                log(depth, format!("{name} @ {}:{}", start.line, start.column));
+                DEPTH.set(depth + 1);
+                let (input_, result) = (p.run)(input);
+                DEPTH.set(depth);
+                let end = input_.location();
+                let outcome = match &result {
+                    Ok(_) => "ok".to_string(),
+                    Err(e) => format!("failed: {}", e.desc),
+                };
+                let at = format!("{}:{}", end.line, end.column);
+                log(depth, format!("{name} {outcome} @ {at}"));
+                (input_, result)
+            }),
+            shape,
+        }
+    }
+}
+
+impl<'a, T: 'a> Parser<'a, T> {
+    /// Logs to stderr where the parser starts, where it stops and whether it
+    /// succeeded, indented by how deep in other `debug` parsers it runs.
+    /// Only debug builds with `std` log, otherwise this does nothing.
+    #[allow(unused_variables)]
+    pub fn debug(self, name: &str) -> Parser<'a, T> {
+        #[cfg(all(feature = "std", debug_assertions))]
+        return trace::debug(name.into(), self);
+        #[cfg(not(all(feature = "std", debug_assertions)))]
+        self
+    }
+}
+
+#[cfg(all(test, feature = "std", debug_assertions))]
+mod tests {
+    use super::trace::LINES;
+    use crate::{prefix, run};
+
+    #[test]
+    fn debug_test() {
+        let ab = (prefix("a") + prefix("b")).debug("ab");
+        let ac = (prefix("a").debug("a") + prefix("c")).debug("ac");
+        let parser = (ab | ac).debug("choice");
+        assert_eq!(run(parser, "ac".to_string()), Ok(("a", "c")));
+        let lines = LINES.with(|lines| lines.take());
+        assert_eq!(
+            lines,
+            vec![
+                "choice @ 1:1",
+                "  ab @ 1:1",
+                "  ab failed: expected b @ 1:2",
+                "  ac @ 1:1",
+                "    a @ 1:1",
+                "    a ok @ 1:2",
+                "  ac ok @ 1:3",
+                "choice ok @ 1:3",
+            ]
+        );
+    }
+}
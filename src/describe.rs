@@ -0,0 +1,277 @@
+//! Listing the grammar of a parser, see [`Parser::describe`].
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::profile::profiled;
+use crate::shape::{of, Shape, ShapeCell};
+use crate::{Parser, Ptr};
+
+impl<'a, T: 'a> Parser<'a, T> {
+    /// Names `self` as a rule of the grammar [`describe`](Parser::describe)
+    /// lists. Other rules refer to it by its name instead of spelling it out.
+    /// In runs through [`run_profiled`](crate::profile::run_profiled) the
+    /// rule is also profiled under its name.
+    pub fn named(self, name: &str) -> Parser<'a, T> {
+        let shape = Shape::Named(name.to_string(), of(&self));
+        profiled(name.to_string(), self).with_shape(shape)
+    }
+
+    /// The grammar of the parser, one `name ::= ...` rule per line in the
+    /// EBNF of the W3C: sequences are separated by spaces, alternatives by
+    /// `|`, `?`, `*`, `+` and `{min,max}` repeat. Rules are the parsers
+    /// [named](Parser::named), recursive ones being `rule1`, `rule2`, ...
+    /// unless named. Unless the parser itself is a rule the first one is
+    /// `start`.
+    ///
+    /// Labelled parsers that are made of closures or predicates are listed
+    /// as `<label>`, others as `<...>`.
+    ///
+    /// ```
+    /// use parcoom::{chars, prefix, recursive};
+    ///
+    /// let number = chars::digits1().to_owned().named("number");
+    /// let list = recursive(|list| {
+    ///     let item = number | list.map(|_| String::new());
+    ///     item.sep_by(prefix(",")).delimited_by(prefix("["), prefix("]"))
+    /// });
+    /// assert_eq!(
+    ///     list.named("list").describe(),
+    ///     "list ::= \"[\" ((number | list) (\",\" (number | list))*)? \"]\"\n\
+    ///      number ::= <digits>\n"
+    /// );
+    /// ```
+    pub fn describe(&self) -> String {
+        let grammar = Grammar::of(of(self));
+        let mut out = String::new();
+        for (name, shape) in &grammar.rules {
+            out += &format!("{name} ::= {}\n", grammar.render(shape, 0));
+        }
+        out
+    }
+}
+
+/// The rules of a parser.
+#[derive(Default)]
+pub(crate) struct Grammar {
+    /// The names of recursive parsers.
+    names: Vec<(*const ShapeCell, String)>,
+    /// The rules by name, the parser itself first.
+    pub(crate) rules: Vec<(String, Ptr<Shape>)>,
+}
+
+impl Grammar {
+    pub(crate) fn of(mut top: Ptr<Shape>) -> Grammar {
+        while let Shape::Reporting(shape) = &*top {
+            top = shape.clone();
+        }
+        let mut grammar = Grammar::default();
+        match &*top {
+            Shape::Named(..) | Shape::Rule(_) => grammar.collect(&top),
+            _ => {
+                grammar.rules.push(("start".to_string(), top.clone()));
+                grammar.collect(&top);
+            }
+        }
+        grammar
+    }
+
+    /// The name `shape` is referred to by, if it is a rule.
+    pub(crate) fn reference(&self, shape: &Shape) -> Option<String> {
+        let name = match shape {
+            Shape::Named(name, _) => Some(name.as_str()),
+            Shape::Rule(cell) => self.name(cell),
+            Shape::Recurse(cell) => cell.upgrade().and_then(|cell| self.name(&cell)),
+            _ => return None,
+        };
+        Some(name.unwrap_or("<...>").to_string())
+    }
+
+    fn name(&self, cell: &Ptr<ShapeCell>) -> Option<&str> {
+        let ptr = Ptr::as_ptr(cell);
+        let found = self.names.iter().find(|(p, _)| *p == ptr);
+        found.map(|(_, name)| name.as_str())
+    }
+
+    /// Lists the rules in `shape`, in the order they are first referred to.
+    fn collect(&mut self, shape: &Ptr<Shape>) {
+        match &**shape {
+            Shape::Named(name, inner) => {
+                if self.rules.iter().any(|(n, _)| n == name) {
+                    return;
+                }
+                let body = match &**inner {
+                    Shape::Rule(cell) => {
+                        self.names.push((Ptr::as_ptr(cell), name.clone()));
+                        body(cell)
+                    }
+                    _ => inner.clone(),
+                };
+                self.rules.push((name.clone(), body.clone()));
+                self.collect(&body);
+            }
+            Shape::Rule(cell) => self.collect_rule(cell),
+            Shape::Recurse(cell) => {
+                if let Some(cell) = cell.upgrade() {
+                    self.collect_rule(&cell);
+                }
+            }
+            Shape::Seq(items) | Shape::Alt(items) => {
+                for item in items {
+                    self.collect(item);
+                }
+            }
+            Shape::Repeat { item, .. }
+            | Shape::Ahead { shape: item, .. }
+            | Shape::Reporting(item) => self.collect(item),
+            Shape::Literal(_)
+            | Shape::Caseless(_)
+            | Shape::OneOf(_)
+            | Shape::Described(_)
+            | Shape::Opaque => {}
+        }
+    }
+
+    fn collect_rule(&mut self, cell: &Ptr<ShapeCell>) {
+        if self.name(cell).is_some() {
+            return;
+        }
+        let name = format!("rule{}", self.names.len() + 1);
+        self.names.push((Ptr::as_ptr(cell), name.clone()));
+        let body = body(cell);
+        self.rules.push((name, body.clone()));
+        self.collect(&body);
+    }
+
+    /// Renders `shape` where binding at least as tight as `prec` is needed:
+    /// 1 in sequences and 2 under repetitions.
+    fn render(&self, shape: &Shape, prec: u8) -> String {
+        let group = |text: String, needed: bool| match needed {
+            true => format!("({text})"),
+            false => text,
+        };
+        match shape {
+            Shape::Literal(text) | Shape::Caseless(text) => format!("{text:?}"),
+            Shape::OneOf(chars) => class(chars),
+            Shape::Seq(items) if items.is_empty() => "\"\"".to_string(),
+            Shape::Seq(items) => {
+                let items: Vec<_> = items.iter().map(|item| self.render(item, 1)).collect();
+                group(items.join(" "), prec >= 2 && items.len() > 1)
+            }
+            Shape::Alt(alternatives) => {
+                let alternatives: Vec<_> = alternatives
+                    .iter()
+                    .map(|alternative| self.render(alternative, 0))
+                    .collect();
+                group(
+                    alternatives.join(" | "),
+                    prec >= 1 && alternatives.len() > 1,
+                )
+            }
+            Shape::Repeat { item, min, max } => {
+                group(self.render(item, 2) + &times(*min, *max), prec >= 2)
+            }
+            Shape::Ahead { shape, negated } => {
+                let op = if *negated { '!' } else { '&' };
+                format!("{op}{}", self.render(shape, 2))
+            }
+            Shape::Reporting(shape) => self.render(shape, prec),
+            Shape::Described(desc) => format!("<{desc}>"),
+            Shape::Opaque => "<...>".to_string(),
+            Shape::Named(..) | Shape::Rule(_) | Shape::Recurse(_) => self.reference(shape).unwrap(),
+        }
+    }
+}
+
+/// `chars` as a char class such as `[+\-]`.
+pub(crate) fn class(chars: &str) -> String {
+    let mut class = String::from("[");
+    for c in chars.chars() {
+        if matches!(c, ']' | '\\' | '-' | '^') {
+            class.push('\\');
+        }
+        class.extend(c.escape_debug());
+    }
+    class + "]"
+}
+
+/// The suffix repeating between `min` and `max` times, such as `*`.
+pub(crate) fn times(min: usize, max: Option<usize>) -> String {
+    match (min, max) {
+        (0, Some(1)) => "?".to_string(),
+        (0, None) => "*".to_string(),
+        (1, None) => "+".to_string(),
+        (min, None) => format!("{{{min},}}"),
+        (min, Some(max)) if min == max => format!("{{{min}}}"),
+        (min, Some(max)) => format!("{{{min},{max}}}"),
+    }
+}
+
+/// The shape of the recursive parser `cell` is of.
+fn body(cell: &ShapeCell) -> Ptr<Shape> {
+    match cell.get() {
+        Some(Some(shape)) => shape.clone(),
+        _ => Ptr::new(Shape::Opaque),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chars::digits1;
+    use crate::{keyword, one_of, parse_while, peek, prefix, recursive, tuple};
+
+    #[test]
+    fn describe_test() {
+        let sign = one_of("+-").optional();
+        let parser = tuple((sign, digits1(), prefix(".") >> digits1().label("fraction")));
+        assert_eq!(
+            parser.describe(),
+            "start ::= [+\\-]? <digits> \".\" <fraction>\n"
+        );
+
+        let word = keyword("let") | keyword("in").many1().map(|_| "in");
+        let line = word.repeated(2..=3) << prefix("\n").repeated(1..);
+        assert_eq!(
+            line.describe(),
+            "start ::= (\"let\" | \"in\"+){2,3} \"\\n\"+\n"
+        );
+        let opaque = parse_while(|c: char| c != ';');
+        assert_eq!(opaque.describe(), "start ::= <...>\n");
+
+        // wrappers keep the shape of what they wrap
+        let name = keyword("let").not_followed_by(prefix("=")).debug("name");
+        let checked = digits1().verify(|d| d.len() < 4, "too long").memoized();
+        let parser = peek(prefix("l")) >> (name + checked);
+        assert_eq!(
+            parser.describe(),
+            "start ::= &\"l\" \"let\" !\"=\" <digits>\n"
+        );
+    }
+
+    #[test]
+    fn describe_rules_test() {
+        let expr = recursive(|expr| {
+            let atom =
+                digits1().to_owned().named("number") | expr.delimited_by(prefix("("), prefix(")"));
+            let sum = atom.clone().sep_by1(one_of("+-"));
+            sum.map(|terms| terms.join("+")).named("sum")
+        });
+        let statement = (prefix("print ") >> expr.clone()).named("statement");
+        assert_eq!(
+            statement.describe(),
+            "statement ::= \"print \" rule1\n\
+             rule1 ::= sum\n\
+             sum ::= (number | \"(\" rule1 \")\") ([+\\-] (number | \"(\" rule1 \")\"))*\n\
+             number ::= <digits>\n"
+        );
+        let program = expr.named("expr").many();
+        assert_eq!(
+            program.describe(),
+            "start ::= expr*\n\
+             expr ::= sum\n\
+             sum ::= (number | \"(\" expr \")\") ([+\\-] (number | \"(\" expr \")\"))*\n\
+             number ::= <digits>\n"
+        );
+    }
+}
@@ -0,0 +1,157 @@
+//! Warnings collected along a parse, next to the errors it recovered from.
+
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+use crate::{finish, make_input, Failure, MaybeSync, Parse, Parser, ParserError, ParserInput, Ptr};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// Something accepted that may not mean what it seems, such as
+    /// deprecated syntax.
+    Warning,
+    /// An error recovered from, see [`Parser::recover_with`].
+    Error,
+}
+
+/// A warning or an error recovered from, see [`run_with_diagnostics`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub error: ParserError,
+}
+
+impl Diagnostic {
+    /// Renders the diagnostic like [`ParserError::render`], headed by
+    /// `warning:` for warnings.
+    pub fn render(&self, source: &str) -> String {
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        self.error.render_as(severity, None, source)
+    }
+}
+
+impl ParserInput {
+    /// The errors recovered from so far.
+    pub(crate) fn errors(&self) -> impl Iterator<Item = &ParserError> {
+        self.diagnostics
+            .iter()
+            .filter(|d| d.severity == Severity::Error)
+            .map(|d| &d.error)
+    }
+
+    pub(crate) fn report(&mut self, severity: Severity, error: ParserError) {
+        Arc::make_mut(&mut self.diagnostics).push(Diagnostic { severity, error });
+    }
+}
+
+impl<'a, T: 'a> Parser<'a, T> {
+    /// Warns with `message` at the start of what the parser matched, each
+    /// time it does, as deprecated syntax is warned about.
+    pub fn warn(self, message: &str) -> Parser<'a, T> {
+        let message = message.to_string();
+        self.warn_if(move |_| Some(message.clone()))
+    }
+
+    /// Warns with the message `f` returns for the value, if any, at the start
+    /// of what the parser matched.
+    pub fn warn_if(self, f: impl Fn(&T) -> Option<String> + MaybeSync + 'a) -> Parser<'a, T> {
+        let shape = self.shape.clone();
+        Parser {
+            run: Ptr::new(move |input| match (self.run)(input.clone()) {
+                (mut input_, Ok(x)) => {
+                    if let Some(message) = f(&x) {
+                        input_.report(Severity::Warning, Failure::from(message).at(&input));
+                    }
+                    (input_, Ok(x))
+                }
+                (input_, Err(e)) => (input_, Err(e)),
+            }),
+            shape,
+        }
+    }
+}
+
+/// Runs `p` on `input` and returns its value along with the warnings and
+/// the errors it recovered from, in the order they came up. Unlike with
+/// [`run`](crate::run), recovered errors don't fail the run.
+pub fn run_with_diagnostics<A>(
+    p: impl Parse<A>,
+    input: String,
+) -> (Result<A, ParserError>, Vec<Diagnostic>) {
+    let (input, result) = p.parse(make_input(input)).into();
+    let diagnostics = input.diagnostics.to_vec();
+    let input = ParserInput {
+        diagnostics: Arc::new(Vec::new()),
+        ..input
+    };
+    (finish((input, result)), diagnostics)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chars::{alpha, digits1};
+    use crate::{none_of, prefix, run};
+    use alloc::collections::BTreeSet;
+    use alloc::format;
+    use alloc::vec;
+
+    #[test]
+    fn warn_test() {
+        let not_equal = prefix("!=") | prefix("<>").warn("<> is deprecated, use !=");
+        let parser = (alpha() >> not_equal << alpha()).many();
+        let (parsed, diagnostics) = run_with_diagnostics(parser.clone(), "a!=ba<>b".to_string());
+        assert_eq!(parsed, Ok(vec!["!=", "<>"]));
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+        assert_eq!(diagnostics[0].error.location.offset, 5);
+        assert_eq!(
+            diagnostics[0].render("a!=ba<>b"),
+            "warning: <> is deprecated, use !=\n --> 1:6\n  |\n1 | a!=ba<>b\n  |      ^^^\n"
+        );
+        // warnings don't fail runs, nor stay when backtracked out of
+        assert_eq!(run(parser, "a<>b".to_string()), Ok(vec!["<>"]));
+        let backtracked = prefix("<>").warn("deprecated") >> prefix("x") | prefix("<>");
+        let (parsed, diagnostics) = run_with_diagnostics(backtracked, "<>".to_string());
+        assert_eq!((parsed, diagnostics), (Ok("<>"), vec![]));
+    }
+
+    #[test]
+    fn run_with_diagnostics_test() {
+        // keys that come again override the earlier values
+        let entry = (alpha() << prefix("=")) + digits1();
+        let entry = entry.recover_with(none_of(";").many());
+        let keys = entry.sep_by(prefix(";")).map(|entries| {
+            let mut seen = BTreeSet::new();
+            entries
+                .into_iter()
+                .flatten()
+                .map(|(key, _)| (seen.insert(key), key))
+                .collect()
+        });
+        let parser = keys.warn_if(|keys: &Vec<(bool, char)>| {
+            let again: String = keys
+                .iter()
+                .filter(|(new, _)| !new)
+                .map(|(_, key)| *key)
+                .collect();
+            (!again.is_empty()).then(|| format!("duplicate keys {again}"))
+        });
+        let (parsed, diagnostics) = run_with_diagnostics(parser.clone(), "a=1;b=x;a=2".to_string());
+        assert_eq!(parsed.map(|keys| keys.len()), Ok(2));
+        let reported: Vec<_> = diagnostics
+            .iter()
+            .map(|d| (d.severity, d.error.location.offset))
+            .collect();
+        assert_eq!(reported, [(Severity::Error, 6), (Severity::Warning, 0)]);
+        assert_eq!(diagnostics[1].error.desc, "duplicate keys a");
+        assert_eq!(
+            run(parser, "a=1;b=x;a=2".to_string()).map_err(|e| e.location.offset),
+            Err(6)
+        );
+    }
+}
@@ -0,0 +1,330 @@
+//! Railroad diagrams of the grammar of a parser, see [`Parser::diagram`].
+
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::describe::{class, times, Grammar};
+use crate::shape::{of, Shape};
+use crate::Parser;
+
+/// The radius of the arcs tracks turn with.
+const ARC: i32 = 10;
+/// The space between the branches of a choice.
+const GAP: i32 = 8;
+/// The width of a char of the labels.
+const CHAR: i32 = 8;
+/// Half the height of a box.
+const HALF: i32 = 11;
+/// The height of a rule's title.
+const TITLE: i32 = 24;
+const MARGIN: i32 = 20;
+
+const STYLE: &str = "path{fill:none;stroke:#222;stroke-width:1.5}\
+rect{fill:#f4f4f4;stroke:#222;stroke-width:1.5}\
+text{font:14px monospace;text-anchor:middle;dominant-baseline:central}\
+text.title{font-weight:bold;text-anchor:start}\
+text.times{font-size:11px}";
+
+impl<'a, T: 'a> Parser<'a, T> {
+    /// Railroad diagrams of the grammar of the parser as an SVG image, one
+    /// below the other for each of the rules [`describe`](Parser::describe)
+    /// lists. Literals are rounded boxes, rules and opaque parsers square
+    /// ones.
+    ///
+    /// ```
+    /// use parcoom::{one_of, prefix};
+    ///
+    /// let svg = (prefix("let") >> one_of("xyz").many1()).named("decl").diagram();
+    /// assert!(svg.starts_with("<svg"));
+    /// assert!(svg.contains(">decl</text>"));
+    /// assert!(svg.contains(">[xyz]</text>"));
+    /// ```
+    pub fn diagram(&self) -> String {
+        let grammar = Grammar::of(of(self));
+        let rules: Vec<_> = grammar
+            .rules
+            .iter()
+            .map(|(name, shape)| (name, Diagram::of(&grammar, shape)))
+            .collect();
+        let width = rules
+            .iter()
+            .map(|(_, d)| d.width() + 2 * ARC)
+            .max()
+            .unwrap_or(0);
+
+        let mut body = String::new();
+        let mut y = MARGIN;
+        for (name, diagram) in &rules {
+            body += &format!(
+                "<text class=\"title\" x=\"{MARGIN}\" y=\"{}\">{}</text>",
+                y + TITLE / 2,
+                escape(name)
+            );
+            let line = y + TITLE + diagram.up();
+            let (start, end) = (MARGIN, MARGIN + ARC + diagram.width());
+            // the bars at either end of the track
+            body += &format!(
+                "<path d=\"M{start} {} v{} M{start} {line} h{ARC} M{end} {line} h{ARC} M{} {} v{}\"/>",
+                line - HALF,
+                2 * HALF,
+                end + ARC,
+                line - HALF,
+                2 * HALF
+            );
+            diagram.draw(start + ARC, line, &mut body);
+            y = line + diagram.down() + MARGIN;
+        }
+        format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{y}\" \
+             viewBox=\"0 0 {} {y}\"><style>{STYLE}</style>{body}</svg>\n",
+            width + 2 * MARGIN,
+            width + 2 * MARGIN
+        )
+    }
+}
+
+enum Diagram {
+    Terminal(String),
+    NonTerminal(String),
+    /// A straight track.
+    Skip,
+    Seq(Vec<Diagram>),
+    Choice(Vec<Diagram>),
+    /// One or more times, those given below the loop back.
+    Loop(Box<Diagram>, Option<String>),
+}
+
+impl Diagram {
+    fn of(grammar: &Grammar, shape: &Shape) -> Diagram {
+        match shape {
+            Shape::Literal(text) | Shape::Caseless(text) => {
+                Diagram::Terminal(text.escape_debug().to_string())
+            }
+            Shape::OneOf(chars) => Diagram::Terminal(class(chars)),
+            Shape::Seq(items) if items.is_empty() => Diagram::Skip,
+            Shape::Seq(items) => {
+                Diagram::Seq(items.iter().map(|s| Diagram::of(grammar, s)).collect())
+            }
+            Shape::Alt(items) if items.is_empty() => Diagram::NonTerminal("...".to_string()),
+            Shape::Alt(items) => {
+                Diagram::Choice(items.iter().map(|s| Diagram::of(grammar, s)).collect())
+            }
+            Shape::Repeat { item, min, max } => {
+                let item = Box::new(Diagram::of(grammar, item));
+                let diagram = match (min, max) {
+                    (_, Some(0)) => return Diagram::Skip,
+                    (0 | 1, Some(1)) => *item,
+                    (0 | 1, None) => Diagram::Loop(item, None),
+                    _ => Diagram::Loop(item, Some(times((*min).max(1), *max))),
+                };
+                match min {
+                    0 => Diagram::Choice(vec![Diagram::Skip, diagram]),
+                    _ => diagram,
+                }
+            }
+            Shape::Ahead { .. } => Diagram::Skip,
+            Shape::Reporting(shape) => Diagram::of(grammar, shape),
+            Shape::Described(desc) => Diagram::NonTerminal(desc.clone()),
+            Shape::Opaque => Diagram::NonTerminal("...".to_string()),
+            Shape::Named(..) | Shape::Rule(_) | Shape::Recurse(_) => {
+                Diagram::NonTerminal(grammar.reference(shape).unwrap())
+            }
+        }
+    }
+
+    fn width(&self) -> i32 {
+        match self {
+            Diagram::Terminal(text) | Diagram::NonTerminal(text) => {
+                text.chars().count() as i32 * CHAR + 2 * ARC
+            }
+            Diagram::Skip => 0,
+            Diagram::Seq(items) => {
+                let gaps = ARC * (items.len() as i32 - 1);
+                items.iter().map(Diagram::width).sum::<i32>() + gaps
+            }
+            Diagram::Choice(items) => items.iter().map(Diagram::width).max().unwrap() + 4 * ARC,
+            Diagram::Loop(item, _) => item.width() + 2 * ARC,
+        }
+    }
+
+    /// How far the diagram reaches above its track.
+    fn up(&self) -> i32 {
+        match self {
+            Diagram::Terminal(_) | Diagram::NonTerminal(_) => HALF,
+            Diagram::Skip => 0,
+            Diagram::Seq(items) => items.iter().map(Diagram::up).max().unwrap(),
+            Diagram::Choice(items) => items[0].up(),
+            Diagram::Loop(item, _) => item.up(),
+        }
+    }
+
+    /// How far the diagram reaches below its track.
+    fn down(&self) -> i32 {
+        match self {
+            Diagram::Terminal(_) | Diagram::NonTerminal(_) => HALF,
+            Diagram::Skip => 0,
+            Diagram::Seq(items) => items.iter().map(Diagram::down).max().unwrap(),
+            Diagram::Choice(items) => {
+                let last = items.last().unwrap();
+                self.branches().last().unwrap() + last.down()
+            }
+            Diagram::Loop(item, label) => {
+                let below = if label.is_some() { 2 * GAP } else { 0 };
+                loop_depth(item) + below
+            }
+        }
+    }
+
+    /// How far below the track the branches of a choice are.
+    fn branches(&self) -> Vec<i32> {
+        let Diagram::Choice(items) = self else {
+            unreachable!()
+        };
+        let mut ys = vec![0];
+        for pair in items.windows(2) {
+            let dy = pair[0].down() + GAP + pair[1].up();
+            let dy = if ys.len() == 1 { dy.max(2 * ARC) } else { dy };
+            ys.push(ys.last().unwrap() + dy);
+        }
+        ys
+    }
+
+    /// Draws the diagram with its track entering at `x`, `y`.
+    fn draw(&self, x: i32, y: i32, out: &mut String) {
+        match self {
+            Diagram::Terminal(text) | Diagram::NonTerminal(text) => {
+                let rounded = if matches!(self, Diagram::Terminal(_)) {
+                    HALF
+                } else {
+                    0
+                };
+                *out += &format!(
+                    "<rect x=\"{x}\" y=\"{}\" width=\"{}\" height=\"{}\" rx=\"{rounded}\"/>\
+                     <text x=\"{}\" y=\"{y}\">{}</text>",
+                    y - HALF,
+                    self.width(),
+                    2 * HALF,
+                    x + self.width() / 2,
+                    escape(text)
+                );
+            }
+            Diagram::Skip => {}
+            Diagram::Seq(items) => {
+                let mut x = x;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        *out += &format!("<path d=\"M{x} {y} h{ARC}\"/>");
+                        x += ARC;
+                    }
+                    item.draw(x, y, out);
+                    x += item.width();
+                }
+            }
+            Diagram::Choice(items) => {
+                let inner = self.width() - 4 * ARC;
+                for (item, dy) in items.iter().zip(self.branches()) {
+                    let rest = inner - item.width();
+                    let path = match dy {
+                        0 => format!("M{x} {y} h{} M{} {y} h{}", 2 * ARC, x + 2 * ARC + item.width(), rest + 2 * ARC),
+                        _ => format!(
+                            "M{x} {y} a{ARC} {ARC} 0 0 1 {ARC} {ARC} v{} a{ARC} {ARC} 0 0 0 {ARC} {ARC} \
+                             M{} {} h{rest} a{ARC} {ARC} 0 0 0 {ARC} -{ARC} v-{} a{ARC} {ARC} 0 0 1 {ARC} -{ARC}",
+                            dy - 2 * ARC,
+                            x + 2 * ARC + item.width(),
+                            y + dy,
+                            dy - 2 * ARC
+                        ),
+                    };
+                    *out += &format!("<path d=\"{path}\"/>");
+                    item.draw(x + 2 * ARC, y + dy, out);
+                }
+            }
+            Diagram::Loop(item, label) => {
+                let w = item.width();
+                let dy = loop_depth(item);
+                let end = x + ARC + w;
+                *out += &format!(
+                    "<path d=\"M{x} {y} h{ARC} M{end} {y} h{ARC} M{end} {y} \
+                     a{ARC} {ARC} 0 0 1 {ARC} {ARC} v{} a{ARC} {ARC} 0 0 1 -{ARC} {ARC} h-{w} \
+                     a{ARC} {ARC} 0 0 1 -{ARC} -{ARC} v-{} a{ARC} {ARC} 0 0 1 {ARC} -{ARC}\"/>",
+                    dy - 2 * ARC,
+                    dy - 2 * ARC
+                );
+                if let Some(label) = label {
+                    *out += &format!(
+                        "<text class=\"times\" x=\"{}\" y=\"{}\">{}</text>",
+                        x + ARC + w / 2,
+                        y + dy + GAP,
+                        escape(label)
+                    );
+                }
+                item.draw(x + ARC, y, out);
+            }
+        }
+    }
+}
+
+/// How far below the track the loop back of `item` runs.
+fn loop_depth(item: &Diagram) -> i32 {
+    (item.down() + GAP).max(2 * ARC)
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chars::digits1;
+    use crate::{one_of, prefix, recursive};
+
+    #[test]
+    fn diagram_test() {
+        let value = recursive(|value| {
+            let list = value
+                .sep_by(prefix(","))
+                .delimited_by(prefix("("), prefix(")"));
+            list.map(|_| ()) | digits1().named("number").map(|_| ())
+        });
+        let svg = value.named("value").diagram();
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+        assert!(svg.ends_with("</svg>\n"));
+        for label in ["value", "number", "(", ",", ")", "digits"] {
+            assert!(svg.contains(&format!(">{label}</text>")), "{label}");
+        }
+        // number is a rule of its own, listed below value
+        let rules = |svg: &str| svg.matches("class=\"title\"").count();
+        assert_eq!(rules(&svg), 2);
+        assert!(svg.find(">value</text>") < svg.find(">digits</text>"));
+
+        let counted = one_of("<&").repeated(2..=3);
+        let svg = counted.diagram();
+        assert_eq!(rules(&svg), 1);
+        assert!(svg.contains(">[&lt;&amp;]</text>"));
+        assert!(svg.contains(">{2,3}</text>"));
+    }
+
+    #[test]
+    fn layout_test() {
+        let item = || Diagram::Terminal("ab".to_string());
+        assert_eq!(item().width(), 2 * CHAR + 2 * ARC);
+        let seq = Diagram::Seq(vec![item(), item()]);
+        assert_eq!(seq.width(), 2 * item().width() + ARC);
+
+        let choice = Diagram::Choice(vec![Diagram::Skip, seq, item()]);
+        assert_eq!(choice.up(), 0);
+        // the first branch is far enough below for the arcs to fit
+        assert_eq!(choice.branches(), [0, 2 * ARC, 2 * ARC + 2 * HALF + GAP]);
+        assert_eq!(choice.down(), 2 * ARC + 2 * HALF + GAP + HALF);
+
+        let repeat = Diagram::Loop(Box::new(item()), None);
+        assert_eq!(repeat.down(), 2 * ARC);
+    }
+}
@@ -0,0 +1,266 @@
+//! Choosing between alternatives by the next char.
+//!
+//! [`Parser::or`] and [`choice`](crate::choice) skip the alternatives that
+//! can't start with the next char, as far as their structure tells: the
+//! literals, char sets, sequences, alternatives and repetitions they are
+//! made of. Those would fail without consuming anything, and when all others
+//! fail as well, their part of the error is told from that structure too.
+//! Grammars of many keywords thus only try the alternatives that may match.
+//! Alternatives failing in their own way, such as those given a
+//! [`label`](Parser::label), are run for their error instead.
+//!
+//! The skipping relies on alternatives failing without being committed when
+//! their first char doesn't match, which a [`cut`](crate::cut) right at
+//! their start breaks. Such alternatives make those after them unreachable
+//! anyway.
+//!
+//! [`switch_on_first_char`] dispatches on chars given by hand, for parsers
+//! without a structure to tell.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::combinator::Parse;
+use crate::shape::Shape;
+use crate::{choice, Failure, Merge, Needed, Parser, ParserInput, Ptr};
+
+/// Whether a parser whose inputs start with one of `first`, sorted, can be
+/// told to fail on `input` without running it. Never when more input may
+/// follow.
+pub(crate) fn excluded(first: &[char], input: &ParserInput) -> bool {
+    if input.partial {
+        return false;
+    }
+    match input.text().chars().next() {
+        Some(c) => first.binary_search(&c).is_err(),
+        None => true,
+    }
+}
+
+/// The failure of a parser of `shape` on `input`, which it is
+/// [`excluded`] from, if its shape tells it.
+pub(crate) fn failure(shape: &Shape, input: &ParserInput) -> Option<Failure> {
+    failure_within(shape, input.text().chars().next(), 8)
+}
+
+/// [`failure`] before the char `next`, looking into recursive parsers
+/// `depth` deep as [`first`](crate::shape::first) does.
+fn failure_within(shape: &Shape, next: Option<char>, depth: usize) -> Option<Failure> {
+    match shape {
+        Shape::Literal(text) => {
+            let desc = format!("expected {text}");
+            Some(Failure {
+                truncated: next.is_none(),
+                ..Failure::expecting(format!("{text:?}"), desc)
+            })
+        }
+        Shape::OneOf(chars) => {
+            let expected = format!("one of {chars:?}");
+            let desc = match next {
+                Some(c) => format!("expected {expected}, got {c:?}"),
+                None => format!("expected {expected}, got end of input"),
+            };
+            Some(Failure::expecting(expected, desc))
+        }
+        Shape::Seq(items) => failure_within(items.first()?, next, depth),
+        Shape::Alt(items) => {
+            let mut failures = items.iter().map(|item| failure_within(item, next, depth));
+            let first = failures.next()??;
+            failures.try_fold(first, |e, e_| Some(e.merge(e_?)))
+        }
+        Shape::Named(_, shape)
+        | Shape::Ahead {
+            shape,
+            negated: false,
+        } => failure_within(shape, next, depth),
+        Shape::Rule(cell) if depth > 0 => failure_within(cell.get()?.as_ref()?, next, depth - 1),
+        Shape::Recurse(cell) if depth > 0 => {
+            let cell = cell.upgrade()?;
+            failure_within(cell.get()?.as_ref()?, next, depth - 1)
+        }
+        _ => None,
+    }
+}
+
+/// `p | q` skipping `p` when its inputs, starting with one of `first`, can't
+/// match.
+pub(crate) fn or<'a, A: 'a>(first: Vec<char>, p: Parser<'a, A>, q: Parser<'a, A>) -> Parser<'a, A> {
+    let either = Parse::or(p.clone(), q.clone());
+    Parser {
+        run: Ptr::new(move |input| {
+            if !excluded(&first, &input) {
+                return either.parse(input).into();
+            }
+            let pos = input.pos;
+            let (input__, e_) = match (q.run)(input.clone()) {
+                (input__, Err(e_)) if input__.pos == pos => (input__, e_),
+                // where `p` would have failed
+                output => return output,
+            };
+            let failure = p.shape.as_deref().and_then(|shape| failure(shape, &input));
+            let output = match failure {
+                Some(e) => (input, Err(e)),
+                None => (p.run)(input),
+            };
+            match output {
+                (input_, Err(e)) if !e.fatal => {
+                    let (input, e) = Merge::Farthest.pick((input_, e), (input__, e_));
+                    (input, Err(e))
+                }
+                output => output,
+            }
+        }),
+        shape: None,
+    }
+}
+
+/// Runs the parser of the first case whose chars the next char is one of,
+/// and only that one. When the next char is none of them, or at the end of
+/// the input, all of them are run as by [`choice`] for their errors.
+///
+/// ```
+/// use parcoom::{chars, prefix, run, switch_on_first_char};
+///
+/// let value = switch_on_first_char(vec![
+///     ("0123456789", chars::digits1().to_owned()),
+///     ("\"", prefix("\"") >> chars::rest_of_line().to_owned()),
+///     ("tf", (prefix("true") | prefix("false")).map(str::to_string)),
+/// ]);
+/// assert_eq!(run(value.clone(), "false".to_string()), Ok("false".to_string()));
+/// assert_eq!(run(value.clone(), "42".to_string()), Ok("42".to_string()));
+/// assert!(run(value, "x".to_string()).is_err());
+/// ```
+pub fn switch_on_first_char<'a, A: 'a>(cases: Vec<(&str, Parser<'a, A>)>) -> Parser<'a, A> {
+    let chars: Vec<String> = cases
+        .iter()
+        .map(|(chars, _)| String::from(*chars))
+        .collect();
+    let parsers: Vec<_> = cases.into_iter().map(|(_, p)| p).collect();
+    let all = choice(parsers.clone());
+    Parser {
+        run: Ptr::new(move |input| {
+            let Some(c) = input.text().chars().next() else {
+                if input.partial {
+                    return (input, Err(Failure::incomplete(Needed::Size(1))));
+                }
+                return (all.run)(input);
+            };
+            match chars.iter().position(|chars| chars.contains(c)) {
+                Some(i) => (parsers[i].run)(input),
+                None => (all.run)(input),
+            }
+        }),
+        shape: all.shape.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chars::digits1;
+    use crate::{keyword, one_of, prefix, recursive, run, run_streaming, StreamResult};
+    use alloc::string::ToString;
+    use alloc::vec;
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn or_dispatch_test() {
+        let tried = Ptr::new(AtomicUsize::new(0));
+        let counted = |p: Parser<'static, &'static str>| {
+            let tried = tried.clone();
+            let shape = p.shape.clone();
+            Parser {
+                run: Ptr::new(move |input| {
+                    tried.fetch_add(1, Ordering::Relaxed);
+                    (p.run)(input)
+                }),
+                shape,
+            }
+        };
+        let keywords = ["if", "else", "while", "return", "let"];
+        let statement = keywords
+            .into_iter()
+            .map(|k| counted(keyword(k)))
+            .reduce(|p, q| p | q)
+            .unwrap();
+        assert_eq!(run(statement.clone(), "let".to_string()), Ok("let"));
+        assert_eq!(tried.load(Ordering::Relaxed), 1);
+        assert_eq!(run(statement.clone(), "else".to_string()), Ok("else"));
+        assert_eq!(tried.load(Ordering::Relaxed), 2);
+
+        // errors are the same as those of trying all of them, without
+        // trying them
+        tried.store(0, Ordering::Relaxed);
+        let e = run(statement.clone(), "loop".to_string()).unwrap_err();
+        assert_eq!(e.expected.len(), 5);
+        assert_eq!(tried.load(Ordering::Relaxed), 1);
+        let e = run(statement.clone(), "lex".to_string()).unwrap_err();
+        assert_eq!(e.location.offset, 0);
+        assert_eq!(e.expected.len(), 5);
+        assert!(run(statement.clone(), "".to_string()).is_err());
+        let streamed = run_streaming(statement, "whi".to_string());
+        assert!(matches!(streamed, StreamResult::Incomplete(_)));
+
+        let group = || prefix("(") >> digits1() << prefix(")");
+        let dispatched = group() | digits1();
+        let tried_all = Parse::or(group(), digits1()).boxed();
+        for text in ["12", "(12)", "(12", "x", ""] {
+            let run = |p: &Parser<crate::Substr>| run(p.clone(), text.to_string());
+            assert_eq!(run(&dispatched), run(&tried_all), "{text}");
+        }
+
+        // labelled ones are run for their errors
+        tried.store(0, Ordering::Relaxed);
+        let labelled = counted(keyword("if").label("condition")) | counted(keyword("let"));
+        let e = run(labelled, "loop".to_string()).unwrap_err();
+        assert_eq!(e.expected, ["condition", "\"let\""]);
+        assert_eq!(tried.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn choice_dispatch_test() {
+        let value = recursive(|value| {
+            choice(vec![
+                value
+                    .sep_by(prefix(","))
+                    .delimited_by(prefix("["), prefix("]"))
+                    .map(|v| v.len()),
+                one_of("xyz").map(|_| 0),
+                prefix("-").optional().map(|_| 1),
+            ])
+        });
+        assert_eq!(run(value.clone(), "[x,[],y]".to_string()), Ok(3));
+        assert_eq!(run(value.clone(), "-".to_string()), Ok(1));
+        assert_eq!(run(value.clone(), "".to_string()), Ok(1));
+        assert_eq!(run(value, "q".to_string()), Ok(1));
+
+        let group = || (prefix("(") >> digits1() << prefix(")")).to_owned();
+        let word = || one_of("xyz").map(|c| c.to_string());
+        let number = || digits1().to_owned();
+        let p = choice(vec![group(), word(), number()]);
+        let tried_all = Parse::or(Parse::or(group(), word()), number()).boxed();
+        for text in ["(1)", "x", "", "-", "(-"] {
+            let run = |p: &Parser<String>| run(p.clone(), text.to_string());
+            assert_eq!(run(&p), run(&tried_all), "{text}");
+        }
+    }
+
+    #[test]
+    fn switch_on_first_char_test() {
+        let p = switch_on_first_char(vec![
+            ("ab", prefix("a") | prefix("b")),
+            ("a", prefix("aa")),
+            ("c", prefix("cd")),
+        ]);
+        // only the first matching case
+        assert!(run(p.clone(), "aa".to_string()).is_ok());
+        assert!(run(p.clone() << crate::eof(), "aa".to_string()).is_err());
+        let e = run(p.clone(), "ce".to_string()).unwrap_err();
+        assert_eq!(e.expected, ["\"cd\"".to_string()]);
+        let e = run(p.clone(), "x".to_string()).unwrap_err();
+        assert_eq!(e.expected.len(), 4);
+        let streamed = run_streaming(p, "".to_string());
+        assert!(matches!(streamed, StreamResult::Incomplete(_)));
+    }
+}
@@ -0,0 +1,159 @@
+//! Key-value entries, the bulk of small config formats.
+//!
+//! ```
+//! use std::collections::HashMap;
+//! use parcoom::chars::{alpha, digits1, line_ending};
+//! use parcoom::{entries, key_value, run, Duplicates, Parser};
+//!
+//! let key = alpha().many1().map(String::from_iter);
+//! let entry = key_value(key, "=", digits1().to_owned());
+//! let config: Parser<HashMap<String, String>> = entries(entry, line_ending(), Duplicates::Last);
+//! let parsed = run(config, "a = 1\nb=2\na =3".to_string()).unwrap();
+//! assert_eq!(parsed["a"], "3");
+//! ```
+
+use alloc::format;
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::{input_sub, padded, prefix, Failure, Parser, Ptr};
+
+/// What [`entries`] does with the entries of keys that came before.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Duplicates {
+    /// Keeps every entry, in order.
+    Keep,
+    /// Keeps the first entry of each key, ignoring those after.
+    First,
+    /// Keeps the value of the last entry of each key, where the first was.
+    Last,
+    /// Fails at the second entry of a key, without backtracking.
+    Reject,
+}
+
+/// `key`, then `sep`, then `value`, yielding the key and the value.
+pub fn separated_pair<'a, K: 'a, S: 'a, V: 'a>(
+    key: Parser<'a, K>,
+    sep: Parser<'a, S>,
+    value: Parser<'a, V>,
+) -> Parser<'a, (K, V)> {
+    (key << sep) + value
+}
+
+/// `key`, then `sep` with the junk around it skipped, then `value`, as in
+/// `name = value`.
+pub fn key_value<'a, K: 'a, V: 'a>(
+    key: Parser<'a, K>,
+    sep: &'static str,
+    value: Parser<'a, V>,
+) -> Parser<'a, (K, V)> {
+    separated_pair(key, padded(prefix(sep)), value)
+}
+
+/// Entries separated by `sep`, and possibly none, collected into a `Vec` or
+/// a map. Keys that come again are dealt with according to `duplicates`,
+/// before collecting.
+pub fn entries<'a, K, V, S, C>(
+    entry: Parser<'a, (K, V)>,
+    sep: Parser<'a, S>,
+    duplicates: Duplicates,
+) -> Parser<'a, C>
+where
+    K: PartialEq + fmt::Debug + 'a,
+    V: 'a,
+    S: 'a,
+    C: FromIterator<(K, V)> + 'a,
+{
+    let entries = entry.spanned().sep_by(sep);
+    Parser {
+        run: Ptr::new(move |input| {
+            let (input_, parsed) = match (entries.run)(input.clone()) {
+                (input_, Ok(parsed)) => (input_, parsed),
+                (input_, Err(e)) => return (input_, Err(e)),
+            };
+            let mut kept: Vec<(K, V)> = Vec::with_capacity(parsed.len());
+            for entry in parsed {
+                let (key, value) = entry.value;
+                let Some(i) = kept.iter().position(|(k, _)| *k == key) else {
+                    kept.push((key, value));
+                    continue;
+                };
+                match duplicates {
+                    Duplicates::Keep => kept.push((key, value)),
+                    Duplicates::First => {}
+                    Duplicates::Last => kept[i].1 = value,
+                    Duplicates::Reject => {
+                        let e = Failure {
+                            fatal: true,
+                            ..Failure::from(format!("duplicate key {key:?}"))
+                        };
+                        let at = input_sub(entry.span.start - input.pos, &input);
+                        return (at, Err(e));
+                    }
+                }
+            }
+            (input_, Ok(kept.into_iter().collect()))
+        }),
+        shape: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chars::{alpha, digits1};
+    use crate::{run, with_junk, Junk};
+    use alloc::collections::BTreeMap;
+    use alloc::string::{String, ToString};
+    use alloc::vec;
+
+    fn entry<'a>() -> Parser<'a, (char, String)> {
+        key_value(alpha(), "=", digits1().to_owned())
+    }
+
+    #[test]
+    fn key_value_test() {
+        let parsed = run(entry(), "a =\t12".to_string());
+        assert_eq!(parsed, Ok(('a', "12".to_string())));
+        let pair = separated_pair(alpha(), prefix(":"), alpha());
+        assert_eq!(run(pair.clone(), "a:b".to_string()), Ok(('a', 'b')));
+        assert!(run(pair, "a : b".to_string()).is_err());
+
+        // the padding is the junk of the grammar
+        let junk = Junk::new().block_comment("(*", "*)").build();
+        let parsed = run(with_junk(junk, entry()), "a(* x *)=1".to_string());
+        assert_eq!(parsed, Ok(('a', "1".to_string())));
+    }
+
+    #[test]
+    fn entries_test() {
+        let text = "a=1;b=2;a=3";
+        let parse = |duplicates| {
+            let parser = entries(entry(), prefix(";"), duplicates);
+            let parsed: Vec<(char, String)> = run(parser, text.to_string()).unwrap();
+            let values: String = parsed.into_iter().map(|(_, v)| v).collect();
+            values
+        };
+        assert_eq!(parse(Duplicates::Keep), "123");
+        assert_eq!(parse(Duplicates::First), "12");
+        assert_eq!(parse(Duplicates::Last), "32");
+
+        let parser = entries(entry(), prefix(";"), Duplicates::Reject);
+        let parsed: Result<BTreeMap<_, _>, _> = run(parser, text.to_string());
+        let e = parsed.unwrap_err();
+        assert_eq!(
+            (e.desc.as_str(), e.location.offset),
+            ("duplicate key 'a'", 8)
+        );
+
+        let parser = entries(entry(), prefix(";"), Duplicates::Reject);
+        let parsed: Result<BTreeMap<_, _>, _> = run(parser, "".to_string());
+        assert_eq!(parsed, Ok(BTreeMap::new()));
+        let parser = entries(entry(), prefix(";"), Duplicates::Last);
+        let parsed: Result<BTreeMap<_, _>, _> = run(parser, "b=1;a=2;b=3".to_string());
+        assert_eq!(
+            parsed.map(|map| map.into_iter().collect::<Vec<_>>()),
+            Ok(vec![('a', "2".to_string()), ('b', "3".to_string())])
+        );
+    }
+}
@@ -0,0 +1,345 @@
+//! Reporting failures as an error type of the grammar's own.
+//!
+//! Parsers are not generic over the error type: they fail with a
+//! [`Failure`], which [`run_as`] converts once the run is over, following
+//! the failures of the alternatives that were tried. Grammars raise errors
+//! of their own type mid-parse with [`fail_with`], [`Failure::custom`] or
+//! [`Parser::map_res`].
+
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::any::Any;
+use core::fmt;
+use core::str::FromStr;
+
+use crate::{
+    expected_desc, make_input, Failure, Location, MaybeSync, Parse, Parser, ParserError,
+    ParserInput, Ptr,
+};
+
+/// An error type [`run_as`] can report failures as, for grammars that want
+/// to attach their own diagnostics. [`ParserError`] is one.
+pub trait ParseError: Sized {
+    /// A failure at `location` where one of `expected` would have been
+    /// accepted, e.g. `"let"` or `identifier`.
+    fn from_expected(location: Location, desc: &str, expected: &[String]) -> Self;
+
+    /// A failure that isn't about what was expected, such as a value out of
+    /// range. `error` is what a [`Parser::map_res`] function failed with,
+    /// if that is how it came about.
+    fn from_external_error(location: Location, desc: &str, error: Option<&dyn Any>) -> Self;
+
+    /// The error in the context of [`Parser::context`], applied innermost
+    /// context first.
+    fn add_context(self, context: &str) -> Self;
+
+    /// Combines the errors of two alternatives that gave up at the same
+    /// position, `self` being the one tried first.
+    fn merge(self, other: Self) -> Self;
+
+    /// The error, marked as being at the end of the input, where more of it
+    /// might have let the parser go on. Unchanged by default.
+    fn at_end_of_input(self) -> Self {
+        self
+    }
+}
+
+impl ParseError for ParserError {
+    fn from_expected(location: Location, desc: &str, expected: &[String]) -> Self {
+        ParserError {
+            desc: desc.to_string(),
+            location,
+            context: Vec::new(),
+            expected: expected.to_vec(),
+            cause: None,
+            at_end: false,
+        }
+    }
+
+    fn from_external_error(location: Location, desc: &str, _error: Option<&dyn Any>) -> Self {
+        ParserError::from_expected(location, desc, &[])
+    }
+
+    fn add_context(mut self, context: &str) -> Self {
+        self.context.insert(0, context.to_string());
+        self
+    }
+
+    /// Like [`run`](crate::run): the expectations are merged when both
+    /// errors have some, otherwise `other` wins.
+    fn merge(self, other: Self) -> Self {
+        if self.expected.is_empty() || other.expected.is_empty() {
+            return other;
+        }
+        let mut expected = self.expected;
+        for item in other.expected {
+            if !expected.contains(&item) {
+                expected.push(item);
+            }
+        }
+        ParserError {
+            desc: expected_desc(&expected),
+            expected,
+            ..other
+        }
+    }
+
+    fn at_end_of_input(self) -> Self {
+        ParserError {
+            at_end: true,
+            ..self
+        }
+    }
+}
+
+/// The error of a [`Failure::custom`], such as what a [`Parser::map_res`]
+/// function failed with. Behind a thin pointer, to keep failures small.
+#[derive(Clone)]
+pub(crate) struct External(Arc<Box<dyn Any>>);
+
+impl fmt::Debug for External {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("External")
+    }
+}
+
+impl PartialEq for External {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+fn convert<E: ParseError>(e: &ParserError, external: Option<&External>) -> E {
+    let error = match external {
+        None if !e.expected.is_empty() => E::from_expected(e.location, &e.desc, &e.expected),
+        _ => E::from_external_error(e.location, &e.desc, external.map(|x| &**x.0)),
+    };
+    let error = match e.at_end {
+        true => error.at_end_of_input(),
+        false => error,
+    };
+    e.context
+        .iter()
+        .rev()
+        .fold(error, |error, context| error.add_context(context))
+}
+
+impl Failure {
+    /// A failure with an error of the grammar's own, which [`run_as`] hands
+    /// to [`ParseError::from_external_error`]. [`run`](crate::run)
+    /// describes it with its `Display` impl.
+    pub fn custom<X: fmt::Display + 'static>(error: X) -> Failure {
+        let mut failure = Failure::from(error.to_string());
+        failure.external = Some(External(Arc::new(Box::new(error))));
+        failure
+    }
+}
+
+/// Converts the failure of a run ending at `input`, merging the errors of
+/// the alternatives it is made of.
+fn convert_failure<E: ParseError>(mut e: Failure, input: &ParserInput) -> E {
+    match e.alternatives.take() {
+        Some(alternatives) => {
+            let (first, second) = *alternatives;
+            // The context the failure got after the alternatives were merged.
+            let outer = e.context.split_off(second.context.len());
+            let error = convert_failure::<E>(first, input).merge(convert_failure(second, input));
+            outer
+                .iter()
+                .fold(error, |error, context| error.add_context(context))
+        }
+        None => {
+            let external = e.external.clone();
+            convert(&e.at(input), external.as_ref())
+        }
+    }
+}
+
+/// Like [`run`](crate::run), but reports failures as `E`. Like `run` too,
+/// the first of the errors `p` recovered from is the one returned.
+pub fn run_as<A, E: ParseError>(p: impl Parse<A>, input: String) -> Result<A, E> {
+    let (input, result) = p.parse(make_input(input)).into();
+    if let Some(e) = input.errors().next() {
+        return Err(convert(e, None));
+    }
+    result.map_err(|e| convert_failure(e, &input))
+}
+
+/// Fails with `error`, an error of the grammar's own, see
+/// [`Failure::custom`].
+pub fn fail_with<'a, T, X: fmt::Display + Clone + MaybeSync + 'static>(error: X) -> Parser<'a, T> {
+    Parser {
+        run: Ptr::new(move |input| (input, Err(Failure::custom(error.clone())))),
+        shape: None,
+    }
+}
+
+impl<'a, T: 'a> Parser<'a, T> {
+    /// Maps the value with `f`. If `f` fails the parser fails at the start
+    /// of the value, described by the error's `Display` impl; [`run_as`]
+    /// hands the error itself to [`ParseError::from_external_error`].
+    pub fn map_res<B: 'a, X: fmt::Display + 'static>(
+        self,
+        f: impl Fn(T) -> Result<B, X> + MaybeSync + 'a,
+    ) -> Parser<'a, B> {
+        Parser {
+            run: Ptr::new(move |input| match (self.run)(input.clone()) {
+                (input_, Ok(value)) => match f(value) {
+                    Ok(x) => (input_, Ok(x)),
+                    Err(error) => (input, Err(Failure::custom(error))),
+                },
+                (input_, Err(e)) => (input_, Err(e)),
+            }),
+            shape: None,
+        }
+    }
+}
+
+/// Parses the text of `lexeme` into a `T`, such as an `f64` or an
+/// `IpAddr`, failing like [`Parser::map_res`] when `T::from_str` does.
+///
+/// ```
+/// use core::net::Ipv4Addr;
+/// use parcoom::{chars, from_str, run};
+///
+/// let addr = from_str::<Ipv4Addr, _>(chars::take_while1(|c: char| c == '.' || c.is_ascii_digit()));
+/// assert_eq!(run(addr.clone(), "127.0.0.1".to_string()), Ok(Ipv4Addr::LOCALHOST));
+/// let e = run(addr, "127.0.0.300".to_string()).unwrap_err();
+/// assert_eq!(e.desc, "invalid IPv4 address syntax");
+/// ```
+pub fn from_str<'a, T, L>(lexeme: Parser<'a, L>) -> Parser<'a, T>
+where
+    T: FromStr + 'a,
+    T::Err: fmt::Display + 'static,
+    L: AsRef<str> + 'a,
+{
+    lexeme.map_res(|text| text.as_ref().parse::<T>())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chars::digits1;
+    use crate::number::unsigned;
+    use crate::{choice, cut, prefix, run};
+    use core::num::ParseIntError;
+
+    #[derive(Debug, PartialEq)]
+    enum ConfigError {
+        Expected(usize, Vec<String>),
+        BadPort(usize, ParseIntError),
+        Other(usize, String),
+        In(String, Box<ConfigError>),
+    }
+
+    impl ParseError for ConfigError {
+        fn from_expected(location: Location, _desc: &str, expected: &[String]) -> Self {
+            ConfigError::Expected(location.offset, expected.to_vec())
+        }
+
+        fn from_external_error(location: Location, desc: &str, error: Option<&dyn Any>) -> Self {
+            match error.and_then(|e| e.downcast_ref::<ParseIntError>()) {
+                Some(e) => ConfigError::BadPort(location.offset, e.clone()),
+                None => ConfigError::Other(location.offset, desc.to_string()),
+            }
+        }
+
+        fn add_context(self, context: &str) -> Self {
+            ConfigError::In(context.to_string(), Box::new(self))
+        }
+
+        fn merge(self, other: Self) -> Self {
+            match (self, other) {
+                (ConfigError::Expected(at, mut expected), ConfigError::Expected(_, more)) => {
+                    expected.extend(more);
+                    ConfigError::Expected(at, expected)
+                }
+                (_, other) => other,
+            }
+        }
+    }
+
+    #[test]
+    fn run_as_test() {
+        let port = || digits1().map_res(|s| s.parse::<u16>()).context("port");
+        let parsed: Result<u16, ConfigError> = run_as(prefix(":") >> port(), ":70000".to_string());
+        let error = "70000".parse::<u16>().unwrap_err();
+        assert_eq!(
+            parsed,
+            Err(ConfigError::In(
+                "port".to_string(),
+                Box::new(ConfigError::BadPort(1, error))
+            ))
+        );
+
+        let parser = choice(vec![prefix("a"), prefix("b")]);
+        let parsed: Result<&str, ConfigError> = run_as(parser, "c".to_string());
+        let expected = vec!["\"a\"".to_string(), "\"b\"".to_string()];
+        assert_eq!(parsed, Err(ConfigError::Expected(0, expected)));
+
+        let parsed: Result<u8, ConfigError> = run_as(unsigned::<u8>(), "300".to_string());
+        let desc = "300 does not fit into u8".to_string();
+        assert_eq!(parsed, Err(ConfigError::Other(0, desc)));
+    }
+
+    #[test]
+    fn fail_with_test() {
+        let error = "x".parse::<u16>().unwrap_err();
+        let port = prefix("0") >> cut(fail_with::<u16, _>(error.clone())) | from_str(digits1());
+        let parsed: Result<u16, ConfigError> = run_as(port.clone(), "0".to_string());
+        assert_eq!(parsed, Err(ConfigError::BadPort(1, error)));
+        let e = run(port.clone(), "0".to_string()).unwrap_err();
+        assert_eq!(e.desc, "invalid digit found in string");
+        assert_eq!(run(port, "80".to_string()), Ok(80));
+
+        // the context around merged alternatives
+        let parser = (prefix("a") | prefix("b")).context("letter");
+        let parsed: Result<&str, ConfigError> = run_as(parser, "d".to_string());
+        let expected = ["\"a\"", "\"b\""].map(String::from).to_vec();
+        let letter = ConfigError::In(
+            "letter".to_string(),
+            Box::new(ConfigError::Expected(0, expected)),
+        );
+        assert_eq!(parsed, Err(letter));
+    }
+
+    #[test]
+    fn from_str_test() {
+        let float = from_str::<f64, _>(crate::chars::take_while1(|c: char| c != ' '));
+        let pair = (float.clone() << prefix(" ")) + float;
+        assert_eq!(run(pair.clone(), "1.5 -2e3".to_string()), Ok((1.5, -2e3)));
+        let e = run(pair, "1.5 2x".to_string()).unwrap_err();
+        assert_eq!(e.desc, "invalid float literal");
+        assert_eq!(e.location.offset, 4);
+
+        let port = from_str::<u16, _>(digits1());
+        let parsed: Result<u16, ConfigError> = run_as(port, "70000".to_string());
+        let error = "70000".parse::<u16>().unwrap_err();
+        assert_eq!(parsed, Err(ConfigError::BadPort(0, error)));
+        assert_eq!(
+            run(from_str::<char, _>(prefix("x")), "x".to_string()),
+            Ok('x')
+        );
+    }
+
+    #[test]
+    fn parser_error_test() {
+        let parser = || (prefix("x=") >> digits1().map_res(|s| s.parse::<u8>())).context("x");
+        for input in ["x=1", "x=256", "y"] {
+            let parsed: Result<u8, ParserError> = run_as(parser(), input.to_string());
+            assert_eq!(parsed, run(parser(), input.to_string()));
+        }
+
+        let parser = || {
+            let bc = (prefix("b") >> prefix("c")).context("bc");
+            let a = prefix("a").map_res(|a| a.parse::<u8>());
+            choice(vec![a.to("a"), bc, prefix("b").context("b"), prefix("d")]).context("letter")
+        };
+        for input in ["a", "bd", "x"] {
+            let parsed: Result<&str, ParserError> = run_as(parser(), input.to_string());
+            assert_eq!(parsed, run(parser(), input.to_string()));
+        }
+    }
+}
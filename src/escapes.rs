@@ -0,0 +1,259 @@
+//! Decoding escape sequences, for the insides of string, char and other
+//! literals.
+//!
+//! ```
+//! use parcoom::escapes::Decoder;
+//! use parcoom::{prefix, run};
+//!
+//! let decoder = Decoder::new().char('n', '\n').char('"', '"').hex('x', 2).braced('u');
+//! let string = prefix("\"") >> decoder.build(|c| c == '"') << prefix("\"");
+//! let parsed = run(string, r#""a\"\n\x41\u{1F600}""#.to_string());
+//! assert_eq!(parsed, Ok("a\"\nA😀".to_string()));
+//! ```
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::chars::CharPredicate;
+use crate::limits::counted;
+use crate::{input_sub, Failure, Needed, Parser, ParserInput, Ptr};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Escape {
+    Char(char),
+    /// Exactly that many hex digits.
+    Hex(usize),
+    /// One to six hex digits in braces.
+    Braced,
+}
+
+/// Builds parsers decoding text with escapes. An escape is the introducer,
+/// `\` by default, followed by one of the chars given to the builder
+/// methods. Others are unknown escapes, which fail.
+#[derive(Debug, Clone)]
+pub struct Decoder {
+    introducer: char,
+    escapes: Vec<(char, Escape)>,
+}
+
+impl Default for Decoder {
+    fn default() -> Self {
+        Decoder {
+            introducer: '\\',
+            escapes: Vec::new(),
+        }
+    }
+}
+
+impl Decoder {
+    pub fn new() -> Self {
+        Decoder::default()
+    }
+
+    pub fn introducer(mut self, introducer: char) -> Self {
+        self.introducer = introducer;
+        self
+    }
+
+    /// `key` after the introducer stands for `c`, as `n` does for a line
+    /// feed.
+    pub fn char(self, key: char, c: char) -> Self {
+        self.with(key, Escape::Char(c))
+    }
+
+    /// `key` after the introducer is followed by exactly `digits` hex digits
+    /// of a char, as in `\x41`.
+    pub fn hex(self, key: char, digits: usize) -> Self {
+        self.with(key, Escape::Hex(digits))
+    }
+
+    /// `key` after the introducer is followed by one to six hex digits of a
+    /// char in braces, as in `\u{1F600}`.
+    pub fn braced(self, key: char) -> Self {
+        self.with(key, Escape::Braced)
+    }
+
+    fn with(mut self, key: char, escape: Escape) -> Self {
+        self.escapes.retain(|&(k, _)| k != key);
+        self.escapes.push((key, escape));
+        self
+    }
+
+    /// Decodes the escape at the start of `text`, past the introducer, into
+    /// its char and length.
+    fn decode(&self, text: &str, partial: bool) -> Result<(char, usize), Failure> {
+        let incomplete = || Failure::incomplete(Needed::Unknown);
+        let Some(key) = text.chars().next() else {
+            return Err(match partial {
+                true => incomplete(),
+                false => Failure {
+                    truncated: true,
+                    ..Failure::expecting(
+                        "an escape".to_string(),
+                        format!("unterminated escape {}", self.introducer),
+                    )
+                },
+            });
+        };
+        let escape = self.escapes.iter().find(|&&(k, _)| k == key);
+        let Some(&(_, escape)) = escape else {
+            let desc = format!("unknown escape {}{key}", self.introducer);
+            return Err(Failure::from(desc));
+        };
+        let rest = &text[key.len_utf8()..];
+        let invalid = || Failure::from(format!("invalid escape {}{key}", self.introducer));
+        // Counted on the bytes, the text is only sliced where they are digits.
+        let hex_digits = |s: &str, max: usize| {
+            s.bytes()
+                .take(max)
+                .take_while(u8::is_ascii_hexdigit)
+                .count()
+        };
+        let (code, len) = match escape {
+            Escape::Char(c) => return Ok((c, key.len_utf8())),
+            Escape::Hex(digits) => match hex_digits(rest, digits) {
+                n if n == rest.len() && n < digits && partial => return Err(incomplete()),
+                n if n < digits => return Err(invalid()),
+                _ => (&rest[..digits], digits),
+            },
+            Escape::Braced => match rest.strip_prefix('{') {
+                None if rest.is_empty() && partial => return Err(incomplete()),
+                None => return Err(invalid()),
+                Some(braced) => match hex_digits(braced, 7) {
+                    n if n == braced.len() && n <= 6 && partial => return Err(incomplete()),
+                    n if n == 0 || n > 6 || braced.as_bytes().get(n) != Some(&b'}') => {
+                        return Err(invalid())
+                    }
+                    n => (&braced[..n], n + 2),
+                },
+            },
+        };
+        let c = char::from_u32(u32::from_str_radix(code, 16).unwrap()).ok_or_else(invalid)?;
+        Ok((c, key.len_utf8() + len))
+    }
+
+    /// The char or escape at the start of the input and its length, `None`
+    /// at the end of the input.
+    fn next(&self, input: &ParserInput) -> Result<Option<(char, usize)>, Failure> {
+        let text = input.text();
+        let Some(c) = text.chars().next() else {
+            return Ok(None);
+        };
+        if c != self.introducer {
+            return Ok(Some((c, c.len_utf8())));
+        }
+        let at = c.len_utf8();
+        let (c, len) = self.decode(&text[at..], input.partial)?;
+        Ok(Some((c, at + len)))
+    }
+
+    /// Decodes text up to the first unescaped char matching `stop`, which is
+    /// left unconsumed, or the end of the input. Bad escapes fail committed,
+    /// at their introducer.
+    pub fn build<'a>(self, stop: impl CharPredicate + 'a) -> Parser<'a, String> {
+        counted(Parser {
+            run: Ptr::new(move |input| {
+                let mut value = String::new();
+                let mut i = 0;
+                loop {
+                    let rest = input_sub(i, &input);
+                    let escaped = rest.text().starts_with(self.introducer);
+                    match self.next(&rest) {
+                        Ok(Some((c, len))) if escaped || !stop.matches(c) => {
+                            value.push(c);
+                            i += len;
+                        }
+                        Ok(Some(_)) => return (rest, Ok(value)),
+                        Ok(None) if input.partial => {
+                            return (input, Err(Failure::incomplete(Needed::Unknown)))
+                        }
+                        Ok(None) => return (rest, Ok(value)),
+                        Err(e) if e.needed.is_some() => return (input, Err(e)),
+                        Err(e) => return (rest, Err(Failure { fatal: true, ..e })),
+                    }
+                }
+            }),
+            shape: None,
+        })
+    }
+
+    /// A single char or escape, as in a char literal.
+    pub fn one<'a>(self) -> Parser<'a, char> {
+        Parser {
+            run: Ptr::new(move |input| match self.next(&input) {
+                Ok(Some((c, len))) => (input_sub(len, &input), Ok(c)),
+                Ok(None) if input.partial => (input, Err(Failure::incomplete(Needed::Size(1)))),
+                Ok(None) => {
+                    let desc = "expected a char, found the end of the input".to_string();
+                    (input, Err(Failure::expecting("a char".to_string(), desc)))
+                }
+                Err(e) if e.needed.is_some() => (input, Err(e)),
+                Err(e) => (input, Err(Failure { fatal: true, ..e })),
+            }),
+            shape: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{prefix, run, run_streaming, StreamResult};
+
+    fn decoder() -> Decoder {
+        Decoder::new()
+            .char('n', '\n')
+            .char('\\', '\\')
+            .char('"', '"')
+            .hex('x', 2)
+            .braced('u')
+    }
+
+    #[test]
+    fn decoder_test() {
+        let string = || decoder().build(|c| c == '"');
+        let parsed = run(
+            string() << prefix("\""),
+            r#"\\\"\x7e\u{e9}\u{1F600}é""#.to_string(),
+        );
+        assert_eq!(parsed, Ok("\\\"~é😀é".to_string()));
+        assert_eq!(run(string(), "".to_string()), Ok("".to_string()));
+        let parser = string().then(prefix("\"!"));
+        assert_eq!(
+            run(parser, "a\"!".to_string()),
+            Ok(("a".to_string(), "\"!"))
+        );
+
+        let dollars = Decoder::new().introducer('$').char('$', '$').braced('x');
+        let parsed = run(dollars.build(|c| c == ';'), "$$5 is $x{41}".to_string());
+        assert_eq!(parsed, Ok("$5 is A".to_string()));
+
+        let one = |text: &str| run(decoder().one(), text.to_string());
+        assert_eq!(one("\\n"), Ok('\n'));
+        assert_eq!(one("é"), Ok('é'));
+        assert!(one("").is_err());
+    }
+
+    #[test]
+    fn decoder_error_test() {
+        let error = |text: &str| {
+            let e = run(decoder().build(|c| c == '"'), text.to_string()).unwrap_err();
+            (e.location.offset, e.desc)
+        };
+        assert_eq!(error("ab\\q"), (2, "unknown escape \\q".to_string()));
+        assert_eq!(error("\\x4"), (0, "invalid escape \\x".to_string()));
+        assert_eq!(error("\\xaé"), (0, "invalid escape \\x".to_string()));
+        assert_eq!(error("\\u{}"), (0, "invalid escape \\u".to_string()));
+        assert_eq!(error("\\u{d800}"), (0, "invalid escape \\u".to_string()));
+        assert_eq!(error("a\\"), (1, "unterminated escape \\".to_string()));
+        // committed to
+        let parser = decoder().one() | prefix("\\").to('?');
+        assert!(run(parser, "\\q".to_string()).is_err());
+
+        for text in ["ab", "a\\", "\\x4", "\\u{1F6"] {
+            let parsed = run_streaming(decoder().build(|c| c == '"'), text.to_string());
+            assert!(matches!(parsed, StreamResult::Incomplete(_)), "{text}");
+        }
+    }
+}
@@ -0,0 +1,155 @@
+//! Syntax tree events, for building lossless concrete syntax trees.
+//!
+//! Parsers marked with [`Parser::node`] and [`Parser::token`] emit events
+//! when run through [`run_events`], and the events can be folded into a
+//! tree, e.g. with rowan's `GreenNodeBuilder`. Other runs skip them.
+
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use crate::{finish, make_input, Parse, Parser, ParserError, ParserInput, Ptr};
+
+/// The kind of the tokens for text no [`Parser::token`] covers, such as
+/// the whitespace and comments [`padded`](crate::padded) parsers skip.
+pub const TRIVIA: &str = "trivia";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    StartNode(&'static str),
+    /// A token of the kind and the byte range of the source it covers.
+    Token(&'static str, Range<usize>),
+    FinishNode,
+}
+
+fn push(input: &mut ParserInput, event: Event) {
+    if let Some(events) = &mut input.events {
+        Arc::make_mut(events).push(event);
+    }
+}
+
+impl<'a, T: 'a> Parser<'a, T> {
+    /// Makes what the parser matches a node of `kind`.
+    pub fn node(self, kind: &'static str) -> Parser<'a, T> {
+        Parser {
+            run: Ptr::new(move |mut input| {
+                let events = input.events.clone();
+                push(&mut input, Event::StartNode(kind));
+                match (self.run)(input) {
+                    (mut input_, Ok(x)) => {
+                        push(&mut input_, Event::FinishNode);
+                        (input_, Ok(x))
+                    }
+                    (input_, Err(e)) => (ParserInput { events, ..input_ }, Err(e)),
+                }
+            }),
+            shape: None,
+        }
+    }
+
+    /// Makes what the parser matches a token of `kind`. The nodes and
+    /// tokens of the parser itself are dropped.
+    pub fn token(self, kind: &'static str) -> Parser<'a, T> {
+        Parser {
+            run: Ptr::new(move |input| {
+                let (start, events) = (input.pos, input.events.clone());
+                match (self.run)(input) {
+                    (input_, Ok(x)) => {
+                        let mut input_ = ParserInput { events, ..input_ };
+                        let span = start..input_.pos;
+                        push(&mut input_, Event::Token(kind, span));
+                        (input_, Ok(x))
+                    }
+                    (input_, Err(e)) => (ParserInput { events, ..input_ }, Err(e)),
+                }
+            }),
+            shape: None,
+        }
+    }
+}
+
+/// Runs `p` on `input` like [`run`](crate::run), along with the events it
+/// emitted. The consumed text is covered without gaps: the text between
+/// tokens becomes [`TRIVIA`] tokens, placed right before the next token,
+/// or after the last one. The events of parsers replayed by
+/// [`Parser::memoized`] are not emitted again.
+pub fn run_events<A>(p: impl Parse<A>, input: String) -> (Result<A, ParserError>, Vec<Event>) {
+    let input = ParserInput {
+        events: Some(Arc::default()),
+        ..make_input(input)
+    };
+    let (input, result) = p.parse(input).into();
+    let end = input.pos;
+    let events = input.events.clone().unwrap_or_default();
+    (finish((input, result)), fill_gaps(events.to_vec(), end))
+}
+
+fn fill_gaps(events: Vec<Event>, end: usize) -> Vec<Event> {
+    let mut filled = Vec::new();
+    let mut pos = 0;
+    let mut last_token = None;
+    for event in events {
+        if let Event::Token(_, span) = &event {
+            if span.start > pos {
+                filled.push(Event::Token(TRIVIA, pos..span.start));
+            }
+            pos = span.end;
+            last_token = Some(filled.len() + 1);
+        }
+        filled.push(event);
+    }
+    if end > pos {
+        let i = last_token.unwrap_or(filled.len());
+        filled.insert(i, Event::Token(TRIVIA, pos..end));
+    }
+    filled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chars::digits1;
+    use crate::{prefix, run};
+
+    #[test]
+    fn run_events_test() {
+        let number = || digits1().token("number").padded();
+        let plus = prefix("+").token("plus").padded();
+        let sum = (number() + (plus >> number()).node("operand").many()).node("sum");
+        let (parsed, events) = run_events(sum.clone(), "1 + 22 ".to_string());
+        assert_eq!(parsed, run(sum, "1 + 22 ".to_string()));
+        assert_eq!(
+            events,
+            vec![
+                Event::StartNode("sum"),
+                Event::Token("number", 0..1),
+                Event::StartNode("operand"),
+                Event::Token(TRIVIA, 1..2),
+                Event::Token("plus", 2..3),
+                Event::Token(TRIVIA, 3..4),
+                Event::Token("number", 4..6),
+                Event::Token(TRIVIA, 6..7),
+                Event::FinishNode,
+                Event::FinishNode,
+            ]
+        );
+    }
+
+    #[test]
+    fn backtracking_test() {
+        // the events of the failed alternative are dropped
+        let a = prefix("a").token("a");
+        let parser = (a.clone() + prefix("b")).node("ab") | (a + prefix("c")).node("ac");
+        let (_, events) = run_events(parser, "ac".to_string());
+        assert_eq!(
+            events,
+            vec![
+                Event::StartNode("ac"),
+                Event::Token("a", 0..1),
+                Event::Token(TRIVIA, 1..2),
+                Event::FinishNode,
+            ]
+        );
+    }
+}
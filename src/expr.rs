@@ -0,0 +1,253 @@
+//! Operator-precedence expression parsing.
+//!
+//! ```
+//! use parcoom::expr::{Assoc, PrattParser};
+//! use parcoom::{chars, prefix, run};
+//!
+//! let number = chars::digits1().map(|x| x.parse::<i64>().unwrap());
+//! let expr = PrattParser::new(number)
+//!     .infix(prefix("+"), 1, Assoc::Left, |a, b| a + b)
+//!     .infix(prefix("*"), 2, Assoc::Left, |a, b| a * b)
+//!     .infix(prefix("^"), 3, Assoc::Right, |a, b| a.pow(b as u32))
+//!     .prefix(prefix("-"), 4, |a| -a)
+//!     .postfix(prefix("!"), 5, |a| (1..=a).product())
+//!     .build();
+//!
+//! assert_eq!(run(expr, "1+2*3!^2".to_string()), Ok(73));
+//! ```
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+use crate::{DynFn, Failure, MaybeSync, Parser, ParserInput, Ptr};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Assoc {
+    Left,
+    Right,
+}
+
+struct Prefix<'a, T> {
+    op: Parser<'a, ()>,
+    prec: u8,
+    f: Box<DynFn<'a, T, T>>,
+}
+
+struct Infix<'a, T> {
+    op: Parser<'a, ()>,
+    prec: u8,
+    assoc: Assoc,
+    f: Box<DynFn<'a, (T, T), T>>,
+}
+
+/// Builds an expression parser out of an atom parser and operators.
+/// Operators with a higher precedence bind tighter.
+pub struct PrattParser<'a, T> {
+    atom: Parser<'a, T>,
+    prefix: Vec<Prefix<'a, T>>,
+    infix: Vec<Infix<'a, T>>,
+    postfix: Vec<Prefix<'a, T>>,
+}
+
+impl<'a, T: 'a> PrattParser<'a, T> {
+    pub fn new(atom: Parser<'a, T>) -> Self {
+        PrattParser {
+            atom,
+            prefix: Vec::new(),
+            infix: Vec::new(),
+            postfix: Vec::new(),
+        }
+    }
+
+    pub fn prefix<O: 'a>(
+        mut self,
+        op: Parser<'a, O>,
+        prec: u8,
+        f: impl Fn(T) -> T + MaybeSync + 'a,
+    ) -> Self {
+        self.prefix.push(Prefix {
+            op: op.map(|_| ()),
+            prec,
+            f: Box::new(f),
+        });
+        self
+    }
+
+    pub fn infix<O: 'a>(
+        mut self,
+        op: Parser<'a, O>,
+        prec: u8,
+        assoc: Assoc,
+        f: impl Fn(T, T) -> T + MaybeSync + 'a,
+    ) -> Self {
+        self.infix.push(Infix {
+            op: op.map(|_| ()),
+            prec,
+            assoc,
+            f: Box::new(move |(lhs, rhs)| f(lhs, rhs)),
+        });
+        self
+    }
+
+    pub fn postfix<O: 'a>(
+        mut self,
+        op: Parser<'a, O>,
+        prec: u8,
+        f: impl Fn(T) -> T + MaybeSync + 'a,
+    ) -> Self {
+        self.postfix.push(Prefix {
+            op: op.map(|_| ()),
+            prec,
+            f: Box::new(f),
+        });
+        self
+    }
+
+    pub fn build(self) -> Parser<'a, T> {
+        let this = Ptr::new(self);
+        Parser {
+            run: Ptr::new(move |input| this.expr(input, 0)),
+            shape: None,
+        }
+    }
+
+    /// Parses an expression made of operators binding at least as tight as
+    /// `min_prec` (precedence climbing).
+    fn expr(&self, input: ParserInput, min_prec: u8) -> (ParserInput, Result<T, Failure>) {
+        let (mut input, mut lhs) = match self.operand(input) {
+            (input, Ok(x)) => (input, x),
+            (input, Err(e)) => return (input, Err(e)),
+        };
+        'outer: loop {
+            for op in &self.postfix {
+                if op.prec < min_prec {
+                    continue;
+                }
+                if let (input_, Ok(())) = (op.op.run)(input.clone()) {
+                    input = input_;
+                    lhs = (op.f)(lhs);
+                    continue 'outer;
+                }
+            }
+            for op in &self.infix {
+                if op.prec < min_prec {
+                    continue;
+                }
+                if let (input_, Ok(())) = (op.op.run)(input.clone()) {
+                    let next = match op.assoc {
+                        Assoc::Left => op.prec + 1,
+                        Assoc::Right => op.prec,
+                    };
+                    match self.expr(input_, next) {
+                        (input_, Ok(rhs)) => {
+                            input = input_;
+                            lhs = (op.f)((lhs, rhs));
+                            continue 'outer;
+                        }
+                        (input_, Err(e)) => return (input_, Err(e)),
+                    }
+                }
+            }
+            return (input, Ok(lhs));
+        }
+    }
+
+    fn operand(&self, input: ParserInput) -> (ParserInput, Result<T, Failure>) {
+        for op in &self.prefix {
+            if let (input_, Ok(())) = (op.op.run)(input.clone()) {
+                return match self.expr(input_, op.prec) {
+                    (input_, Ok(x)) => (input_, Ok((op.f)(x))),
+                    (input_, Err(e)) => (input_, Err(e)),
+                };
+            }
+        }
+        (self.atom.run)(input)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{chars, prefix, run};
+
+    #[derive(Debug, Clone, PartialEq)]
+    enum Expr {
+        Num(i64),
+        Neg(Box<Expr>),
+        Bin(char, Box<Expr>, Box<Expr>),
+        Fact(Box<Expr>),
+    }
+
+    fn bin(op: char) -> impl Fn(Expr, Expr) -> Expr {
+        move |a, b| Expr::Bin(op, Box::new(a), Box::new(b))
+    }
+
+    fn pratt<'a>() -> Parser<'a, Expr> {
+        let number = chars::digits1().map(|x| Expr::Num(x.parse().unwrap()));
+        PrattParser::new(number)
+            .infix(prefix("+"), 1, Assoc::Left, bin('+'))
+            .infix(prefix("-"), 1, Assoc::Left, bin('-'))
+            .infix(prefix("*"), 2, Assoc::Left, bin('*'))
+            .infix(prefix("^"), 3, Assoc::Right, bin('^'))
+            .prefix(prefix("-"), 4, |a| Expr::Neg(Box::new(a)))
+            .postfix(prefix("!"), 5, |a| Expr::Fact(Box::new(a)))
+            .build()
+    }
+
+    fn num(x: i64) -> Box<Expr> {
+        Box::new(Expr::Num(x))
+    }
+
+    #[test]
+    fn precedence_test() {
+        let parsed = run(pratt(), "1+2*3".to_string());
+        assert_eq!(
+            parsed,
+            Ok(Expr::Bin(
+                '+',
+                num(1),
+                Box::new(Expr::Bin('*', num(2), num(3)))
+            ))
+        );
+    }
+
+    #[test]
+    fn associativity_test() {
+        let parsed = run(pratt(), "1-2-3".to_string());
+        assert_eq!(
+            parsed,
+            Ok(Expr::Bin(
+                '-',
+                Box::new(Expr::Bin('-', num(1), num(2))),
+                num(3)
+            ))
+        );
+
+        let parsed = run(pratt(), "1^2^3".to_string());
+        assert_eq!(
+            parsed,
+            Ok(Expr::Bin(
+                '^',
+                num(1),
+                Box::new(Expr::Bin('^', num(2), num(3)))
+            ))
+        );
+    }
+
+    #[test]
+    fn prefix_postfix_test() {
+        let parsed = run(pratt(), "-2!*3".to_string());
+        assert_eq!(
+            parsed,
+            Ok(Expr::Bin(
+                '*',
+                Box::new(Expr::Neg(Box::new(Expr::Fact(num(2))))),
+                num(3)
+            ))
+        );
+
+        // a dangling operator is an error
+        let parsed = run(pratt(), "1+".to_string());
+        assert_eq!(parsed.unwrap_err().location.offset, 2);
+    }
+}
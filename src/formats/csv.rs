@@ -0,0 +1,224 @@
+//! CSV files as RFC 4180 defines them.
+//!
+//! Records end with a line break, `\r\n` or `\n`, which the last one may go
+//! without. Fields are separated by the delimiter, and may be quoted to hold
+//! delimiters, line breaks and quotes, the latter doubled.
+//!
+//! ```
+//! use parcoom::formats::csv::Dialect;
+//! use parcoom::run;
+//!
+//! let csv = Dialect::new().delimiter(';').build();
+//! let rows = run(csv, "name;note\r\nada;\"said \"\"hi\"\";\nleft\"\n".to_string());
+//! let row = |fields: &[&str]| fields.iter().map(|f| f.to_string()).collect::<Vec<_>>();
+//! assert_eq!(rows, Ok(vec![row(&["name", "note"]), row(&["ada", "said \"hi\";\nleft"])]));
+//! ```
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::chars::line_ending;
+use crate::limits::counted;
+use crate::{eof, input_sub, not, satisfy, Failure, Needed, Parser, Ptr};
+
+/// Builds CSV parsers. The delimiter is `,` and the quote `"` by default.
+#[derive(Debug, Clone, Copy)]
+pub struct Dialect {
+    delimiter: char,
+    quote: char,
+}
+
+impl Default for Dialect {
+    fn default() -> Self {
+        Dialect {
+            delimiter: ',',
+            quote: '"',
+        }
+    }
+}
+
+impl Dialect {
+    pub fn new() -> Self {
+        Dialect::default()
+    }
+
+    pub fn delimiter(mut self, delimiter: char) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    pub fn quote(mut self, quote: char) -> Self {
+        self.quote = quote;
+        self
+    }
+
+    /// A field, its quotes removed if it is quoted.
+    pub fn field<'a>(self) -> Parser<'a, String> {
+        let Dialect { delimiter, quote } = self;
+        counted(Parser {
+            run: Ptr::new(move |input| {
+                let text = input.text();
+                if !text.starts_with(quote) {
+                    let end = text.find([delimiter, '\n']);
+                    let end = match end {
+                        Some(end) if text[..end].ends_with('\r') => end - 1,
+                        Some(end) => end,
+                        None if input.partial => {
+                            return (input, Err(Failure::incomplete(Needed::Unknown)))
+                        }
+                        None => text.len(),
+                    };
+                    return (input_sub(end, &input), Ok(text[..end].to_string()));
+                }
+                let mut value = String::new();
+                let mut rest = &text[quote.len_utf8()..];
+                while let Some(i) = rest.find(quote) {
+                    value.push_str(&rest[..i]);
+                    rest = &rest[i + quote.len_utf8()..];
+                    if rest.is_empty() && input.partial {
+                        break;
+                    }
+                    match rest.strip_prefix(quote) {
+                        Some(after) => {
+                            value.push(quote);
+                            rest = after;
+                        }
+                        None => return (input_sub(text.len() - rest.len(), &input), Ok(value)),
+                    }
+                }
+                if input.partial {
+                    return (input, Err(Failure::incomplete(Needed::Unknown)));
+                }
+                let desc = format!("unterminated quoted field, expected {quote:?}");
+                let e = Failure::unterminated(format!("{:?}", quote.to_string()), desc);
+                (input_sub(text.len(), &input), Err(e))
+            }),
+            shape: None,
+        })
+    }
+
+    /// A record and the line break after it.
+    pub fn record<'a>(self) -> Parser<'a, Vec<String>> {
+        let delimiter = satisfy(move |c| c == self.delimiter);
+        self.field().sep_by1(delimiter) << (line_ending().ignored() | eof())
+    }
+
+    /// All the records of a file.
+    pub fn build<'a>(self) -> Parser<'a, Vec<Vec<String>>> {
+        (not(eof()) >> self.record()).many() << eof()
+    }
+
+    /// The records `reader` yields, read one line at a time, or more for
+    /// quoted fields with line breaks.
+    #[cfg(feature = "std")]
+    pub fn rows<R: std::io::BufRead>(self, reader: R) -> Rows<'static, R> {
+        Rows {
+            record: self.record(),
+            reader,
+            pending: String::new(),
+            done: false,
+        }
+    }
+}
+
+/// The records of a reader, see [`Dialect::rows`]. The locations of errors
+/// are in their record, after an error the iterator ends.
+#[cfg(feature = "std")]
+pub struct Rows<'a, R> {
+    record: Parser<'a, Vec<String>>,
+    reader: R,
+    /// What was read but not parsed yet.
+    pending: String,
+    done: bool,
+}
+
+#[cfg(feature = "std")]
+impl<R: std::io::BufRead> Iterator for Rows<'_, R> {
+    type Item = std::io::Result<Result<Vec<String>, crate::ParserError>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        use crate::{run, run_streaming, StreamResult};
+
+        while !self.done {
+            if !self.pending.is_empty() {
+                match run_streaming(self.record.clone(), self.pending.clone()) {
+                    StreamResult::Ok(row, consumed) => {
+                        self.pending.drain(..consumed);
+                        return Some(Ok(Ok(row)));
+                    }
+                    StreamResult::Err(e) => {
+                        self.done = true;
+                        return Some(Ok(Err(e)));
+                    }
+                    StreamResult::Incomplete(_) => {}
+                }
+            }
+            match self.reader.read_line(&mut self.pending) {
+                Ok(0) => self.done = true,
+                Ok(_) => {}
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+        if self.pending.is_empty() {
+            return None;
+        }
+        let pending = core::mem::take(&mut self.pending);
+        Some(Ok(run(self.record.clone(), pending)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::run;
+    use alloc::vec;
+
+    fn rows(rows: &[&[&str]]) -> Vec<Vec<String>> {
+        let row = |fields: &&[&str]| fields.iter().map(|f| f.to_string()).collect();
+        rows.iter().map(row).collect()
+    }
+
+    #[test]
+    fn csv_test() {
+        let csv = || Dialect::new().build();
+        let parsed = run(csv(), "a,b,c\r\n1,,\"x,\"\"y\"\"\"\n".to_string());
+        assert_eq!(parsed, Ok(rows(&[&["a", "b", "c"], &["1", "", "x,\"y\""]])));
+        let parsed = run(csv(), "a\n\n\"b\r\nc\"".to_string());
+        assert_eq!(parsed, Ok(rows(&[&["a"], &[""], &["b\r\nc"]])));
+        assert_eq!(run(csv(), "".to_string()), Ok(vec![]));
+
+        let tabs = Dialect::new().delimiter('\t').quote('\'').build();
+        let parsed = run(tabs, "'a\t''b'\tc,d".to_string());
+        assert_eq!(parsed, Ok(rows(&[&["a\t'b", "c,d"]])));
+
+        let parsed = run(csv(), "a,\"b\nc".to_string()).map_err(|e| e.desc);
+        let desc = "unterminated quoted field, expected '\"'";
+        assert_eq!(parsed, Err(desc.to_string()));
+        assert!(run(csv(), "\"a\"b\n".to_string()).is_err());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn rows_test() {
+        let source = "a,\"multi\nline\"\n\"x\"\"\"\nlast,";
+        let reader = std::io::BufReader::with_capacity(3, source.as_bytes());
+        let parsed: Vec<_> = Dialect::new()
+            .rows(reader)
+            .map(|row| row.unwrap())
+            .collect();
+        let expected = rows(&[&["a", "multi\nline"], &["x\""], &["last", ""]]);
+        assert_eq!(parsed, expected.into_iter().map(Ok).collect::<Vec<_>>());
+
+        let reader = std::io::BufReader::new("ok\n\"open\n".as_bytes());
+        let parsed: Vec<_> = Dialect::new()
+            .rows(reader)
+            .map(|row| row.unwrap())
+            .collect();
+        assert_eq!(parsed.len(), 2);
+        assert!(parsed[1].is_err());
+    }
+}
@@ -0,0 +1,453 @@
+//! The head of HTTP/1.1 messages as RFC 9112 writes it: the request line or
+//! the status line, then the header fields up to the empty line before the
+//! body.
+//!
+//! The parsers read bytes, run with [`run_bytes`](crate::bytes::run_bytes)
+//! or, as data comes in, [`run_bytes_streaming`], with which a head is
+//! incomplete until its empty line is there. Bytes past ASCII in values
+//! are read as Latin-1, as HTTP/1.1 once defined them.
+//!
+//! ```
+//! use parcoom::bytes::{run_bytes, run_bytes_streaming};
+//! use parcoom::formats::http::request;
+//! use parcoom::StreamResult;
+//!
+//! let head = b"GET /index.html HTTP/1.1\r\nHost: example.com\r\nAccept: */*\r\n\r\n";
+//! let parsed = run_bytes(request(), head.to_vec()).unwrap();
+//! assert_eq!(parsed.line.method, "GET");
+//! assert_eq!(parsed.line.target, "/index.html");
+//! assert_eq!(parsed.header("host"), Some("example.com"));
+//! let streamed = run_bytes_streaming(request(), head[..40].to_vec());
+//! assert!(matches!(streamed, StreamResult::Incomplete(_)));
+//! ```
+//!
+//! [`run_bytes_streaming`]: crate::bytes::run_bytes_streaming
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::limits::counted;
+use crate::{cut, input_sub, Failure, Needed, Parser, Ptr};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Version {
+    pub major: u8,
+    pub minor: u8,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestLine {
+    pub method: String,
+    /// As written, usually a path with a query.
+    pub target: String,
+    pub version: Version,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatusLine {
+    pub version: Version,
+    pub status: u16,
+    /// Possibly empty.
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Header {
+    pub name: String,
+    /// Without the whitespace around it. The lines folded into it, an
+    /// obsolete `CRLF` followed by whitespace, are joined by a space.
+    pub value: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Request {
+    pub line: RequestLine,
+    pub headers: Vec<Header>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Response {
+    pub line: StatusLine,
+    pub headers: Vec<Header>,
+}
+
+/// The value of the first header named `name`, ignoring case.
+fn get<'h>(headers: &'h [Header], name: &str) -> Option<&'h str> {
+    let header = headers.iter().find(|h| h.name.eq_ignore_ascii_case(name));
+    header.map(|h| h.value.as_str())
+}
+
+impl Request {
+    /// The value of the first header named `name`, ignoring case.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        get(&self.headers, name)
+    }
+}
+
+impl Response {
+    /// The value of the first header named `name`, ignoring case.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        get(&self.headers, name)
+    }
+}
+
+fn is_tchar(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b)
+}
+
+/// What values are made of: visible chars, spaces, tabs and `obs-text`.
+fn is_field_text(b: u8) -> bool {
+    b == b'\t' || (b >= b' ' && b != 0x7f)
+}
+
+fn latin1(bytes: &[u8]) -> String {
+    bytes.iter().map(|&b| char::from(b)).collect()
+}
+
+/// The bytes of `literal`, `name` in errors.
+fn literal<'a>(literal: &'static [u8], name: &'static str) -> Parser<'a, ()> {
+    Parser {
+        run: Ptr::new(move |input| {
+            let rest = input.bytes();
+            if rest.starts_with(literal) {
+                (input_sub(literal.len(), &input), Ok(()))
+            } else if input.partial && literal.starts_with(rest) {
+                let needed = Needed::Size(literal.len() - rest.len());
+                (input, Err(Failure::incomplete(needed)))
+            } else {
+                input.error_at(0, name).into()
+            }
+        }),
+        shape: None,
+    }
+}
+
+fn crlf<'a>() -> Parser<'a, ()> {
+    literal(b"\r\n", "CRLF")
+}
+
+fn sp<'a>() -> Parser<'a, ()> {
+    literal(b" ", "a space")
+}
+
+/// One or more bytes of `allowed`, `name` in errors.
+fn run1<'a>(name: &'static str, allowed: fn(u8) -> bool) -> Parser<'a, String> {
+    counted(Parser {
+        run: Ptr::new(move |input| {
+            let bytes = input.bytes();
+            let n = bytes.iter().take_while(|&&b| allowed(b)).count();
+            if n == bytes.len() && input.partial {
+                return (input, Err(Failure::incomplete(Needed::Size(1))));
+            }
+            if n == 0 {
+                return input.error_at(0, name).into();
+            }
+            (input_sub(n, &input), Ok(latin1(&bytes[..n])))
+        }),
+        shape: None,
+    })
+}
+
+/// A token, what methods and header names are, such as `GET` or
+/// `Content-Type`.
+pub fn token<'a>() -> Parser<'a, String> {
+    run1("a token", is_tchar)
+}
+
+/// A string in double quotes, in which `\` escapes the char after it,
+/// yielding its contents.
+pub fn quoted_string<'a>() -> Parser<'a, String> {
+    counted(Parser {
+        run: Ptr::new(|input| {
+            let bytes = input.bytes();
+            match bytes.first() {
+                Some(b'"') => {}
+                None if input.partial => {
+                    return (input, Err(Failure::incomplete(Needed::Size(1))));
+                }
+                _ => return input.error_at(0, "a quoted string").into(),
+            }
+            let mut value = String::new();
+            let mut i = 1;
+            loop {
+                let (b, n) = match bytes.get(i) {
+                    Some(b'"') => return (input_sub(i + 1, &input), Ok(value)),
+                    Some(b'\\') => match bytes.get(i + 1) {
+                        Some(&b) if is_field_text(b) => (b, 2),
+                        Some(_) => return input.error_at(i + 1, "a char after \\").into(),
+                        None if input.partial => {
+                            return (input, Err(Failure::incomplete(Needed::Size(1))));
+                        }
+                        None => return input.error_at(i + 1, "a char after \\").into(),
+                    },
+                    Some(&b) if is_field_text(b) => (b, 1),
+                    None if input.partial => {
+                        return (input, Err(Failure::incomplete(Needed::Size(1))));
+                    }
+                    _ => return input.error_at(i, "\" to end the quoted string").into(),
+                };
+                value.push(char::from(b));
+                i += n;
+            }
+        }),
+        shape: None,
+    })
+}
+
+fn digit<'a>() -> Parser<'a, u8> {
+    Parser {
+        run: Ptr::new(|input| match input.bytes().first() {
+            Some(&b) if b.is_ascii_digit() => (input_sub(1, &input), Ok(b - b'0')),
+            None if input.partial => (input, Err(Failure::incomplete(Needed::Size(1)))),
+            _ => input.error_at(0, "a digit").into(),
+        }),
+        shape: None,
+    }
+}
+
+/// `HTTP/1.1` and the like.
+pub fn version<'a>() -> Parser<'a, Version> {
+    let major = literal(b"HTTP/", "\"HTTP/\"") >> digit() << literal(b".", "\".\"");
+    (major + digit()).map(|(major, minor)| Version { major, minor })
+}
+
+/// `GET /index.html HTTP/1.1` and the `CRLF` ending it.
+pub fn request_line<'a>() -> Parser<'a, RequestLine> {
+    let target = run1("a request target", |b| b.is_ascii_graphic());
+    let line = (token() << sp()) + (target << sp()) + (version() << crlf());
+    line.map(|((method, target), version)| RequestLine {
+        method,
+        target,
+        version,
+    })
+}
+
+/// Three digits, from 100 to 599.
+fn status<'a>() -> Parser<'a, u16> {
+    Parser {
+        run: Ptr::new(|input| {
+            let bytes = input.bytes();
+            let n = bytes
+                .iter()
+                .take(3)
+                .take_while(|b| b.is_ascii_digit())
+                .count();
+            if n < 3 && n == bytes.len() && input.partial {
+                return (input, Err(Failure::incomplete(Needed::Size(3 - n))));
+            }
+            if n < 3 {
+                return input.error_at(n, "a digit of the status code").into();
+            }
+            let status = bytes[..3]
+                .iter()
+                .fold(0, |status, b| status * 10 + u16::from(b - b'0'));
+            if !(100..=599).contains(&status) {
+                let desc = format!("status {status} is out of range, expected 100 to 599");
+                return (input, Err(Failure::from(desc)));
+            }
+            (input_sub(3, &input), Ok(status))
+        }),
+        shape: None,
+    }
+}
+
+/// `HTTP/1.1 404 Not Found` and the `CRLF` ending it.
+pub fn status_line<'a>() -> Parser<'a, StatusLine> {
+    let reason = Parser {
+        run: Ptr::new(|input| {
+            let bytes = input.bytes();
+            let n = bytes.iter().take_while(|&&b| is_field_text(b)).count();
+            (input_sub(n, &input), Ok(latin1(&bytes[..n])))
+        }),
+        shape: None,
+    };
+    let line = (version() << sp()) + (status() << sp()) + (reason << crlf());
+    line.map(|((version, status), reason)| StatusLine {
+        version,
+        status,
+        reason,
+    })
+}
+
+/// The value of a header and the `CRLF` ending it, along with the lines
+/// folded into it.
+fn field_value<'a>() -> Parser<'a, String> {
+    counted(Parser {
+        run: Ptr::new(|input| {
+            let bytes = input.bytes();
+            let mut value = String::new();
+            let mut i = 0;
+            loop {
+                let n = bytes[i..].iter().take_while(|&&b| is_field_text(b)).count();
+                value.extend(bytes[i..i + n].iter().map(|&b| char::from(b)));
+                i += n;
+                let rest = &bytes[i..];
+                if input.partial && rest.len() < 2 && b"\r\n".starts_with(rest) {
+                    let needed = Needed::Size(2 - rest.len());
+                    return (input, Err(Failure::incomplete(needed)));
+                }
+                if !rest.starts_with(b"\r\n") {
+                    return input.error_at(i, "CRLF").into();
+                }
+                i += 2;
+                match bytes.get(i) {
+                    Some(b' ' | b'\t') => {
+                        let folded = value.trim_end_matches([' ', '\t']).len();
+                        value.truncate(folded);
+                        value.push(' ');
+                        i += bytes[i..]
+                            .iter()
+                            .take_while(|&&b| b == b' ' || b == b'\t')
+                            .count();
+                    }
+                    // the next line could be folded into this one
+                    None if input.partial => {
+                        return (input, Err(Failure::incomplete(Needed::Size(1))));
+                    }
+                    _ => break,
+                }
+            }
+            let value = value.trim_matches([' ', '\t']).into();
+            (input_sub(i, &input), Ok(value))
+        }),
+        shape: None,
+    })
+}
+
+/// `Name: value` and the `CRLF` ending it. Once the name is there the rest
+/// must be too, so errors in a header are reported where they are.
+pub fn header<'a>() -> Parser<'a, Header> {
+    let name = token() << cut(literal(b":", "\":\""));
+    (name + cut(field_value())).map(|(name, value)| Header { name, value })
+}
+
+/// The headers of a message and the empty line after them.
+pub fn headers<'a>() -> Parser<'a, Vec<Header>> {
+    header().many() << crlf()
+}
+
+/// The head of a request: its request line and headers.
+pub fn request<'a>() -> Parser<'a, Request> {
+    (request_line() + headers()).map(|(line, headers)| Request { line, headers })
+}
+
+/// The head of a response: its status line and headers.
+pub fn response<'a>() -> Parser<'a, Response> {
+    (status_line() + headers()).map(|(line, headers)| Response { line, headers })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bytes::{run_bytes, run_bytes_streaming};
+    use crate::{eof, padded, prefix, run, StreamResult};
+    use alloc::string::ToString;
+    use alloc::vec;
+
+    fn header_of(name: &str, value: &str) -> Header {
+        Header {
+            name: name.to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    #[test]
+    fn request_test() {
+        let head = b"POST /a?b=1 HTTP/1.0\r\nHost: x\r\nX-Long: one,\r\n \t two  \r\n\r\nbody";
+        let parsed = run_bytes(request(), head.to_vec()).unwrap();
+        let line = RequestLine {
+            method: "POST".to_string(),
+            target: "/a?b=1".to_string(),
+            version: Version { major: 1, minor: 0 },
+        };
+        assert_eq!(parsed.line, line);
+        let headers = vec![header_of("Host", "x"), header_of("X-Long", "one, two")];
+        assert_eq!(parsed.headers, headers);
+        assert_eq!(parsed.header("x-long"), Some("one, two"));
+        assert_eq!(parsed.header("Accept"), None);
+
+        // obs-text is Latin-1
+        let parsed = run_bytes(header(), b"Title: caf\xe9\r\n\r\n".to_vec());
+        assert_eq!(parsed, Ok(header_of("Title", "café")));
+
+        let e = run_bytes(request(), b"GET / HTTP/1.1\r\nHost : x\r\n\r\n".to_vec()).unwrap_err();
+        assert_eq!((e.desc.as_str(), e.location.offset), ("expected \":\"", 20));
+        let e = run_bytes(request(), b"GET / HTTP/1.1\r\nA: \x01\r\n\r\n".to_vec()).unwrap_err();
+        assert_eq!((e.desc.as_str(), e.location.offset), ("expected CRLF", 19));
+        let e = run_bytes(request(), b"GET  / HTTP/1.1\r\n\r\n".to_vec()).unwrap_err();
+        assert_eq!(e.location.offset, 4);
+        assert!(run_bytes(request(), b"GET / HTTP/x.1\r\n\r\n".to_vec()).is_err());
+    }
+
+    #[test]
+    fn response_test() {
+        let head = b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n";
+        let parsed = run_bytes(response(), head.to_vec()).unwrap();
+        assert_eq!(parsed.line.version, Version { major: 1, minor: 1 });
+        assert_eq!(
+            (parsed.line.status, parsed.line.reason.as_str()),
+            (404, "Not Found")
+        );
+        assert_eq!(parsed.header("content-length"), Some("0"));
+
+        let parsed = run_bytes(status_line(), b"HTTP/1.1 204 \r\n".to_vec());
+        assert_eq!(parsed.map(|line| line.reason), Ok(String::new()));
+        let e = run_bytes(status_line(), b"HTTP/1.1 600 Odd\r\n".to_vec()).unwrap_err();
+        assert_eq!(e.desc, "status 600 is out of range, expected 100 to 599");
+        assert_eq!(e.location.offset, 9);
+        let e = run_bytes(status_line(), b"HTTP/1.1 20 OK\r\n".to_vec()).unwrap_err();
+        assert_eq!(e.location.offset, 11);
+    }
+
+    #[test]
+    fn token_test() {
+        let parameter = (token() << prefix("=")) + (token() | quoted_string());
+        let parameters = (padded(prefix(";")) >> parameter).many();
+        let content_type = ((token() << prefix("/")) + token() + parameters) << eof();
+        let parsed = run(
+            content_type,
+            r#"text/html; charset=utf-8 ;title="a \"b\"""#.to_string(),
+        );
+        let parameters = vec![
+            ("charset".to_string(), "utf-8".to_string()),
+            ("title".to_string(), "a \"b\"".to_string()),
+        ];
+        assert_eq!(
+            parsed,
+            Ok((("text".to_string(), "html".to_string()), parameters))
+        );
+
+        let e = run(quoted_string(), "\"abc".to_string()).unwrap_err();
+        assert_eq!(
+            e.desc,
+            "expected \" to end the quoted string, got end of input"
+        );
+        assert_eq!(e.location.offset, 4);
+        assert!(run(token(), "(".to_string()).is_err());
+    }
+
+    #[test]
+    fn streaming_test() {
+        let head = b"GET / HTTP/1.1\r\nHost: a\r\n folded\r\n\r\n";
+        // every byte short of the empty line is incomplete
+        for n in 0..head.len() {
+            let streamed = run_bytes_streaming(request(), head[..n].to_vec());
+            assert!(
+                matches!(streamed, StreamResult::Incomplete(_)),
+                "{n} bytes: {streamed:?}"
+            );
+        }
+        let streamed = run_bytes_streaming(request(), head.to_vec());
+        let StreamResult::Ok(parsed, consumed) = streamed else {
+            panic!("{streamed:?}")
+        };
+        assert_eq!(consumed, head.len());
+        assert_eq!(parsed.header("host"), Some("a folded"));
+
+        let streamed = run_bytes_streaming(quoted_string(), b"\"a\\".to_vec());
+        assert_eq!(streamed, StreamResult::Incomplete(Needed::Size(1)));
+        let streamed = run_bytes_streaming(request(), b"GET / HTTP/1.1\r\n\x01".to_vec());
+        assert!(matches!(streamed, StreamResult::Err(_)));
+    }
+}
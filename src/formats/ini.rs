@@ -0,0 +1,314 @@
+//! INI and properties files.
+//!
+//! Lines hold a `[section]` header, a `key = value` or `key: value` entry
+//! or a comment starting with `;` or `#`. Entries before the first header
+//! are in a section with no name. Values are trimmed, and may be quoted as a
+//! whole to keep their spaces or hold escapes such as `\n`. Comments only
+//! take up whole lines, a `;` in an unquoted value is part of it.
+//!
+//! ```
+//! use parcoom::formats::ini::parse;
+//!
+//! let ini = parse("name = demo\n[server]\nport: 8080 ; not a comment\n").unwrap();
+//! assert_eq!(ini.get(None, "name"), Some("demo"));
+//! assert_eq!(ini.get(Some("server"), "port"), Some("8080 ; not a comment"));
+//! ```
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use crate::chars::{line_ending, rest_of_line, take_while, while1};
+use crate::literal::{string_literal, ESCAPES};
+use crate::{cut, eof, prefix, run, wrap, Failure, Parser, ParserError, ParserInput, Ptr};
+use crate::{Spanned, Substr};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ini {
+    /// In the order of their first header.
+    pub sections: Vec<Section>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Section {
+    /// The name of the first header, `None` for the entries before it.
+    pub name: Option<Spanned<String>>,
+    /// In the order of the source, as [`Duplicates`] leaves them.
+    pub entries: Vec<Entry>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub key: Spanned<String>,
+    pub value: Spanned<String>,
+    /// From the start of the key to the end of the value.
+    pub span: Range<usize>,
+}
+
+impl Ini {
+    pub fn section(&self, name: Option<&str>) -> Option<&Section> {
+        let sections = self.sections.iter();
+        sections
+            .into_iter()
+            .find(|s| s.name.as_ref().map(|n| n.value.as_str()) == name)
+    }
+
+    /// The value of `key` in the section `section`.
+    pub fn get(&self, section: Option<&str>, key: &str) -> Option<&str> {
+        self.section(section)?.get(key)
+    }
+}
+
+impl Section {
+    /// The value of the last entry for `key`.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        let entry = self.entries.iter().rev().find(|e| e.key.value == key);
+        entry.map(|e| e.value.value.as_str())
+    }
+
+    /// The values of all entries for `key`, which [`Duplicates::Keep`]
+    /// leaves more than one of.
+    pub fn get_all<'s>(&'s self, key: &'s str) -> impl Iterator<Item = &'s str> {
+        let entries = self.entries.iter().filter(move |e| e.key.value == key);
+        entries.map(|e| e.value.value.as_str())
+    }
+}
+
+/// What to do with a key that is already in its section. Sections with
+/// the same name are one section.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Duplicates {
+    /// Keep all entries.
+    Keep,
+    /// Keep the first entry.
+    First,
+    /// Keep the last entry, in place of the first one.
+    #[default]
+    Last,
+    /// Fail, committed, at the duplicate key.
+    Error,
+}
+
+/// Builds an INI parser.
+#[derive(Debug, Clone, Default)]
+pub struct Dialect {
+    duplicates: Duplicates,
+}
+
+#[derive(Clone)]
+enum Line {
+    Header(Spanned<String>),
+    Entry(Entry),
+    Blank,
+}
+
+impl Dialect {
+    pub fn new() -> Self {
+        Dialect::default()
+    }
+
+    pub fn duplicates(mut self, duplicates: Duplicates) -> Self {
+        self.duplicates = duplicates;
+        self
+    }
+
+    /// The parser of whole files.
+    pub fn build<'a>(self) -> Parser<'a, Ini> {
+        let lines = (space() >> line() << end_of_line()).many() << eof();
+        Parser {
+            run: Ptr::new(move |input| match (lines.run)(input) {
+                (input_, Ok(lines)) => match self.sections(lines) {
+                    Ok(ini) => (input_, Ok(ini)),
+                    Err((pos, desc)) => {
+                        let mut e = Failure::from(desc);
+                        e.fatal = true;
+                        (ParserInput { pos, ..input_ }, Err(e))
+                    }
+                },
+                (input_, Err(e)) => (input_, Err(e)),
+            }),
+            shape: None,
+        }
+    }
+
+    /// Groups `lines` into sections, or fails at the position of a
+    /// duplicate key.
+    fn sections(&self, lines: Vec<Line>) -> Result<Ini, (usize, String)> {
+        let mut sections = vec![Section {
+            name: None,
+            entries: Vec::new(),
+        }];
+        let mut current = 0;
+        for line in lines {
+            let entry = match line {
+                Line::Blank => continue,
+                Line::Entry(entry) => entry,
+                Line::Header(name) => {
+                    let same = |s: &Section| s.name.as_ref().is_some_and(|n| n.value == name.value);
+                    current = sections.iter().position(same).unwrap_or(sections.len());
+                    if current == sections.len() {
+                        let entries = Vec::new();
+                        sections.push(Section {
+                            name: Some(name),
+                            entries,
+                        });
+                    }
+                    continue;
+                }
+            };
+            let entries = &mut sections[current].entries;
+            let same_key = |e: &Entry| e.key.value == entry.key.value;
+            match (self.duplicates, entries.iter().position(same_key)) {
+                (Duplicates::Keep, _) | (_, None) => entries.push(entry),
+                (Duplicates::First, Some(_)) => {}
+                (Duplicates::Last, Some(i)) => entries[i] = entry,
+                (Duplicates::Error, Some(_)) => {
+                    let desc = format!("duplicate key {}", entry.key.value);
+                    return Err((entry.key.span.start, desc));
+                }
+            }
+        }
+        if sections[0].entries.is_empty() {
+            sections.remove(0);
+        }
+        Ok(Ini { sections })
+    }
+}
+
+/// Parses a whole file, keeping the last of duplicate keys.
+pub fn parse(text: &str) -> Result<Ini, ParserError> {
+    run(Dialect::new().build(), text.to_string())
+}
+
+fn space<'a>() -> Parser<'a, Substr> {
+    take_while(|c: char| c == ' ' || c == '\t')
+}
+
+fn end_of_line<'a>() -> Parser<'a, ()> {
+    space() >> (line_ending().ignored() | eof())
+}
+
+/// `s` with its end trimmed, `start` being where it starts in the source.
+fn trimmed(s: &str, start: usize) -> Spanned<String> {
+    let value = s.trim_end().to_string();
+    let span = start..start + value.len();
+    Spanned { value, span }
+}
+
+fn line<'a>() -> Parser<'a, Line> {
+    let comment = (prefix(";") | prefix("#")) >> rest_of_line();
+    let name = take_while(|c: char| c != ']' && c != '\n' && c != '\r');
+    let name = name.spanned().map(|name| {
+        let lead = name.value.len() - name.value.trim_start().len();
+        trimmed(&name.value[lead..], name.span.start + lead)
+    });
+    let header = prefix("[") >> cut(name << prefix("]"));
+    let key = while1("a key", |c| !matches!(c, '=' | ':' | '\n' | '\r'));
+    let key = key.spanned().map(|key| trimmed(&key.value, key.span.start));
+    let quoted = string_literal('"', ESCAPES).spanned() << end_of_line().peek();
+    let unquoted = rest_of_line()
+        .spanned()
+        .map(|v| trimmed(&v.value, v.span.start));
+    let value = space() >> (quoted | unquoted);
+    let entry = key + cut((prefix("=") | prefix(":")) >> value);
+    let entry = entry.map(|(key, value)| Entry {
+        span: key.span.start..value.span.end,
+        key,
+        value,
+    });
+    comment.to(Line::Blank) | header.map(Line::Header) | entry.map(Line::Entry) | wrap(Line::Blank)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Location, ParserError};
+
+    const SOURCE: &str = "\
+; global settings
+debug = true
+
+[server]
+host = example.org
+# the default
+port: 80
+motd = \"  hi\\tthere \"
+[client]
+retries=3
+[server]
+port = 8080
+";
+
+    #[test]
+    fn ini_test() {
+        let ini = parse(SOURCE).unwrap();
+        let names: Vec<_> = ini
+            .sections
+            .iter()
+            .map(|s| s.name.clone().map(|n| n.value))
+            .collect();
+        assert_eq!(
+            names,
+            vec![None, Some("server".to_string()), Some("client".to_string())]
+        );
+        assert_eq!(ini.get(None, "debug"), Some("true"));
+        assert_eq!(ini.get(Some("server"), "motd"), Some("  hi\tthere "));
+        assert_eq!(ini.get(Some("server"), "port"), Some("8080"));
+        assert_eq!(ini.get(Some("client"), "retries"), Some("3"));
+        assert_eq!(ini.get(Some("client"), "port"), None);
+
+        let host = &ini.section(Some("server")).unwrap().entries[0];
+        assert_eq!(&SOURCE[host.key.span.clone()], "host");
+        assert_eq!(&SOURCE[host.value.span.clone()], "example.org");
+        assert_eq!(&SOURCE[host.span.clone()], "host = example.org");
+        let name = ini.sections[1].name.clone().unwrap();
+        assert_eq!(&SOURCE[name.span], "server");
+    }
+
+    #[test]
+    fn duplicates_test() {
+        let parse_with = |duplicates| {
+            let parser = Dialect::new().duplicates(duplicates).build();
+            run(parser, SOURCE.to_string())
+        };
+        let ini = parse_with(Duplicates::Keep).unwrap();
+        let server = ini.section(Some("server")).unwrap();
+        assert_eq!(
+            server.get_all("port").collect::<Vec<_>>(),
+            vec!["80", "8080"]
+        );
+        let ini = parse_with(Duplicates::First).unwrap();
+        assert_eq!(ini.get(Some("server"), "port"), Some("80"));
+
+        let parsed = parse_with(Duplicates::Error);
+        assert_eq!(
+            parsed,
+            Err(ParserError {
+                desc: "duplicate key port".to_string(),
+                location: Location {
+                    offset: 133,
+                    char_offset: 133,
+                    line: 12,
+                    column: 1,
+                },
+                context: vec![],
+                expected: vec![],
+                cause: None,
+                at_end: false,
+            })
+        );
+    }
+
+    #[test]
+    fn syntax_test() {
+        let desc = |text: &str| parse(text).map_err(|e| e.desc);
+        assert_eq!(desc("[open\n"), Err("expected ]".to_string()));
+        assert_eq!(desc("key\n"), Err("expected \"=\" or \":\"".to_string()));
+        // only a whole value is quoted
+        let ini = parse("key = \"a\" b\n").unwrap();
+        assert_eq!(ini.get(None, "key"), Some("\"a\" b"));
+        assert_eq!(parse("\r\n  \n").unwrap().sections, vec![]);
+    }
+}
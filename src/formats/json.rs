@@ -0,0 +1,307 @@
+//! JSON values as RFC 8259 defines them.
+//!
+//! ```
+//! use parcoom::formats::json::{parse, Json};
+//!
+//! let json = parse(r#"{"name": "parcoom", "tags": ["parser", 1.5]}"#).unwrap();
+//! let Json::Object(members) = json.value else { panic!() };
+//! assert_eq!(members[0].0.value, "name");
+//! assert_eq!(members[1].1.span, 28..43);
+//! ```
+//!
+//! Nesting is only limited by the stack, see
+//! [`run_with_config`](crate::run_with_config) for untrusted input.
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::chars::take_while;
+use crate::limits::counted;
+use crate::{
+    cut, eof, input_sub, prefix, recursive, run, Failure, Needed, Parser, ParserError, Ptr,
+};
+use crate::{ParserInput, Spanned, Substr};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Spanned<Json>>),
+    /// The members in the order of the source, duplicate keys included.
+    Object(Vec<(Spanned<String>, Spanned<Json>)>),
+}
+
+/// Parses a whole JSON text, a value with nothing but whitespace around it.
+pub fn parse(text: &str) -> Result<Spanned<Json>, ParserError> {
+    run(document(), text.to_string())
+}
+
+/// A value with nothing but whitespace around it, up to the end of the
+/// input.
+pub fn document<'a>() -> Parser<'a, Spanned<Json>> {
+    ws() >> value() << eof()
+}
+
+/// A value and the whitespace after it.
+pub fn value<'a>() -> Parser<'a, Spanned<Json>> {
+    recursive(|value| {
+        let comma = || token(prefix(","));
+        // Past an opening bracket only this value can go on.
+        let items = value.clone().sep_by(comma()) << token(prefix("]"));
+        let array = token(prefix("[")) >> cut(items);
+        let member = (token(string().spanned()) << token(prefix(":"))) + value;
+        let members = member.sep_by(comma()) << token(prefix("}"));
+        let object = token(prefix("{")) >> cut(members);
+        let json = prefix("null").to(Json::Null)
+            | prefix("true").to(Json::Bool(true))
+            | prefix("false").to(Json::Bool(false))
+            | number().map(Json::Number)
+            | string().map(Json::String)
+            | array.map(Json::Array)
+            | object.map(Json::Object);
+        token(json.spanned())
+    })
+}
+
+fn ws<'a>() -> Parser<'a, Substr> {
+    take_while(|c: char| matches!(c, ' ' | '\t' | '\n' | '\r'))
+}
+
+fn token<'a, A: 'a>(p: Parser<'a, A>) -> Parser<'a, A> {
+    p << ws()
+}
+
+/// A string, its escapes decoded. Once the opening quote matched, failures
+/// are committed to.
+pub fn string<'a>() -> Parser<'a, String> {
+    counted(Parser {
+        run: Ptr::new(|input| {
+            if !input.text().starts_with('"') {
+                let desc = "expected a string".to_string();
+                return (input, Err(Failure::expecting("a string".to_string(), desc)));
+            }
+            let text = input.text();
+            let mut value = String::new();
+            let mut i = 1;
+            while let Some(c) = text[i..].chars().next() {
+                match c {
+                    '"' => return (input_sub(i + 1, &input), Ok(value)),
+                    '\\' => match escape(&text[i + 1..]) {
+                        Ok((c, len)) => {
+                            value.push(c);
+                            i += 1 + len;
+                        }
+                        Err(desc) => return fatal(&input, i, desc),
+                    },
+                    '\0'..='\x1f' => {
+                        return fatal(&input, i, format!("control character {c:?} in string"))
+                    }
+                    c => {
+                        value.push(c);
+                        i += c.len_utf8();
+                    }
+                }
+            }
+            if input.partial {
+                return (input, Err(Failure::incomplete(Needed::Unknown)));
+            }
+            let desc = "unterminated string, expected '\"'".to_string();
+            let e = Failure::unterminated("\"\\\"\"".to_string(), desc);
+            (input_sub(text.len(), &input), Err(e))
+        }),
+        shape: None,
+    })
+}
+
+fn fatal<T>(input: &ParserInput, i: usize, desc: String) -> (ParserInput, Result<T, Failure>) {
+    let mut e = Failure::from(desc);
+    e.fatal = true;
+    (input_sub(i, input), Err(e))
+}
+
+/// The char the escape after a backslash at the start of `s` stands for,
+/// and its length.
+fn escape(s: &str) -> Result<(char, usize), String> {
+    let c = match s.chars().next() {
+        Some('u') => return unicode_escape(s),
+        Some(c) => c,
+        None => return Err("unterminated string, expected '\"'".to_string()),
+    };
+    let c = match c {
+        '"' | '\\' | '/' => c,
+        'b' => '\u{8}',
+        'f' => '\u{c}',
+        'n' => '\n',
+        'r' => '\r',
+        't' => '\t',
+        c => return Err(format!("unknown escape \\{c}")),
+    };
+    Ok((c, 1))
+}
+
+/// `uXXXX`, or two of them for a char outside of the BMP.
+fn unicode_escape(s: &str) -> Result<(char, usize), String> {
+    // The four digits `s` starts with.
+    let hex = |s: &str| {
+        let digits = s
+            .get(..4)
+            .filter(|d| d.bytes().all(|b| b.is_ascii_hexdigit()));
+        digits.map(|d| u32::from_str_radix(d, 16).unwrap())
+    };
+    let invalid = || "invalid unicode escape".to_string();
+    let high = s.strip_prefix('u').and_then(hex).ok_or_else(invalid)?;
+    if let Some(c) = char::from_u32(high) {
+        return Ok((c, 5));
+    }
+    // A surrogate, of which only a high one followed by a low one is a char.
+    let low = s[5..]
+        .strip_prefix("\\u")
+        .and_then(hex)
+        .filter(|low| (0xd800..0xdc00).contains(&high) && (0xdc00..0xe000).contains(low))
+        .ok_or_else(|| "unpaired surrogate in unicode escape".to_string())?;
+    let code = 0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00);
+    Ok((char::from_u32(code).unwrap(), 11))
+}
+
+/// `-?(0|[1-9][0-9]*)(\.[0-9]+)?([eE][+-]?[0-9]+)?`
+pub fn number<'a>() -> Parser<'a, f64> {
+    Parser {
+        run: Ptr::new(|input| {
+            let bytes = input.text().as_bytes();
+            let digits = |i: usize| bytes[i..].iter().take_while(|b| b.is_ascii_digit()).count();
+            let mut i = usize::from(bytes.first() == Some(&b'-'));
+            match (bytes.get(i), digits(i)) {
+                (Some(b'0'), _) => i += 1,
+                (_, 0) => {
+                    let desc = "expected a number".to_string();
+                    return (input, Err(Failure::expecting("a number".to_string(), desc)));
+                }
+                (_, n) => i += n,
+            }
+            if bytes.get(i) == Some(&b'.') {
+                match digits(i + 1) {
+                    0 => return fatal(&input, i + 1, "expected digits after '.'".to_string()),
+                    n => i += 1 + n,
+                }
+            }
+            if let Some(b'e' | b'E') = bytes.get(i) {
+                let sign = usize::from(matches!(bytes.get(i + 1), Some(b'+' | b'-')));
+                match digits(i + 1 + sign) {
+                    0 => {
+                        let desc = "expected digits in the exponent".to_string();
+                        return fatal(&input, i + 1 + sign, desc);
+                    }
+                    n => i += 1 + sign + n,
+                }
+            }
+            if i == bytes.len() && input.partial {
+                return (input, Err(Failure::incomplete(Needed::Unknown)));
+            }
+            let x = input.text()[..i].parse().unwrap();
+            (input_sub(i, &input), Ok(x))
+        }),
+        shape: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Location, ParserError};
+
+    fn values(json: Json) -> Vec<Json> {
+        match json {
+            Json::Array(items) => items.into_iter().map(|item| item.value).collect(),
+            json => panic!("not an array: {json:?}"),
+        }
+    }
+
+    #[test]
+    fn json_test() {
+        let text = " {\"a\" : [1, -0.5e1, true, null], \"\" :{}}\n";
+        let Json::Object(members) = parse(text).unwrap().value else {
+            panic!()
+        };
+        assert_eq!(members.len(), 2);
+        let (key, value) = &members[0];
+        assert_eq!((key.value.as_str(), key.span.clone()), ("a", 2..5));
+        assert_eq!(value.span, 8..31);
+        let items = values(value.value.clone());
+        let expected = [
+            Json::Number(1.0),
+            Json::Number(-5.0),
+            Json::Bool(true),
+            Json::Null,
+        ];
+        assert_eq!(items, expected);
+        assert_eq!(members[1].1.value, Json::Object(vec![]));
+
+        let parsed = parse("[1,]");
+        assert_eq!(
+            parsed,
+            Err(ParserError {
+                desc: "expected ]".to_string(),
+                location: Location {
+                    offset: 2,
+                    char_offset: 2,
+                    line: 1,
+                    column: 3,
+                },
+                context: vec![],
+                expected: vec!["\"]\"".to_string()],
+                cause: None,
+                at_end: false,
+            })
+        );
+        assert!(parse("[1] x").is_err());
+        let parsed = parse("").map_err(|e| e.desc);
+        let desc = "expected \"null\", \"true\", \"false\", a number, a string, \"[\" or \"{\"";
+        assert_eq!(parsed, Err(desc.to_string()));
+    }
+
+    #[test]
+    fn string_test() {
+        let parsed = parse(r#"["a\"\\\/\b\f\n\r\t", "é😀", "é"]"#);
+        let strings = values(parsed.unwrap().value);
+        let expected = ["a\"\\/\u{8}\u{c}\n\r\t", "é😀", "é"];
+        let expected: Vec<Json> = expected.map(|s| Json::String(s.to_string())).into();
+        assert_eq!(strings, expected);
+
+        let desc = |text: &str| parse(text).map_err(|e| e.desc);
+        assert_eq!(desc(r#""\x""#), Err("unknown escape \\x".to_string()));
+        let unpaired = Err("unpaired surrogate in unicode escape".to_string());
+        assert_eq!(desc(r#""\ud83d""#), unpaired);
+        assert_eq!(desc(r#""\ude00\ud83d""#), unpaired);
+        assert_eq!(desc(r#""\ud83d\\de00""#), unpaired);
+        assert_eq!(desc(r#""\u12""#), Err("invalid unicode escape".to_string()));
+        let control = Err("control character '\\n' in string".to_string());
+        assert_eq!(desc("\"a\nb\""), control);
+        let unterminated = Err("unterminated string, expected '\"'".to_string());
+        assert_eq!(desc("\"ab"), unterminated);
+    }
+
+    #[test]
+    fn number_test() {
+        let numbers = values(
+            parse("[0, -0, 12, 1.25, 1e3, 2E-2, -3.5e+1]")
+                .unwrap()
+                .value,
+        );
+        let expected = [0.0, -0.0, 12.0, 1.25, 1e3, 2e-2, -35.0].map(Json::Number);
+        assert_eq!(numbers, expected);
+
+        assert!(parse("01").is_err());
+        assert!(parse("+1").is_err());
+        assert!(parse(".5").is_err());
+        assert!(parse("-").is_err());
+        let desc = |text: &str| parse(text).map_err(|e| e.desc);
+        assert_eq!(desc("1."), Err("expected digits after '.'".to_string()));
+        assert_eq!(
+            desc("1e+"),
+            Err("expected digits in the exponent".to_string())
+        );
+    }
+}
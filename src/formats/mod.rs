@@ -0,0 +1,9 @@
+//! Parsers for common data formats, built out of the crate's combinators.
+//!
+//! Needs the `formats` feature.
+
+pub mod csv;
+pub mod http;
+pub mod ini;
+pub mod json;
+pub mod sexpr;
@@ -0,0 +1,149 @@
+//! S-expressions: atoms, strings and lists of them in parentheses.
+//!
+//! Atoms are runs of chars other than whitespace, parentheses, `"` and `;`,
+//! those that read as numbers being numbers. Strings take the escapes of
+//! [`ESCAPES`]. A `;` starts a comment up to the end of the line.
+//!
+//! ```
+//! use parcoom::formats::sexpr::{parse, Sexpr};
+//!
+//! let exprs = parse("(define (square x) (* x x)) ; squares\n(square 1.5)").unwrap();
+//! assert_eq!(exprs.len(), 2);
+//! let Sexpr::List(items) = &exprs[1].value else { panic!() };
+//! assert_eq!(items[1].value, Sexpr::Float(1.5));
+//! assert_eq!(items[1].span, 46..49);
+//! ```
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::chars::{rest_of_line, while1};
+use crate::literal::{string_literal, ESCAPES};
+use crate::{cut, eof, prefix, recursive, run, Parser, ParserError, Spanned};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Sexpr {
+    Symbol(String),
+    String(String),
+    Integer(i64),
+    /// Also integers too large for an `i64`.
+    Float(f64),
+    List(Vec<Spanned<Sexpr>>),
+}
+
+/// Parses a sequence of expressions, as in a source file.
+pub fn parse(text: &str) -> Result<Vec<Spanned<Sexpr>>, ParserError> {
+    run(document(), text.to_string())
+}
+
+/// Expressions with nothing but whitespace and comments around them, up to
+/// the end of the input.
+pub fn document<'a>() -> Parser<'a, Vec<Spanned<Sexpr>>> {
+    ws() >> value().many() << eof()
+}
+
+/// An expression and the whitespace and comments after it.
+pub fn value<'a>() -> Parser<'a, Spanned<Sexpr>> {
+    recursive(|value| {
+        let list = token(prefix("(")) >> cut(value.many() << prefix(")"));
+        let sexpr =
+            list.map(Sexpr::List) | string_literal('"', ESCAPES).map(Sexpr::String) | atom();
+        token(sexpr.spanned())
+    })
+}
+
+fn atom<'a>() -> Parser<'a, Sexpr> {
+    let atom = while1("an atom", |c: char| {
+        !c.is_whitespace() && !matches!(c, '(' | ')' | '"' | ';')
+    });
+    atom.map(|atom| {
+        // Not "inf" or "nan", which `f64` would read.
+        let digit = atom
+            .trim_start_matches(['+', '-', '.'])
+            .starts_with(|c: char| c.is_ascii_digit());
+        match (atom.parse(), atom.parse()) {
+            (Ok(n), _) => Sexpr::Integer(n),
+            (_, Ok(x)) if digit => Sexpr::Float(x),
+            _ => Sexpr::Symbol(atom.to_string()),
+        }
+    })
+}
+
+fn ws<'a>() -> Parser<'a, ()> {
+    let space = while1("whitespace", char::is_whitespace).ignored();
+    let comment = (prefix(";") >> rest_of_line()).ignored();
+    (space | comment).many().ignored()
+}
+
+fn token<'a, A: 'a>(p: Parser<'a, A>) -> Parser<'a, A> {
+    p << ws()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{run_with_config, RunConfig};
+    use alloc::vec;
+
+    fn symbol(s: &str) -> Sexpr {
+        Sexpr::Symbol(s.to_string())
+    }
+
+    fn values(exprs: Vec<Spanned<Sexpr>>) -> Vec<Sexpr> {
+        exprs.into_iter().map(|e| e.value).collect()
+    }
+
+    #[test]
+    fn sexpr_test() {
+        let exprs = parse(" a -1 +2.5 1e3 - -x 1+ \"a\\n\\\"b\" ; comment\n").unwrap();
+        let expected = [
+            symbol("a"),
+            Sexpr::Integer(-1),
+            Sexpr::Float(2.5),
+            Sexpr::Float(1e3),
+            symbol("-"),
+            symbol("-x"),
+            symbol("1+"),
+            Sexpr::String("a\n\"b".to_string()),
+        ];
+        assert_eq!(values(exprs), expected);
+        assert_eq!(
+            values(parse("inf nan").unwrap()),
+            [symbol("inf"), symbol("nan")]
+        );
+
+        let text = "(a (b ()) ;c\n \"d\")";
+        let exprs = parse(text).unwrap();
+        assert_eq!(exprs[0].span, 0..text.len());
+        let Sexpr::List(items) = exprs[0].value.clone() else {
+            panic!()
+        };
+        assert_eq!(items[1].span, 3..9);
+        let b = Spanned {
+            value: symbol("b"),
+            span: 4..5,
+        };
+        let empty = Spanned {
+            value: Sexpr::List(vec![]),
+            span: 6..8,
+        };
+        assert_eq!(items[1].value, Sexpr::List(vec![b, empty]));
+    }
+
+    #[test]
+    fn errors_test() {
+        let offset = |text: &str| parse(text).map_err(|e| (e.location.offset, e.desc));
+        assert_eq!(offset("(a (b)"), Err((6, "expected )".to_string())));
+        assert!(parse(")").is_err());
+        assert!(parse("\"open").is_err());
+
+        let config = RunConfig {
+            max_depth: Some(40),
+            ..RunConfig::default()
+        };
+        let deep = "(".repeat(30) + &")".repeat(30);
+        assert!(run_with_config(document(), deep, config).is_ok());
+        let deeper = "(".repeat(100_000);
+        assert!(run_with_config(document(), deeper, config).is_err());
+    }
+}
@@ -0,0 +1,248 @@
+//! The [`parser!`](crate::parser) grammar macro.
+
+/// Builds parsers from rules written much like EBNF, one
+/// `name: Type = alternatives;` per rule. It evaluates to a struct with a
+/// `Parser<Type>` field per rule.
+///
+/// - `"lit"` matches the literal with [`prefix`](crate::prefix).
+/// - `name` is another rule, or any parser variable in scope.
+/// - `{ expr }` is a parser written in Rust.
+/// - `a b c` matches in sequence, yielding the tuple `(a, b, c)`, or the
+///   value itself for a single item.
+/// - `a | b` tries the alternatives in order.
+/// - `a*`, `a+` and `a?` repeat with `many`, `many1` and `optional`.
+/// - `( ... )` groups.
+/// - `... => { f }` maps the value of a sequence with `f`.
+///
+/// Rules may refer to each other and to themselves in any order. As with
+/// [`recursive`](crate::recursive) they must consume input before
+/// recursing. Sequences have up to 12 items.
+///
+/// ```
+/// use parcoom::chars::digits1;
+/// use parcoom::{parser, run};
+///
+/// #[derive(Debug, PartialEq)]
+/// enum Expr {
+///     Num(String),
+///     Sum(Vec<Expr>),
+/// }
+///
+/// let num = digits1().to_owned();
+/// let grammar = parser! {
+///     expr: Expr = num => { Expr::Num } | "(" sum ")" => { |(_, sum, _)| sum };
+///     sum: Expr = expr ("+" expr => { |(_, e)| e })* => { |(first, mut rest): (Expr, Vec<_>)| {
+///         rest.insert(0, first);
+///         Expr::Sum(rest)
+///     } };
+/// };
+/// let parsed = run(grammar.expr, "(1+(2))".to_string());
+/// let sum = |es| Expr::Sum(es);
+/// let num = |n: &str| Expr::Num(n.to_string());
+/// assert_eq!(parsed, Ok(sum(vec![num("1"), sum(vec![num("2")])])));
+/// ```
+#[macro_export]
+macro_rules! parser {
+    ($($rules:tt)+) => {
+        $crate::__parser_rules!([] $($rules)+)
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __parser_rules {
+    ([$(($name:ident $ty:ty [$($body:tt)+]))+]) => {{
+        struct __Cells<'a> {
+            $($name: $crate::__private::OnceCell<$crate::Parser<'a, $ty>>,)+
+        }
+        #[allow(dead_code)]
+        struct __Rules<'a> {
+            $($name: $crate::Parser<'a, $ty>,)+
+        }
+        let cells = $crate::__private::cells(__Cells {
+            $($name: $crate::__private::OnceCell::new(),)+
+        });
+        {
+            $(
+                #[allow(unused_variables)]
+                let $name = $crate::__private::forward(&cells, |c: &__Cells| &c.$name);
+            )+
+            $(let _ = cells.$name.set($crate::__parser_alt!([] [] $($body)+));)+
+        }
+        __Rules {
+            $($name: $crate::__private::rule(&cells, |c: &__Cells| &c.$name),)+
+        }
+    }};
+    ([$($done:tt)*] $name:ident : $ty:ty = $($rest:tt)+) => {
+        $crate::__parser_rules!(@body [$($done)*] [$name $ty] [] $($rest)+)
+    };
+    (@body [$($done:tt)*] [$name:ident $ty:ty] [$($body:tt)+] ; $($rest:tt)*) => {
+        $crate::__parser_rules!([$($done)* ($name $ty [$($body)+])] $($rest)*)
+    };
+    (@body $done:tt $rule:tt [$($body:tt)*] $t:tt $($rest:tt)*) => {
+        $crate::__parser_rules!(@body $done $rule [$($body)* $t] $($rest)*)
+    };
+}
+
+/// Splits alternatives on `|`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __parser_alt {
+    ([$($alt:tt)*] [$($seq:tt)+]) => {
+        $($alt |)* ($crate::__parser_seq!([] @ $($seq)+))
+    };
+    ([$($alt:tt)*] [$($seq:tt)+] | $($rest:tt)+) => {
+        $crate::__parser_alt!([$($alt)* ($crate::__parser_seq!([] @ $($seq)+))] [] $($rest)+)
+    };
+    ([$($alt:tt)*] [$($seq:tt)*] $t:tt $($rest:tt)*) => {
+        $crate::__parser_alt!([$($alt)*] [$($seq)* $t] $($rest)*)
+    };
+}
+
+/// Collects the items of a sequence, the last one apart so postfix
+/// operators can wrap it. `@` stands for no item yet.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __parser_seq {
+    ([$($done:tt)*] $last:tt) => {
+        $crate::__parser_join!([$($done)* $last])
+    };
+    ([$($done:tt)*] $last:tt => $f:block) => {
+        $crate::Parser::map($crate::__parser_join!([$($done)* $last]), $f)
+    };
+    ([$($done:tt)*] $last:tt * $($rest:tt)*) => {
+        $crate::__parser_seq!([$($done)*] ($crate::Parser::many($last)) $($rest)*)
+    };
+    ([$($done:tt)*] $last:tt + $($rest:tt)*) => {
+        $crate::__parser_seq!([$($done)*] ($crate::Parser::many1($last)) $($rest)*)
+    };
+    ([$($done:tt)*] $last:tt ? $($rest:tt)*) => {
+        $crate::__parser_seq!([$($done)*] ($crate::Parser::optional($last)) $($rest)*)
+    };
+    ([$($done:tt)*] $last:tt $lit:literal $($rest:tt)*) => {
+        $crate::__parser_seq!([$($done)* $last] ($crate::prefix($lit)) $($rest)*)
+    };
+    ([$($done:tt)*] $last:tt $name:ident $($rest:tt)*) => {
+        $crate::__parser_seq!([$($done)* $last] ($name.clone()) $($rest)*)
+    };
+    ([$($done:tt)*] $last:tt { $($e:tt)* } $($rest:tt)*) => {
+        $crate::__parser_seq!([$($done)* $last] ({ $($e)* }) $($rest)*)
+    };
+    ([$($done:tt)*] $last:tt ( $($group:tt)+ ) $($rest:tt)*) => {
+        $crate::__parser_seq!([$($done)* $last] ($crate::__parser_alt!([] [] $($group)+)) $($rest)*)
+    };
+}
+
+/// Joins the items of a sequence into a flat tuple.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __parser_join {
+    ([@ $item:tt]) => {
+        $item
+    };
+    ([@ $($items:tt)+]) => {
+        $crate::tuple(($($items,)+))
+    };
+}
+
+#[doc(hidden)]
+pub mod __private {
+    #[cfg(not(feature = "sync"))]
+    pub use core::cell::OnceCell;
+    #[cfg(feature = "sync")]
+    pub use std::sync::OnceLock as OnceCell;
+
+    pub use crate::parsable::{lazy, Separated};
+    use crate::{MaybeSync, Parser, Ptr};
+
+    pub fn cells<C>(cells: C) -> Ptr<C> {
+        Ptr::new(cells)
+    }
+
+    /// The rule `get` picks, for the rules themselves to refer to. Like the
+    /// parsers of [`recursive`](crate::recursive) it doesn't keep the rules
+    /// alive, so that they don't keep themselves alive.
+    pub fn forward<'a, C: MaybeSync + 'a, T: 'a>(
+        cells: &Ptr<C>,
+        get: fn(&C) -> &OnceCell<Parser<'a, T>>,
+    ) -> Parser<'a, T> {
+        let cells = Ptr::downgrade(cells);
+        Parser {
+            run: Ptr::new(move |input| {
+                let cells = cells
+                    .upgrade()
+                    .expect("grammar rule used after its grammar was dropped");
+                let p = get(&cells)
+                    .get()
+                    .expect("grammar rule used while being defined");
+                crate::limits::nested(input, |input| (p.run)(input))
+            }),
+            shape: None,
+        }
+    }
+
+    /// The rule `get` picks, keeping all of the rules alive.
+    pub fn rule<'a, C: MaybeSync + 'a, T: 'a>(
+        cells: &Ptr<C>,
+        get: fn(&C) -> &OnceCell<Parser<'a, T>>,
+    ) -> Parser<'a, T> {
+        let cells = cells.clone();
+        Parser {
+            run: Ptr::new(move |input| (get(&cells).get().unwrap().run)(input)),
+            shape: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chars::digits1;
+    use crate::{run, Location, ParserError};
+
+    #[test]
+    fn parser_test() {
+        let ws = crate::prefix(" ").many().ignored();
+        let word = digits1().to_owned() | crate::ident::identifier();
+        let grammar = parser! {
+            entry: (String, String) = word ws "=" ws value => { |(key, _, _, _, value)| (key, value) };
+            value: String = word | "[" ws (word ws)* "]" => { |(_, _, words, _)| {
+                let words: Vec<String> = words.into_iter().map(|(w, _)| w).collect();
+                words.join(",")
+            } };
+        };
+        let parsed = run(grammar.entry.clone(), "a = [1 2  b]".to_string());
+        assert_eq!(parsed, Ok(("a".to_string(), "1,2,b".to_string())));
+        let parsed = run(grammar.value, "x".to_string());
+        assert_eq!(parsed, Ok("x".to_string()));
+
+        let parsed = run(grammar.entry, "a = [1".to_string());
+        assert_eq!(
+            parsed,
+            Err(ParserError {
+                desc: "expected ]".to_string(),
+                location: Location {
+                    offset: 6,
+                    char_offset: 6,
+                    line: 1,
+                    column: 7,
+                },
+                context: vec![],
+                expected: vec!["\"]\"".to_string()],
+                cause: None,
+                at_end: true,
+            })
+        );
+    }
+
+    #[test]
+    fn recursive_test() {
+        // mutually recursive rules, defined before they are used
+        let grammar = parser! {
+            list: usize = "(" items ")" => { |(_, n, _)| n + 1 };
+            items: usize = list* => { |lists: Vec<usize>| lists.into_iter().sum() };
+        };
+        assert_eq!(run(grammar.list, "(()(()))".to_string()), Ok(4));
+        let optional = parser! { sign: Option<&'static str> = ("+" | "-")?; };
+        assert_eq!(run(optional.sign, "-".to_string()), Ok(Some("-")));
+    }
+}
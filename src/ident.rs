@@ -0,0 +1,214 @@
+//! Identifier parsers.
+//!
+//! ```
+//! use parcoom::ident::Identifier;
+//! use parcoom::run;
+//!
+//! let ident = Identifier::new().reserved(&["let", "fn"]).max_len(8).build();
+//! assert_eq!(run(ident.clone(), "x_1 = 2".to_string()), Ok("x_1".to_string()));
+//! assert!(run(ident, "let".to_string()).is_err());
+//! ```
+
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::intern::{interned, Interner};
+use crate::limits::counted;
+use crate::{input_sub, DynFn, Failure, MaybeSync, Needed, Parser, Ptr};
+
+/// Builds an identifier parser. By default identifiers start with a letter
+/// or `_` and go on with letters, digits and `_`, letters and digits being
+/// Unicode ones.
+pub struct Identifier<'a> {
+    start: Box<DynFn<'a, char, bool>>,
+    rest: Box<DynFn<'a, char, bool>>,
+    reserved: Vec<String>,
+    max_len: Option<usize>,
+}
+
+impl<'a> Default for Identifier<'a> {
+    fn default() -> Self {
+        Identifier::new()
+    }
+}
+
+impl<'a> Identifier<'a> {
+    pub fn new() -> Self {
+        Identifier {
+            start: Box::new(|c| c.is_alphabetic() || c == '_'),
+            rest: Box::new(|c| c.is_alphanumeric() || c == '_'),
+            reserved: Vec::new(),
+            max_len: None,
+        }
+    }
+
+    /// Only ASCII letters and digits.
+    pub fn ascii(self) -> Self {
+        self.start(|c| c.is_ascii_alphabetic() || c == '_')
+            .rest(|c| c.is_ascii_alphanumeric() || c == '_')
+    }
+
+    /// Unicode identifiers, `XID_Start` followed by `XID_Continue` chars as
+    /// [`unicode`](crate::unicode) approximates them. Unlike the default,
+    /// they may have combining marks.
+    #[cfg(feature = "unicode")]
+    pub fn xid(self) -> Self {
+        use crate::unicode::{is_xid_continue, is_xid_start};
+        self.start(is_xid_start).rest(is_xid_continue)
+    }
+
+    /// The chars an identifier may start with.
+    pub fn start(mut self, f: impl Fn(char) -> bool + MaybeSync + 'a) -> Self {
+        self.start = Box::new(f);
+        self
+    }
+
+    /// The chars an identifier may go on with.
+    pub fn rest(mut self, f: impl Fn(char) -> bool + MaybeSync + 'a) -> Self {
+        self.rest = Box::new(f);
+        self
+    }
+
+    /// Words that are not identifiers, such as the keywords of a language.
+    pub fn reserved(mut self, words: &[&str]) -> Self {
+        self.reserved.extend(words.iter().map(|w| w.to_string()));
+        self
+    }
+
+    /// The most chars an identifier may have.
+    pub fn max_len(mut self, n: usize) -> Self {
+        self.max_len = Some(n);
+        self
+    }
+
+    pub fn build(self) -> Parser<'a, String> {
+        counted(self.parser(str::to_string))
+    }
+
+    /// Yields symbols for identifiers instead of strings, not allocating one
+    /// for each, see [`interned`].
+    pub fn interned<I: Interner + 'static>(self) -> Parser<'a, I::Symbol> {
+        interned::<I, _>(self.parser(|_| ()))
+    }
+
+    /// The parser yielding `value` of the identifier.
+    fn parser<T>(self, value: impl Fn(&str) -> T + MaybeSync + 'a) -> Parser<'a, T> {
+        Parser {
+            run: Ptr::new(move |input| {
+                let text = input.text();
+                let mut chars = text.char_indices();
+                match chars.next() {
+                    Some((_, c)) if (self.start)(c) => {}
+                    None if input.partial => {
+                        return (input, Err(Failure::incomplete(Needed::Size(1))))
+                    }
+                    c => {
+                        let got = c.map_or("end of input".to_string(), |(_, c)| format!("{c:?}"));
+                        let desc = format!("expected identifier, got {got}");
+                        return (
+                            input,
+                            Err(Failure::expecting("identifier".to_string(), desc)),
+                        );
+                    }
+                }
+                let end = match chars.find(|&(_, c)| !(self.rest)(c)) {
+                    Some((end, _)) => end,
+                    None if input.partial => {
+                        return (input, Err(Failure::incomplete(Needed::Unknown)))
+                    }
+                    None => text.len(),
+                };
+                let ident = &text[..end];
+                if self.reserved.iter().any(|w| w == ident) {
+                    let desc = format!("expected identifier, got keyword {ident}");
+                    return (
+                        input,
+                        Err(Failure::expecting("identifier".to_string(), desc)),
+                    );
+                }
+                if let Some(max) = self.max_len.filter(|&max| ident.chars().count() > max) {
+                    let desc = format!("identifier {ident} is longer than {max} chars");
+                    return (input, Err(Failure::from(desc)));
+                }
+                let ident = value(ident);
+                (input_sub(end, &input), Ok(ident))
+            }),
+            shape: None,
+        }
+    }
+}
+
+/// An identifier as configured by default, see [`Identifier`].
+pub fn identifier<'a>() -> Parser<'a, String> {
+    Identifier::new().build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{run, Location, ParserError};
+
+    #[test]
+    fn identifier_test() {
+        assert_eq!(
+            run(identifier(), "_été2 x".to_string()),
+            Ok("_été2".to_string())
+        );
+        let parser = Identifier::new().ascii().build();
+        assert_eq!(run(parser, "ab€".to_string()), Ok("ab".to_string()));
+        let parser = Identifier::new()
+            .rest(|c| c.is_alphanumeric() || c == '-')
+            .build();
+        assert_eq!(
+            run(parser, "kebab-case".to_string()),
+            Ok("kebab-case".to_string())
+        );
+
+        let parsed = run(identifier(), "1x".to_string());
+        assert_eq!(
+            parsed,
+            Err(ParserError {
+                desc: "expected identifier, got '1'".to_string(),
+                location: Location {
+                    offset: 0,
+                    char_offset: 0,
+                    line: 1,
+                    column: 1,
+                },
+                context: vec![],
+                expected: vec!["identifier".to_string()],
+                cause: None,
+                at_end: false,
+            })
+        );
+    }
+
+    #[test]
+    fn reserved_test() {
+        let parser = || Identifier::new().reserved(&["let"]).max_len(3).build();
+        let parsed = run(parser(), "lets".to_string());
+        assert_eq!(
+            parsed.map_err(|e| e.desc),
+            Err("identifier lets is longer than 3 chars".to_string())
+        );
+        assert_eq!(run(parser(), "le".to_string()), Ok("le".to_string()));
+        assert_eq!(
+            run(parser(), "let".to_string()),
+            Err(ParserError {
+                desc: "expected identifier, got keyword let".to_string(),
+                location: Location {
+                    offset: 0,
+                    char_offset: 0,
+                    line: 1,
+                    column: 1,
+                },
+                context: vec![],
+                expected: vec!["identifier".to_string()],
+                cause: None,
+                at_end: false,
+            })
+        );
+    }
+}
@@ -0,0 +1,206 @@
+//! Re-parsing a document after edits without redoing the unchanged parts.
+//!
+//! Sub-parsers marked with [`Parser::cached`] remember what they parsed at
+//! each offset. After [`Document::edit`] only the entries overlapping the
+//! edit are thrown away, the others are shifted along with the text after
+//! them and reused by the next [`Document::parse`].
+
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::sync::Arc;
+use core::any::Any;
+use core::cell::RefCell;
+use core::ops::Range;
+
+use crate::{finish, input_sub, make_input, next_id, Parser, ParserError, ParserInput, Ptr};
+
+#[derive(Debug)]
+struct Entry {
+    end: usize,
+    value: Box<dyn Any>,
+}
+
+/// Results of cached parsers, keyed by parser id and start offset.
+#[derive(Debug, Default)]
+pub(crate) struct Cache {
+    entries: BTreeMap<(usize, usize), Entry>,
+}
+
+impl Cache {
+    fn edit(&mut self, range: Range<usize>, len: usize) {
+        let delta = len as isize - range.len() as isize;
+        self.entries = core::mem::take(&mut self.entries)
+            .into_iter()
+            .filter_map(|((id, start), entry)| {
+                if entry.end < range.start {
+                    Some(((id, start), entry))
+                } else if start >= range.end {
+                    let shift = |x: usize| (x as isize + delta) as usize;
+                    let entry = Entry {
+                        end: shift(entry.end),
+                        value: entry.value,
+                    };
+                    Some(((id, shift(start)), entry))
+                } else {
+                    // the edit happened inside the entry or right where it
+                    // stopped looking
+                    None
+                }
+            })
+            .collect();
+    }
+}
+
+impl<'a, T: Clone + 'static> Parser<'a, T> {
+    /// Remembers what this parser produced at each offset of a
+    /// [`Document`] so that parsing it again after an edit can skip over
+    /// the regions that didn't change.
+    ///
+    /// The parser must not look further than one char past the input it
+    /// consumes, or its results may be reused when they shouldn't. Outside
+    /// of a `Document` this does nothing.
+    pub fn cached(self) -> Parser<'a, T> {
+        let id = next_id();
+        Parser {
+            run: Ptr::new(move |input| {
+                let Some(cache) = input.cache.clone() else {
+                    return (self.run)(input);
+                };
+                let key = (id, input.pos);
+                if let Some(entry) = cache.borrow_mut().entries.get(&key) {
+                    let value = entry.value.downcast_ref::<T>().unwrap().clone();
+                    let end = entry.end;
+                    return (input_sub(end - input.pos, &input), Ok(value));
+                }
+                let diagnostics = input.diagnostics.len();
+                let state = input.state.clone();
+                let (input_, result) = (self.run)(input);
+                match &result {
+                    // recovered errors, warnings and state changes would be
+                    // lost on a cache hit
+                    Ok(x)
+                        if input_.diagnostics.len() == diagnostics
+                            && same_state(&state, &input_) =>
+                    {
+                        let entry = Entry {
+                            end: input_.pos,
+                            value: Box::new(x.clone()),
+                        };
+                        cache.borrow_mut().entries.insert(key, entry);
+                    }
+                    _ => {}
+                }
+                (input_, result)
+            }),
+            shape: None,
+        }
+    }
+}
+
+fn same_state(state: &Option<Arc<dyn Any>>, input: &ParserInput) -> bool {
+    match (state, &input.state) {
+        (None, None) => true,
+        (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+        _ => false,
+    }
+}
+
+/// A source text that is parsed again and again as it gets edited.
+pub struct Document<'a, T> {
+    parser: Parser<'a, T>,
+    source: String,
+    cache: Rc<RefCell<Cache>>,
+}
+
+impl<'a, T> Document<'a, T> {
+    pub fn new(parser: Parser<'a, T>, source: String) -> Self {
+        Document {
+            parser,
+            source,
+            cache: Rc::default(),
+        }
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Replaces the bytes in `range` with `text`.
+    pub fn edit(&mut self, range: Range<usize>, text: &str) {
+        self.cache.borrow_mut().edit(range.clone(), text.len());
+        self.source.replace_range(range, text);
+    }
+
+    pub fn parse(&self) -> Result<T, ParserError> {
+        let mut input = make_input(self.source.clone());
+        input.cache = Some(self.cache.clone());
+        finish((self.parser.run)(input))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{prefix, satisfy};
+
+    #[test]
+    fn document_test() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let word = satisfy(|x| x.is_alphabetic())
+            .many1()
+            .map(move |xs| {
+                counter.fetch_add(1, Ordering::Relaxed);
+                String::from_iter(xs)
+            })
+            .cached();
+        let parser = word.sep_by(prefix(" "));
+
+        let mut document = Document::new(parser, "one two three".to_string());
+        assert_eq!(
+            document.parse(),
+            Ok(vec![
+                "one".to_string(),
+                "two".to_string(),
+                "three".to_string()
+            ])
+        );
+        assert_eq!(calls.load(Ordering::Relaxed), 3);
+
+        // only the edited word is parsed again
+        document.edit(4..7, "fourty");
+        assert_eq!(document.source(), "one fourty three");
+        assert_eq!(
+            document.parse(),
+            Ok(vec![
+                "one".to_string(),
+                "fourty".to_string(),
+                "three".to_string()
+            ])
+        );
+        assert_eq!(calls.load(Ordering::Relaxed), 4);
+
+        // appending right after a word invalidates it
+        document.edit(16..16, "s");
+        assert_eq!(
+            document.parse(),
+            Ok(vec![
+                "one".to_string(),
+                "fourty".to_string(),
+                "threes".to_string()
+            ])
+        );
+        assert_eq!(calls.load(Ordering::Relaxed), 5);
+
+        document.edit(3..4, "");
+        assert_eq!(
+            document.parse(),
+            Ok(vec!["onefourty".to_string(), "threes".to_string()])
+        );
+    }
+}
@@ -0,0 +1,198 @@
+//! Layouts where indentation delimits blocks, as in Python or YAML.
+//!
+//! The indentation of a line is the number of spaces and tabs it starts
+//! with. [`indent_block`] parses lines indented deeper than the enclosing
+//! block as a block of their own, which ends at the first line indented
+//! less. Lines of nothing but whitespace are skipped.
+//!
+//! ```
+//! use parcoom::indent::indent_block;
+//! use parcoom::{chars, prefix, recursive, run_complete};
+//!
+//! #[derive(Debug, PartialEq)]
+//! struct Node(String, Vec<Node>);
+//!
+//! let tree = recursive(|tree| {
+//!     let children = prefix(":") >> chars::line_ending() >> indent_block(tree);
+//!     (chars::alpha().many1() + children.or_default())
+//!         .map(|(name, children)| Node(String::from_iter(name), children))
+//! });
+//! let parsed = run_complete(tree, "a:\n  b\n  c:\n    d\n  e".to_string());
+//! let leaf = |name: &str| Node(name.to_string(), vec![]);
+//! let c = Node("c".to_string(), vec![leaf("d")]);
+//! assert_eq!(parsed, Ok(Node("a".to_string(), vec![leaf("b"), c, leaf("e")])));
+//! ```
+
+use alloc::format;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+
+use crate::{input_sub, Failure, Needed, Parser, ParserInput, Ptr, Source};
+
+/// Whether `input` is at the start of a line.
+fn at_line_start(input: &ParserInput) -> bool {
+    match &input.source {
+        Source::Text(s) => input.pos == 0 || s.as_bytes()[..input.pos].ends_with(b"\n"),
+        Source::Bytes(_) | Source::Tokens(..) => false,
+    }
+}
+
+/// Skips the rest of the line if blank, and the blank lines after it, then
+/// the indentation of the next line, whose width is checked with `fits`.
+/// `what` describes the widths that fit.
+fn indentation<'a>(what: &'static str, fits: fn(usize, usize) -> bool) -> Parser<'a, usize> {
+    Parser {
+        run: Ptr::new(move |input| {
+            let text = input.text();
+            let mut start = match text.find(|c: char| !c.is_whitespace() || c == '\n') {
+                Some(i) if text[i..].starts_with('\n') => i + 1,
+                _ if at_line_start(&input) => 0,
+                None if input.partial => return (input, Err(Failure::incomplete(Needed::Unknown))),
+                _ => {
+                    let desc = "expected a new line".to_string();
+                    return (
+                        input,
+                        Err(Failure::expecting("a new line".to_string(), desc)),
+                    );
+                }
+            };
+            loop {
+                let line = &text[start..];
+                let width = line.find(|c| c != ' ' && c != '\t').unwrap_or(line.len());
+                match line[width..].chars().next() {
+                    Some('\n') => start += width + 1,
+                    Some('\r') if line[width..].starts_with("\r\n") => start += width + 2,
+                    None if input.partial => {
+                        return (input, Err(Failure::incomplete(Needed::Unknown)))
+                    }
+                    None => {
+                        let desc = format!("expected {what}, got end of input");
+                        return (input, Err(Failure::expecting(what.to_string(), desc)));
+                    }
+                    Some(_) if fits(width, input.indent) => {
+                        return (input_sub(start + width, &input), Ok(width))
+                    }
+                    Some(_) => {
+                        let desc = format!("expected {what}, got an indentation of {width}");
+                        return (input, Err(Failure::expecting(what.to_string(), desc)));
+                    }
+                }
+            }
+        }),
+        shape: None,
+    }
+}
+
+/// The indentation of the next line that isn't blank, which must be that of
+/// the enclosing [`indent_block`], or none outside of one. Starts at the end
+/// of a line or at the start of the next.
+pub fn same_indent<'a>() -> Parser<'a, ()> {
+    indentation("the same indentation", |width, indent| width == indent).ignored()
+}
+
+/// The width of the indentation of the next line that isn't blank, which
+/// must be deeper than that of the enclosing [`indent_block`]. Starts at the
+/// end of a line or at the start of the next.
+pub fn deeper_indent<'a>() -> Parser<'a, usize> {
+    indentation("a deeper indentation", |width, indent| width > indent)
+}
+
+/// Lines indented deeper than the enclosing block, each starting an `item`,
+/// and at least one. Items may span several lines, those indented as deep
+/// or deeper nested blocks of their own.
+pub fn indent_block<'a, A: 'a>(item: Parser<'a, A>) -> Parser<'a, Vec<A>> {
+    let items = item.clone() + (same_indent() >> item).many();
+    let deeper = deeper_indent();
+    Parser {
+        run: Ptr::new(move |input| {
+            let outer = input.indent;
+            let (input_, indent) = match (deeper.run)(input) {
+                (input_, Ok(indent)) => (input_, indent),
+                (input_, Err(e)) => return (input_, Err(e)),
+            };
+            let (input_, result) = (items.run)(ParserInput { indent, ..input_ });
+            let input_ = ParserInput {
+                indent: outer,
+                ..input_
+            };
+            match result {
+                Ok((first, mut rest)) => {
+                    rest.insert(0, first);
+                    (input_, Ok(rest))
+                }
+                Err(e) => (input_, Err(e)),
+            }
+        }),
+        shape: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chars::{alpha, line, take_while, WHITESPACE};
+    use crate::{prefix, recursive, run, run_complete, run_streaming, StreamResult};
+    use alloc::string::String;
+    use alloc::vec;
+
+    /// The names of a tree, nested like the lines of `- name` below `name:`.
+    fn outline<'a>() -> Parser<'a, Vec<String>> {
+        recursive(|outline| {
+            let name = alpha().many1().map(String::from_iter);
+            let children = prefix(":") >> indent_block(outline);
+            (prefix("- ") >> (name + children.or_default())).map(|(name, children)| {
+                let nested = children
+                    .into_iter()
+                    .flatten()
+                    .map(|c| format!("{name}.{c}"));
+                [vec![name.clone()], nested.collect()].concat()
+            })
+        })
+    }
+
+    #[test]
+    fn indent_block_test() {
+        let program = outline().sep_by(same_indent()) << take_while(WHITESPACE);
+        let text = "- a:\n  - b\n\n  - c:\n     - d\n  \n  - e\n";
+        let names = ["a", "a.b", "a.c", "a.c.d", "a.e"].map(String::from);
+        assert_eq!(
+            run_complete(program.clone(), text.to_string()),
+            Ok(vec![names.to_vec()])
+        );
+
+        let parsed = run_complete(program.clone(), "- a:\n - b\n- c".to_string());
+        assert_eq!(parsed.map(|nodes| nodes.len()), Ok(2));
+        // the second child, indented less than the first, is left over
+        let e = run_complete(program, "- a:\n   - b\n  - c".to_string()).unwrap_err();
+        assert_eq!(e.location.offset, 14);
+    }
+
+    #[test]
+    fn indent_test() {
+        let p = deeper_indent() + line().to_owned();
+        assert_eq!(
+            run(p.clone(), "\n  \n\t x".to_string()),
+            Ok((2, "x".to_string()))
+        );
+        assert_eq!(
+            run(p.clone(), "  \r\n  x".to_string()),
+            Ok((2, "x".to_string()))
+        );
+        let e = run(p.clone(), "\nx".to_string()).unwrap_err();
+        assert_eq!(
+            e.desc,
+            "expected a deeper indentation, got an indentation of 0"
+        );
+        assert_eq!(e.location.offset, 0);
+        let e = run(p, "\n \n".to_string()).unwrap_err();
+        assert_eq!(e.desc, "expected a deeper indentation, got end of input");
+
+        let p = prefix("a") >> same_indent() >> prefix("b");
+        assert!(run(p.clone(), "a  \nb".to_string()).is_ok());
+        let e = run(p, "a b".to_string()).unwrap_err();
+        assert_eq!(e.expected, ["a new line".to_string()]);
+
+        let streamed = run_streaming(same_indent(), "\n  ".to_string());
+        assert_eq!(streamed, StreamResult::Incomplete(Needed::Unknown));
+    }
+}
@@ -0,0 +1,177 @@
+//! Interning the text of identifiers and the like into small symbols, so
+//! that sources repeating them don't allocate a string for each time.
+//!
+//! ```
+//! use parcoom::ident::Identifier;
+//! use parcoom::intern::{run_interned, StringInterner};
+//! use parcoom::prefix;
+//!
+//! let ident = Identifier::new().interned::<StringInterner>();
+//! let idents = ident.sep_by(prefix(" "));
+//! let (parsed, interner) = run_interned(idents, "a b a".to_string(), StringInterner::new());
+//! let symbols = parsed.unwrap();
+//! assert_eq!(symbols[0], symbols[2]);
+//! assert_eq!(interner.resolve(symbols[1]), "b");
+//! assert_eq!(interner.len(), 2);
+//! ```
+
+use alloc::collections::BTreeMap;
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::any::type_name;
+use core::cell::RefCell;
+
+use crate::{finish, make_input, Parse, Parser, ParserError, ParserInput, Ptr};
+
+/// What [`interned`] parsers intern text into, [`StringInterner`] or one of
+/// the crates for that with a little wrapping.
+pub trait Interner {
+    type Symbol;
+
+    /// The symbol of `text`, the same one each time.
+    fn intern(&mut self, text: &str) -> Self::Symbol;
+}
+
+/// A handle of an interned string, see [`StringInterner`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Symbol(u32);
+
+impl Symbol {
+    /// The index of the symbol, the number of symbols interned before it.
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// Interns strings into [`Symbol`]s numbered from 0 on, keeping one copy of
+/// each.
+#[derive(Debug, Clone, Default)]
+pub struct StringInterner {
+    symbols: BTreeMap<Arc<str>, Symbol>,
+    strings: Vec<Arc<str>>,
+}
+
+impl StringInterner {
+    pub fn new() -> Self {
+        StringInterner::default()
+    }
+
+    /// The symbol of `text` if it was interned.
+    pub fn get(&self, text: &str) -> Option<Symbol> {
+        self.symbols.get(text).copied()
+    }
+
+    /// The string of `symbol`, which must be from this interner.
+    pub fn resolve(&self, symbol: Symbol) -> &str {
+        &self.strings[symbol.index()]
+    }
+
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+}
+
+impl Interner for StringInterner {
+    type Symbol = Symbol;
+
+    fn intern(&mut self, text: &str) -> Symbol {
+        if let Some(symbol) = self.get(text) {
+            return symbol;
+        }
+        let symbol = Symbol(self.strings.len() as u32);
+        let text: Arc<str> = Arc::from(text);
+        self.strings.push(text.clone());
+        self.symbols.insert(text, symbol);
+        symbol
+    }
+}
+
+/// Interns the text `p` matched into the interner of type `I` the parse
+/// runs with, see [`run_interned`], which it panics without.
+pub fn interned<'a, I: Interner + 'static, A: 'a>(p: Parser<'a, A>) -> Parser<'a, I::Symbol> {
+    Parser {
+        run: Ptr::new(move |input| match (p.run)(input.clone()) {
+            (input_, Ok(_)) => {
+                let interner = input.interner.as_ref();
+                let interner = interner.and_then(|interner| interner.downcast_ref::<RefCell<I>>());
+                let Some(interner) = interner else {
+                    panic!("no interner of type {}, see run_interned", type_name::<I>())
+                };
+                let text = &input.text()[..input_.pos - input.pos];
+                let symbol = interner.borrow_mut().intern(text);
+                (input_, Ok(symbol))
+            }
+            (input_, Err(e)) => (input_, Err(e)),
+        }),
+        shape: p.shape.clone(),
+    }
+}
+
+/// Runs `p` on `input` with `interner` for its [`interned`] parsers, and
+/// returns the interner along with the result.
+pub fn run_interned<A, I: Interner + 'static>(
+    p: impl Parse<A>,
+    input: String,
+    interner: I,
+) -> (Result<A, ParserError>, I) {
+    let interner = Rc::new(RefCell::new(interner));
+    let input = ParserInput {
+        interner: Some(interner.clone()),
+        ..make_input(input)
+    };
+    let result = finish(p.parse(input));
+    match Rc::try_unwrap(interner) {
+        Ok(interner) => (result, interner.into_inner()),
+        Err(_) => unreachable!("the input is gone"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chars::digits1;
+    use crate::ident::identifier;
+    use crate::{prefix, run};
+    use alloc::string::ToString;
+    use alloc::vec;
+
+    /// Interns by length, to check that other interners plug in.
+    struct ByLength;
+
+    impl Interner for ByLength {
+        type Symbol = usize;
+
+        fn intern(&mut self, text: &str) -> usize {
+            text.len()
+        }
+    }
+
+    #[test]
+    fn interned_test() {
+        let assignment = (identifier() << prefix("=")) + interned::<StringInterner, _>(digits1());
+        let parser = assignment.sep_by(prefix(";"));
+        let input = "x=12;y=3;z=12".to_string();
+        let (parsed, interner) = run_interned(parser, input, StringInterner::new());
+        let parsed = parsed.unwrap();
+        assert_eq!(parsed[0].1, parsed[2].1);
+        assert_eq!(parsed[1].1.index(), 1);
+        assert_eq!(interner.get("12"), Some(parsed[0].1));
+        assert_eq!(interner.get("x"), None);
+
+        let words = interned::<ByLength, _>(identifier()).sep_by(prefix(" "));
+        let (parsed, _) = run_interned(words, "ab c def".to_string(), ByLength);
+        assert_eq!(parsed, Ok(vec![2, 1, 3]));
+    }
+
+    #[test]
+    #[should_panic(expected = "no interner of type")]
+    fn no_interner_test() {
+        let _ = run(interned::<StringInterner, _>(digits1()), "1".to_string());
+    }
+}
@@ -0,0 +1,265 @@
+//! Skipping the junk between tokens, whitespace unless configured otherwise.
+//!
+//! Wrap a grammar in [`with_junk`] to say once what counts as junk, and every
+//! [`padded`] or [`lexeme`] parser in it skips that. [`Junk`] builds junk
+//! parsers skipping comments.
+
+use alloc::format;
+use alloc::vec::Vec;
+
+use crate::{input_sub, Failure, Parser, ParserInput, Ptr};
+
+/// Skips the junk at the start of `input`.
+fn skip_junk(input: ParserInput) -> (ParserInput, Result<(), Failure>) {
+    if let Some(junk) = input.junk.clone() {
+        return (junk.run)(input);
+    }
+    let n = input.text().len() - input.text().trim_start().len();
+    let pos = input.pos + n;
+    (ParserInput { pos, ..input }, Ok(()))
+}
+
+/// Runs `p` with the junk before and after it skipped.
+pub fn padded<'a, A: 'a>(p: Parser<'a, A>) -> Parser<'a, A> {
+    Parser {
+        run: Ptr::new(move |input| match skip_junk(input) {
+            (input_, Ok(())) => lexeme_run(&p, input_),
+            (input_, Err(e)) => (input_, Err(e)),
+        }),
+        shape: None,
+    }
+}
+
+fn lexeme_run<A>(p: &Parser<A>, input: ParserInput) -> (ParserInput, Result<A, Failure>) {
+    match (p.run)(input) {
+        (input_, Ok(x)) => match skip_junk(input_) {
+            (input__, Ok(())) => (input__, Ok(x)),
+            (input__, Err(e)) => (input__, Err(e)),
+        },
+        (input_, Err(e)) => (input_, Err(e)),
+    }
+}
+
+/// Runs `p` with the junk after it skipped, the usual way to write the
+/// tokens of a grammar that skips the junk at its start once.
+pub fn lexeme<'a, A: 'a>(p: Parser<'a, A>) -> Parser<'a, A> {
+    Parser {
+        run: Ptr::new(move |input| lexeme_run(&p, input)),
+        shape: None,
+    }
+}
+
+/// Runs `p` with `junk` being what [`padded`] and [`lexeme`] parsers skip,
+/// and puts back the junk of the surrounding parser afterwards.
+pub fn with_junk<'a, B: 'static, A: 'a>(
+    junk: Parser<'static, B>,
+    p: Parser<'a, A>,
+) -> Parser<'a, A> {
+    let junk = Ptr::new(junk.ignored());
+    Parser {
+        run: Ptr::new(move |input| {
+            let outer = input.junk.clone();
+            let inner = ParserInput {
+                junk: Some(junk.clone()),
+                ..input
+            };
+            let (input_, result) = (p.run)(inner);
+            (
+                ParserInput {
+                    junk: outer,
+                    ..input_
+                },
+                result,
+            )
+        }),
+        shape: None,
+    }
+}
+
+/// Builds a parser skipping whitespace and comments, for [`with_junk`].
+#[derive(Debug, Clone, Default)]
+pub struct Junk {
+    line_comments: Vec<&'static str>,
+    /// Their start, end and whether they nest.
+    block_comments: Vec<(&'static str, &'static str, bool)>,
+}
+
+impl Junk {
+    pub fn new() -> Self {
+        Junk::default()
+    }
+
+    /// Comments from `start` to the end of the line, such as `//`.
+    pub fn line_comment(mut self, start: &'static str) -> Self {
+        self.line_comments.push(start);
+        self
+    }
+
+    /// Comments from `start` to `end`, such as `/*` and `*/`.
+    pub fn block_comment(mut self, start: &'static str, end: &'static str) -> Self {
+        self.block_comments.push((start, end, false));
+        self
+    }
+
+    /// Like [`Junk::block_comment`], but comments may contain comments.
+    pub fn nested_block_comment(mut self, start: &'static str, end: &'static str) -> Self {
+        self.block_comments.push((start, end, true));
+        self
+    }
+
+    /// The junk parser. It fails on block comments that are not closed.
+    pub fn build(self) -> Parser<'static, ()> {
+        Parser {
+            run: Ptr::new(move |input| {
+                let mut input = input;
+                loop {
+                    let n = input.text().len() - input.text().trim_start().len();
+                    input = input_sub(n, &input);
+                    let text = input.text();
+                    if self.line_comments.iter().any(|&s| text.starts_with(s)) {
+                        let end = text.find('\n').unwrap_or(text.len());
+                        input = input_sub(end, &input);
+                        continue;
+                    }
+                    let block = self
+                        .block_comments
+                        .iter()
+                        .find(|(s, _, _)| text.starts_with(s));
+                    let Some(&(start, end, nested)) = block else {
+                        return (input, Ok(()));
+                    };
+                    match block_comment_len(text, start, end, nested) {
+                        Some(n) => input = input_sub(n, &input),
+                        None => {
+                            let desc = format!("unterminated block comment, expected {end}");
+                            let e = Failure::unterminated(format!("{end:?}"), desc);
+                            return (input, Err(e));
+                        }
+                    }
+                }
+            }),
+            shape: None,
+        }
+    }
+}
+
+/// The length of the block comment `text` starts with, if it is closed.
+fn block_comment_len(text: &str, start: &str, end: &str, nested: bool) -> Option<usize> {
+    let mut depth = 0;
+    let mut i = 0;
+    while i < text.len() {
+        let rest = &text[i..];
+        if rest.starts_with(start) && (depth == 0 || nested) {
+            depth += 1;
+            i += start.len();
+        } else if rest.starts_with(end) {
+            depth -= 1;
+            i += end.len();
+            if depth == 0 {
+                return Some(i);
+            }
+        } else {
+            i += rest.chars().next().unwrap().len_utf8();
+        }
+    }
+    None
+}
+
+impl<'a, T: 'a> Parser<'a, T> {
+    pub fn padded(self) -> Parser<'a, T> {
+        padded(self)
+    }
+
+    pub fn lexeme(self) -> Parser<'a, T> {
+        lexeme(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chars::digits1;
+    use crate::{prefix, run, satisfy, Location, ParserError};
+
+    #[test]
+    fn padded_test() {
+        let key = digits1().to_owned().padded();
+        let parser = (key.clone() << prefix("=")) + key;
+        let parsed = run(parser, "  1 =\n 2  ".to_string());
+        assert_eq!(parsed, Ok(("1".to_string(), "2".to_string())));
+    }
+
+    #[test]
+    fn with_junk_test() {
+        let junk = (satisfy(|c| c == ' ') | prefix("~").map(|_| '~')).many();
+        let items = digits1().to_owned().lexeme().many();
+        let parser = with_junk(junk, items) + digits1().to_owned().padded();
+        let parsed = run(parser, "1 ~ 2~3\n4".to_string());
+        assert_eq!(
+            parsed,
+            Ok((
+                vec!["1".to_string(), "2".to_string(), "3".to_string()],
+                "4".to_string()
+            ))
+        );
+
+        // outside of `with_junk` `~` is no junk
+        let parsed = run(digits1().padded(), " ~1".to_string());
+        assert_eq!(
+            parsed,
+            Err(ParserError {
+                desc: "expected digits".to_string(),
+                location: Location {
+                    offset: 1,
+                    char_offset: 1,
+                    line: 1,
+                    column: 2,
+                },
+                context: vec![],
+                expected: vec!["digits".to_string()],
+                cause: None,
+                at_end: false,
+            })
+        );
+    }
+
+    #[test]
+    fn comments_test() {
+        let junk = || {
+            Junk::new()
+                .line_comment("//")
+                .line_comment("#")
+                .nested_block_comment("/*", "*/")
+        };
+        let items = digits1().padded().many();
+        let source = "1 // one\n/* two /* 2 */ */ 2 # three\n3/**/";
+        let parsed = run(with_junk(junk().build(), items.clone()), source.to_string());
+        assert_eq!(parsed.unwrap(), ["1", "2", "3"]);
+
+        let parsed = run(with_junk(junk().build(), items), "1 /* /* */".to_string());
+        assert_eq!(
+            parsed,
+            Err(ParserError {
+                desc: "unterminated block comment, expected */".to_string(),
+                location: Location {
+                    offset: 2,
+                    char_offset: 2,
+                    line: 1,
+                    column: 3,
+                },
+                context: vec![],
+                expected: vec!["\"*/\"".to_string()],
+                cause: None,
+                at_end: true,
+            })
+        );
+
+        // without nesting the first end closes the comment
+        let junk = Junk::new().block_comment("(*", "*)").build();
+        let parser = with_junk(junk, digits1().to_owned().padded() + prefix("*)"));
+        assert_eq!(
+            run(parser, "(* (* *) 1 *)".to_string()),
+            Ok(("1".to_string(), "*)"))
+        );
+    }
+}
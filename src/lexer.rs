@@ -0,0 +1,264 @@
+//! Splitting text into tokens ahead of parsing them with [`crate::tokens`].
+//!
+//! ```
+//! use parcoom::lexer::Lexer;
+//!
+//! #[derive(Debug, Clone, PartialEq)]
+//! enum Token {
+//!     Let,
+//!     Ident(String),
+//!     Eq,
+//! }
+//!
+//! let lexer = Lexer::new()
+//!     .literal("let", Token::Let)
+//!     .literal("=", Token::Eq)
+//!     .class("identifier", char::is_alphabetic, |s| Token::Ident(s.to_string()))
+//!     .skip_class("whitespace", char::is_whitespace);
+//! let tokens: Result<Vec<_>, _> = lexer.tokens("let x = y".to_string()).collect();
+//! let tokens: Vec<_> = tokens.unwrap().into_iter().map(|t| t.value).collect();
+//! assert_eq!(tokens[..2], [Token::Let, Token::Ident("x".to_string())]);
+//! ```
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::chars::while1;
+use crate::{make_input, prefix, Failure, MaybeSync, Parser, ParserError, ParserInput, Spanned};
+
+/// A set of token rules. At each position the rule matching the most input
+/// wins, the one declared first if several match as much.
+pub struct Lexer<'a, T> {
+    /// `None` results are skipped.
+    rules: Vec<Parser<'a, Option<T>>>,
+}
+
+impl<'a, T: 'a> Default for Lexer<'a, T> {
+    fn default() -> Self {
+        Lexer::new()
+    }
+}
+
+impl<'a, T: 'a> Lexer<'a, T> {
+    pub fn new() -> Self {
+        Lexer { rules: Vec::new() }
+    }
+
+    /// Tokens produced by `p`.
+    pub fn rule(mut self, p: Parser<'a, T>) -> Self {
+        self.rules.push(p.map(Some));
+        self
+    }
+
+    /// `token` wherever the source reads `literal`.
+    pub fn literal(self, literal: &'static str, token: T) -> Self
+    where
+        T: Clone + MaybeSync,
+    {
+        self.rule(prefix(literal).map(move |_| token.clone()))
+    }
+
+    /// A token made by `f` out of every run of chars accepted by `class`.
+    pub fn class(
+        self,
+        expected: &'static str,
+        class: impl Fn(char) -> bool + MaybeSync + 'a,
+        f: impl Fn(&str) -> T + MaybeSync + 'a,
+    ) -> Self {
+        self.rule(while1(expected, class).map(move |s| f(&s)))
+    }
+
+    /// Skips what `p` matches, e.g. comments.
+    pub fn skip<B: 'a>(mut self, p: Parser<'a, B>) -> Self {
+        self.rules.push(p.map(|_| None));
+        self
+    }
+
+    /// Skips runs of chars accepted by `class`, e.g. whitespace.
+    pub fn skip_class(
+        self,
+        expected: &'static str,
+        class: impl Fn(char) -> bool + MaybeSync + 'a,
+    ) -> Self {
+        self.skip(while1(expected, class))
+    }
+
+    /// The tokens of `input`. After an error the iterator ends.
+    pub fn tokens(&self, input: String) -> Tokens<'_, 'a, T> {
+        Tokens {
+            lexer: self,
+            input: Some(make_input(input)),
+        }
+    }
+}
+
+/// The iterator returned by [`Lexer::tokens`].
+pub struct Tokens<'l, 'a, T> {
+    lexer: &'l Lexer<'a, T>,
+    /// `None` once done.
+    input: Option<ParserInput>,
+}
+
+impl<T> Iterator for Tokens<'_, '_, T> {
+    type Item = Result<Spanned<T>, ParserError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let input = self.input.take()?;
+            if input.text().is_empty() {
+                return None;
+            }
+            let mut best: Option<(ParserInput, Option<T>)> = None;
+            let mut failure: Option<(ParserInput, Failure)> = None;
+            for rule in &self.lexer.rules {
+                match (rule.run)(input.clone()) {
+                    (input_, Ok(x)) if input_.pos > input.pos => {
+                        if best.as_ref().is_none_or(|(b, _)| input_.pos > b.pos) {
+                            best = Some((input_, x));
+                        }
+                    }
+                    (_, Ok(_)) => {}
+                    (input_, Err(e)) => {
+                        failure = match failure {
+                            Some((f_input, f)) if f_input.pos == input_.pos => {
+                                Some((input_, f.merge(e)))
+                            }
+                            Some((f_input, f)) if f_input.pos > input_.pos => Some((f_input, f)),
+                            _ => Some((input_, e)),
+                        }
+                    }
+                }
+            }
+            match best {
+                Some((input_, Some(value))) => {
+                    let span = input.pos..input_.pos;
+                    self.input = Some(input_);
+                    return Some(Ok(Spanned { value, span }));
+                }
+                Some((input_, None)) => self.input = Some(input_),
+                None => {
+                    let (input_, e) = failure.unwrap_or_else(|| {
+                        let e = Failure::from("no token rule matches".to_string());
+                        (input.clone(), e)
+                    });
+                    return Some(Err(e.at(&input_)));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tokens::{satisfy_token, token};
+    use crate::{none_of, run_tokens, Location};
+
+    #[derive(Debug, Clone, PartialEq)]
+    enum Token {
+        Let,
+        Ident(String),
+        Num(i64),
+        Eq,
+        Plus,
+    }
+
+    fn lexer<'a>() -> Lexer<'a, Token> {
+        let comment = prefix("//") >> none_of("\n").many();
+        Lexer::new()
+            .literal("let", Token::Let)
+            .literal("=", Token::Eq)
+            .literal("+", Token::Plus)
+            .class("identifier", char::is_alphabetic, |s| {
+                Token::Ident(s.to_string())
+            })
+            .class(
+                "a number",
+                |c| c.is_ascii_digit(),
+                |s| Token::Num(s.parse().unwrap()),
+            )
+            .skip_class("whitespace", char::is_whitespace)
+            .skip(comment)
+    }
+
+    #[test]
+    fn lexer_test() {
+        let tokens: Vec<_> = lexer()
+            .tokens("let letter = 4 + 2 // six\n".to_string())
+            .collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Ok(Spanned {
+                    value: Token::Let,
+                    span: 0..3
+                }),
+                Ok(Spanned {
+                    value: Token::Ident("letter".to_string()),
+                    span: 4..10
+                }),
+                Ok(Spanned {
+                    value: Token::Eq,
+                    span: 11..12
+                }),
+                Ok(Spanned {
+                    value: Token::Num(4),
+                    span: 13..14
+                }),
+                Ok(Spanned {
+                    value: Token::Plus,
+                    span: 15..16
+                }),
+                Ok(Spanned {
+                    value: Token::Num(2),
+                    span: 17..18
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn lexer_error_test() {
+        let tokens: Vec<_> = lexer().tokens("x ?".to_string()).collect();
+        assert_eq!(tokens.len(), 2);
+        assert_eq!(
+            tokens[1],
+            Err(ParserError {
+                desc: "expected \"let\", \"=\", \"+\", identifier, a number, whitespace or \"//\""
+                    .to_string(),
+                location: Location {
+                    offset: 2,
+                    char_offset: 2,
+                    line: 1,
+                    column: 3,
+                },
+                context: vec![],
+                expected: vec![
+                    "\"let\"".to_string(),
+                    "\"=\"".to_string(),
+                    "\"+\"".to_string(),
+                    "identifier".to_string(),
+                    "a number".to_string(),
+                    "whitespace".to_string(),
+                    "\"//\"".to_string(),
+                ],
+                cause: None,
+                at_end: false,
+            })
+        );
+    }
+
+    #[test]
+    fn lex_then_parse_test() {
+        let tokens: Result<Vec<_>, _> = lexer().tokens("1 + 2 + 3".to_string()).collect();
+        let tokens: Vec<_> = tokens.unwrap().into_iter().map(|t| t.value).collect();
+        let num = satisfy_token(|t| matches!(t, Token::Num(_))).map(|t| match t {
+            Token::Num(n) => n,
+            _ => unreachable!(),
+        });
+        let sum = num
+            .sep_by1(token(Token::Plus))
+            .map(|xs| xs.iter().sum::<i64>());
+        assert_eq!(run_tokens(sum, tokens), Ok(6));
+    }
+}
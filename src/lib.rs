@@ -1,128 +1,815 @@
-use std::ops;
-use std::sync::Arc;
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 
-#[derive(Clone)]
-struct Parser<'a, T> {
+extern crate alloc;
+
+#[cfg(feature = "arena")]
+pub mod arena;
+pub mod balanced;
+pub mod bits;
+pub mod bytes;
+pub mod chars;
+pub mod combinator;
+pub mod datetime;
+mod debug;
+mod describe;
+mod diagnostic;
+#[cfg(feature = "diagram")]
+mod diagram;
+mod dispatch;
+mod entries;
+mod error;
+pub mod escapes;
+mod events;
+pub mod expr;
+#[cfg(feature = "formats")]
+pub mod formats;
+mod grammar;
+pub mod ident;
+mod incremental;
+pub mod indent;
+pub mod intern;
+mod junk;
+pub mod lexer;
+mod limits;
+pub mod literal;
+mod memo;
+pub mod net;
+pub mod number;
+#[cfg(feature = "sync")]
+pub mod parallel;
+mod parsable;
+mod permutation;
+pub mod profile;
+mod recursion;
+#[cfg(feature = "regex")]
+pub mod regex;
+mod render;
+mod sample;
+mod session;
+mod shape;
+mod state;
+mod streaming;
+mod substr;
+mod symbols;
+pub mod testing;
+pub mod tokens;
+mod tuple;
+#[cfg(feature = "unicode")]
+pub mod unicode;
+
+pub use bytes::run_bytes;
+pub use combinator::{BoxedParser, Parse, ParseResult};
+pub use diagnostic::{run_with_diagnostics, Diagnostic, Severity};
+pub use dispatch::switch_on_first_char;
+pub use entries::{entries, key_value, separated_pair, Duplicates};
+pub use error::{fail_with, from_str, run_as, ParseError};
+pub use events::{run_events, Event, TRIVIA};
+#[doc(hidden)]
+pub use grammar::__private;
+pub use incremental::Document;
+pub use junk::{lexeme, padded, with_junk, Junk};
+pub use limits::{run_with_config, RunConfig};
+#[cfg(feature = "derive")]
+pub use parcoom_derive::Parse;
+pub use parsable::Parsable;
+pub use permutation::{permutation, Permutation};
+pub use recursion::{left_rec, recursive};
+pub use sample::{SampleRng, SplitMix64};
+pub use session::ParseSession;
+pub use state::{get_state, modify_state, run_with_state, set_state, with_state};
+#[cfg(feature = "async")]
+pub use streaming::AsyncRead;
+#[cfg(feature = "std")]
+pub use streaming::StreamReader;
+pub use streaming::{run_streaming, Needed, StreamResult};
+pub use substr::Substr;
+pub use symbols::SymbolTable;
+pub use tokens::run_tokens;
+pub use tuple::{tuple, Sequence};
+
+use alloc::boxed::Box;
+use alloc::rc::Rc;
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use alloc::{format, vec};
+use core::any::Any;
+use core::cell::RefCell;
+use core::cmp::Reverse;
+use core::fmt;
+use core::ops;
+use core::ops::{Bound, Range, RangeBounds};
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use combinator::{many_onto, AnyChar, Prefix, Satisfy};
+use error::External;
+use incremental::Cache;
+use limits::{collect, step, Limits};
+use memo::Memo;
+use shape::Shape;
+
+pub struct Parser<'a, T> {
     run: Run<'a, T>,
+    /// What the parser is made of, for [`Parser::sample`].
+    shape: Option<Ptr<Shape>>,
 }
 
-type Run<'a, T> = Arc<dyn 'a + Fn(ParserInput) -> (ParserInput, Result<T, String>)>;
+impl<T> fmt::Debug for Parser<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Parser")
+    }
+}
 
-#[derive(Debug, Clone)]
-struct ParserInput {
-    text: String,
-    pos: usize,
+impl<T> Clone for Parser<'_, T> {
+    fn clone(&self) -> Self {
+        Parser {
+            run: self.run.clone(),
+            shape: self.shape.clone(),
+        }
+    }
 }
 
+/// What parsers are shared through: `Arc` with the `sync` feature, `Rc`
+/// without it.
+#[cfg(feature = "sync")]
+pub(crate) type Ptr<T> = Arc<T>;
+#[cfg(not(feature = "sync"))]
+pub(crate) type Ptr<T> = Rc<T>;
+
+#[cfg(feature = "sync")]
+type Run<'a, T> = Ptr<dyn 'a + Fn(ParserInput) -> (ParserInput, Result<T, Failure>) + Send + Sync>;
+#[cfg(not(feature = "sync"))]
+type Run<'a, T> = Ptr<dyn 'a + Fn(ParserInput) -> (ParserInput, Result<T, Failure>)>;
+
+/// `Send + Sync` with the `sync` feature, implemented by everything without
+/// it. Closures and values captured by parsers have to implement it, so that
+/// with the feature parsers can be shared between threads.
+#[cfg(feature = "sync")]
+pub trait MaybeSync: Send + Sync {}
+#[cfg(feature = "sync")]
+impl<T: Send + Sync + ?Sized> MaybeSync for T {}
+#[cfg(not(feature = "sync"))]
+pub trait MaybeSync {}
+#[cfg(not(feature = "sync"))]
+impl<T: ?Sized> MaybeSync for T {}
+
+/// `dyn Fn(A) -> B`, plus `Send + Sync` with the `sync` feature.
+#[cfg(feature = "sync")]
+pub type DynFn<'a, A, B> = dyn Fn(A) -> B + Send + Sync + 'a;
+#[cfg(not(feature = "sync"))]
+pub type DynFn<'a, A, B> = dyn Fn(A) -> B + 'a;
+
+/// The error a parser fails with internally. It becomes a [`ParserError`]
+/// once [`run`] knows where in the source it happened. Its contents are
+/// private; custom [`Parse`] impls pass on the failures of the parsers
+/// they delegate to.
 #[derive(Debug, Clone, PartialEq)]
-struct ParserError {
+pub struct Failure {
     desc: String,
+    /// Innermost context first.
+    context: Vec<String>,
+    /// What would have been accepted instead, e.g. `"let"` or `identifier`.
+    expected: Vec<String>,
+    /// Set once the parser committed to its path through [`cut`]; such
+    /// failures are not backtracked out of by `|`, `optional` or `many`.
+    fatal: bool,
+    /// Set when a streaming parser ran out of input, see [`run_streaming`].
+    /// The failure is fatal too, more input might make it go away.
+    needed: Option<Needed>,
+    /// What a [`Parser::map_res`] function failed with, for [`run_as`].
+    external: Option<External>,
+    /// The failure this one explains, see [`Parser::explain`].
+    cause: Option<Box<ParserError>>,
+    /// Set when the input ended partway through what was expected, such as
+    /// `wh` for `while`, which makes the error one of the input ending.
+    truncated: bool,
+    /// The failures of the two alternatives this one merges, which
+    /// [`run_as`] combines with [`ParseError::merge`]. Dropped when the
+    /// failure is described anew.
+    alternatives: Option<Box<(Failure, Failure)>>,
+}
+
+impl Failure {
+    fn expecting(item: String, desc: String) -> Failure {
+        Failure {
+            desc,
+            context: Vec::new(),
+            expected: vec![item],
+            fatal: false,
+            needed: None,
+            external: None,
+            cause: None,
+            truncated: false,
+            alternatives: None,
+        }
+    }
+
+    fn incomplete(needed: Needed) -> Failure {
+        Failure {
+            fatal: true,
+            needed: Some(needed),
+            ..Failure::from("more input needed".to_string())
+        }
+    }
+
+    /// A committed failure of the input ending before `item`, which closes
+    /// what came before, such as the quote of a string.
+    fn unterminated(item: String, desc: String) -> Failure {
+        Failure {
+            fatal: true,
+            truncated: true,
+            ..Failure::expecting(item, desc)
+        }
+    }
+
+    /// Combines the failures of two alternatives that gave up at the same
+    /// position. The expectations are merged when both sides have some,
+    /// otherwise the latter failure wins.
+    fn merge(self, mut other: Failure) -> Failure {
+        let alternatives = other.alternatives.take();
+        let mut merged = other.clone();
+        other.alternatives = alternatives;
+        if !self.expected.is_empty() && !other.expected.is_empty() {
+            let mut expected = self.expected.clone();
+            for item in &other.expected {
+                if !expected.contains(item) {
+                    expected.push(item.clone());
+                }
+            }
+            merged.desc = expected_desc(&expected);
+            merged.expected = expected;
+            merged.truncated = self.truncated || other.truncated;
+        }
+        merged.alternatives = Some(Box::new((self, other)));
+        merged
+    }
+
+    fn at(self, input: &ParserInput) -> ParserError {
+        let mut context = self.context;
+        context.reverse();
+        ParserError {
+            desc: self.desc,
+            location: input.location(),
+            context,
+            expected: self.expected,
+            cause: self.cause,
+            at_end: input.at_end() || self.truncated,
+        }
+    }
+}
+
+/// `expected "let", "fn" or identifier`
+fn expected_desc(expected: &[String]) -> String {
+    match expected {
+        [] => "expected nothing".to_string(),
+        [item] => format!("expected {item}"),
+        [init @ .., last] => format!("expected {} or {last}", init.join(", ")),
+    }
+}
+
+impl From<String> for Failure {
+    fn from(desc: String) -> Self {
+        Failure {
+            desc,
+            context: Vec::new(),
+            expected: Vec::new(),
+            fatal: false,
+            needed: None,
+            external: None,
+            cause: None,
+            truncated: false,
+            alternatives: None,
+        }
+    }
+}
+
+/// The whole source being parsed and how far into it the parser is.
+/// Cloning only bumps a reference count.
+#[derive(Debug, Clone)]
+pub struct ParserInput {
+    source: Source,
+    pos: usize,
+    /// How many bits of the byte at `pos` are consumed, see [`bits::bits`].
+    bit: u8,
+    /// Errors that were recovered from so far, see [`Parser::recover_with`],
+    /// and warnings, see [`Parser::warn`]. They are part of the input so
+    /// backtracking forgets them too.
+    diagnostics: Arc<Vec<Diagnostic>>,
+    /// Whether the source may continue past its end, see [`run_streaming`].
+    partial: bool,
+    /// Results of [`Parser::cached`] parsers when parsing a [`Document`].
+    cache: Option<Rc<RefCell<Cache>>>,
+    /// Results of [`Parser::memoized`] parsers during this run.
+    memo: Rc<RefCell<Memo>>,
+    /// The user state, see [`get_state`] and [`modify_state`].
+    state: Option<Arc<dyn Any>>,
+    /// What [`padded`] parsers skip if not whitespace, see [`with_junk`].
+    junk: Option<Ptr<Parser<'static, ()>>>,
+    /// The syntax tree events so far, when running through [`run_events`].
+    events: Option<Arc<Vec<Event>>>,
+    /// The limits of the run, see [`run_with_config`].
+    limits: Option<Rc<Limits>>,
+    /// What [`intern::interned`] parsers intern into, a `RefCell` of it,
+    /// see [`intern::run_interned`].
+    interner: Option<Rc<dyn Any>>,
+    /// The indentation of the innermost [`indent::indent_block`], 0 outside
+    /// of one. Blocks put back the one of the block around them when done.
+    indent: usize,
+    /// What the named rules did, when running through
+    /// [`profile::run_profiled`].
+    profile: Option<Rc<RefCell<profile::Profile>>>,
+    /// Where the source ends for the parser, inside of a field of which
+    /// [`bytes::length_value`] knows the length.
+    end: Option<usize>,
+}
+
+/// What is being parsed: text from [`run`] and friends, binary data from
+/// [`run_bytes`] or a `Vec` of tokens from [`run_tokens`].
+#[derive(Debug, Clone)]
+pub(crate) enum Source {
+    Text(Arc<str>),
+    Bytes(Arc<[u8]>),
+    /// The `Vec` of tokens and how many there are.
+    Tokens(Arc<dyn Any>, usize),
+}
+
+impl ParserInput {
+    /// The not yet consumed part of the source. Binary sources have no text,
+    /// neither has a text source after a byte parser stopped inside a char.
+    pub fn text(&self) -> &str {
+        match &self.source {
+            Source::Text(s) => s.get(self.pos..self.end.unwrap_or(s.len())).unwrap_or(""),
+            Source::Bytes(_) | Source::Tokens(..) => "",
+        }
+    }
+
+    /// The not yet consumed part of the source as bytes.
+    pub fn bytes(&self) -> &[u8] {
+        let bytes: &[u8] = match &self.source {
+            Source::Text(s) => s.as_bytes(),
+            Source::Bytes(b) => b,
+            Source::Tokens(..) => &[],
+        };
+        bytes
+            .get(self.pos..self.end.unwrap_or(bytes.len()))
+            .unwrap_or(&[])
+    }
+
+    /// Whether all of the source is consumed.
+    fn at_end(&self) -> bool {
+        let len = match &self.source {
+            Source::Text(s) => s.len(),
+            Source::Bytes(b) => b.len(),
+            Source::Tokens(_, len) => *len,
+        };
+        self.pos >= self.end.map_or(len, |end| end.min(len))
+    }
+
+    pub fn location(&self) -> Location {
+        Location::of(&self.source, self.pos)
+    }
+
+    /// How far into the source the input is, in bytes or tokens.
+    pub fn offset(&self) -> usize {
+        self.pos
+    }
+
+    /// Where the input is, to go back to with [`rewind`](ParserInput::rewind)
+    /// when a parser run from here fails. Cheaper than keeping a clone of
+    /// the input.
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            pos: self.pos,
+            bit: self.bit,
+            diagnostics: self.diagnostics.clone(),
+            state: self.state.clone(),
+            events: self.events.clone(),
+        }
+    }
+
+    /// Goes back to `checkpoint`, which must come from this input or one a
+    /// parser returned for it. The position, diagnostics, user state and
+    /// events are put back as they were.
+    pub fn rewind(&mut self, checkpoint: Checkpoint) {
+        self.pos = checkpoint.pos;
+        self.bit = checkpoint.bit;
+        self.diagnostics = checkpoint.diagnostics;
+        self.state = checkpoint.state;
+        self.events = checkpoint.events;
+    }
+
+    /// The input after the next `n` bytes, or tokens, which it must have.
+    pub fn advance(&self, n: usize) -> ParserInput {
+        let len = match &self.source {
+            Source::Tokens(_, len) => self.end.unwrap_or(*len).saturating_sub(self.pos),
+            _ => self.bytes().len(),
+        };
+        assert!(
+            n <= len,
+            "advancing {n} past the end of the input, {len} ahead"
+        );
+        ParserInput {
+            pos: self.pos + n,
+            ..self.clone()
+        }
+    }
+
+    /// The text between `start`, an earlier input of the same source, and
+    /// this one, i.e. what was parsed since. Empty for binary and token
+    /// sources.
+    pub fn slice_from(&self, start: &ParserInput) -> &str {
+        match &self.source {
+            Source::Text(s) => s.get(start.pos..self.pos).unwrap_or(""),
+            Source::Bytes(_) | Source::Tokens(..) => "",
+        }
+    }
+
+    /// Fails `n` bytes into the input, expecting `expected` there, e.g. at a
+    /// bad char found while looking ahead. Not fatal, like the failures of
+    /// the built-in parsers.
+    pub fn error_at<T>(&self, n: usize, expected: &str) -> ParseResult<T> {
+        let at = self.advance(n);
+        let desc = match at.text().chars().next() {
+            Some(c) => format!("expected {expected}, got {c:?}"),
+            None if at.at_end() => format!("expected {expected}, got end of input"),
+            None => format!("expected {expected}"),
+        };
+        ParseResult::err(at, Failure::expecting(expected.to_string(), desc))
+    }
+}
+
+/// A position of a [`ParserInput`] or [`ParseSession`] to go back to.
+#[derive(Debug, Clone)]
+pub struct Checkpoint {
     pos: usize,
+    bit: u8,
+    diagnostics: Arc<Vec<Diagnostic>>,
+    state: Option<Arc<dyn Any>>,
+    events: Option<Arc<Vec<Event>>>,
+}
+
+/// A position in the source. `offset` is in bytes and `char_offset` in
+/// chars, `line` and `column` are 1-based and `column` counts chars. Binary
+/// sources are a single line with a column per byte, token sources one with
+/// a column per token and both offsets being the index of the token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Location {
+    pub offset: usize,
+    pub char_offset: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Location {
+    fn of(source: &Source, offset: usize) -> Location {
+        let before = match source {
+            Source::Text(s) => &s.as_bytes()[..offset],
+            Source::Bytes(_) | Source::Tokens(..) => {
+                return Location {
+                    offset,
+                    char_offset: offset,
+                    line: 1,
+                    column: offset + 1,
+                }
+            }
+        };
+        let line_start = before
+            .iter()
+            .rposition(|&b| b == b'\n')
+            .map_or(0, |i| i + 1);
+        // Counted on bytes, `offset` may be inside a char after byte parsers.
+        let chars = |bytes: &[u8]| bytes.iter().filter(|&&b| b & 0xc0 != 0x80).count();
+        Location {
+            offset,
+            char_offset: chars(before),
+            line: before.iter().filter(|&&b| b == b'\n').count() + 1,
+            column: chars(&before[line_start..]) + 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParserError {
+    pub desc: String,
+    pub location: Location,
+    /// The contexts the failing parser was running in, outermost first.
+    pub context: Vec<String>,
+    /// Everything that would have been accepted at `location`.
+    pub expected: Vec<String>,
+    /// The lower level error this one explains, see [`Parser::explain`].
+    pub cause: Option<Box<ParserError>>,
+    /// Whether the input ended at `location`, or before the end of what was
+    /// expected there, such as in the middle of a keyword, see
+    /// [`ParserError::is_incomplete`].
+    pub at_end: bool,
+}
+
+/// What kind of failure a [`ParserError`] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// The input ended where more of it was expected.
+    UnexpectedEof,
+    /// Something else would have been accepted, see [`ParserError::expected`].
+    Expected,
+    /// Any other failure, such as a value out of range.
+    Message,
+}
+
+impl ParserError {
+    /// The description prefixed with the context stack, e.g.
+    /// `in entry > value: expected "`.
+    pub fn message(&self) -> String {
+        if self.context.is_empty() {
+            self.desc.clone()
+        } else {
+            format!("in {}: {}", self.context.join(" > "), self.desc)
+        }
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        if self.at_end && !self.expected.is_empty() {
+            ErrorKind::UnexpectedEof
+        } else if self.expected.is_empty() {
+            ErrorKind::Message
+        } else {
+            ErrorKind::Expected
+        }
+    }
+
+    /// Whether the parse failed because the input ended, rather than at
+    /// something wrong in it, so that more input, such as the next line at
+    /// an interactive prompt, could still make it parse. Looks at the
+    /// [`root_cause`](ParserError::root_cause) of explained errors.
+    ///
+    /// ```
+    /// use parcoom::{prefix, run};
+    ///
+    /// let call = prefix("f(") >> prefix("x") << prefix(")");
+    /// assert!(run(call.clone(), "f(x".to_string()).unwrap_err().is_incomplete());
+    /// assert!(!run(call, "f(y)".to_string()).unwrap_err().is_incomplete());
+    /// ```
+    pub fn is_incomplete(&self) -> bool {
+        self.root_cause().kind() == ErrorKind::UnexpectedEof
+    }
+
+    /// Where in the source the error is, in bytes.
+    pub fn offset(&self) -> usize {
+        self.location.offset
+    }
+
+    pub fn expected(&self) -> &[String] {
+        &self.expected
+    }
+
+    /// This error followed by its causes, from the highest level one to the
+    /// failure of a primitive.
+    pub fn chain(&self) -> impl Iterator<Item = &ParserError> {
+        core::iter::successors(Some(self), |e| e.cause.as_deref())
+    }
+
+    /// The error at the end of [`ParserError::chain`].
+    pub fn root_cause(&self) -> &ParserError {
+        self.chain().last().unwrap()
+    }
+}
+
+/// `line:column: message`, e.g. `2:5: in entry: expected "="`.
+impl fmt::Display for ParserError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Location { line, column, .. } = self.location;
+        write!(f, "{line}:{column}: {}", self.message())
+    }
 }
 
-fn input_sub(start: usize, len: usize, s: &ParserInput) -> ParserInput {
+impl core::error::Error for ParserError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        self.cause.as_deref().map(|e| e as _)
+    }
+}
+
+/// A fresh id to key cached results of a parser by.
+pub(crate) fn next_id() -> usize {
+    static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+    NEXT_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+/// A parsed value along with the byte range of the source it came from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Spanned<T> {
+    pub value: T,
+    pub span: Range<usize>,
+}
+
+/// Skips the first `start` bytes of `s`. Text parsers must end on a char
+/// boundary, byte parsers may not.
+pub(crate) fn input_sub(start: usize, s: &ParserInput) -> ParserInput {
+    debug_assert!(start <= s.bytes().len());
     ParserInput {
-        text: s.text[start..start + len].to_string(),
         pos: s.pos + start,
+        ..s.clone()
     }
 }
 
-fn fail<'a, T>(e: String) -> Parser<'a, T> {
+pub fn fail<'a, T>(e: String) -> Parser<'a, T> {
     Parser {
-        run: Arc::new(move |input| {
+        run: Ptr::new(move |input| {
             let e = e.clone();
-            (input, Err(e))
+            (input, Err(e.into()))
         }),
+        shape: None,
     }
 }
 
-fn wrap<'a, T: Clone + 'static>(x: T) -> Parser<'a, T> {
+pub fn wrap<'a, T: Clone + MaybeSync + 'static>(x: T) -> Parser<'a, T> {
     Parser {
-        run: Arc::new(move |input| {
+        run: Ptr::new(move |input| {
             let x = x.clone();
             (input, Ok(x))
         }),
+        shape: Some(Ptr::new(shape::empty())),
     }
 }
 
-fn map<'a: 'b, 'b, A: 'a, B: 'b>(f: Box<dyn Fn(A) -> B>, p: Parser<'a, A>) -> Parser<'b, B> {
-    Parser {
-        run: Arc::new(move |input| match (p.run)(input) {
-            (input_, Ok(x)) => (input_, Ok(f(x))),
-            (input_, Err(error)) => (input_, Err(error)),
-        }),
+pub fn map<'a, A: 'a, B: 'a>(
+    f: impl Fn(A) -> B + MaybeSync + 'a,
+    p: Parser<'a, A>,
+) -> Parser<'a, B> {
+    p.map(f)
+}
+
+/// The run of chars `p` accepts at the start of the input, possibly empty,
+/// as by [`chars::take_while`]: the [`CharClass`](chars::CharClass)
+/// constants take its fast path.
+pub fn parse_while<'a>(p: impl chars::CharPredicate + 'a) -> Parser<'a, Substr> {
+    chars::take_while(p)
+}
+
+/// Runs the parser `f` builds out of the value of `p`, once for each value;
+/// [`then`](Parser::then) builds the next parser once when it doesn't depend
+/// on the value.
+pub fn bind<'a, A: 'a, B: 'a>(
+    f: impl Fn(A) -> Parser<'a, B> + MaybeSync + 'a,
+    p: Parser<'a, A>,
+) -> Parser<'a, B> {
+    p.and_then(f)
+}
+
+pub fn prefix(prefix_str: &'static str) -> Parser<'static, &'static str> {
+    Prefix(prefix_str)
+        .boxed()
+        .with_shape(Shape::Literal(prefix_str.to_string()))
+}
+
+/// The length of the start of `text` matching `pattern` char by char
+/// according to `eq`, or how much of `text` matched when it ran out.
+fn match_chars(text: &str, pattern: &str, eq: impl Fn(char, char) -> bool) -> Result<usize, usize> {
+    let mut chars = text.char_indices();
+    for p in pattern.chars() {
+        match chars.next() {
+            Some((_, c)) if eq(c, p) => {}
+            Some(_) => return Err(0),
+            None => return Err(text.len()),
+        }
     }
+    Ok(chars.next().map_or(text.len(), |(i, _)| i))
 }
 
-fn parse_while<'a>(p: Box<dyn Fn(char) -> bool>) -> Parser<'a, String> {
+fn prefix_by<'a>(
+    pattern: &'static str,
+    eq: impl Fn(char, char) -> bool + MaybeSync + 'a,
+) -> Parser<'a, String> {
     Parser {
-        run: Arc::new(move |input| {
-            let n = input.text.len();
-            let text = &input.text.as_bytes();
-            let mut i = 0;
-            while i < n && p(text[i] as char) {
-                i += 1;
+        run: Ptr::new(move |input| match match_chars(input.text(), pattern, &eq) {
+            Ok(n) => {
+                let matched = input.text()[..n].to_string();
+                (input_sub(n, &input), Ok(matched))
+            }
+            Err(n) if input.partial && n == input.text().len() => {
+                (input, Err(Failure::incomplete(Needed::Unknown)))
+            }
+            Err(n) => {
+                let desc = format!("expected {pattern}");
+                let e = Failure {
+                    truncated: n == input.text().len(),
+                    ..Failure::expecting(format!("{pattern:?}"), desc)
+                };
+                (input, Err(e))
             }
-            (
-                input_sub(i, n - i, &input),
-                Ok(input.text[0..i].to_string()),
-            )
         }),
+        shape: Some(Ptr::new(Shape::Caseless(pattern.to_string()))),
     }
 }
 
-fn bind<'a: 'b, 'b, A: 'a, B: 'b>(
-    f: Box<dyn Fn(A) -> Parser<'b, B>>,
-    p: Parser<'b, A>,
-) -> Parser<'b, B> {
-    Parser {
-        run: Arc::new(move |input| match (p.run)(input) {
-            (input_, Ok(x)) => ((f(x)).run)(input_),
-            (input_, Err(error)) => (input_, Err(error)),
-        }),
-    }
+/// Like [`prefix`], but ignoring ASCII case. It yields the text as it
+/// appears in the source.
+pub fn prefix_ci<'a>(pattern: &'static str) -> Parser<'a, String> {
+    prefix_by(pattern, |c, p| c.eq_ignore_ascii_case(&p))
 }
 
-fn prefix(prefix_str: &'static str) -> Parser<&str> {
-    Parser {
-        run: Arc::new(move |input| {
-            let unexpected_prefix_error = format!("expected {}", prefix_str).to_string();
+/// Like [`prefix_ci`], but ignoring the case of any letter, as far as it
+/// lowercases char by char.
+pub fn prefix_ci_unicode<'a>(pattern: &'static str) -> Parser<'a, String> {
+    prefix_by(pattern, |c, p| c.to_lowercase().eq(p.to_lowercase()))
+}
 
-            let prefix_size = prefix_str.len();
-            let input_size = input.text.len();
+/// `keyword` as a whole word: unlike [`prefix`] it won't match the start
+/// of `format` for `for`. Words go on with letters, digits and `_`.
+pub fn keyword<'a>(keyword: &'static str) -> Parser<'a, &'static str> {
+    keyword_with(keyword, |c| c.is_alphanumeric() || c == '_')
+}
 
-            let prefix_input = input_sub(0, prefix_size, &input);
-            if prefix_input.text == prefix_str {
-                let rest = input_sub(prefix_size, input_size - prefix_size, &input);
-                (rest, Ok(prefix_str))
-            } else {
-                (input, Err(unexpected_prefix_error))
-            }
+/// [`keyword`] with words going on with the chars accepted by `is_word`.
+pub fn keyword_with<'a>(
+    keyword: &'static str,
+    is_word: impl Fn(char) -> bool + MaybeSync + 'a,
+) -> Parser<'a, &'static str> {
+    let p = prefix(keyword);
+    let shape = p.shape.clone();
+    Parser {
+        run: Ptr::new(move |input| match (p.run)(input.clone()) {
+            (input_, Ok(k)) => match input_.text().chars().next() {
+                Some(c) if is_word(c) => {
+                    let desc = format!("expected keyword {keyword}, got {keyword}{c}...");
+                    (input, Err(Failure::expecting(format!("{keyword:?}"), desc)))
+                }
+                None if input_.partial => (input_, Err(Failure::incomplete(Needed::Size(1)))),
+                _ => (input_, Ok(k)),
+            },
+            output => output,
         }),
+        shape,
     }
 }
 
-fn optional<A: 'static>(p: Parser<A>) -> Parser<Option<A>> {
+/// The longest of `literals` the input starts with, yielding its index in
+/// `literals`, which can pick the value it stands for:
+///
+/// ```
+/// use parcoom::{literal_of, run};
+///
+/// let boolean = literal_of(["true", "false"]).map(|i| [true, false][i]);
+/// assert_eq!(run(boolean, "false".to_string()), Ok(false));
+/// let op = literal_of(["<", "<=", "<<"]);
+/// assert_eq!(run(op, "<=".to_string()), Ok(1));
+/// ```
+pub fn literal_of<'a>(literals: impl AsRef<[&'static str]>) -> Parser<'a, usize> {
+    let literals = literals.as_ref();
+    let mut by_length: Vec<(usize, &'static str)> = literals.iter().copied().enumerate().collect();
+    by_length.sort_by_key(|&(_, literal)| Reverse(literal.len()));
+    let expected: Vec<String> = literals
+        .iter()
+        .map(|literal| format!("{literal:?}"))
+        .collect();
+    let shape = Shape::Alt(
+        literals
+            .iter()
+            .map(|literal| Ptr::new(Shape::Literal(literal.to_string())))
+            .collect(),
+    );
     Parser {
-        run: Arc::new(move |input| {
-            let (input_, result) = (p.run)(input);
-            match result {
-                Ok(x) => (input_, Ok(Some(x))),
-                Err(_) => (input_, Ok(None)),
+        run: Ptr::new(move |input| {
+            let text = input.text();
+            for &(i, literal) in &by_length {
+                if text.starts_with(literal) {
+                    return (input_sub(literal.len(), &input), Ok(i));
+                }
+                if input.partial && literal.starts_with(text) {
+                    let needed = Needed::Size(literal.len() - text.len());
+                    return (input, Err(Failure::incomplete(needed)));
+                }
             }
+            let e = Failure {
+                expected: expected.clone(),
+                truncated: by_length
+                    .iter()
+                    .any(|(_, literal)| literal.starts_with(text)),
+                ..Failure::from(expected_desc(&expected))
+            };
+            (input, Err(e))
         }),
+        shape: Some(Ptr::new(shape)),
     }
 }
 
-fn many_exact<A: 'static>(n: i32, p: Parser<A>) -> Parser<Vec<A>> {
+pub fn optional<'a, A: 'a>(p: Parser<'a, A>) -> Parser<'a, Option<A>> {
+    let shape = shape::repeat(&p, 0, Some(1));
+    Parse::optional(p).boxed().with_shape(shape)
+}
+
+pub fn many_exact<'a, A: 'a>(n: i32, p: Parser<'a, A>) -> Parser<'a, Vec<A>> {
+    let times = n.max(0) as usize;
+    let shape = Some(Ptr::new(shape::repeat(&p, times, Some(times))));
     Parser {
-        run: Arc::new(move |input| {
+        run: Ptr::new(move |input| {
             let mut xs = Vec::new();
             let mut input_ = input;
             for _ in 0..n {
-                let (input__, result) = (p.run)(input_);
+                let (input__, result) = (p.run)(input_.clone());
                 match result {
                     Ok(x) => {
+                        if let Err(e) = collect::<A>(&input_, xs.len() + 1) {
+                            return (input_, Err(e));
+                        }
                         xs.push(x);
                         input_ = input__;
                     }
@@ -133,238 +820,2835 @@ fn many_exact<A: 'static>(n: i32, p: Parser<A>) -> Parser<Vec<A>> {
             }
             (input_, Ok(xs))
         }),
+        shape,
     }
 }
 
-fn many<A: 'static>(p: Parser<A>) -> Parser<Vec<A>> {
+/// Matches `p` as many times as it can, but at most as many as `times`
+/// allows, and fails unless that is at least as many as `times` requires.
+pub fn repeated<'a, A: 'a>(times: impl RangeBounds<usize>, p: Parser<'a, A>) -> Parser<'a, Vec<A>> {
+    repeated_with(NoProgress::Stop, times, p)
+}
+
+/// Like `repeated`, doing what `on` says when an item consumes nothing and
+/// there is no most to stop at.
+pub fn repeated_with<'a, A: 'a>(
+    on: NoProgress,
+    times: impl RangeBounds<usize>,
+    p: Parser<'a, A>,
+) -> Parser<'a, Vec<A>> {
+    let min = match times.start_bound() {
+        Bound::Included(&n) => n,
+        Bound::Excluded(&n) => n + 1,
+        Bound::Unbounded => 0,
+    };
+    let max = match times.end_bound() {
+        Bound::Included(&n) => Some(n),
+        Bound::Excluded(&n) => Some(n.saturating_sub(1)),
+        Bound::Unbounded => None,
+    };
+    let shape = Some(Ptr::new(shape::repeat(&p, min, max)));
     Parser {
-        run: Arc::new(move |input| {
+        run: Ptr::new(move |input| {
             let mut xs = Vec::new();
-            let mut input_ = input;
-            loop {
-                let (input__, result) = (p.run)(input_);
-                input_ = input__;
-                match result {
-                    Ok(x) => {
+            let mut input = input;
+            while max.is_none_or(|max| xs.len() < max) {
+                if let Err(e) = step(&input) {
+                    return (input, Err(e));
+                }
+                match (p.run)(input.clone()) {
+                    (input_, Ok(x)) => {
+                        if let Err(e) = collect::<A>(&input, xs.len() + 1) {
+                            return (input, Err(e));
+                        }
+                        let pos = input.pos;
                         xs.push(x);
+                        input = input_;
+                        // An item consuming nothing would match forever.
+                        if input.pos == pos && max.is_none() {
+                            match on {
+                                NoProgress::Stop => break,
+                                NoProgress::Fail => return (input, Err(no_progress(pos))),
+                            }
+                        }
                     }
-                    Err(_) => {
-                        break;
+                    (input_, Err(e)) if e.fatal => return (input_, Err(e)),
+                    (_, Err(mut e)) if xs.len() < min => {
+                        e.alternatives = None;
+                        e.desc = format!(
+                            "expected at least {min} occurrences, found {} at pos {}",
+                            xs.len(),
+                            input.pos
+                        );
+                        return (input, Err(e));
                     }
+                    (_, Err(_)) => break,
                 }
             }
-            (input_, Ok(xs))
+            (input, Ok(xs))
         }),
+        shape,
     }
 }
 
-fn any_char<'a>() -> Parser<'a, char> {
-    Parser {
-        run: Arc::new(|input| {
-            let n = input.text.len();
-            if n >= 1 {
-                (
-                    input_sub(1, n - 1, &input),
-                    Ok(input.text.as_bytes()[0] as char),
-                )
-            } else {
-                let empty_input_error =
-                    format!("expected any char, got none (input.len() = {n}").to_string();
+pub fn many<'a, A: 'a>(p: Parser<'a, A>) -> Parser<'a, Vec<A>> {
+    let shape = shape::repeat(&p, 0, None);
+    Parse::many(p).boxed().with_shape(shape)
+}
 
-                (input, Err(empty_input_error))
-            }
-        }),
+/// What repetitions do when an item matches without consuming anything,
+/// which it would go on doing forever.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoProgress {
+    /// Keep the item and stop repeating, the default.
+    Stop,
+    /// Fail, committed, as the grammar most likely has a bug.
+    Fail,
+}
+
+pub(crate) fn no_progress(pos: usize) -> Failure {
+    let desc = format!("repeated item matched without consuming input at pos {pos}");
+    Failure {
+        fatal: true,
+        ..Failure::from(desc)
     }
 }
 
-impl<'a, 'b: 'a, B: 'a, A: 'a> ops::Shl<Parser<'b, B>> for Parser<'a, A> {
-    type Output = Parser<'a, A>;
+/// Like `many`, doing what `on` says when an item consumes nothing.
+pub fn many_with<'a, A: 'a>(on: NoProgress, p: Parser<'a, A>) -> Parser<'a, Vec<A>> {
+    let shape = shape::repeat(&p, 0, None);
+    combinator::Many(p, on).boxed().with_shape(shape)
+}
 
-    fn shl(self, p2: Parser<'b, B>) -> Self::Output {
-        Parser {
-            run: Arc::new(move |input| {
-                let (input_, result) = (self.run)(input);
-                match result {
-                    Ok(x) => {
-                        let (input__, result_) = (p2.run)(input_);
-                        match result_ {
-                            Ok(_) => (input__, Ok(x)),
-                            Err(e) => (input__, Err(e)),
-                        }
+pub fn many1<'a, A: 'a>(p: Parser<'a, A>) -> Parser<'a, Vec<A>> {
+    many1_with(NoProgress::Stop, p)
+}
+
+/// Like `many1`, doing what `on` says when an item consumes nothing.
+pub fn many1_with<'a, A: 'a>(on: NoProgress, p: Parser<'a, A>) -> Parser<'a, Vec<A>> {
+    let shape = Some(Ptr::new(shape::repeat(&p, 1, None)));
+    Parser {
+        run: Ptr::new(move |input| {
+            if let Err(e) = step(&input) {
+                return (input, Err(e));
+            }
+            match (p.run)(input.clone()) {
+                (input_, Ok(x)) => {
+                    if let Err(e) = collect::<A>(&input, 1) {
+                        return (input, Err(e));
+                    }
+                    match on {
+                        _ if input_.pos > input.pos => many_onto(&p, on, vec![x], input_).into(),
+                        NoProgress::Stop => (input_, Ok(vec![x])),
+                        NoProgress::Fail => (input_, Err(no_progress(input.pos))),
                     }
-                    Err(e) => (input_, Err(e)),
                 }
-            }),
-        }
+                (input_, Err(e)) => (input_, Err(e)),
+            }
+        }),
+        shape,
     }
 }
 
-impl<'a: 'b, 'b, B: 'a, A: 'a> ops::Shr<Parser<'b, B>> for Parser<'a, A> {
-    type Output = Parser<'b, B>;
-    fn shr(self, p2: Parser<'b, B>) -> Self::Output {
-        Parser {
-            run: Arc::new(move |input| {
-                let (input_, result) = (self.run)(input);
-                match result {
-                    Ok(_) => {
-                        let (input__, result_) = (p2.run)(input_);
-                        match result_ {
-                            Ok(x) => (input__, Ok(x)),
-                            Err(e) => (input__, Err(e)),
-                        }
-                    }
-                    Err(e) => (input_, Err(e)),
-                }
-            }),
+/// Feeds what `p` yields from `input` on to `acc` with `f` for as long as
+/// it matches, like `many` does collecting them.
+fn fold_rest<A, B>(
+    p: &Parser<A>,
+    on: NoProgress,
+    mut input: ParserInput,
+    mut acc: B,
+    f: &impl Fn(B, A) -> B,
+) -> (ParserInput, Result<B, Failure>) {
+    loop {
+        if let Err(e) = step(&input) {
+            return (input, Err(e));
+        }
+        let pos = input.pos;
+        let checkpoint = input.checkpoint();
+        let (input_, result) = (p.run)(input);
+        input = input_;
+        match result {
+            Ok(x) if input.pos == pos => match on {
+                NoProgress::Stop => return (input, Ok(f(acc, x))),
+                NoProgress::Fail => return (input, Err(no_progress(pos))),
+            },
+            Ok(x) => acc = f(acc, x),
+            Err(e) if e.fatal => return (input, Err(e)),
+            // back to where the item started
+            Err(_) => {
+                input.rewind(checkpoint);
+                return (input, Ok(acc));
+            }
         }
     }
 }
 
-impl<'a: 'b, 'b, B: 'b, A: 'a> ops::Add<Parser<'b, B>> for Parser<'a, A> {
-    type Output = Parser<'b, (A, B)>;
+/// Like `many`, but combines the items with `f` as they come, starting out
+/// from `init()`, instead of collecting them into a `Vec`.
+pub fn fold_many0<'a, A: 'a, B: 'a>(
+    p: Parser<'a, A>,
+    init: impl Fn() -> B + MaybeSync + 'a,
+    f: impl Fn(B, A) -> B + MaybeSync + 'a,
+) -> Parser<'a, B> {
+    fold_many0_with(NoProgress::Stop, p, init, f)
+}
 
-    fn add(self, p2: Parser<'b, B>) -> Self::Output {
-        Parser {
-            run: Arc::new(move |input| {
-                let (input_, result) = (self.run)(input);
+/// Like `fold_many0`, doing what `on` says when an item consumes nothing.
+pub fn fold_many0_with<'a, A: 'a, B: 'a>(
+    on: NoProgress,
+    p: Parser<'a, A>,
+    init: impl Fn() -> B + MaybeSync + 'a,
+    f: impl Fn(B, A) -> B + MaybeSync + 'a,
+) -> Parser<'a, B> {
+    let shape = Some(Ptr::new(shape::repeat(&p, 0, None)));
+    Parser {
+        run: Ptr::new(move |input| fold_rest(&p, on, input, init(), &f)),
+        shape,
+    }
+}
+
+/// Like `fold_many0`, but fails unless `p` matches at least once.
+pub fn fold_many1<'a, A: 'a, B: 'a>(
+    p: Parser<'a, A>,
+    init: impl Fn() -> B + MaybeSync + 'a,
+    f: impl Fn(B, A) -> B + MaybeSync + 'a,
+) -> Parser<'a, B> {
+    fold_many1_with(NoProgress::Stop, p, init, f)
+}
+
+/// Like `fold_many1`, doing what `on` says when an item consumes nothing.
+pub fn fold_many1_with<'a, A: 'a, B: 'a>(
+    on: NoProgress,
+    p: Parser<'a, A>,
+    init: impl Fn() -> B + MaybeSync + 'a,
+    f: impl Fn(B, A) -> B + MaybeSync + 'a,
+) -> Parser<'a, B> {
+    let shape = Some(Ptr::new(shape::repeat(&p, 1, None)));
+    Parser {
+        run: Ptr::new(move |input| {
+            if let Err(e) = step(&input) {
+                return (input, Err(e));
+            }
+            let pos = input.pos;
+            match (p.run)(input) {
+                (input_, Ok(x)) => match on {
+                    _ if input_.pos > pos => fold_rest(&p, on, input_, f(init(), x), &f),
+                    NoProgress::Stop => (input_, Ok(f(init(), x))),
+                    NoProgress::Fail => (input_, Err(no_progress(pos))),
+                },
+                (input_, Err(e)) => (input_, Err(e)),
+            }
+        }),
+        shape,
+    }
+}
+
+pub fn sep_by1<'a, A: 'a, S: 'a>(p: Parser<'a, A>, sep: Parser<'a, S>) -> Parser<'a, Vec<A>> {
+    sep_by1_with(NoProgress::Stop, p, sep)
+}
+
+/// Like `sep_by1`, doing what `on` says when a separator and an item
+/// consume nothing.
+pub fn sep_by1_with<'a, A: 'a, S: 'a>(
+    on: NoProgress,
+    p: Parser<'a, A>,
+    sep: Parser<'a, S>,
+) -> Parser<'a, Vec<A>> {
+    let shape = Some(Ptr::new(shape::sep_by1(&p, &sep)));
+    Parser {
+        run: Ptr::new(move |input| {
+            let (mut input_, result) = (p.run)(input.clone());
+            let mut xs = match result {
+                Ok(x) => vec![x],
+                Err(e) => return (input_, Err(e)),
+            };
+            if let Err(e) = collect::<A>(&input, 1) {
+                return (input, Err(e));
+            }
+            loop {
+                if let Err(e) = step(&input_) {
+                    return (input_, Err(e));
+                }
+                // a separator only counts when an item follows it, otherwise
+                // it is left in the input for whoever comes next
+                let (item, result) = (sep.run)(input_.clone());
+                match result {
+                    Ok(_) => {}
+                    Err(e) if e.fatal => return (item, Err(e)),
+                    Err(_) => break,
+                }
+                let (input__, result) = (p.run)(item.clone());
                 match result {
+                    // A separator and an item consuming nothing would match
+                    // forever.
+                    Ok(_) if input__.pos == input_.pos => match on {
+                        NoProgress::Stop => break,
+                        NoProgress::Fail => return (input__, Err(no_progress(input_.pos))),
+                    },
                     Ok(x) => {
-                        let (input__, result_) = (p2.run)(input_);
-                        match result_ {
-                            Ok(x_) => (input__, Ok((x, x_))),
-                            Err(e) => (input__, Err(e)),
+                        if let Err(e) = collect::<A>(&item, xs.len() + 1) {
+                            return (item, Err(e));
                         }
+                        xs.push(x);
+                        input_ = input__;
                     }
-                    Err(e) => (input_, Err(e)),
+                    Err(e) if e.fatal => return (input__, Err(e)),
+                    Err(_) => break,
                 }
-            }),
-        }
+            }
+            (input_, Ok(xs))
+        }),
+        shape,
     }
 }
 
-impl<'a, A: 'a> ops::BitOr<Parser<'a, A>> for Parser<'a, A> {
-    type Output = Parser<'a, A>;
+pub fn sep_by<'a, A: 'a, S: 'a>(p: Parser<'a, A>, sep: Parser<'a, S>) -> Parser<'a, Vec<A>> {
+    sep_by_with(NoProgress::Stop, p, sep)
+}
 
-    fn bitor(self, p2: Parser<'a, A>) -> Self::Output {
-        Parser {
-            run: Arc::new(move |input| {
-                let (input_, result) = (self.run)(input.clone());
-                match result {
-                    Ok(x) => (input_, Ok(x)),
-                    Err(_) => (p2.run)(input),
+/// Like `sep_by`, doing what `on` says when a separator and an item
+/// consume nothing.
+pub fn sep_by_with<'a, A: 'a, S: 'a>(
+    on: NoProgress,
+    p: Parser<'a, A>,
+    sep: Parser<'a, S>,
+) -> Parser<'a, Vec<A>> {
+    let p = sep_by1_with(on, p, sep);
+    let shape = Some(Ptr::new(shape::repeat(&p, 0, Some(1))));
+    Parser {
+        run: Ptr::new(move |input| match (p.run)(input.clone()) {
+            (input_, Ok(xs)) => (input_, Ok(xs)),
+            (input_, Err(e)) if e.fatal => (input_, Err(e)),
+            (_, Err(_)) => (input, Ok(Vec::new())),
+        }),
+        shape,
+    }
+}
+
+pub fn between<'a, O: 'a, A: 'a, C: 'a>(
+    open: Parser<'a, O>,
+    p: Parser<'a, A>,
+    close: Parser<'a, C>,
+) -> Parser<'a, A> {
+    let shape = Some(Ptr::new(shape::seq_of(&[
+        &open.shape,
+        &p.shape,
+        &close.shape,
+    ])));
+    Parser {
+        run: Ptr::new(move |input| {
+            let (input_, result) = (open.run)(input);
+            if let Err(mut e) = result {
+                e.alternatives = None;
+                e.desc = format!("missing opening delimiter: {}", e.desc);
+                return (input_, Err(e));
+            }
+            let (input_, result) = (p.run)(input_);
+            let x = match result {
+                Ok(x) => x,
+                Err(e) => return (input_, Err(e)),
+            };
+            let (input_, result) = (close.run)(input_);
+            match result {
+                Ok(_) => (input_, Ok(x)),
+                Err(mut e) => {
+                    e.alternatives = None;
+                    e.desc = format!("missing closing delimiter: {}", e.desc);
+                    (input_, Err(e))
                 }
-            }),
-        }
+            }
+        }),
+        shape,
     }
 }
 
-fn make_input(s: String) -> ParserInput {
-    ParserInput { text: s, pos: 0 }
+/// Which failure alternatives report when all of them failed, see
+/// [`choice_with`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Merge {
+    /// The one that got the farthest into the input, the default, merged
+    /// with those that got as far.
+    Farthest,
+    /// The last one, merged with those before it that failed at the same
+    /// position.
+    Last,
 }
 
-fn run<A>(p: Parser<A>, input: String) -> Result<A, ParserError> {
-    match (p.run)(make_input(input)) {
-        (_, Ok(x)) => Ok(x),
-        (input, Err(desc)) => Err(ParserError {
-            pos: input.pos,
-            desc,
-        }),
+impl Merge {
+    /// Picks between the failures of an alternative and of a later one.
+    pub(crate) fn pick(
+        self,
+        (input_, e): (ParserInput, Failure),
+        (input__, e_): (ParserInput, Failure),
+    ) -> (ParserInput, Failure) {
+        if input_.pos == input__.pos {
+            (input__, e.merge(e_))
+        } else if self == Merge::Farthest && input_.pos > input__.pos && !e_.fatal {
+            (input_, e)
+        } else {
+            (input__, e_)
+        }
     }
 }
 
-#[cfg(test)]
+/// Tries the parsers in order, yielding the value of the first that
+/// matches. The failure of the alternative that got the farthest is
+/// reported when none does, see [`choice_with`].
+pub fn choice<'a, A: 'a>(parsers: Vec<Parser<'a, A>>) -> Parser<'a, A> {
+    choice_with(Merge::Farthest, parsers)
+}
+
+/// [`choice`] reporting the failure `merge` picks when all parsers fail.
+pub fn choice_with<'a, A: 'a>(merge: Merge, parsers: Vec<Parser<'a, A>>) -> Parser<'a, A> {
+    let shape = Some(Ptr::new(shape::alt(&parsers.iter().collect::<Vec<_>>())));
+    let firsts: Vec<_> = parsers
+        .iter()
+        .map(|p| p.shape.as_deref().and_then(shape::first))
+        .collect();
+    Parser {
+        run: Ptr::new(move |input| {
+            // the errors of those skipped are told from their shapes if all
+            // others fail, or else they are run for them, see `dispatch`
+            let mut outputs = Vec::new();
+            for (p, first) in parsers.iter().zip(&firsts) {
+                if first
+                    .as_ref()
+                    .is_some_and(|first| dispatch::excluded(first, &input))
+                {
+                    let failure = p
+                        .shape
+                        .as_deref()
+                        .and_then(|s| dispatch::failure(s, &input));
+                    outputs.push(failure.map(|e| (input.clone(), e)));
+                    continue;
+                }
+                match (p.run)(input.clone()) {
+                    (input_, Ok(x)) => return (input_, Ok(x)),
+                    (input_, Err(e)) if e.fatal => return (input_, Err(e)),
+                    (input_, Err(e)) => outputs.push(Some((input_, e))),
+                }
+            }
+            let mut failure: Option<(ParserInput, Failure)> = None;
+            for (p, output) in parsers.iter().zip(outputs) {
+                let (input_, e) =
+                    match output.map_or_else(|| (p.run)(input.clone()), |(i, e)| (i, Err(e))) {
+                        (input_, Ok(x)) => return (input_, Ok(x)),
+                        (input_, Err(e)) if e.fatal => return (input_, Err(e)),
+                        (input_, Err(e)) => (input_, e),
+                    };
+                failure = Some(match failure {
+                    Some(last) => merge.pick(last, (input_, e)),
+                    None => (input_, e),
+                });
+            }
+            match failure {
+                Some((input_, e)) => (input_, Err(e)),
+                None => (
+                    input,
+                    Err("no alternatives to choose from".to_string().into()),
+                ),
+            }
+        }),
+        shape,
+    }
+}
+
+pub fn context<'a, A: 'a>(ctx: &str, p: Parser<'a, A>) -> Parser<'a, A> {
+    let ctx = ctx.to_string();
+    let shape = shape::reporting(&p);
+    Parser {
+        run: Ptr::new(move |input| match (p.run)(input) {
+            (input_, Ok(x)) => (input_, Ok(x)),
+            (input_, Err(mut e)) => {
+                e.context.push(ctx.clone());
+                (input_, Err(e))
+            }
+        }),
+        shape,
+    }
+}
+
+/// Commits to `p`: if it fails, enclosing alternatives and repetitions give
+/// up instead of trying something else.
+pub fn cut<'a, A: 'a>(p: Parser<'a, A>) -> Parser<'a, A> {
+    let shape = p.shape.clone();
+    Parser {
+        run: Ptr::new(move |input| match (p.run)(input) {
+            (input_, Err(mut e)) => {
+                e.fatal = true;
+                (input_, Err(e))
+            }
+            result => result,
+        }),
+        shape,
+    }
+}
+
+/// Backtracks out of `p`: if it fails, it does so at the position it
+/// started from, having consumed nothing, and uncommitted even if `p` used
+/// [`cut`]. Enclosing alternatives and repetitions then go on with
+/// something else.
+pub fn attempt<'a, A: 'a>(p: Parser<'a, A>) -> Parser<'a, A> {
+    let shape = p.shape.clone();
+    Parser {
+        run: Ptr::new(move |input| {
+            let checkpoint = input.checkpoint();
+            match (p.run)(input) {
+                (mut input_, Err(e)) => {
+                    input_.rewind(checkpoint);
+                    (input_, Err(Failure { fatal: false, ..e }))
+                }
+                output => output,
+            }
+        }),
+        shape,
+    }
+}
+
+/// Runs `p` without consuming input when it succeeds. A failure is reported
+/// where `p` failed.
+pub fn peek<'a, A: 'a>(p: Parser<'a, A>) -> Parser<'a, A> {
+    let shape = Some(Ptr::new(shape::ahead(&p, false)));
+    Parser {
+        run: Ptr::new(move |input| match (p.run)(input.clone()) {
+            (_, Ok(x)) => (input, Ok(x)),
+            (input_, Err(e)) => (input_, Err(e)),
+        }),
+        shape,
+    }
+}
+
+/// Runs `p` without consuming input, whether it succeeds or fails.
+pub fn look_ahead<'a, A: 'a>(p: Parser<'a, A>) -> Parser<'a, A> {
+    let shape = Some(Ptr::new(shape::ahead(&p, false)));
+    Parser {
+        run: Ptr::new(move |input| {
+            let (_, result) = (p.run)(input.clone());
+            (input, result)
+        }),
+        shape,
+    }
+}
+
+/// Succeeds without consuming input only when `p` fails.
+pub fn not<'a, A: 'a>(p: Parser<'a, A>) -> Parser<'a, ()> {
+    let shape = Some(Ptr::new(shape::ahead(&p, true)));
+    Parser {
+        run: Ptr::new(move |input| match (p.run)(input.clone()) {
+            (input_, Ok(_)) => {
+                // Bytes and tokens have no text, nor has half a char.
+                let n = input_.pos - input.pos;
+                let desc = match input.text().get(..n) {
+                    Some(matched) => format!("unexpected {matched:?}"),
+                    None => format!("unexpected input of length {n}"),
+                };
+                (input, Err(desc.into()))
+            }
+            (input_, Err(e)) if e.needed.is_some() => (input_, Err(e)),
+            (_, Err(_)) => (input, Ok(())),
+        }),
+        shape,
+    }
+}
+
+/// Like `many`, but throws the items away instead of collecting them.
+pub fn skip_many<'a, A: 'a>(p: Parser<'a, A>) -> Parser<'a, ()> {
+    skip_many_with(NoProgress::Stop, p)
+}
+
+/// Like `skip_many`, doing what `on` says when an item consumes nothing.
+pub fn skip_many_with<'a, A: 'a>(on: NoProgress, p: Parser<'a, A>) -> Parser<'a, ()> {
+    fold_many0_with(on, p, || (), |(), _| ())
+}
+
+pub fn value<'a, A: 'a, B: Clone + MaybeSync + 'a>(value: B, p: Parser<'a, A>) -> Parser<'a, B> {
+    p.to(value)
+}
+
+pub fn recognize<'a, A: 'a>(p: Parser<'a, A>) -> Parser<'a, String> {
+    p.recognize()
+}
+
+pub fn consumed<'a, A: 'a>(p: Parser<'a, A>) -> Parser<'a, (String, A)> {
+    p.consumed()
+}
+
+/// Collects `p` values until `end` matches, yielding them along with the
+/// value of `end`. `end` is tried first at each step; the parser fails if
+/// neither matches, or if `p` matches without consuming anything.
+pub fn many_till<'a, A: 'a, B: 'a>(
+    p: Parser<'a, A>,
+    end: Parser<'a, B>,
+) -> Parser<'a, (Vec<A>, B)> {
+    many_till_with(NoProgress::Stop, p, end)
+}
+
+/// Like `many_till`, doing what `on` says when `p` consumes nothing: the
+/// failure of `end` there when stopping, as `end` has to match.
+pub fn many_till_with<'a, A: 'a, B: 'a>(
+    on: NoProgress,
+    p: Parser<'a, A>,
+    end: Parser<'a, B>,
+) -> Parser<'a, (Vec<A>, B)> {
+    let items = Some(Ptr::new(shape::repeat(&p, 0, None)));
+    let shape = Some(Ptr::new(shape::seq_of(&[&items, &end.shape])));
+    Parser {
+        run: Ptr::new(move |input| {
+            let mut xs = Vec::new();
+            let mut input = input;
+            loop {
+                if let Err(e) = step(&input) {
+                    return (input, Err(e));
+                }
+                let e = match (end.run)(input.clone()) {
+                    (input_, Ok(y)) => return (input_, Ok((xs, y))),
+                    (input_, Err(e)) if e.fatal => return (input_, Err(e)),
+                    (_, Err(e)) => e,
+                };
+                match (p.run)(input.clone()) {
+                    (input_, Ok(_)) if input_.pos == input.pos => match on {
+                        NoProgress::Stop => return (input, Err(e)),
+                        NoProgress::Fail => return (input_, Err(no_progress(input.pos))),
+                    },
+                    (input_, Ok(x)) => {
+                        if let Err(e) = collect::<A>(&input, xs.len() + 1) {
+                            return (input, Err(e));
+                        }
+                        xs.push(x);
+                        input = input_;
+                    }
+                    (input_, Err(e_)) if e_.fatal => return (input_, Err(e_)),
+                    (_, Err(e_)) => return (input, Err(e.merge(e_))),
+                }
+            }
+        }),
+        shape,
+    }
+}
+
+/// `p` over and over up to the end of the input, which must be reached
+/// right after one of them. A failing `p` fails the parser where it failed,
+/// instead of stopping the repetition like [`many`] does.
+pub fn repeat_until_eof<'a, A: 'a>(p: Parser<'a, A>) -> Parser<'a, Vec<A>> {
+    let end = eof();
+    let shape = Some(Ptr::new(shape::repeat(&p, 0, None)));
+    Parser {
+        run: Ptr::new(move |input| {
+            let mut xs = Vec::new();
+            let mut input = input;
+            loop {
+                if let Err(e) = step(&input) {
+                    return (input, Err(e));
+                }
+                let e = match (end.run)(input.clone()) {
+                    (input_, Ok(())) => return (input_, Ok(xs)),
+                    (input_, Err(e)) if e.fatal => return (input_, Err(e)),
+                    (_, Err(e)) => e,
+                };
+                match (p.run)(input.clone()) {
+                    (input_, Ok(_)) if input_.pos == input.pos => return (input, Err(e)),
+                    (input_, Ok(x)) => {
+                        if let Err(e) = collect::<A>(&input, xs.len() + 1) {
+                            return (input, Err(e));
+                        }
+                        xs.push(x);
+                        input = input_;
+                    }
+                    (input_, Err(e)) => return (input_, Err(e)),
+                }
+            }
+        }),
+        shape,
+    }
+}
+
+/// Skips input up to and including the next match of `p`.
+pub fn skip_until<'a, A: 'a>(p: Parser<'a, A>) -> Parser<'a, ()> {
+    Parser {
+        run: Ptr::new(move |input| {
+            let mut input_ = input;
+            loop {
+                match (p.run)(input_.clone()) {
+                    (input__, Ok(_)) => return (input__, Ok(())),
+                    (input__, Err(e)) if e.needed.is_some() => return (input__, Err(e)),
+                    (_, Err(e)) => match input_.text().chars().next() {
+                        Some(c) => input_ = input_sub(c.len_utf8(), &input_),
+                        None if input_.partial => {
+                            return (input_, Err(Failure::incomplete(Needed::Unknown)))
+                        }
+                        None => return (input_, Err(e)),
+                    },
+                }
+            }
+        }),
+        shape: None,
+    }
+}
+
+/// The current byte offset, without consuming anything.
+pub fn position<'a>() -> Parser<'a, usize> {
+    Parser {
+        run: Ptr::new(|input| {
+            let pos = input.pos;
+            (input, Ok(pos))
+        }),
+        shape: Some(Ptr::new(shape::empty())),
+    }
+}
+
+/// The current location, like [`position`] but with line and column.
+pub fn location<'a>() -> Parser<'a, Location> {
+    Parser {
+        run: Ptr::new(|input| {
+            let location = input.location();
+            (input, Ok(location))
+        }),
+        shape: Some(Ptr::new(shape::empty())),
+    }
+}
+
+/// Succeeds without consuming anything at the end of the input only.
+pub fn eof<'a>() -> Parser<'a, ()> {
+    Parser {
+        run: Ptr::new(|input| {
+            if !input.at_end() {
+                let desc = format!("unexpected trailing input at pos {}", input.pos);
+                (
+                    input,
+                    Err(Failure::expecting("end of input".to_string(), desc)),
+                )
+            } else if input.partial {
+                (input, Err(Failure::incomplete(Needed::Unknown)))
+            } else {
+                (input, Ok(()))
+            }
+        }),
+        shape: Some(Ptr::new(shape::empty())),
+    }
+}
+
+pub fn any_char<'a>() -> Parser<'a, char> {
+    AnyChar.boxed()
+}
+
+/// `satisfy` that describes the chars it accepts as `expected`.
+pub(crate) fn satisfy_or<'a>(
+    expected: String,
+    f: impl Fn(char) -> bool + MaybeSync + 'a,
+) -> Parser<'a, char> {
+    let shape = Shape::Described(expected.clone());
+    Satisfy { expected, f }.boxed().with_shape(shape)
+}
+
+pub fn satisfy<'a>(f: impl Fn(char) -> bool + MaybeSync + 'a) -> Parser<'a, char> {
+    satisfy_or("a matching char".to_string(), f)
+}
+
+pub fn one_of(chars: &str) -> Parser<'_, char> {
+    let p = satisfy_or(format!("one of {chars:?}"), move |c| chars.contains(c));
+    p.with_shape(Shape::OneOf(chars.to_string()))
+}
+
+pub fn none_of(chars: &str) -> Parser<'_, char> {
+    satisfy_or(format!("none of {chars:?}"), move |c| !chars.contains(c))
+}
+
+impl<'a, T: 'a> Parser<'a, T> {
+    /// A parser of its own, running `f` on each input. [`ParserInput`] has
+    /// what primitives need: the rest of the source, moving past what they
+    /// matched, and failing where they stopped.
+    ///
+    /// ```
+    /// use parcoom::{run, Parser};
+    ///
+    /// // four chars of base64
+    /// let chunk = Parser::from_fn(|input| {
+    ///     let is_base64 = |c: char| c.is_ascii_alphanumeric() || "+/=".contains(c);
+    ///     let valid = input.text().chars().take(4).take_while(|&c| is_base64(c));
+    ///     let n = valid.count();
+    ///     if n < 4 {
+    ///         return input.error_at(n, "a base64 char");
+    ///     }
+    ///     let rest = input.advance(4);
+    ///     let chunk = rest.slice_from(&input).to_string();
+    ///     parcoom::ParseResult::ok(rest, chunk)
+    /// });
+    /// let chunks = chunk.many1();
+    /// assert_eq!(run(chunks.clone(), "QUJD".to_string()), Ok(vec!["QUJD".to_string()]));
+    /// let e = run(chunks, "QU*D".to_string()).unwrap_err();
+    /// assert_eq!((e.desc.as_str(), e.location.offset), ("expected a base64 char, got '*'", 2));
+    /// ```
+    pub fn from_fn(f: impl Fn(ParserInput) -> ParseResult<T> + MaybeSync + 'a) -> Parser<'a, T> {
+        Parser {
+            run: Ptr::new(move |input| f(input).into()),
+            shape: None,
+        }
+    }
+
+    pub fn map<B: 'a>(self, f: impl Fn(T) -> B + MaybeSync + 'a) -> Parser<'a, B> {
+        let shape = self.shape.clone();
+        Parser {
+            shape,
+            ..Parse::map(self, f).boxed()
+        }
+    }
+
+    pub fn and_then<B: 'a>(self, f: impl Fn(T) -> Parser<'a, B> + MaybeSync + 'a) -> Parser<'a, B> {
+        Parse::and_then(self, f).boxed()
+    }
+
+    pub fn then<B: 'a>(self, p2: Parser<'a, B>) -> Parser<'a, (T, B)> {
+        let shape = shape::seq(&self, &p2);
+        Parse::then(self, p2).boxed().with_shape(shape)
+    }
+
+    pub fn then_ignore<B: 'a>(self, p2: Parser<'a, B>) -> Parser<'a, T> {
+        let shape = shape::seq(&self, &p2);
+        Parse::then_ignore(self, p2).boxed().with_shape(shape)
+    }
+
+    pub fn ignore_then<B: 'a>(self, p2: Parser<'a, B>) -> Parser<'a, B> {
+        let shape = shape::seq(&self, &p2);
+        Parse::ignore_then(self, p2).boxed().with_shape(shape)
+    }
+
+    /// `self`, or else `p2`. When both fail the failure that got the
+    /// farthest is reported, see [`Merge`].
+    pub fn or(self, p2: Parser<'a, T>) -> Parser<'a, T> {
+        let shape = shape::alt(&[&self, &p2]);
+        let p = match self.shape.as_deref().and_then(shape::first) {
+            Some(first) => dispatch::or(first, self, p2),
+            None => Parse::or(self, p2).boxed(),
+        };
+        p.with_shape(shape)
+    }
+
+    pub fn optional(self) -> Parser<'a, Option<T>> {
+        optional(self)
+    }
+
+    /// Like [`Parser::optional`], but yielding `T::default()` for no match.
+    pub fn or_default(self) -> Parser<'a, T>
+    where
+        T: Default,
+    {
+        self.optional().map(Option::unwrap_or_default)
+    }
+
+    /// Like [`Parser::optional`], but yielding `value` for no match.
+    pub fn unwrap_or(self, value: T) -> Parser<'a, T>
+    where
+        T: Clone + MaybeSync,
+    {
+        self.optional()
+            .map(move |x| x.unwrap_or_else(|| value.clone()))
+    }
+
+    pub fn many(self) -> Parser<'a, Vec<T>> {
+        many(self)
+    }
+
+    pub fn many_with(self, on: NoProgress) -> Parser<'a, Vec<T>> {
+        many_with(on, self)
+    }
+
+    pub fn many_exact(self, n: i32) -> Parser<'a, Vec<T>> {
+        many_exact(n, self)
+    }
+
+    pub fn repeated(self, times: impl RangeBounds<usize>) -> Parser<'a, Vec<T>> {
+        repeated(times, self)
+    }
+
+    pub fn repeated_with(
+        self,
+        on: NoProgress,
+        times: impl RangeBounds<usize>,
+    ) -> Parser<'a, Vec<T>> {
+        repeated_with(on, times, self)
+    }
+
+    pub fn many1(self) -> Parser<'a, Vec<T>> {
+        many1(self)
+    }
+
+    pub fn many1_with(self, on: NoProgress) -> Parser<'a, Vec<T>> {
+        many1_with(on, self)
+    }
+
+    /// Runs `self` for what it consumes, discarding its result.
+    pub fn ignored(self) -> Parser<'a, ()> {
+        self.map(|_| ())
+    }
+
+    /// Yields `value` in place of the parsed value.
+    pub fn to<B: Clone + MaybeSync + 'a>(self, value: B) -> Parser<'a, B> {
+        self.map(move |_| value.clone())
+    }
+
+    pub fn skip_many(self) -> Parser<'a, ()> {
+        skip_many(self)
+    }
+
+    pub fn skip_many_with(self, on: NoProgress) -> Parser<'a, ()> {
+        skip_many_with(on, self)
+    }
+
+    pub fn many_till<B: 'a>(self, end: Parser<'a, B>) -> Parser<'a, (Vec<T>, B)> {
+        many_till(self, end)
+    }
+
+    pub fn many_till_with<B: 'a>(
+        self,
+        on: NoProgress,
+        end: Parser<'a, B>,
+    ) -> Parser<'a, (Vec<T>, B)> {
+        many_till_with(on, self, end)
+    }
+
+    pub fn repeat_until_eof(self) -> Parser<'a, Vec<T>> {
+        repeat_until_eof(self)
+    }
+
+    pub fn fold_many0<B: 'a>(
+        self,
+        init: impl Fn() -> B + MaybeSync + 'a,
+        f: impl Fn(B, T) -> B + MaybeSync + 'a,
+    ) -> Parser<'a, B> {
+        fold_many0(self, init, f)
+    }
+
+    pub fn fold_many0_with<B: 'a>(
+        self,
+        on: NoProgress,
+        init: impl Fn() -> B + MaybeSync + 'a,
+        f: impl Fn(B, T) -> B + MaybeSync + 'a,
+    ) -> Parser<'a, B> {
+        fold_many0_with(on, self, init, f)
+    }
+
+    pub fn fold_many1<B: 'a>(
+        self,
+        init: impl Fn() -> B + MaybeSync + 'a,
+        f: impl Fn(B, T) -> B + MaybeSync + 'a,
+    ) -> Parser<'a, B> {
+        fold_many1(self, init, f)
+    }
+
+    pub fn fold_many1_with<B: 'a>(
+        self,
+        on: NoProgress,
+        init: impl Fn() -> B + MaybeSync + 'a,
+        f: impl Fn(B, T) -> B + MaybeSync + 'a,
+    ) -> Parser<'a, B> {
+        fold_many1_with(on, self, init, f)
+    }
+
+    pub fn sep_by<S: 'a>(self, sep: Parser<'a, S>) -> Parser<'a, Vec<T>> {
+        sep_by(self, sep)
+    }
+
+    pub fn sep_by_with<S: 'a>(self, on: NoProgress, sep: Parser<'a, S>) -> Parser<'a, Vec<T>> {
+        sep_by_with(on, self, sep)
+    }
+
+    pub fn sep_by1<S: 'a>(self, sep: Parser<'a, S>) -> Parser<'a, Vec<T>> {
+        sep_by1(self, sep)
+    }
+
+    pub fn sep_by1_with<S: 'a>(self, on: NoProgress, sep: Parser<'a, S>) -> Parser<'a, Vec<T>> {
+        sep_by1_with(on, self, sep)
+    }
+
+    pub fn peek(self) -> Parser<'a, T> {
+        peek(self)
+    }
+
+    pub fn look_ahead(self) -> Parser<'a, T> {
+        look_ahead(self)
+    }
+
+    pub fn not_followed_by<B: 'a>(self, p2: Parser<'a, B>) -> Parser<'a, T> {
+        self.then_ignore(not(p2))
+    }
+
+    /// On failure, records the error and runs `recovery` from where `self`
+    /// failed, yielding `None` if that gets parsing back on track.
+    pub fn recover_with<B: 'a>(self, recovery: Parser<'a, B>) -> Parser<'a, Option<T>> {
+        let shape = shape::reporting(&self);
+        Parser {
+            run: Ptr::new(move |input| match (self.run)(input) {
+                (input_, Ok(x)) => (input_, Ok(Some(x))),
+                (input_, Err(e)) => match (recovery.run)(input_.clone()) {
+                    (mut input__, Ok(_)) => {
+                        input__.report(Severity::Error, e.at(&input_));
+                        (input__, Ok(None))
+                    }
+                    (_, Err(_)) => (input_, Err(e)),
+                },
+            }),
+            shape,
+        }
+    }
+
+    /// The text the parser consumed along with its value. Only for parsers
+    /// of text: it fails on bytes, tokens, or part of a char.
+    pub fn consumed(self) -> Parser<'a, (String, T)> {
+        let shape = self.shape.clone();
+        Parser {
+            run: Ptr::new(move |input| match (self.run)(input.clone()) {
+                (input_, Ok(x)) => match input.text().get(..input_.pos - input.pos) {
+                    Some(text) => match limits::output(&input, text.len()) {
+                        Ok(()) => (input_, Ok((text.to_string(), x))),
+                        Err(e) => (input, Err(e)),
+                    },
+                    None => {
+                        let desc = String::from("consumed needs a text source");
+                        (input_, Err(Failure::from(desc)))
+                    }
+                },
+                (input_, Err(e)) => (input_, Err(e)),
+            }),
+            shape,
+        }
+    }
+
+    /// The text the parser consumed instead of its value.
+    pub fn recognize(self) -> Parser<'a, String> {
+        self.consumed().map(|(text, _)| text)
+    }
+
+    pub fn spanned(self) -> Parser<'a, Spanned<T>> {
+        let shape = self.shape.clone();
+        Parser {
+            run: Ptr::new(move |input| {
+                let start = input.pos;
+                match (self.run)(input) {
+                    (input_, Ok(value)) => {
+                        let span = start..input_.pos;
+                        (input_, Ok(Spanned { value, span }))
+                    }
+                    (input_, Err(e)) => (input_, Err(e)),
+                }
+            }),
+            shape,
+        }
+    }
+
+    /// Maps the value with `f`, which also gets its span. If `f` fails the
+    /// parser fails with its message, located at the start of the value.
+    pub fn try_map<B: 'a>(
+        self,
+        f: impl Fn(T, Range<usize>) -> Result<B, String> + MaybeSync + 'a,
+    ) -> Parser<'a, B> {
+        let shape = self.shape.clone();
+        Parser {
+            run: Ptr::new(move |input| match (self.run)(input.clone()) {
+                (input_, Ok(value)) => match f(value, input.pos..input_.pos) {
+                    Ok(x) => (input_, Ok(x)),
+                    Err(desc) => (input, Err(Failure::from(desc))),
+                },
+                (input_, Err(e)) => (input_, Err(e)),
+            }),
+            shape,
+        }
+    }
+
+    /// Fails with `message`, at the start of the value and without
+    /// consuming it, if the value doesn't satisfy `f`.
+    pub fn verify(self, f: impl Fn(&T) -> bool + MaybeSync + 'a, message: &str) -> Parser<'a, T> {
+        let message = message.to_string();
+        let shape = self.shape.clone();
+        Parser {
+            run: Ptr::new(move |input| match (self.run)(input.clone()) {
+                (input_, Ok(x)) if f(&x) => (input_, Ok(x)),
+                (_, Ok(_)) => (input, Err(Failure::from(message.clone()))),
+                (input_, Err(e)) => (input_, Err(e)),
+            }),
+            shape,
+        }
+    }
+
+    pub fn committed(self) -> Parser<'a, T> {
+        cut(self)
+    }
+
+    pub fn backtrack(self) -> Parser<'a, T> {
+        attempt(self)
+    }
+
+    pub fn context(self, ctx: &str) -> Parser<'a, T> {
+        context(ctx, self)
+    }
+
+    /// Like [`Parser::context`], but a failure that happens before any
+    /// input was consumed is reported as expecting `name` instead.
+    pub fn label(self, name: &str) -> Parser<'a, T> {
+        let name = name.to_string();
+        let shape = match self.shape.as_deref() {
+            None | Some(Shape::Described(_)) => Some(Ptr::new(Shape::Described(name.clone()))),
+            _ => shape::reporting(&self),
+        };
+        Parser {
+            run: Ptr::new(move |input| {
+                let pos = input.pos;
+                match (self.run)(input) {
+                    (input_, Err(mut e)) if input_.pos == pos => {
+                        e.alternatives = None;
+                        e.desc = format!("expected {name}");
+                        e.expected = vec![name.clone()];
+                        (input_, Err(e))
+                    }
+                    (input_, Err(mut e)) => {
+                        e.context.push(name.clone());
+                        (input_, Err(e))
+                    }
+                    result => result,
+                }
+            }),
+            shape,
+        }
+    }
+
+    /// A failure of `self` is described by `message` instead, wherever it
+    /// happened. Its position, what was expected and whether it was
+    /// committed stay the same.
+    pub fn expect(self, message: &str) -> Parser<'a, T> {
+        let message = message.to_string();
+        let shape = shape::reporting(&self);
+        Parser {
+            run: Ptr::new(move |input| match (self.run)(input) {
+                (input_, Err(mut e)) if e.needed.is_none() => {
+                    e.alternatives = None;
+                    e.desc = message.clone();
+                    (input_, Err(e))
+                }
+                result => result,
+            }),
+            shape,
+        }
+    }
+
+    /// A failure of `self` becomes one described by `desc` at where `self`
+    /// started, caused by the original failure, e.g. `invalid entry` caused
+    /// by `expected =`. Committed failures stay committed, and streaming
+    /// parsers still ask for more input.
+    pub fn explain(self, desc: &str) -> Parser<'a, T> {
+        let desc = desc.to_string();
+        let shape = shape::reporting(&self);
+        Parser {
+            run: Ptr::new(move |input| match (self.run)(input.clone()) {
+                (input_, Err(e)) if e.needed.is_none() => {
+                    let fatal = e.fatal;
+                    let mut failure = Failure::from(desc.clone());
+                    failure.fatal = fatal;
+                    failure.cause = Some(Box::new(e.at(&input_)));
+                    (input, Err(failure))
+                }
+                result => result,
+            }),
+            shape,
+        }
+    }
+
+    pub fn delimited_by<O: 'a, C: 'a>(
+        self,
+        open: Parser<'a, O>,
+        close: Parser<'a, C>,
+    ) -> Parser<'a, T> {
+        between(open, self, close)
+    }
+}
+
+impl<'a, 'b: 'a, B: 'a, A: 'a> ops::Shl<Parser<'b, B>> for Parser<'a, A> {
+    type Output = Parser<'a, A>;
+
+    fn shl(self, p2: Parser<'b, B>) -> Self::Output {
+        self.then_ignore(p2)
+    }
+}
+
+impl<'a: 'b, 'b, B: 'a, A: 'a> ops::Shr<Parser<'b, B>> for Parser<'a, A> {
+    type Output = Parser<'b, B>;
+    fn shr(self, p2: Parser<'b, B>) -> Self::Output {
+        self.ignore_then(p2)
+    }
+}
+
+impl<'a: 'b, 'b, B: 'b, A: 'a> ops::Add<Parser<'b, B>> for Parser<'a, A> {
+    type Output = Parser<'b, (A, B)>;
+
+    fn add(self, p2: Parser<'b, B>) -> Self::Output {
+        self.then(p2)
+    }
+}
+
+impl<'a, A: 'a> ops::BitOr<Parser<'a, A>> for Parser<'a, A> {
+    type Output = Parser<'a, A>;
+
+    fn bitor(self, p2: Parser<'a, A>) -> Self::Output {
+        self.or(p2)
+    }
+}
+
+/// `p * (2..=4)` is `p.repeated(2..=4)`.
+impl<'a, A: 'a, R: RangeBounds<usize>> ops::Mul<R> for Parser<'a, A> {
+    type Output = Parser<'a, Vec<A>>;
+
+    fn mul(self, times: R) -> Self::Output {
+        self.repeated(times)
+    }
+}
+
+/// `!p` is `not(p)`.
+impl<'a, A: 'a> ops::Not for Parser<'a, A> {
+    type Output = Parser<'a, ()>;
+
+    fn not(self) -> Self::Output {
+        not(self)
+    }
+}
+
+/// `-p` is `p.ignored()`.
+impl<'a, A: 'a> ops::Neg for Parser<'a, A> {
+    type Output = Parser<'a, ()>;
+
+    fn neg(self) -> Self::Output {
+        self.ignored()
+    }
+}
+
+pub(crate) fn make_input(s: String) -> ParserInput {
+    input_from(Source::Text(s.into()))
+}
+
+pub(crate) fn input_from(source: Source) -> ParserInput {
+    ParserInput {
+        source,
+        pos: 0,
+        bit: 0,
+        diagnostics: Arc::new(Vec::new()),
+        partial: false,
+        cache: None,
+        memo: Rc::default(),
+        state: None,
+        junk: None,
+        events: None,
+        limits: None,
+        interner: None,
+        profile: None,
+        end: None,
+        indent: 0,
+    }
+}
+
+/// Runs `p` on `input`. When `p` recovered from errors along the way, the
+/// first of them is returned even though parsing went through.
+pub fn run<A>(p: impl Parse<A>, input: String) -> Result<A, ParserError> {
+    finish(p.parse(make_input(input)))
+}
+
+/// Turns what a parser returned into what [`run`] returns.
+pub(crate) fn finish<A>(output: impl Into<ParseResult<A>>) -> Result<A, ParserError> {
+    let ParseResult { input, result } = output.into();
+    match (input, result) {
+        (input, Ok(_)) if input.errors().next().is_some() => {
+            Err(input.errors().next().unwrap().clone())
+        }
+        (_, Ok(x)) => Ok(x),
+        (input, Err(e)) => match input.errors().next() {
+            Some(first) => Err(first.clone()),
+            None => Err(e.at(&input)),
+        },
+    }
+}
+
+/// What a parser left of its input, see [`run_partial`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Remaining(Substr);
+
+impl Remaining {
+    pub fn text(&self) -> &str {
+        self.0.as_str()
+    }
+
+    /// Where in the input the remaining text starts, in bytes.
+    pub fn offset(&self) -> usize {
+        self.0.span().start
+    }
+
+    /// The remaining text, sharing the input instead of copying it.
+    pub fn into_substr(self) -> Substr {
+        self.0
+    }
+}
+
+/// Like [`run`], but also yields what `p` left of `input`.
+pub fn run_partial<A>(p: impl Parse<A>, input: String) -> Result<(A, Remaining), ParserError> {
+    let (input, result) = p.parse(make_input(input)).into();
+    let remaining = Remaining(Substr::take(&input, input.text().len()));
+    finish((input, result)).map(|x| (x, remaining))
+}
+
+/// Like [`run`], but fails unless `p` consumes all of `input`.
+pub fn run_complete<A>(p: impl Parse<A>, input: String) -> Result<A, ParserError> {
+    run(p.then_ignore(eof()), input)
+}
+
+/// Like [`run_complete`], but the error is rendered against `input`, which
+/// is called `name` in it, see [`ParserError::render_in`].
+pub fn parse_str_complete<A>(p: impl Parse<A>, name: &str, input: &str) -> Result<A, String> {
+    run_complete(p, input.to_string()).map_err(|e| e.render_in(name, input))
+}
+
+/// Parses all of the file at `path` with `p`, like [`parse_str_complete`]
+/// with the path as the name. Fails with `InvalidData` on invalid UTF-8.
+#[cfg(feature = "std")]
+pub fn parse_file<A>(
+    path: impl AsRef<std::path::Path>,
+    p: impl Parse<A>,
+) -> std::io::Result<Result<A, String>> {
+    let path = path.as_ref();
+    let text = std::fs::read_to_string(path)?;
+    Ok(parse_str_complete(p, &path.display().to_string(), &text))
+}
+
+/// Runs `p` on `input` and returns every error it ran into: the ones it
+/// recovered from and, if it failed in the end, the final one.
+pub fn run_all_errors<A>(p: impl Parse<A>, input: String) -> (Option<A>, Vec<ParserError>) {
+    match p.parse(make_input(input)).into() {
+        (input, Ok(x)) => (Some(x), input.errors().cloned().collect()),
+        (input, Err(e)) => {
+            let mut errors: Vec<_> = input.errors().cloned().collect();
+            errors.push(e.at(&input));
+            (None, errors)
+        }
+    }
+}
+
+/// Runs `p` over and over on `input`, yielding the items one at a time
+/// instead of collecting them like `many` does. It ends at the end of the
+/// input, after an error, or after an item that consumed nothing.
+pub fn parse_iter<'p, A: 'p>(
+    p: impl Parse<A> + 'p,
+    input: String,
+) -> impl Iterator<Item = Result<A, ParserError>> + 'p {
+    let mut input = Some(make_input(input));
+    core::iter::from_fn(move || {
+        let before = input.take()?;
+        if before.text().is_empty() {
+            return None;
+        }
+        // Each item starts afresh, so memory doesn't grow with the input.
+        let before = ParserInput {
+            diagnostics: Arc::new(Vec::new()),
+            memo: Rc::default(),
+            ..before
+        };
+        let start = before.pos;
+        match p.parse(before).into() {
+            (after, Ok(x)) if after.errors().next().is_none() => {
+                if after.pos > start {
+                    input = Some(after);
+                }
+                Some(Ok(x))
+            }
+            output => Some(finish(output)),
+        }
+    })
+}
+
+#[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
-    fn key_value_pair_parser_test() {
-        let input = "key1 = value1".to_string();
-        let wss = parse_while(Box::new(|x| x.is_whitespace()));
+    fn key_value_pair_parser_test() {
+        let input = "key1 = value1".to_string();
+        let wss = parse_while(chars::WHITESPACE);
+
+        let name_parser = parse_while(chars::ALPHANUMERIC).to_owned();
+        let entry_parser = (wss.clone() >> name_parser.clone() << wss.clone() << prefix("="))
+            + (wss.clone() >> name_parser.clone());
+
+        let parsed = run(entry_parser, input);
+        assert_eq!(parsed, Ok(("key1".to_string(), "value1".to_string())));
+    }
+
+    #[test]
+    fn or_test() {
+        let input = "111aaa".to_string();
+        let parser = prefix("aaa") | prefix("111");
+
+        let parsed = run(parser, input);
+        assert_eq!(parsed, Ok("111"));
+    }
+
+    #[test]
+    fn optional_test() {
+        // test with working input
+        let input = "111aaa".to_string();
+        let parser = optional(prefix("111"));
+
+        let parsed = run(parser, input);
+        assert_eq!(parsed, Ok(Some("111")));
+
+        // test with no valid input to parse
+        let input = "aaa".to_string();
+        let parser = optional(prefix("111"));
+
+        let parsed = run(parser, input);
+        assert_eq!(parsed, Ok(None));
+    }
+
+    #[test]
+    fn any_char_test() {
+        // test with working input
+        let parser = any_char();
+        let parsed = run(parser, "hello".to_string());
+        assert_eq!(parsed, Ok('h'));
+
+        // test with empty input, should fail
+        let parser = any_char();
+        let parsed = run(parser, "".to_string());
+
+        assert_eq!(
+            parsed,
+            Err(ParserError {
+                desc: format!("expected any char, got none (input.len() = {}", 0).to_string(),
+                location: Location {
+                    offset: 0,
+                    char_offset: 0,
+                    line: 1,
+                    column: 1,
+                },
+                context: vec![],
+                expected: vec!["any char".to_string()],
+                cause: None,
+                at_end: true,
+            })
+        );
+    }
+
+    #[test]
+    fn operators_test() {
+        let digit = || satisfy(|c| c.is_ascii_digit());
+        let parser = digit() * (2..=3) + -prefix(";");
+        assert_eq!(
+            run(parser.clone(), "123;".to_string()),
+            Ok((vec!['1', '2', '3'], ()))
+        );
+        assert!(run(parser, "1;".to_string()).is_err());
+
+        let word = (!prefix("end") >> any_char()) * ..;
+        let parser = word << prefix("end");
+        assert_eq!(run(parser, "abend".to_string()), Ok(vec!['a', 'b']));
+    }
+
+    #[test]
+    fn repeated_test() {
+        let digit = || satisfy(|c| c.is_ascii_digit());
+        let parsed = run(digit().repeated(2..=3), "1234".to_string());
+        assert_eq!(parsed, Ok(vec!['1', '2', '3']));
+        assert_eq!(run(digit().repeated(..), "".to_string()), Ok(vec![]));
+        assert_eq!(run(digit().repeated(..2), "12".to_string()), Ok(vec!['1']));
+        let parser = prefix("x=123456") >> digit().repeated(2..);
+        assert_eq!(
+            run(parser, "x=1234567a".to_string()),
+            Err(ParserError {
+                desc: "expected at least 2 occurrences, found 1 at pos 9".to_string(),
+                location: Location {
+                    offset: 9,
+                    char_offset: 9,
+                    line: 1,
+                    column: 10,
+                },
+                context: vec![],
+                expected: vec!["a matching char".to_string()],
+                cause: None,
+                at_end: false,
+            })
+        );
+        // items consuming nothing are not repeated forever
+        let parser = prefix("a").optional().repeated(..);
+        assert_eq!(run(parser, "b".to_string()), Ok(vec![None]));
+        let parser = prefix("a").optional().many();
+        assert_eq!(
+            run(parser, "aab".to_string()),
+            Ok(vec![Some("a"), Some("a"), None])
+        );
+        let parser = parse_while(|x: char| x == 'a').skip_many() >> prefix("b");
+        assert_eq!(run(parser, "b".to_string()), Ok("b"));
+        let parser = prefix(",").optional().sep_by(prefix(";").optional());
+        assert_eq!(run(parser, ",c".to_string()), Ok(vec![Some(",")]));
+        let parser = prefix("a").optional().many_with(NoProgress::Fail) | wrap(vec![]);
+        let parsed = run(parser, "ab".to_string()).map_err(|e| e.desc);
+        let desc = "repeated item matched without consuming input at pos 1";
+        assert_eq!(parsed, Err(desc.to_string()));
+
+        fn error<'a, T: 'a>(p: Parser<'a, T>, input: &str) -> Option<String> {
+            run(p, input.to_string()).err().map(|e| e.desc)
+        }
+        let at = |pos| {
+            Some(format!(
+                "repeated item matched without consuming input at pos {pos}"
+            ))
+        };
+        let a = || prefix("a").optional();
+        let fail = NoProgress::Fail;
+        assert_eq!(error(a().repeated_with(fail, ..), "ab"), at(1));
+        assert_eq!(error(a().repeated_with(fail, ..3), "ab"), None);
+        assert_eq!(error(a().many1_with(fail), "b"), at(0));
+        assert_eq!(error(a().many1_with(fail), "ab"), at(1));
+        assert_eq!(run(a().many1(), "b".to_string()), Ok(vec![None]));
+        assert_eq!(
+            error(a().fold_many0_with(fail, || 0, |n, _| n + 1), "ab"),
+            at(1)
+        );
+        assert_eq!(
+            error(a().fold_many1_with(fail, || 0, |n, _| n + 1), "b"),
+            at(0)
+        );
+        assert_eq!(
+            run(a().fold_many1(|| 0, |n, _| n + 1), "b".to_string()),
+            Ok(1)
+        );
+        assert_eq!(error(a().skip_many_with(fail), "ab"), at(1));
+        let sep = || prefix(";").optional();
+        let item = || prefix(",").optional();
+        assert_eq!(error(item().sep_by_with(fail, sep()), ",c"), at(1));
+        assert_eq!(error(item().sep_by1_with(fail, sep()), ",c"), at(1));
+        assert_eq!(error(a().many_till_with(fail, prefix(".")), "ab"), at(1));
+        assert_eq!(
+            error(a().many_till(prefix(".")), "ab"),
+            Some("expected .".to_string())
+        );
+    }
+
+    #[test]
+    fn many_exact_test() {
+        // test with input.len() = 3 (so parser succeeds)
+        let input = "hel".to_string();
+        let parser = many_exact(3, any_char());
+
+        let parsed = run(parser, input);
+        assert_eq!(parsed, Ok(vec!['h', 'e', 'l']));
+
+        // test with input.len() < 3 (so parser fails)
+        let input = "he".to_string();
+        let parser = many_exact(3, any_char());
+
+        let parsed = run(parser, input);
+        assert_eq!(
+            parsed,
+            Err(ParserError {
+                desc: format!("expected any char, got none (input.len() = {}", 0).to_string(),
+                location: Location {
+                    offset: 2,
+                    char_offset: 2,
+                    line: 1,
+                    column: 3,
+                },
+                context: vec![],
+                expected: vec!["any char".to_string()],
+                cause: None,
+                at_end: true,
+            })
+        );
+    }
+
+    #[test]
+    fn many_test() {
+        // test with working input
+        let input = "hello".to_string();
+        let parser = many(any_char());
+
+        let parsed = run(parser, input);
+        assert_eq!(parsed, Ok(vec!['h', 'e', 'l', 'l', 'o']));
+    }
+
+    #[test]
+    fn utf8_test() {
+        // any_char should return whole code points
+        let parser = many(any_char());
+        let parsed = run(parser, "héllo".to_string());
+        assert_eq!(parsed, Ok(vec!['h', 'é', 'l', 'l', 'o']));
+
+        // parse_while should stop on a char boundary
+        let parser =
+            parse_while(chars::ALPHABETIC).to_owned() + parse_while(|_: char| true).to_owned();
+        let parsed = run(parser, "日本語 ok".to_string());
+        assert_eq!(parsed, Ok(("日本語".to_string(), " ok".to_string())));
+
+        // prefix should fail cleanly when the input is shorter than the prefix
+        let parser = prefix("aé") >> prefix("été");
+        let parsed = run(parser, "aéét".to_string());
+        assert_eq!(
+            parsed,
+            Err(ParserError {
+                desc: "expected été".to_string(),
+                location: Location {
+                    offset: 3,
+                    char_offset: 2,
+                    line: 1,
+                    column: 3,
+                },
+                context: vec![],
+                expected: vec!["\"été\"".to_string()],
+                cause: None,
+                at_end: true,
+            })
+        );
+    }
+
+    #[test]
+    fn method_api_test() {
+        let wss = parse_while(chars::WHITESPACE);
+        let name_parser = parse_while(chars::ALPHANUMERIC);
+        let entry_parser = wss
+            .clone()
+            .ignore_then(name_parser.clone())
+            .then_ignore(wss.clone())
+            .then_ignore(prefix("="))
+            .then(wss.ignore_then(name_parser.map(|x| x.len())));
+
+        let parsed = run(entry_parser, "key1 = value1".to_string());
+        assert_eq!(
+            parsed.map(|(k, n)| (k.to_string(), n)),
+            Ok(("key1".to_string(), 6))
+        );
+
+        let parser = prefix("a")
+            .or(prefix("b"))
+            .many()
+            .and_then(|xs| wrap(xs.len()));
+        let parsed = run(parser, "abbac".to_string());
+        assert_eq!(parsed, Ok(4));
+
+        let parser = prefix("a").optional().then(any_char().many_exact(2));
+        let parsed = run(parser, "xyz".to_string());
+        assert_eq!(parsed, Ok((None, vec!['x', 'y'])));
+    }
+
+    #[test]
+    fn many1_test() {
+        let parser = many1(prefix("a"));
+        let parsed = run(parser, "aab".to_string());
+        assert_eq!(parsed, Ok(vec!["a", "a"]));
+
+        // zero matches is an error
+        let parser = many1(prefix("a"));
+        let parsed = run(parser, "b".to_string());
+        assert_eq!(
+            parsed,
+            Err(ParserError {
+                desc: "expected a".to_string(),
+                location: Location {
+                    offset: 0,
+                    char_offset: 0,
+                    line: 1,
+                    column: 1,
+                },
+                context: vec![],
+                expected: vec!["\"a\"".to_string()],
+                cause: None,
+                at_end: false,
+            })
+        );
+    }
+
+    #[test]
+    fn sep_by_test() {
+        let item = parse_while(chars::ALPHANUMERIC).to_owned();
+
+        let parser = sep_by(item.clone(), prefix(","));
+        let parsed = run(parser, "a,b,c".to_string());
+        assert_eq!(
+            parsed,
+            Ok(vec!["a".to_string(), "b".to_string(), "c".to_string()])
+        );
+
+        // no items at all is fine for sep_by
+        let parser = sep_by(prefix("x"), prefix(","));
+        let parsed = run(parser, "".to_string());
+        assert_eq!(parsed, Ok(vec![]));
+
+        // the trailing separator is not consumed
+        let parser = sep_by(prefix("x"), prefix(",")) + prefix(",;");
+        let parsed = run(parser, "x,x,;".to_string());
+        assert_eq!(parsed, Ok((vec!["x", "x"], ",;")));
+    }
+
+    #[test]
+    fn sep_by1_test() {
+        let parser = sep_by1(any_char(), prefix(";"));
+        let parsed = run(parser, "a;b".to_string());
+        assert_eq!(parsed, Ok(vec!['a', 'b']));
+
+        let parser = sep_by1(prefix("x"), prefix(";"));
+        let parsed = run(parser, "".to_string());
+        assert_eq!(
+            parsed,
+            Err(ParserError {
+                desc: "expected x".to_string(),
+                location: Location {
+                    offset: 0,
+                    char_offset: 0,
+                    line: 1,
+                    column: 1,
+                },
+                context: vec![],
+                expected: vec!["\"x\"".to_string()],
+                cause: None,
+                at_end: true,
+            })
+        );
+    }
+
+    #[test]
+    fn between_test() {
+        let parser = between(prefix("("), many(prefix("x")), prefix(")"));
+        let parsed = run(parser, "(xx)".to_string());
+        assert_eq!(parsed, Ok(vec!["x", "x"]));
+
+        let parser = many(prefix("x")).delimited_by(prefix("["), prefix("]"));
+        let parsed = run(parser, "xx]".to_string());
+        assert_eq!(
+            parsed,
+            Err(ParserError {
+                desc: "missing opening delimiter: expected [".to_string(),
+                location: Location {
+                    offset: 0,
+                    char_offset: 0,
+                    line: 1,
+                    column: 1,
+                },
+                context: vec![],
+                expected: vec!["\"[\"".to_string()],
+                cause: None,
+                at_end: false,
+            })
+        );
+
+        let parser = any_char().delimited_by(prefix("\""), prefix("\""));
+        let parsed = run(parser, "\"ab\"".to_string());
+        assert_eq!(
+            parsed,
+            Err(ParserError {
+                desc: "missing closing delimiter: expected \"".to_string(),
+                location: Location {
+                    offset: 2,
+                    char_offset: 2,
+                    line: 1,
+                    column: 3,
+                },
+                context: vec![],
+                expected: vec!["\"\\\"\"".to_string()],
+                cause: None,
+                at_end: false,
+            })
+        );
+    }
+
+    #[test]
+    fn choice_test() {
+        let parser = choice(vec![prefix("let"), prefix("fn"), prefix("if")]);
+        let parsed = run(parser, "fn main".to_string());
+        assert_eq!(parsed, Ok("fn"));
+
+        // every alternative failed, so all of their errors are reported
+        let parser = choice(vec![prefix("let"), prefix("fn")]);
+        let parsed = run(parser, "x".to_string());
+        assert_eq!(
+            parsed,
+            Err(ParserError {
+                desc: "expected \"let\" or \"fn\"".to_string(),
+                location: Location {
+                    offset: 0,
+                    char_offset: 0,
+                    line: 1,
+                    column: 1,
+                },
+                context: vec![],
+                expected: vec!["\"let\"".to_string(), "\"fn\"".to_string()],
+                cause: None,
+                at_end: false,
+            })
+        );
+
+        let parser = choice::<char>(vec![]);
+        assert!(run(parser, "x".to_string()).is_err());
+    }
+
+    #[test]
+    fn satisfy_test() {
+        let parser = satisfy(|c| c.is_ascii_digit());
+        let parsed = run(parser, "1a".to_string());
+        assert_eq!(parsed, Ok('1'));
+
+        let parser = prefix("a") >> satisfy(|c| c.is_ascii_digit());
+        let parsed = run(parser, "ab".to_string());
+        assert_eq!(
+            parsed,
+            Err(ParserError {
+                desc: "expected a matching char, got 'b'".to_string(),
+                location: Location {
+                    offset: 1,
+                    char_offset: 1,
+                    line: 1,
+                    column: 2,
+                },
+                context: vec![],
+                expected: vec!["a matching char".to_string()],
+                cause: None,
+                at_end: false,
+            })
+        );
+    }
+
+    #[test]
+    fn one_of_test() {
+        let parser = many(one_of("abé"));
+        let parsed = run(parser, "béac".to_string());
+        assert_eq!(parsed, Ok(vec!['b', 'é', 'a']));
+
+        let parser = one_of("ab");
+        let parsed = run(parser, "".to_string());
+        assert_eq!(
+            parsed,
+            Err(ParserError {
+                desc: "expected one of \"ab\", got end of input".to_string(),
+                location: Location {
+                    offset: 0,
+                    char_offset: 0,
+                    line: 1,
+                    column: 1,
+                },
+                context: vec![],
+                expected: vec!["one of \"ab\"".to_string()],
+                cause: None,
+                at_end: true,
+            })
+        );
+    }
+
+    #[test]
+    fn none_of_test() {
+        let parser = many(none_of("\"\\"));
+        let parsed = run(parser, "ab\\c".to_string());
+        assert_eq!(parsed, Ok(vec!['a', 'b']));
+
+        let parser = none_of("\"");
+        let parsed = run(parser, "\"".to_string());
+        assert_eq!(
+            parsed,
+            Err(ParserError {
+                desc: "expected none of \"\\\"\", got '\"'".to_string(),
+                location: Location {
+                    offset: 0,
+                    char_offset: 0,
+                    line: 1,
+                    column: 1,
+                },
+                context: vec![],
+                expected: vec!["none of \"\\\"\"".to_string()],
+                cause: None,
+                at_end: false,
+            })
+        );
+    }
+
+    #[test]
+    fn location_test() {
+        let line = parse_while(chars::CharClass::except('\n')) << prefix("\n");
+        let parser = many_exact(2, line) >> prefix("=");
+        let parsed = run(parser, "a = 1\nçé\nkey ? value".to_string());
+        assert_eq!(
+            parsed,
+            Err(ParserError {
+                desc: "expected =".to_string(),
+                location: Location {
+                    offset: 11,
+                    char_offset: 9,
+                    line: 3,
+                    column: 1,
+                },
+                context: vec![],
+                expected: vec!["\"=\"".to_string()],
+                cause: None,
+                at_end: false,
+            })
+        );
+
+        let parser = prefix("\n") >> prefix("é") >> any_char() >> prefix("x");
+        let parsed = run(parser, "\néé!".to_string());
+        assert_eq!(
+            parsed,
+            Err(ParserError {
+                desc: "expected x".to_string(),
+                location: Location {
+                    offset: 5,
+                    char_offset: 3,
+                    line: 2,
+                    column: 3,
+                },
+                context: vec![],
+                expected: vec!["\"x\"".to_string()],
+                cause: None,
+                at_end: false,
+            })
+        );
+    }
+
+    #[test]
+    fn char_offset_test() {
+        fn at<A: fmt::Debug>(parsed: Result<A, ParserError>) -> (usize, usize, usize) {
+            let location = parsed.unwrap_err().location;
+            (location.offset, location.char_offset, location.column)
+        }
+        let letters = || parse_while(chars::ALPHABETIC);
+        assert_eq!(
+            at(run(letters() >> prefix("!"), "日本?".to_string())),
+            (6, 2, 3)
+        );
+        assert_eq!(
+            at(run(any_char() >> chars::digit(), "ééx".to_string())),
+            (2, 1, 2)
+        );
+        assert_eq!(at(run(letters() >> eof(), "ça va".to_string())), (3, 2, 3));
+        let parser = prefix("€\n") >> satisfy(|c| c == 'x');
+        assert_eq!(at(run(parser, "€\nüx".to_string())), (4, 2, 1));
+        // byte sources count a char per byte
+        let parsed = run_bytes(
+            crate::bytes::tag(b"\xc3\xa9") >> crate::bytes::tag(b"x"),
+            b"\xc3\xa9y".to_vec(),
+        );
+        assert_eq!(at(parsed), (2, 2, 3));
+    }
+
+    #[test]
+    fn expect_test() {
+        let port = chars::digits1()
+            .try_map(|x, _| x.parse::<u16>().map_err(|e| e.to_string()))
+            .verify(|&x| x > 0, "port 0")
+            .expect("a port number between 1 and 65535");
+        let parser = prefix(":") >> port.clone();
+        let parsed = run(parser, ":x".to_string());
+        assert_eq!(
+            parsed,
+            Err(ParserError {
+                desc: "a port number between 1 and 65535".to_string(),
+                location: Location {
+                    offset: 1,
+                    char_offset: 1,
+                    line: 1,
+                    column: 2,
+                },
+                context: vec![],
+                expected: vec!["digits".to_string()],
+                cause: None,
+                at_end: false,
+            })
+        );
+        let parsed = run(port.clone(), "0".to_string()).map_err(|e| e.desc);
+        assert_eq!(parsed, Err("a port number between 1 and 65535".to_string()));
+
+        // committed failures stay committed
+        let parser = (prefix("a") >> cut(port)) | wrap(1);
+        assert!(run(parser, "ax".to_string()).is_err());
+    }
+
+    #[test]
+    fn explain_test() {
+        let key = parse_while(chars::ALPHABETIC);
+        let entry = (key << prefix("=") << prefix("1")).explain("invalid entry");
+        let parser = prefix("[") >> entry.context("section");
+        let error = run(parser, "[key:1".to_string()).unwrap_err();
+        let messages: Vec<String> = error.chain().map(|e| e.message()).collect();
+        assert_eq!(messages, vec!["in section: invalid entry", "expected ="]);
+        assert_eq!(error.location.offset, 1);
+        assert_eq!(error.root_cause().location.offset, 4);
+        assert_eq!(error.root_cause().expected, vec!["\"=\"".to_string()]);
+        let source = core::error::Error::source(&error).unwrap();
+        assert_eq!(source.to_string(), "1:5: expected =");
+
+        // alternatives still run after an explained failure
+        let parser = prefix("a").explain("no a") | prefix("b");
+        assert_eq!(run(parser, "b".to_string()), Ok("b"));
+    }
+
+    #[test]
+    fn context_test() {
+        let string_literal =
+            between(prefix("\""), many(none_of("\"")), prefix("\"")).label("string literal");
+        let value = context("value", string_literal);
+        let entry = (parse_while(chars::ALPHANUMERIC) << prefix("=")) + value.context("entry");
+
+        let parsed = run(entry, "key=\"abc".to_string());
+        let error = parsed.unwrap_err();
+        assert_eq!(
+            error,
+            ParserError {
+                desc: "missing closing delimiter: expected \"".to_string(),
+                location: Location {
+                    offset: 8,
+                    char_offset: 8,
+                    line: 1,
+                    column: 9,
+                },
+                context: vec![
+                    "entry".to_string(),
+                    "value".to_string(),
+                    "string literal".to_string()
+                ],
+                expected: vec!["\"\\\"\"".to_string()],
+                cause: None,
+                at_end: true,
+            }
+        );
+        assert_eq!(
+            error.message(),
+            "in entry > value > string literal: missing closing delimiter: expected \""
+        );
+
+        // without any context the message is just the description
+        let parsed = run(prefix("a"), "b".to_string());
+        assert_eq!(parsed.unwrap_err().message(), "expected a");
+    }
+
+    #[test]
+    fn display_test() {
+        let entry = prefix("a=") >> (prefix("1") | prefix("2")).context("value");
+        let error = run(entry, "a=3".to_string()).unwrap_err();
+        assert_eq!(error.to_string(), "1:3: in value: expected \"1\" or \"2\"");
+        assert_eq!(error.kind(), ErrorKind::Expected);
+        assert_eq!(error.offset(), 2);
+        assert_eq!(error.expected(), ["\"1\"", "\"2\""]);
 
-        let name_parser = parse_while(Box::new(|x| x.is_alphanumeric()));
-        let entry_parser = (wss.clone() >> name_parser.clone() << wss.clone() << prefix("="))
-            + (wss.clone() >> name_parser.clone());
+        let error = run(fail::<()>("too deep".to_string()), String::new()).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::Message);
 
-        let parsed = run(entry_parser, input);
-        assert_eq!(parsed, Ok(("key1".to_string(), "value1".to_string())));
+        fn parse_main() -> Result<(), Box<dyn std::error::Error>> {
+            run(prefix("a"), "b".to_string())?;
+            Ok(())
+        }
+        assert_eq!(parse_main().unwrap_err().to_string(), "1:1: expected a");
     }
 
     #[test]
-    fn or_test() {
-        let input = "111aaa".to_string();
-        let parser = prefix("aaa") | prefix("111");
+    fn incomplete_test() {
+        let list = (prefix("[") >> chars::digits1().sep_by(prefix(",")) << prefix("]")) << eof();
+        for text in ["", "[", "[1", "[1,2"] {
+            let error = run(list.clone(), text.to_string()).unwrap_err();
+            assert_eq!(error.kind(), ErrorKind::UnexpectedEof, "{text:?}");
+            assert!(error.is_incomplete());
+        }
+        for text in ["]", "[1;", "[1]]"] {
+            let error = run(list.clone(), text.to_string()).unwrap_err();
+            assert_eq!(error.kind(), ErrorKind::Expected, "{text:?}");
+            assert!(!error.is_incomplete());
+        }
+        // a message at the end is not about the input ending
+        let error = run(fail::<()>("no".to_string()), String::new()).unwrap_err();
+        assert!(error.at_end && !error.is_incomplete());
 
-        let parsed = run(parser, input);
-        assert_eq!(parsed, Ok("111"));
+        // explained errors start elsewhere, their causes decide
+        let explained = list.clone().explain("invalid list");
+        let error = run(explained.clone(), "[1,2".to_string()).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::Message);
+        assert!(error.is_incomplete());
+        assert!(!run(explained, "[1;".to_string())
+            .unwrap_err()
+            .is_incomplete());
+
+        // and through other error types
+        let error: ParserError = run_as(list, "[1,2".to_string()).unwrap_err();
+        assert!(error.is_incomplete());
+
+        // the input ending within a literal
+        let call = prefix("f(") >> prefix("while");
+        let error = run(call.clone(), "f(wh".to_string()).unwrap_err();
+        assert_eq!(
+            (error.offset(), error.kind()),
+            (2, ErrorKind::UnexpectedEof)
+        );
+        assert!(error.is_incomplete());
+        assert!(!run(call, "f(wx".to_string()).unwrap_err().is_incomplete());
+        for p in [keyword("while").to(()), prefix_ci("WHILE").to(())] {
+            assert!(run(p.clone(), "whi".to_string())
+                .unwrap_err()
+                .is_incomplete());
+            assert!(!run(p, "wha".to_string()).unwrap_err().is_incomplete());
+        }
+        let op = literal_of(["<<=", "=="]);
+        assert!(run(op.clone(), "<<".to_string())
+            .unwrap_err()
+            .is_incomplete());
+        assert!(!run(op, "<=".to_string()).unwrap_err().is_incomplete());
+        let either = prefix("while") | prefix("x");
+        assert!(run(either, "wh".to_string()).unwrap_err().is_incomplete());
+
+        // and within a string
+        let string = crate::literal::string_literal('"', &[('n', '\n')]);
+        for text in ["\"ab", "\"ab\\"] {
+            let error = run(string.clone(), text.to_string()).unwrap_err();
+            assert!(error.is_incomplete(), "{text:?}");
+        }
+        assert!(!run(string, "ab".to_string()).unwrap_err().is_incomplete());
     }
 
     #[test]
-    fn optional_test() {
-        // test with working input
-        let input = "111aaa".to_string();
-        let parser = optional(prefix("111"));
+    fn expected_test() {
+        let identifier = parse_while(chars::ALPHABETIC)
+            .and_then(|x| {
+                if x.is_empty() {
+                    fail("empty".to_string())
+                } else {
+                    wrap(x)
+                }
+            })
+            .label("identifier");
+        let parser = (prefix("let") | prefix("fn") | identifier.map(|_| "ident")) + prefix("!");
+        let parsed = run(parser.clone(), "12".to_string());
+        assert_eq!(
+            parsed,
+            Err(ParserError {
+                desc: "expected \"let\", \"fn\" or identifier".to_string(),
+                location: Location {
+                    offset: 0,
+                    char_offset: 0,
+                    line: 1,
+                    column: 1,
+                },
+                context: vec![],
+                expected: vec![
+                    "\"let\"".to_string(),
+                    "\"fn\"".to_string(),
+                    "identifier".to_string()
+                ],
+                cause: None,
+                at_end: false,
+            })
+        );
 
-        let parsed = run(parser, input);
-        assert_eq!(parsed, Ok(Some("111")));
+        // alternatives that failed further ahead are not merged, the farthest
+        // failure is reported by default
+        let ab = || prefix("a") >> prefix("b");
+        let parsed = run(ab() | prefix("c"), "ax".to_string());
+        assert_eq!(parsed.unwrap_err().expected, vec!["\"b\"".to_string()]);
+        let parser = choice_with(Merge::Last, vec![ab(), prefix("c")]);
+        let parsed = run(parser, "ax".to_string());
+        assert_eq!(parsed.unwrap_err().expected, vec!["\"c\"".to_string()]);
+        let parsed = run(
+            choice(vec![prefix("c"), ab(), prefix("d")]),
+            "ax".to_string(),
+        );
+        let e = parsed.unwrap_err();
+        assert_eq!(
+            (e.location.offset, e.expected),
+            (1, vec!["\"b\"".to_string()])
+        );
+    }
 
-        // test with no valid input to parse
-        let input = "aaa".to_string();
-        let parser = optional(prefix("111"));
+    #[test]
+    fn cut_test() {
+        let name = parse_while(chars::ALPHABETIC).to_owned() << prefix(";");
+        let statement = (prefix("let ") >> cut(name.clone())) | name.clone();
 
-        let parsed = run(parser, input);
-        assert_eq!(parsed, Ok(None));
+        let parsed = run(statement.clone(), "let x;".to_string());
+        assert_eq!(parsed, Ok("x".to_string()));
+
+        // without the cut the second alternative would be tried as well
+        let parsed = run(statement.clone(), "let x".to_string());
+        assert_eq!(
+            parsed,
+            Err(ParserError {
+                desc: "expected ;".to_string(),
+                location: Location {
+                    offset: 5,
+                    char_offset: 5,
+                    line: 1,
+                    column: 6,
+                },
+                context: vec![],
+                expected: vec!["\";\"".to_string()],
+                cause: None,
+                at_end: true,
+            })
+        );
+
+        // optional and many don't swallow committed failures either
+        let parser = optional(prefix("let ") >> name.clone().committed());
+        assert!(run(parser, "let 1".to_string()).is_err());
+        let parser = many(prefix("let ") >> name.committed());
+        assert!(run(parser, "let a;let ".to_string()).is_err());
     }
 
     #[test]
-    fn any_char_test() {
-        // test with working input
-        let parser = any_char();
-        let parsed = run(parser, "hello".to_string());
-        assert_eq!(parsed, Ok('h'));
+    fn attempt_test() {
+        let name = parse_while(chars::ALPHABETIC).to_owned() << prefix(";");
+        let statement = (prefix("let ") >> cut(name.clone())).backtrack() | name.clone();
+        let parsed = run(statement.clone(), "let x;".to_string());
+        assert_eq!(parsed, Ok("x".to_string()));
+        // the cut inside is undone, the second alternative fails at 3
+        let parsed = run(statement, "let x".to_string());
+        assert_eq!(parsed.unwrap_err().location.offset, 3);
 
-        // test with empty input, should fail
-        let parser = any_char();
-        let parsed = run(parser, "".to_string());
+        let parser = attempt(prefix("a") >> prefix("b"));
+        let parsed = run_partial(parser, "ac".to_string());
+        assert_eq!(parsed.unwrap_err().location.offset, 0);
+
+        // optional and many go back to where the item they tried started
+        let pair = || prefix("a") >> prefix("b");
+        let parser = optional(pair()) + prefix("ac");
+        assert_eq!(run(parser, "ac".to_string()), Ok((None, "ac")));
+        let parser = many(pair()) + prefix("ac");
+        assert_eq!(
+            run(parser, "ababac".to_string()),
+            Ok((vec!["b", "b"], "ac"))
+        );
+    }
+
+    #[test]
+    fn peek_test() {
+        let parser = peek(prefix("ab")) + any_char();
+        let parsed = run(parser, "abc".to_string());
+        assert_eq!(parsed, Ok(("ab", 'a')));
+
+        // the failure is reported where it happened
+        let parser = peek(prefix("a") >> prefix("b"));
+        let parsed = run(parser, "ac".to_string());
+        assert_eq!(parsed.unwrap_err().location.offset, 1);
+    }
+
+    #[test]
+    fn look_ahead_test() {
+        let parser = prefix("a").look_ahead() + many(any_char());
+        let parsed = run(parser, "ab".to_string());
+        assert_eq!(parsed, Ok(("a", vec!['a', 'b'])));
 
+        // nothing is consumed on failure either
+        let parser = look_ahead(prefix("a") >> prefix("b"));
+        let parsed = run(parser, "ac".to_string());
+        assert_eq!(parsed.unwrap_err().location.offset, 0);
+    }
+
+    #[test]
+    fn not_test() {
+        let keyword = prefix("for").not_followed_by(satisfy(|c| c.is_alphanumeric()));
+        let parsed = run(keyword.clone(), "for x".to_string());
+        assert_eq!(parsed, Ok("for"));
+
+        let parsed = run(keyword, "fortytwo".to_string());
         assert_eq!(
             parsed,
             Err(ParserError {
-                desc: format!("expected any char, got none (input.len() = {}", 0).to_string(),
-                pos: 0
+                desc: "unexpected \"t\"".to_string(),
+                location: Location {
+                    offset: 3,
+                    char_offset: 3,
+                    line: 1,
+                    column: 4,
+                },
+                context: vec![],
+                expected: vec![],
+                cause: None,
+                at_end: false,
             })
         );
+
+        // nothing is consumed on success
+        let parser = not(prefix("x")) >> any_char();
+        let parsed = run(parser, "ab".to_string());
+        assert_eq!(parsed, Ok('a'));
+
+        let e = run_bytes(not(bytes::u8()), vec![1]).unwrap_err();
+        assert_eq!(e.desc, "unexpected input of length 1");
+        let e = run(not(bytes::u8()), "é".to_string()).unwrap_err();
+        assert_eq!(e.desc, "unexpected input of length 1");
     }
 
     #[test]
-    fn many_exact_test() {
-        // test with input.len() = 3 (so parser succeeds)
-        let input = "hel".to_string();
-        let parser = many_exact(3, any_char());
+    fn skip_until_test() {
+        let parser = skip_until(prefix(";")) >> any_char();
+        let parsed = run(parser, "abc;d".to_string());
+        assert_eq!(parsed, Ok('d'));
 
-        let parsed = run(parser, input);
-        assert_eq!(parsed, Ok(vec!['h', 'e', 'l']));
+        let parser = skip_until(prefix(";"));
+        let parsed = run(parser, "abc".to_string());
+        assert_eq!(parsed.unwrap_err().location.offset, 3);
+    }
 
-        // test with input.len() < 3 (so parser fails)
-        let input = "he".to_string();
-        let parser = many_exact(3, any_char());
+    #[test]
+    fn recover_with_test() {
+        let statement = (parse_while(chars::ALPHABETIC).to_owned() << prefix(";"))
+            .recover_with(skip_until(prefix(";")));
+        let parser = many(statement);
 
-        let parsed = run(parser, input);
+        let parsed = run(parser.clone(), "a;b;".to_string());
+        assert_eq!(
+            parsed,
+            Ok(vec![Some("a".to_string()), Some("b".to_string())])
+        );
+
+        // parsing continues after the bad statement but the error is reported
+        let parsed = run(parser.clone() << prefix("!"), "a;b2;c;!".to_string());
         assert_eq!(
             parsed,
             Err(ParserError {
-                desc: format!("expected any char, got none (input.len() = {}", 0).to_string(),
-                pos: 2
+                desc: "expected ;".to_string(),
+                location: Location {
+                    offset: 3,
+                    char_offset: 3,
+                    line: 1,
+                    column: 4,
+                },
+                context: vec![],
+                expected: vec!["\";\"".to_string()],
+                cause: None,
+                at_end: false,
             })
         );
+
+        // recovered errors from a branch that was backtracked out of are dropped
+        let parser = (parser << prefix("!")) | many(any_char()).map(|_| vec![]);
+        let parsed = run(parser, "a;b2;c;?".to_string());
+        assert_eq!(parsed, Ok(vec![]));
     }
 
     #[test]
-    fn many_test() {
-        // test with working input
-        let input = "hello".to_string();
-        let parser = many(any_char());
+    fn run_all_errors_test() {
+        let statement = (parse_while(chars::ALPHABETIC).to_owned() << prefix(";"))
+            .recover_with(skip_until(prefix(";")));
+        let parser = many(statement) << prefix("!");
 
-        let parsed = run(parser, input);
-        assert_eq!(parsed, Ok(vec!['h', 'e', 'l', 'l', 'o']));
+        let (parsed, errors) = run_all_errors(parser.clone(), "a;b;!".to_string());
+        assert_eq!(
+            parsed,
+            Some(vec![Some("a".to_string()), Some("b".to_string())])
+        );
+        assert_eq!(errors, vec![]);
+
+        let (parsed, errors) = run_all_errors(parser.clone(), "1;b;c2;!".to_string());
+        assert_eq!(parsed, Some(vec![None, Some("b".to_string()), None]));
+        let offsets: Vec<usize> = errors.iter().map(|e| e.location.offset).collect();
+        assert_eq!(offsets, vec![0, 5]);
+
+        // a final failure comes after the recovered ones
+        let (parsed, errors) = run_all_errors(parser, "1;b;?".to_string());
+        assert_eq!(parsed, None);
+        let offsets: Vec<usize> = errors.iter().map(|e| e.location.offset).collect();
+        assert_eq!(offsets, vec![0, 4]);
+    }
+
+    #[test]
+    fn spanned_test() {
+        let word = many1(satisfy(|x| x.is_alphabetic()))
+            .map(String::from_iter)
+            .spanned();
+        let parser = word.sep_by(many1(prefix(" ")));
+
+        let parsed = run(parser, "ab  çd".to_string());
+        assert_eq!(
+            parsed,
+            Ok(vec![
+                Spanned {
+                    value: "ab".to_string(),
+                    span: 0..2
+                },
+                Spanned {
+                    value: "çd".to_string(),
+                    span: 4..7
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn try_map_test() {
+        let port = many1(satisfy(|c| c.is_ascii_digit()))
+            .map(String::from_iter)
+            .try_map(|s, span| {
+                s.parse::<u16>()
+                    .map_err(|_| format!("port {s} at {span:?} is out of range"))
+            });
+        let parser = prefix("localhost:") >> port;
+        assert_eq!(run(parser.clone(), "localhost:8080".to_string()), Ok(8080));
+        assert_eq!(
+            run(parser, "localhost:80800".to_string()),
+            Err(ParserError {
+                desc: "port 80800 at 10..15 is out of range".to_string(),
+                location: Location {
+                    offset: 10,
+                    char_offset: 10,
+                    line: 1,
+                    column: 11,
+                },
+                context: vec![],
+                expected: vec![],
+                cause: None,
+                at_end: false,
+            })
+        );
+    }
+
+    #[test]
+    fn or_default_test() {
+        let sign = prefix("-").to(-1).unwrap_or(1);
+        let parser = sign + number::unsigned::<i32>();
+        assert_eq!(run(parser.clone(), "-3".to_string()), Ok((-1, 3)));
+        assert_eq!(run(parser, "3".to_string()), Ok((1, 3)));
+        let flags = many1(one_of("abc")).or_default();
+        assert_eq!(run(flags, "x".to_string()), Ok(vec![]));
+    }
+
+    #[test]
+    fn to_test() {
+        #[derive(Debug, Clone, PartialEq)]
+        enum Token {
+            Comma,
+            Semi,
+        }
+        let token = prefix(",").to(Token::Comma) | value(Token::Semi, prefix(";"));
+        let parsed = run(token.many(), ",;,".to_string());
+        assert_eq!(parsed, Ok(vec![Token::Comma, Token::Semi, Token::Comma]));
+    }
+
+    #[test]
+    fn map_bind_test() {
+        // plain closures, borrowing from around them
+        let names = ["zero", "one", "two"];
+        let digit = satisfy(|c| c.is_ascii_digit()).map(|c| c as usize - '0' as usize);
+        let name = map(|i| names[i], digit.clone());
+        assert_eq!(run(name, "1".to_string()), Ok("one"));
+        let counted = bind(move |n| any_char().many_exact(n as i32), digit);
+        assert_eq!(run(counted, "2ab".to_string()), Ok(vec!['a', 'b']));
+    }
+
+    #[test]
+    fn recognize_test() {
+        let digits = || many1(satisfy(|c| c.is_ascii_digit()));
+        let number = digits() + (prefix(".") + digits()).optional();
+        let parsed = run(
+            recognize(number.clone()) << prefix(";"),
+            "3.14;".to_string(),
+        );
+        assert_eq!(parsed, Ok("3.14".to_string()));
+        let parsed = run(consumed(prefix("é") + prefix("t")), "été".to_string());
+        assert_eq!(parsed, Ok(("ét".to_string(), ("é", "t"))));
+        let e = run_bytes(bytes::u8().recognize(), vec![1]).unwrap_err();
+        assert_eq!(e.desc, "consumed needs a text source");
+        assert!(run_tokens(tokens::any_token::<u8>().recognize(), vec![1u8]).is_err());
+    }
+
+    #[test]
+    fn verify_test() {
+        let port =
+            prefix(":") >> number::unsigned::<u32>().verify(|&n| n < 65536, "port out of range");
+        assert_eq!(run(port.clone(), ":8080".to_string()), Ok(8080));
+        let parser = port.map(Some) | prefix(":").map(|_| None);
+        assert_eq!(run(parser.clone(), ":70000".to_string()), Ok(None));
+        assert_eq!(
+            run(parser.then(prefix("70000")), ":70000".to_string()),
+            Ok((None, "70000"))
+        );
+        let parsed = run(
+            number::unsigned::<u32>().verify(|&n| n > 0, "zero port"),
+            "0".to_string(),
+        );
+        assert_eq!(parsed.map_err(|e| e.desc), Err("zero port".to_string()));
+    }
+
+    #[cfg(feature = "sync")]
+    #[test]
+    fn sync_test() {
+        let parser = prefix("a").many().map(|xs| xs.len());
+        let handles: Vec<_> = (0..4)
+            .map(|i| {
+                let parser = parser.clone();
+                std::thread::spawn(move || run(parser, "a".repeat(i)))
+            })
+            .collect();
+        let parsed: Vec<_> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+        assert_eq!(parsed, vec![Ok(0), Ok(1), Ok(2), Ok(3)]);
+    }
+
+    #[test]
+    fn parse_iter_test() {
+        let line = many(none_of("\n")).map(String::from_iter) << prefix("\n");
+        let mut lines = parse_iter(line, "a\nbc\nd".to_string());
+        assert_eq!(lines.next(), Some(Ok("a".to_string())));
+        assert_eq!(lines.next(), Some(Ok("bc".to_string())));
+        assert_eq!(
+            lines.next(),
+            Some(Err(ParserError {
+                desc: "expected \n".to_string(),
+                location: Location {
+                    offset: 6,
+                    char_offset: 6,
+                    line: 3,
+                    column: 2,
+                },
+                context: vec![],
+                expected: vec!["\"\\n\"".to_string()],
+                cause: None,
+                at_end: true,
+            }))
+        );
+        assert_eq!(lines.next(), None);
+
+        let items: Vec<_> = parse_iter(any_char(), "xyz".to_string()).collect();
+        assert_eq!(items, vec![Ok('x'), Ok('y'), Ok('z')]);
+    }
+
+    #[test]
+    fn fold_many_test() {
+        let digit = satisfy(|c| c.is_ascii_digit()).map(|c| c.to_digit(10).unwrap());
+        let parser = digit.fold_many0(|| 0, |acc, d| acc * 10 + d);
+        assert_eq!(run(parser.clone(), "1234x".to_string()), Ok(1234));
+        assert_eq!(run(parser, "x".to_string()), Ok(0));
+
+        let word =
+            many1(satisfy(char::is_alphabetic)).map(String::from_iter) << optional(prefix(" "));
+        let counts = word.fold_many1(std::collections::BTreeMap::new, |mut counts, w| {
+            *counts.entry(w).or_insert(0) += 1;
+            counts
+        });
+        let parsed = run(counts.clone(), "a b a".to_string());
+        assert_eq!(
+            parsed,
+            Ok([("a".to_string(), 2), ("b".to_string(), 1)].into())
+        );
+        let parsed = run(counts, "1".to_string());
+        assert_eq!(
+            parsed.map_err(|e| e.desc),
+            Err("expected a matching char, got '1'".to_string())
+        );
+    }
+
+    #[test]
+    fn from_fn_test() {
+        // a number of exactly three digits
+        let three = Parser::from_fn(|input| {
+            let n = input
+                .text()
+                .chars()
+                .take_while(char::is_ascii_digit)
+                .count();
+            if n < 3 {
+                return input.error_at(n, "a digit");
+            }
+            if n > 3 {
+                return input.error_at(3, "no more digits");
+            }
+            let rest = input.advance(3);
+            let digits = rest.slice_from(&input).to_string();
+            ParseResult::ok(rest, digits)
+        });
+        let parser = three.clone().sep_by1(prefix(","));
+        let parsed = run(parser.clone(), "123,456".to_string());
+        assert_eq!(parsed, Ok(vec!["123".to_string(), "456".to_string()]));
+        let e = run(three.clone(), "45".to_string()).unwrap_err();
+        assert_eq!(e.desc, "expected a digit, got end of input");
+        assert_eq!(e.location.offset, 2);
+        let e = run(parser, "1234".to_string()).unwrap_err();
+        assert_eq!(
+            (e.desc.as_str(), e.location.offset),
+            ("expected no more digits, got '4'", 3)
+        );
+        assert_eq!(e.expected, ["no more digits"]);
+
+        let input = make_input("ab".to_string());
+        assert_eq!(input.advance(2).slice_from(&input), "ab");
+        assert!(input.advance(2).text().is_empty());
+        assert_eq!(input.bytes(), b"ab");
+    }
+
+    #[test]
+    #[should_panic(expected = "past the end")]
+    fn advance_past_end_test() {
+        make_input("ab".to_string()).advance(3);
+    }
+
+    #[test]
+    fn rewind_test() {
+        // each item matches "a" before failing, and must not leave it eaten
+        let ab = || prefix("a") >> prefix("b");
+        let stopping: Vec<(&str, Parser<()>, &str, &str)> = vec![
+            ("many", ab().many().ignored(), "abac", "ac"),
+            ("many1", ab().many1().ignored(), "abac", "ac"),
+            ("repeated", ab().repeated(1..3).ignored(), "abac", "ac"),
+            ("sep_by", ab().sep_by(prefix(",")).ignored(), "ab,ac", ",ac"),
+            (
+                "sep_by1",
+                ab().sep_by1(prefix(",")).ignored(),
+                "ab,ac",
+                ",ac",
+            ),
+            (
+                "fold_many0",
+                ab().fold_many0(|| (), |(), _| ()),
+                "abac",
+                "ac",
+            ),
+            (
+                "fold_many1",
+                ab().fold_many1(|| (), |(), _| ()),
+                "abac",
+                "ac",
+            ),
+            ("skip_many", ab().skip_many(), "abac", "ac"),
+            ("optional", ab().optional().ignored(), "ac", "ac"),
+        ];
+        for (name, p, text, rest) in stopping {
+            let parser = p >> recognize(any_char().many());
+            assert_eq!(
+                run(parser, text.to_string()),
+                Ok(rest.to_string()),
+                "{name}"
+            );
+        }
+
+        let failing: Vec<(&str, Parser<()>)> = vec![
+            ("many_exact", ab().many_exact(2).ignored()),
+            ("repeated", ab().repeated(2..).ignored()),
+            ("many_till", ab().many_till(prefix("x")).ignored()),
+            (
+                "sep_by1",
+                (ab().sep_by1(prefix(",")) << prefix(";")).ignored(),
+            ),
+            (
+                "choice",
+                choice(vec![prefix("a") >> prefix("c"), prefix("a") >> prefix("x")]).ignored(),
+            ),
+            (
+                "|",
+                (prefix("a") >> prefix("c") | prefix("a") >> prefix("x")).ignored(),
+            ),
+        ];
+        for (name, p) in failing {
+            let parser = optional(p.clone()) >> prefix("abac");
+            assert_eq!(run(parser, "abac".to_string()), Ok("abac"), "{name}");
+            let parser = (p >> wrap("")) | prefix("abac");
+            assert_eq!(run(parser, "abac".to_string()), Ok("abac"), "{name}");
+        }
+
+        // and what the item reported is forgotten with it
+        let warned = (prefix("a").warn("a") >> prefix("b")).many() >> prefix("ac");
+        let (parsed, diagnostics) = run_with_diagnostics(warned, "abac".to_string());
+        assert_eq!((parsed, diagnostics.len()), (Ok("ac"), 1));
+    }
+
+    #[test]
+    fn skip_test() {
+        let comment = prefix("#") >> skip_until(prefix("\n"));
+        let junk = (satisfy(char::is_whitespace).ignored() | comment).skip_many();
+        let parser = junk.clone() >> prefix("x") << junk;
+        assert_eq!(
+            run(parser, " # one\n\t# two\nx # three\n".to_string()),
+            Ok("x")
+        );
+    }
+
+    #[test]
+    fn many_till_test() {
+        let parser = || prefix("<b>") >> any_char().many_till(prefix("</b>"));
+        let parsed = run(parser(), "<b>a<i></b>".to_string());
+        assert_eq!(parsed, Ok((vec!['a', '<', 'i', '>'], "</b>")));
+        assert_eq!(
+            run(parser(), "<b>ab".to_string()),
+            Err(ParserError {
+                desc: "expected \"</b>\" or any char".to_string(),
+                location: Location {
+                    offset: 5,
+                    char_offset: 5,
+                    line: 1,
+                    column: 6,
+                },
+                context: vec![],
+                expected: vec!["\"</b>\"".to_string(), "any char".to_string()],
+                cause: None,
+                at_end: true,
+            })
+        );
+        // an item consuming nothing can't go on forever
+        let parser = prefix("a").optional().many_till(prefix("b"));
+        assert!(run(parser, "aac".to_string()).is_err());
+    }
+
+    #[test]
+    fn run_partial_test() {
+        let header = many1(satisfy(|c| c != '\n')).map(String::from_iter) << prefix("\n");
+        let (value, rest) = run_partial(header, "GET /\nbody".to_string()).unwrap();
+        assert_eq!(value, "GET /");
+        assert_eq!((rest.text(), rest.offset()), ("body", 6));
+        assert!(run_partial(prefix("x"), "y".to_string()).is_err());
+    }
+
+    #[test]
+    fn run_complete_test() {
+        let parser = || many(one_of("ab"));
+        assert_eq!(run(parser(), "abc".to_string()), Ok(vec!['a', 'b']));
+        assert_eq!(
+            run_complete(parser(), "abc".to_string()),
+            Err(ParserError {
+                desc: "unexpected trailing input at pos 2".to_string(),
+                location: Location {
+                    offset: 2,
+                    char_offset: 2,
+                    line: 1,
+                    column: 3,
+                },
+                context: vec![],
+                expected: vec!["end of input".to_string()],
+                cause: None,
+                at_end: false,
+            })
+        );
+        assert_eq!(run_complete(parser(), "ab".to_string()), Ok(vec!['a', 'b']));
+        assert_eq!(run(prefix("a") >> eof(), "a".to_string()), Ok(()));
+    }
+
+    #[test]
+    fn repeat_until_eof_test() {
+        let line = chars::digits1().to_owned() << prefix(";");
+        let parser = line.repeat_until_eof();
+        let parsed = run(parser.clone(), "1;22;".to_string());
+        assert_eq!(parsed, Ok(vec!["1".to_string(), "22".to_string()]));
+        assert_eq!(run(parser.clone(), "".to_string()), Ok(vec![]));
+        // fails where the item did, where `many` would have stopped before it
+        let e = run(parser, "1;22".to_string()).unwrap_err();
+        assert_eq!(e.location.offset, 4);
+        assert!(e.expected.contains(&"\";\"".to_string()));
+    }
+
+    #[test]
+    fn parse_str_complete_test() {
+        let parser = || (many(one_of("ab")) << chars::line_ending()).repeat_until_eof();
+        let parsed = parse_str_complete(parser(), "ab.txt", "ab\nb\n");
+        assert_eq!(parsed, Ok(vec![vec!['a', 'b'], vec!['b']]));
+        let e = parse_str_complete(parser(), "ab.txt", "ab\nbc\n").unwrap_err();
+        assert_eq!(
+            e,
+            "error: expected a line ending\n --> ab.txt:2:2\n  |\n2 | bc\n  |  ^\n"
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn parse_file_test() {
+        let path = std::env::temp_dir().join(format!("parcoom-{}.txt", std::process::id()));
+        std::fs::write(&path, "1,2,x").unwrap();
+        let parser = || chars::digits1().sep_by(prefix(","));
+        let e = parse_file(&path, parser()).unwrap().unwrap_err();
+        assert!(e.contains(&format!("--> {}:1:4", path.display())), "{e}");
+        std::fs::write(&path, "1,2").unwrap();
+        assert_eq!(parse_file(&path, parser()).unwrap().map(|v| v.len()), Ok(2));
+        std::fs::remove_file(&path).unwrap();
+        assert!(parse_file(&path, parser()).is_err());
+    }
+
+    #[test]
+    fn position_test() {
+        let word = many1(satisfy(char::is_alphabetic));
+        let parser = many(prefix("\n")) >> (position() + word + position());
+        assert_eq!(
+            run(parser, "\nab".to_string()),
+            Ok(((1, vec!['a', 'b']), 3))
+        );
+
+        let parser = many(prefix("\n")) >> location();
+        assert_eq!(
+            run(parser, "\n\n".to_string()),
+            Ok(Location {
+                offset: 2,
+                char_offset: 2,
+                line: 3,
+                column: 1,
+            })
+        );
+    }
+
+    #[test]
+    fn prefix_ci_test() {
+        let parser = prefix_ci("select") << prefix(" ");
+        assert_eq!(
+            run(parser, "SeLeCt *".to_string()),
+            Ok("SeLeCt".to_string())
+        );
+        assert_eq!(
+            run(prefix_ci("straße"), "STRAßE".to_string()),
+            Ok("STRAßE".to_string())
+        );
+        assert!(run(prefix_ci("été"), "ÉTÉ".to_string()).is_err());
+        assert_eq!(
+            run(prefix_ci_unicode("été"), "ÉTÉ".to_string()),
+            Ok("ÉTÉ".to_string())
+        );
+
+        let parsed = run(prefix_ci("from"), "frum".to_string());
+        assert_eq!(
+            parsed,
+            Err(ParserError {
+                desc: "expected from".to_string(),
+                location: Location {
+                    offset: 0,
+                    char_offset: 0,
+                    line: 1,
+                    column: 1,
+                },
+                context: vec![],
+                expected: vec!["\"from\"".to_string()],
+                cause: None,
+                at_end: false,
+            })
+        );
+    }
+
+    #[test]
+    fn literal_of_test() {
+        let methods = ["GET", "POST", "PUT", "PATCH"];
+        let method = literal_of(methods) << prefix(" ");
+        assert_eq!(run(method.clone(), "PUT /".to_string()), Ok(2));
+        assert_eq!(run(method.clone(), "PATCH /".to_string()), Ok(3));
+        let e = run(method, "HEAD /".to_string()).unwrap_err();
+        assert_eq!(e.desc, "expected \"GET\", \"POST\", \"PUT\" or \"PATCH\"");
+        assert_eq!(e.expected.len(), 4);
+
+        // the longest wins, wherever it is
+        let ops: &[&str] = &["=", "==", "=>", "==="];
+        let op = literal_of(ops).many();
+        assert_eq!(run(op, "====>==".to_string()), Ok(vec![3, 2, 1]));
+        let streamed = run_streaming(literal_of(["a", "abc"]), "ab".to_string());
+        assert_eq!(streamed, StreamResult::Incomplete(Needed::Size(1)));
+    }
+
+    #[test]
+    fn keyword_test() {
+        let parser = keyword("for") | prefix("format");
+        assert_eq!(run(parser.clone(), "for x".to_string()), Ok("for"));
+        assert_eq!(run(parser, "format".to_string()), Ok("format"));
+        assert_eq!(run(keyword("for"), "for".to_string()), Ok("for"));
+
+        let parsed = run(keyword("if"), "if_x".to_string());
+        assert_eq!(
+            parsed,
+            Err(ParserError {
+                desc: "expected keyword if, got if_...".to_string(),
+                location: Location {
+                    offset: 0,
+                    char_offset: 0,
+                    line: 1,
+                    column: 1,
+                },
+                context: vec![],
+                expected: vec!["\"if\"".to_string()],
+                cause: None,
+                at_end: false,
+            })
+        );
+
+        let parser = keyword_with("end", |c| c.is_alphanumeric()) << prefix("_");
+        assert_eq!(run(parser, "end_".to_string()), Ok("end"));
     }
 }
@@ -0,0 +1,255 @@
+//! Limits on how much work a parse may do, for untrusted input.
+
+use alloc::format;
+use alloc::rc::Rc;
+use alloc::string::String;
+use core::cell::Cell;
+
+use crate::{finish, make_input, Failure, Parse, Parser, ParserError, ParserInput, Ptr};
+
+/// How deep and how long [`run_with_config`] lets a parser go, and how
+/// much a repetition may collect. `None` is no limit.
+///
+/// The depth is how many recursive parsers, from [`recursive`],
+/// [`left_rec`], the [`parser!`] macro or [`Parsable`] impls, are nested,
+/// so that input such as 100k opening brackets fails instead of overflowing
+/// the stack. Every such parser entered and every item of a repetition is a
+/// step.
+///
+/// The items and output bytes are those of each repetition collecting into
+/// a `Vec`, such as `many` or `sep_by`, so that `any_char().many()` can't
+/// allocate without bound. The output bytes are the size of the items
+/// themselves, not counting what they own. The text of each `String` a
+/// parser yields, such as that of `recognize`, counts against them too;
+/// [`Substr`](crate::Substr)s don't, as they allocate nothing.
+///
+/// [`recursive`]: crate::recursive
+/// [`left_rec`]: crate::left_rec
+/// [`parser!`]: crate::parser
+/// [`Parsable`]: crate::Parsable
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RunConfig {
+    pub max_depth: Option<usize>,
+    pub max_steps: Option<usize>,
+    pub max_items: Option<usize>,
+    pub max_output_bytes: Option<usize>,
+}
+
+/// The limits of a run and how much of them is used up.
+#[derive(Debug)]
+pub(crate) struct Limits {
+    config: RunConfig,
+    depth: Cell<usize>,
+    steps: Cell<usize>,
+}
+
+/// Runs `p` on `input` within the limits of `config`. Going past them is a
+/// committed failure.
+pub fn run_with_config<A>(
+    p: impl Parse<A>,
+    input: String,
+    config: RunConfig,
+) -> Result<A, ParserError> {
+    let limits = Limits {
+        config,
+        depth: Cell::new(0),
+        steps: Cell::new(0),
+    };
+    let input = ParserInput {
+        limits: Some(Rc::new(limits)),
+        ..make_input(input)
+    };
+    finish(p.parse(input))
+}
+
+fn exceeded(what: &str, max: usize) -> Failure {
+    Failure {
+        fatal: true,
+        ..Failure::from(format!("{what} limit of {max} exceeded"))
+    }
+}
+
+/// Counts a step, failing once there were too many.
+pub(crate) fn step(input: &ParserInput) -> Result<(), Failure> {
+    let Some(limits) = &input.limits else {
+        return Ok(());
+    };
+    let steps = limits.steps.get() + 1;
+    limits.steps.set(steps);
+    match limits.config.max_steps {
+        Some(max) if steps > max => Err(exceeded("step", max)),
+        _ => Ok(()),
+    }
+}
+
+/// Counts the `count`th item of a repetition collecting `A`s, failing once
+/// it collected too many.
+pub(crate) fn collect<A>(input: &ParserInput, count: usize) -> Result<(), Failure> {
+    let Some(limits) = &input.limits else {
+        return Ok(());
+    };
+    if let Some(max) = limits.config.max_items.filter(|&max| count > max) {
+        return Err(exceeded("item", max));
+    }
+    output(input, count.saturating_mul(size_of::<A>()))
+}
+
+/// Counts `bytes` of output, failing when they are more than allowed.
+pub(crate) fn output(input: &ParserInput, bytes: usize) -> Result<(), Failure> {
+    let Some(limits) = &input.limits else {
+        return Ok(());
+    };
+    if let Some(max) = limits.config.max_output_bytes.filter(|&max| bytes > max) {
+        return Err(Failure {
+            fatal: true,
+            ..Failure::from(format!("output limit of {max} bytes exceeded"))
+        });
+    }
+    Ok(())
+}
+
+/// `p`, with the text it yields counted as output.
+pub(crate) fn counted(p: Parser<'_, String>) -> Parser<'_, String> {
+    let shape = p.shape.clone();
+    Parser {
+        run: Ptr::new(move |input| match (p.run)(input.clone()) {
+            (input_, Ok(text)) => match output(&input, text.len()) {
+                Ok(()) => (input_, Ok(text)),
+                Err(e) => (input, Err(e)),
+            },
+            (input_, Err(e)) => (input_, Err(e)),
+        }),
+        shape,
+    }
+}
+
+/// Runs `f` one recursion level deeper, a step too.
+pub(crate) fn nested<T>(
+    input: ParserInput,
+    f: impl FnOnce(ParserInput) -> (ParserInput, Result<T, Failure>),
+) -> (ParserInput, Result<T, Failure>) {
+    let Some(limits) = input.limits.clone() else {
+        return f(input);
+    };
+    if let Err(e) = step(&input) {
+        return (input, Err(e));
+    }
+    let depth = limits.depth.get() + 1;
+    if let Some(max) = limits.config.max_depth.filter(|&max| depth > max) {
+        return (input, Err(exceeded("depth", max)));
+    }
+    limits.depth.set(depth);
+    let output = f(input);
+    limits.depth.set(depth - 1);
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chars::take_while;
+    use crate::{any_char, prefix, recursive, wrap, Parser};
+    use alloc::vec;
+
+    fn nesting<'a>() -> Parser<'a, usize> {
+        recursive(|nesting| (prefix("[") >> nesting << prefix("]")).map(|n| n + 1) | wrap(0))
+    }
+
+    #[test]
+    fn max_depth_test() {
+        let config = RunConfig {
+            max_depth: Some(100),
+            ..RunConfig::default()
+        };
+        let input = "[".repeat(50) + &"]".repeat(50);
+        assert_eq!(run_with_config(nesting(), input, config), Ok(50));
+        let input = "[".repeat(100_000);
+        let parsed = run_with_config(nesting(), input, config).map_err(|e| e.desc);
+        assert_eq!(parsed, Err("depth limit of 100 exceeded".to_string()));
+        // the limit is committed to, no alternative hides it
+        let parser = nesting() | wrap(0);
+        let input = "[".repeat(101) + &"]".repeat(101);
+        assert!(run_with_config(parser, input, config).is_err());
+    }
+
+    #[test]
+    fn max_steps_test() {
+        let config = RunConfig {
+            max_steps: Some(10),
+            ..RunConfig::default()
+        };
+        let parser = || prefix("a").many();
+        assert!(run_with_config(parser(), "a".repeat(9), config).is_ok());
+        let parsed = run_with_config(parser(), "a".repeat(20), config).map_err(|e| e.desc);
+        assert_eq!(parsed, Err("step limit of 10 exceeded".to_string()));
+    }
+
+    #[test]
+    fn max_items_test() {
+        let config = RunConfig {
+            max_items: Some(3),
+            ..RunConfig::default()
+        };
+        let parsed = run_with_config(any_char().many(), "abc".to_string(), config);
+        assert_eq!(parsed, Ok(vec!['a', 'b', 'c']));
+        // at the item past the limit, committed to
+        let parser = any_char().many() | wrap(vec![]);
+        let e = run_with_config(parser, "abcd".to_string(), config).unwrap_err();
+        assert_eq!(
+            (e.desc.as_str(), e.location.offset),
+            ("item limit of 3 exceeded", 3)
+        );
+        // the first item of many1 counts as well
+        let parsed = run_with_config(any_char().many1(), "abc".to_string(), config);
+        assert_eq!(parsed.map(|xs| xs.len()), Ok(3));
+        let e = run_with_config(any_char().many1(), "abcd".to_string(), config).unwrap_err();
+        assert_eq!(e.location.offset, 3);
+        let parser = any_char().sep_by(prefix(","));
+        let e = run_with_config(parser, "a,b,c,d".to_string(), config).unwrap_err();
+        assert_eq!(e.location.offset, 6);
+        let parser = any_char().many_till(prefix("."));
+        assert!(run_with_config(parser, "abcd.".to_string(), config).is_err());
+        // each repetition counts its own
+        let parser = any_char().repeated(3..=3).sep_by(prefix(" "));
+        let parsed = run_with_config(parser, "abc def".to_string(), config);
+        assert_eq!(parsed.map(|words| words.len()), Ok(2));
+    }
+
+    #[test]
+    fn max_output_bytes_test() {
+        let config = RunConfig {
+            max_output_bytes: Some(8),
+            ..RunConfig::default()
+        };
+        // chars are 4 bytes
+        let parsed = run_with_config(any_char().many(), "ab".to_string(), config);
+        assert_eq!(parsed, Ok(vec!['a', 'b']));
+        let e = run_with_config(any_char().many(), "abc".to_string(), config).unwrap_err();
+        assert_eq!(
+            (e.desc.as_str(), e.location.offset),
+            ("output limit of 8 bytes exceeded", 2)
+        );
+        let parser = prefix("a").to(()).many();
+        assert!(run_with_config(parser, "a".repeat(100), config).is_ok());
+        // so does the text of strings, but not that of substrings
+        let parser = || take_while(|_: char| true);
+        assert!(run_with_config(parser(), "a".repeat(100), config).is_ok());
+        let e = run_with_config(parser().to_owned(), "a".repeat(100), config).unwrap_err();
+        assert_eq!(e.desc, "output limit of 8 bytes exceeded");
+        let parser = || prefix("a").skip_many().recognize();
+        assert!(run_with_config(parser(), "a".repeat(8), config).is_ok());
+        let e = run_with_config(parser(), "a".repeat(9), config).unwrap_err();
+        assert_eq!(e.location.offset, 0);
+    }
+
+    #[test]
+    fn fold_many1_steps_test() {
+        let config = RunConfig {
+            max_steps: Some(3),
+            ..RunConfig::default()
+        };
+        let parser = || any_char().fold_many1(|| 0, |n, _| n + 1);
+        assert_eq!(run_with_config(parser(), "ab".to_string(), config), Ok(2));
+        assert!(run_with_config(parser(), "abc".to_string(), config).is_err());
+    }
+}
@@ -0,0 +1,142 @@
+//! Parsers for quoted literals.
+
+use alloc::format;
+use alloc::string::{String, ToString};
+
+use crate::limits::counted;
+use crate::{input_sub, Failure, Needed, Parser, Ptr};
+
+/// The escapes of Rust and most C-like languages, meant for
+/// [`string_literal`]: `\n`, `\r`, `\t`, `\0`, `\\`, `\"` and `\'`.
+pub const ESCAPES: &[(char, char)] = &[
+    ('n', '\n'),
+    ('r', '\r'),
+    ('t', '\t'),
+    ('0', '\0'),
+    ('\\', '\\'),
+    ('"', '"'),
+    ('\'', '\''),
+];
+
+/// A literal enclosed in `quote`, with the escaped chars in it replaced.
+/// A backslash followed by a char of `escapes` stands for the char paired
+/// with it, `\u{...}` for the Unicode scalar value with that hex code.
+///
+/// Once the opening quote matched, failures are not backtracked out of, see
+/// [`cut`](crate::cut).
+pub fn string_literal<'a>(quote: char, escapes: &'a [(char, char)]) -> Parser<'a, String> {
+    counted(Parser {
+        run: Ptr::new(move |input| {
+            if !input.text().starts_with(quote) {
+                let expected = "a string literal".to_string();
+                let desc = format!("expected {quote:?}");
+                return (input, Err(Failure::expecting(expected, desc)));
+            }
+            let fatal = |i: usize, desc: String| {
+                let mut e = Failure::from(desc);
+                e.fatal = true;
+                (input_sub(i, &input), Err(e))
+            };
+            let text = input.text();
+            let mut value = String::new();
+            let mut chars = text.char_indices().skip(1);
+            while let Some((i, c)) = chars.next() {
+                match c {
+                    c if c == quote => return (input_sub(i + c.len_utf8(), &input), Ok(value)),
+                    '\\' => match chars.next() {
+                        Some((_, 'u')) => match unicode_escape(&text[i + 2..]) {
+                            Some((c, len)) => {
+                                value.push(c);
+                                for _ in 0..len {
+                                    chars.next();
+                                }
+                            }
+                            None => return fatal(i, "invalid unicode escape".to_string()),
+                        },
+                        Some((_, e)) => match escapes.iter().find(|&&(k, _)| k == e) {
+                            Some(&(_, c)) => value.push(c),
+                            None => return fatal(i, format!("unknown escape \\{e}")),
+                        },
+                        None => break,
+                    },
+                    c => value.push(c),
+                }
+            }
+            if input.partial {
+                return (input, Err(Failure::incomplete(Needed::Unknown)));
+            }
+            let desc = format!("unterminated string literal, expected closing {quote:?}");
+            let e = Failure::unterminated(format!("{:?}", quote.to_string()), desc);
+            (input, Err(e))
+        }),
+        shape: None,
+    })
+}
+
+/// The char of `{hex}` at the start of `s` and the chars that took.
+fn unicode_escape(s: &str) -> Option<(char, usize)> {
+    let hex = s.strip_prefix('{')?;
+    let end = hex.find('}')?;
+    if end == 0 || end > 6 {
+        return None;
+    }
+    let code = u32::from_str_radix(&hex[..end], 16).ok()?;
+    Some((char::from_u32(code)?, end + 2))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{prefix, run, Location, ParserError};
+
+    fn error(desc: &str, offset: usize) -> ParserError {
+        ParserError {
+            desc: desc.to_string(),
+            location: Location {
+                offset,
+                char_offset: offset,
+                line: 1,
+                column: offset + 1,
+            },
+            context: vec![],
+            expected: vec![],
+            cause: None,
+            at_end: false,
+        }
+    }
+
+    fn unterminated() -> ParserError {
+        ParserError {
+            expected: vec!["\"\\\"\"".to_string()],
+            at_end: true,
+            ..error("unterminated string literal, expected closing '\"'", 0)
+        }
+    }
+
+    #[test]
+    fn string_literal_test() {
+        let parser = string_literal('"', ESCAPES);
+        let parsed = run(parser, r#""a\tb\"c\\ \u{e9}\u{1F600}""#.to_string());
+        assert_eq!(parsed, Ok("a\tb\"c\\ é😀".to_string()));
+
+        let parser = string_literal('\'', &[('q', '\'')]);
+        assert_eq!(run(parser, r"'it\qs'".to_string()), Ok("it's".to_string()));
+    }
+
+    #[test]
+    fn string_literal_error_test() {
+        let parser = || string_literal('"', ESCAPES);
+        assert_eq!(
+            run(parser(), r#""ab\x""#.to_string()),
+            Err(error("unknown escape \\x", 3))
+        );
+        assert_eq!(
+            run(parser(), r#""\u{110000}""#.to_string()),
+            Err(error("invalid unicode escape", 1))
+        );
+        assert_eq!(run(parser(), r#""abc"#.to_string()), Err(unterminated()));
+        // committed after the opening quote
+        let parser = parser() | prefix("\"").map(|s| s.to_string());
+        assert_eq!(run(parser, "\"".to_string()), Err(unterminated()));
+    }
+}
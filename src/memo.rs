@@ -0,0 +1,140 @@
+//! Packrat-style memoization of parser results within a single run.
+
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::any::Any;
+use core::cell::RefCell;
+
+use crate::{input_sub, next_id, Diagnostic, Failure, Parser, ParserInput, Ptr};
+
+#[derive(Debug)]
+struct Entry {
+    end: usize,
+    result: Result<Box<dyn Any>, Failure>,
+    /// Errors recovered from and warnings while producing `result`.
+    diagnostics: Vec<Diagnostic>,
+    /// The user state `result` left behind. Memoized parsers are assumed
+    /// not to depend on the state they start with.
+    state: Option<Arc<dyn Any>>,
+}
+
+/// What memoized parsers produced so far, keyed by parser id and offset.
+#[derive(Debug, Default)]
+pub(crate) struct Memo {
+    entries: BTreeMap<(usize, usize), Entry>,
+}
+
+impl Memo {
+    /// Replays the outcome remembered for `key` on top of `input`.
+    pub(crate) fn lookup<T: Clone + 'static>(
+        memo: &RefCell<Memo>,
+        key: (usize, usize),
+        input: &ParserInput,
+    ) -> Option<(ParserInput, Result<T, Failure>)> {
+        let hit = memo.borrow_mut().entries.get(&key).map(|entry| {
+            let result = match &entry.result {
+                Ok(x) => Ok(x.downcast_ref::<T>().unwrap().clone()),
+                Err(e) => Err(e.clone()),
+            };
+            (
+                entry.end,
+                result,
+                entry.diagnostics.clone(),
+                entry.state.clone(),
+            )
+        });
+        hit.map(|(end, result, diagnostics, state)| {
+            let mut input_ = input_sub(end - input.pos, input);
+            input_.state = state;
+            if !diagnostics.is_empty() {
+                Arc::make_mut(&mut input_.diagnostics).extend(diagnostics);
+            }
+            (input_, result)
+        })
+    }
+
+    /// Remembers that a parser started at `key` with `before` diagnostics
+    /// in its input ended up with `output`.
+    pub(crate) fn store<T: Clone + 'static>(
+        memo: &RefCell<Memo>,
+        key: (usize, usize),
+        before: usize,
+        output: &(ParserInput, Result<T, Failure>),
+    ) {
+        let (input_, result) = output;
+        let entry = Entry {
+            end: input_.pos,
+            result: match result {
+                Ok(x) => Ok(Box::new(x.clone())),
+                Err(e) => Err(e.clone()),
+            },
+            diagnostics: input_.diagnostics[before..].to_vec(),
+            state: input_.state.clone(),
+        };
+        memo.borrow_mut().entries.insert(key, entry);
+    }
+}
+
+impl<'a, T: Clone + 'static> Parser<'a, T> {
+    /// Remembers the outcome of this parser at every position it is tried
+    /// at during a run, so that backtracking alternatives that try it again
+    /// at the same place get the result for free. Trades memory for time.
+    pub fn memoized(self) -> Parser<'a, T> {
+        let id = next_id();
+        let shape = self.shape.clone();
+        Parser {
+            run: Ptr::new(move |input| {
+                let key = (id, input.pos);
+                if let Some(output) = Memo::lookup(&input.memo, key, &input) {
+                    return output;
+                }
+                let before = input.diagnostics.len();
+                let memo = input.memo.clone();
+                let output = (self.run)(input);
+                Memo::store(&memo, key, before, &output);
+                output
+            }),
+            shape,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use crate::{prefix, run, satisfy};
+
+    #[test]
+    fn memoized_test() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let number = satisfy(|x| x.is_ascii_digit())
+            .many1()
+            .map(move |xs| {
+                counter.fetch_add(1, Ordering::Relaxed);
+                String::from_iter(xs)
+            })
+            .memoized();
+        let parser =
+            (number.clone() << prefix("+")) | (number.clone() << prefix("-")) | number.clone();
+
+        let parsed = run(parser.clone(), "123".to_string());
+        assert_eq!(parsed, Ok("123".to_string()));
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+
+        // every run starts with an empty memo
+        let parsed = run(parser, "45-".to_string());
+        assert_eq!(parsed, Ok("45".to_string()));
+        assert_eq!(calls.load(Ordering::Relaxed), 2);
+
+        // failures are remembered too
+        let parser = (number.clone() >> prefix("x")) | (number >> prefix("y"));
+        let parsed = run(parser, "a".to_string());
+        assert_eq!(parsed.unwrap_err().location.offset, 0);
+        assert_eq!(calls.load(Ordering::Relaxed), 2);
+    }
+}
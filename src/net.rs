@@ -0,0 +1,411 @@
+//! URIs as RFC 3986 writes them, IP addresses and `host:port` pairs.
+//!
+//! The components of a URI are kept as written, percent-encoding included,
+//! only checked to be well-formed. Hosts that are IPv4 addresses are told
+//! apart from names the way RFC 3986 says, by whether the whole host is one.
+//!
+//! ```
+//! use std::net::Ipv6Addr;
+//! use parcoom::net::{uri, Host};
+//! use parcoom::run;
+//!
+//! let parsed = run(uri(), "https://user@[::1]:8080/a/b?q=1#top".to_string()).unwrap();
+//! assert_eq!(parsed.scheme, "https");
+//! let authority = parsed.authority.unwrap();
+//! assert_eq!(authority.userinfo.as_deref(), Some("user"));
+//! assert_eq!(authority.host, Host::Ipv6(Ipv6Addr::LOCALHOST));
+//! assert_eq!(authority.port, Some(8080));
+//! assert_eq!(parsed.path, "/a/b");
+//! assert_eq!((parsed.query.as_deref(), parsed.fragment.as_deref()), (Some("q=1"), Some("top")));
+//! ```
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use core::fmt;
+use core::net::{Ipv4Addr, Ipv6Addr};
+use core::str::FromStr;
+
+use crate::limits::counted;
+use crate::{cut, fail, input_sub, prefix, wrap, Failure, Parser, Ptr};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Host {
+    Ipv4(Ipv4Addr),
+    Ipv6(Ipv6Addr),
+    /// An address of a later IP version, `v7.abc` in `[v7.abc]`.
+    IpFuture(String),
+    /// A registered name such as `example.com`, possibly empty.
+    Name(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Authority {
+    pub userinfo: Option<String>,
+    pub host: Host,
+    /// `None` when there is no port or only the `:` of one.
+    pub port: Option<u16>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Uri {
+    pub scheme: String,
+    pub authority: Option<Authority>,
+    /// Empty or starting with `/` when there is an authority.
+    pub path: String,
+    pub query: Option<String>,
+    pub fragment: Option<String>,
+}
+
+impl fmt::Display for Host {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Host::Ipv4(addr) => write!(f, "{addr}"),
+            Host::Ipv6(addr) => write!(f, "[{addr}]"),
+            Host::IpFuture(addr) => write!(f, "[{addr}]"),
+            Host::Name(name) => f.write_str(name),
+        }
+    }
+}
+
+impl fmt::Display for Authority {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(userinfo) = &self.userinfo {
+            write!(f, "{userinfo}@")?;
+        }
+        write!(f, "{}", self.host)?;
+        if let Some(port) = self.port {
+            write!(f, ":{port}")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Uri {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:", self.scheme)?;
+        if let Some(authority) = &self.authority {
+            write!(f, "//{authority}")?;
+        }
+        f.write_str(&self.path)?;
+        if let Some(query) = &self.query {
+            write!(f, "?{query}")?;
+        }
+        if let Some(fragment) = &self.fragment {
+            write!(f, "#{fragment}")?;
+        }
+        Ok(())
+    }
+}
+
+fn unreserved(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "-._~".contains(c)
+}
+
+fn sub_delim(c: char) -> bool {
+    "!$&'()*+,;=".contains(c)
+}
+
+/// What path segments are made of.
+fn pchar(c: char) -> bool {
+    unreserved(c) || sub_delim(c) || c == ':' || c == '@'
+}
+
+/// The chars `allowed` and percent-encoded bytes, possibly none. A `%`
+/// without two hex digits after it fails there, without backtracking, as
+/// no other part of a URI would take it.
+fn encoded<'a>(allowed: fn(char) -> bool) -> Parser<'a, String> {
+    counted(Parser {
+        run: Ptr::new(move |input| {
+            let text = input.text();
+            let bytes = text.as_bytes();
+            let mut n = 0;
+            while let Some(&b) = bytes.get(n) {
+                if b == b'%' {
+                    let hex = bytes.get(n + 1..n + 3);
+                    if !hex.is_some_and(|hex| hex.iter().all(u8::is_ascii_hexdigit)) {
+                        let desc = "expected two hex digits after %".to_string();
+                        let e = Failure {
+                            fatal: true,
+                            ..Failure::expecting("a percent-encoded byte".into(), desc)
+                        };
+                        return (input_sub(n, &input), Err(e));
+                    }
+                    n += 3;
+                } else if allowed(b as char) {
+                    n += 1;
+                } else {
+                    break;
+                }
+            }
+            (input_sub(n, &input), Ok(text[..n].to_string()))
+        }),
+        shape: None,
+    })
+}
+
+/// The longest run of at most `max` chars of `allowed` that starts the
+/// input and parses as a `T`.
+fn address<'a, T: FromStr + 'a>(
+    name: &'static str,
+    max: usize,
+    allowed: fn(char) -> bool,
+) -> Parser<'a, T> {
+    Parser {
+        run: Ptr::new(move |input| {
+            let text = input.text();
+            let run = text.bytes().take(max).take_while(|&b| allowed(b as char));
+            for n in (1..=run.count()).rev() {
+                if let Ok(addr) = text[..n].parse() {
+                    return (input_sub(n, &input), Ok(addr));
+                }
+            }
+            let desc = format!("expected {name}");
+            (input, Err(Failure::expecting(name.to_string(), desc)))
+        }),
+        shape: None,
+    }
+}
+
+/// An IPv4 address in dotted decimal such as `192.0.2.1`, without leading
+/// zeros in its numbers.
+pub fn ipv4<'a>() -> Parser<'a, Ipv4Addr> {
+    address("an IPv4 address", 15, |c| c.is_ascii_digit() || c == '.')
+}
+
+/// An IPv6 address such as `2001:db8::1` or `::ffff:192.0.2.1`, without
+/// the brackets of URIs.
+pub fn ipv6<'a>() -> Parser<'a, Ipv6Addr> {
+    address("an IPv6 address", 45, |c| {
+        c.is_ascii_hexdigit() || c == ':' || c == '.'
+    })
+}
+
+/// `v`, a hex version, `.` and the address, as in `[v7.abc]`.
+fn ip_future<'a>() -> Parser<'a, String> {
+    counted(Parser {
+        run: Ptr::new(|input| {
+            let text = input.text();
+            let version = text.get(1..).unwrap_or("");
+            let digits = version.bytes().take_while(u8::is_ascii_hexdigit).count();
+            let addr = version.get(digits + 1..).unwrap_or("");
+            let len = addr
+                .bytes()
+                .take_while(|&b| unreserved(b as char) || sub_delim(b as char) || b == b':')
+                .count();
+            let valid = text.starts_with(['v', 'V'])
+                && digits > 0
+                && version[digits..].starts_with('.')
+                && len > 0;
+            if !valid {
+                let desc = "expected an IPv6 address or a future IP version".to_string();
+                return (input, Err(Failure::expecting("an IP literal".into(), desc)));
+            }
+            let n = 1 + digits + 1 + len;
+            (input_sub(n, &input), Ok(text[..n].to_string()))
+        }),
+        shape: None,
+    })
+}
+
+/// A host of a URI: an IP address in brackets, an IPv4 address or a
+/// registered name, which may be empty.
+pub fn host<'a>() -> Parser<'a, Host> {
+    let address = ipv6().map(Host::Ipv6) | ip_future().map(Host::IpFuture);
+    let literal = prefix("[") >> cut(address << prefix("]"));
+    let name = encoded(|c| unreserved(c) || sub_delim(c)).map(|name| match name.parse() {
+        Ok(addr) => Host::Ipv4(addr),
+        Err(_) => Host::Name(name),
+    });
+    literal | name
+}
+
+/// Digits of a port, possibly none.
+fn port<'a>() -> Parser<'a, Option<u16>> {
+    Parser {
+        run: Ptr::new(|input| {
+            let text = input.text();
+            let n = text.bytes().take_while(u8::is_ascii_digit).count();
+            if n == 0 {
+                return (input, Ok(None));
+            }
+            match text[..n].parse() {
+                Ok(port) => (input_sub(n, &input), Ok(Some(port))),
+                Err(_) => {
+                    let port = &text[..n];
+                    let desc = format!("port {port} is out of range, expected 0 to 65535");
+                    (input, Err(Failure::from(desc)))
+                }
+            }
+        }),
+        shape: None,
+    }
+}
+
+/// A host and a port such as `example.com:443` or `[::1]:8080`, both of
+/// which must be there.
+pub fn host_port<'a>() -> Parser<'a, (Host, u16)> {
+    let host = host().and_then(|host| match host {
+        Host::Name(name) if name.is_empty() => fail("expected a host".to_string()),
+        host => wrap(host),
+    });
+    let port = port().and_then(|port| match port {
+        Some(port) => wrap(port),
+        None => fail("expected a port".to_string()),
+    });
+    (host << prefix(":")) + port
+}
+
+/// What comes after the `//` of a URI: `user@host:port` with the user and
+/// the port optional.
+pub fn authority<'a>() -> Parser<'a, Authority> {
+    let userinfo = encoded(|c| unreserved(c) || sub_delim(c) || c == ':') << prefix("@");
+    let port = (prefix(":") >> port()).optional();
+    (userinfo.optional() + host() + port).map(|((userinfo, host), port)| Authority {
+        userinfo,
+        host,
+        port: port.flatten(),
+    })
+}
+
+/// The scheme of a URI such as `https` or `svn+ssh`, without the `:`.
+pub fn scheme<'a>() -> Parser<'a, String> {
+    counted(Parser {
+        run: Ptr::new(|input| {
+            let text = input.text();
+            if !text.starts_with(|c: char| c.is_ascii_alphabetic()) {
+                let desc = "expected a scheme".to_string();
+                return (input, Err(Failure::expecting("a scheme".into(), desc)));
+            }
+            let n = text
+                .find(|c: char| !(c.is_ascii_alphanumeric() || "+-.".contains(c)))
+                .unwrap_or(text.len());
+            (input_sub(n, &input), Ok(text[..n].to_string()))
+        }),
+        shape: None,
+    })
+}
+
+/// A URI with a scheme, such as `https://example.com/a?b#c`,
+/// `mailto:someone@example.com` or `urn:isbn:0451450523`. Whatever follows
+/// it that no component takes is left to the parsers after.
+pub fn uri<'a>() -> Parser<'a, Uri> {
+    let path = || encoded(|c| pchar(c) || c == '/');
+    // after an authority, the path is empty or starts with `/`
+    let rooted = (prefix("/") >> path()).map(|path| format!("/{path}"));
+    let with_authority = (prefix("//") >> authority()) + (rooted | wrap(String::new()));
+    let hier = with_authority.map(|(authority, path)| (Some(authority), path))
+        | path().map(|path| (None, path));
+    let rest = || encoded(|c| pchar(c) || c == '/' || c == '?');
+    let query = (prefix("?") >> rest()).optional();
+    let fragment = (prefix("#") >> rest()).optional();
+    let uri = (scheme() << prefix(":")) + hier + query + fragment;
+    uri.map(|(((scheme, (authority, path)), query), fragment)| Uri {
+        scheme,
+        authority,
+        path,
+        query,
+        fragment,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{eof, run};
+
+    fn parse_uri(text: &str) -> Uri {
+        run(uri() << eof(), text.to_string()).unwrap()
+    }
+
+    #[test]
+    fn ip_test() {
+        let parsed = run(ipv4(), "192.0.2.1".to_string());
+        assert_eq!(parsed, Ok(Ipv4Addr::new(192, 0, 2, 1)));
+        // the longest address that is one
+        let parsed = run(ipv4() + prefix(".5"), "1.2.3.4.5".to_string());
+        assert_eq!(parsed, Ok((Ipv4Addr::new(1, 2, 3, 4), ".5")));
+        assert!(run(ipv4() << eof(), "1.2.3.04".to_string()).is_err());
+        let e = run(ipv4(), "256.0.0.1".to_string()).unwrap_err();
+        assert_eq!(
+            (e.desc.as_str(), e.location.offset),
+            ("expected an IPv4 address", 0)
+        );
+
+        let parsed = run(ipv6(), "2001:db8::1".to_string());
+        assert_eq!(parsed, Ok(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1)));
+        let parsed = run(ipv6(), "::ffff:192.0.2.1".to_string());
+        assert_eq!(parsed, Ok(Ipv4Addr::new(192, 0, 2, 1).to_ipv6_mapped()));
+        let parsed = run(ipv6() + prefix(":"), "fe80::1:".to_string());
+        assert_eq!(parsed.map(|(addr, _)| addr.segments()[7]), Ok(1));
+        assert!(run(ipv6(), "1:2".to_string()).is_err());
+    }
+
+    #[test]
+    fn host_port_test() {
+        let parsed = run(host_port(), "example.com:443".to_string());
+        assert_eq!(parsed, Ok((Host::Name("example.com".to_string()), 443)));
+        let parsed = run(host_port(), "[::1]:8080".to_string());
+        assert_eq!(parsed, Ok((Host::Ipv6(Ipv6Addr::LOCALHOST), 8080)));
+        let parsed = run(host_port(), "10.0.0.1:0".to_string());
+        assert_eq!(parsed, Ok((Host::Ipv4(Ipv4Addr::new(10, 0, 0, 1)), 0)));
+        // names that only look like addresses
+        let parsed = run(host(), "1.2.3.4.5".to_string());
+        assert_eq!(parsed, Ok(Host::Name("1.2.3.4.5".to_string())));
+        let parsed = run(host(), "[v1f.a:b]".to_string());
+        assert_eq!(parsed, Ok(Host::IpFuture("v1f.a:b".to_string())));
+
+        let e = run(host_port(), "a:70000".to_string()).unwrap_err();
+        assert_eq!(e.desc, "port 70000 is out of range, expected 0 to 65535");
+        assert_eq!(e.location.offset, 2);
+        assert_eq!(
+            run(host_port(), ":80".to_string()).unwrap_err().desc,
+            "expected a host"
+        );
+        assert_eq!(
+            run(host_port(), "a:".to_string()).unwrap_err().desc,
+            "expected a port"
+        );
+        let e = run(host(), "[::1".to_string()).unwrap_err();
+        assert_eq!(e.location.offset, 4);
+    }
+
+    #[test]
+    fn uri_test() {
+        let parsed = parse_uri("http://example.com");
+        let authority = parsed.authority.as_ref().unwrap();
+        assert_eq!(authority.host, Host::Name("example.com".to_string()));
+        assert_eq!((authority.port, parsed.path.as_str()), (None, ""));
+
+        let parsed = parse_uri("file:///etc/hosts");
+        assert_eq!(parsed.authority.unwrap().host, Host::Name(String::new()));
+        assert_eq!(parsed.path, "/etc/hosts");
+        let parsed = parse_uri("mailto:someone@example.com?subject=hi%20there");
+        assert_eq!(parsed.authority, None);
+        assert_eq!(parsed.path, "someone@example.com");
+        assert_eq!(parsed.query.as_deref(), Some("subject=hi%20there"));
+        let parsed = parse_uri("urn:isbn:0451450523");
+        assert_eq!(
+            (parsed.scheme.as_str(), parsed.path.as_str()),
+            ("urn", "isbn:0451450523")
+        );
+        let parsed = parse_uri("http://a:/p#f?g/h");
+        assert_eq!(parsed.authority.unwrap().port, None);
+        assert_eq!(parsed.fragment.as_deref(), Some("f?g/h"));
+
+        for text in [
+            "https://user:pw@[2001:db8::7]:8080/a/b?x=1&y#z",
+            "svn+ssh://192.0.2.16/repo",
+            "tel:+1-816-555-1212",
+        ] {
+            assert_eq!(parse_uri(text).to_string(), text);
+        }
+
+        let e = run(uri(), "http://a/b%2".to_string()).unwrap_err();
+        assert_eq!(
+            (e.desc.as_str(), e.location.offset),
+            ("expected two hex digits after %", 10)
+        );
+        assert!(run(uri(), "1http://a".to_string()).is_err());
+        assert!(run(uri() << eof(), "http://a b".to_string()).is_err());
+    }
+}
@@ -0,0 +1,267 @@
+//! Parsers for numeric literals, yielding the parsed value.
+//!
+//! Leading zeros are allowed and the number is read as decimal, except by
+//! [`int_literal`] which takes radix prefixes. Values that don't fit the
+//! requested type fail at the start of the literal.
+
+use alloc::format;
+use alloc::string::ToString;
+use core::any::type_name;
+use core::str::FromStr;
+
+use crate::{input_sub, Failure, Needed, Parser, Ptr};
+
+fn digits(s: &[u8], i: usize) -> usize {
+    i + s[i..].iter().take_while(|b| b.is_ascii_digit()).count()
+}
+
+fn sign(s: &[u8], i: usize) -> usize {
+    match s.get(i) {
+        Some(b'+' | b'-') => i + 1,
+        _ => i,
+    }
+}
+
+/// `[+-]?[0-9]+`, the length or 0 if there is none.
+fn scan_integer(s: &[u8]) -> usize {
+    let start = sign(s, 0);
+    match digits(s, start) {
+        end if end == start => 0,
+        end => end,
+    }
+}
+
+/// `[0-9]+`
+fn scan_unsigned(s: &[u8]) -> usize {
+    digits(s, 0)
+}
+
+/// `[+-]?([0-9]+(\.[0-9]*)?|\.[0-9]+)([eE][+-]?[0-9]+)?`
+fn scan_float(s: &[u8]) -> usize {
+    let start = sign(s, 0);
+    let int_end = digits(s, start);
+    let mut end = int_end;
+    if s.get(end) == Some(&b'.') {
+        let frac_end = digits(s, end + 1);
+        if int_end > start || frac_end > end + 1 {
+            end = frac_end;
+        }
+    }
+    if end == start {
+        return 0;
+    }
+    if let Some(b'e' | b'E') = s.get(end) {
+        let exp_start = sign(s, end + 1);
+        let exp_end = digits(s, exp_start);
+        if exp_end > exp_start {
+            end = exp_end;
+        }
+    }
+    end
+}
+
+fn number<'a, T: FromStr>(expected: &'static str, scan: fn(&[u8]) -> usize) -> Parser<'a, T> {
+    Parser {
+        run: Ptr::new(move |input| {
+            let text = input.text();
+            let n = scan(text.as_bytes());
+            // The literal might go on in the part of the stream still to come.
+            if n == text.len() && input.partial {
+                return (input, Err(Failure::incomplete(Needed::Unknown)));
+            }
+            if n == 0 {
+                let desc = format!("expected {expected}");
+                return (input, Err(Failure::expecting(expected.to_string(), desc)));
+            }
+            match text[..n].parse() {
+                Ok(x) => (input_sub(n, &input), Ok(x)),
+                Err(_) => {
+                    let desc = format!("{} does not fit into {}", &text[..n], type_name::<T>());
+                    (input, Err(Failure::from(desc)))
+                }
+            }
+        }),
+        shape: None,
+    }
+}
+
+/// A decimal integer with an optional sign, e.g. `integer::<i64>()`.
+pub fn integer<'a, T: FromStr>() -> Parser<'a, T> {
+    number("an integer", scan_integer)
+}
+
+/// A decimal integer without a sign, e.g. `unsigned::<u32>()`.
+pub fn unsigned<'a, T: FromStr>() -> Parser<'a, T> {
+    number("an unsigned integer", scan_unsigned)
+}
+
+/// A decimal number with an optional sign, fraction and exponent, such as
+/// `-1.5e3`, `.5` or `2.`.
+pub fn float<'a, T: FromStr>() -> Parser<'a, T> {
+    number("a number", scan_float)
+}
+
+/// An integer literal as programming languages write them: an optional
+/// sign, a `0x`, `0o` or `0b` prefix for hex, octal or binary, and digits
+/// that may be grouped with underscores, such as `-0xff_ff` or `1_000`.
+/// An underscore may follow any digit.
+///
+/// Values that don't fit `T` fail at the start of the literal, a prefix
+/// without digits after it at the end of the prefix.
+pub fn int_literal<'a, T: TryFrom<u128> + TryFrom<i128>>() -> Parser<'a, T> {
+    Parser {
+        run: Ptr::new(|input| {
+            let text = input.text();
+            let s = text.as_bytes();
+            let start = sign(s, 0);
+            let (radix, name, digits_start) = match s.get(start..start + 2) {
+                Some(b"0x" | b"0X") => (16, "hex digits", start + 2),
+                Some(b"0o" | b"0O") => (8, "octal digits", start + 2),
+                Some(b"0b" | b"0B") => (2, "binary digits", start + 2),
+                _ => (10, "an integer", start),
+            };
+            let is_digit = |b: &u8| char::from(*b).is_digit(radix);
+            let mut end = digits_start;
+            if s.get(end).is_some_and(is_digit) {
+                end += s[end..]
+                    .iter()
+                    .take_while(|b| is_digit(b) || **b == b'_')
+                    .count();
+            }
+            if end == s.len() && input.partial {
+                return (input, Err(Failure::incomplete(Needed::Unknown)));
+            }
+            if end == digits_start {
+                let (expected, desc) = (name.to_string(), format!("expected {name}"));
+                // A prefix is taken as the start of a literal.
+                let failed = match radix {
+                    10 => input,
+                    _ => input_sub(digits_start, &input),
+                };
+                return (failed, Err(Failure::expecting(expected, desc)));
+            }
+            let magnitude = s[digits_start..end]
+                .iter()
+                .filter(|&&b| b != b'_')
+                .try_fold(0u128, |n, &b| {
+                    let digit = char::from(b).to_digit(radix).unwrap();
+                    n.checked_mul(u128::from(radix))?
+                        .checked_add(u128::from(digit))
+                });
+            let value = match (magnitude, s[0]) {
+                (Some(n), b'-') => 0i128
+                    .checked_sub_unsigned(n)
+                    .and_then(|n| T::try_from(n).ok()),
+                (Some(n), _) => T::try_from(n).ok(),
+                (None, _) => None,
+            };
+            match value {
+                Some(x) => (input_sub(end, &input), Ok(x)),
+                None => {
+                    let desc = format!("{} does not fit into {}", &text[..end], type_name::<T>());
+                    (input, Err(Failure::from(desc)))
+                }
+            }
+        }),
+        shape: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{prefix, run, Location, ParserError};
+
+    #[test]
+    fn integer_test() {
+        assert_eq!(run(integer::<i64>(), "-42x".to_string()), Ok(-42));
+        assert_eq!(run(integer::<i64>(), "+007".to_string()), Ok(7));
+        assert_eq!(run(unsigned::<u8>(), "255".to_string()), Ok(255));
+
+        let parsed = run(integer::<i32>(), "-".to_string());
+        assert_eq!(
+            parsed,
+            Err(ParserError {
+                desc: "expected an integer".to_string(),
+                location: Location {
+                    offset: 0,
+                    char_offset: 0,
+                    line: 1,
+                    column: 1,
+                },
+                context: vec![],
+                expected: vec!["an integer".to_string()],
+                cause: None,
+                at_end: false,
+            })
+        );
+    }
+
+    #[test]
+    fn overflow_test() {
+        let parsed = run(prefix("x=") >> unsigned::<u8>(), "x=256".to_string());
+        assert_eq!(
+            parsed,
+            Err(ParserError {
+                desc: "256 does not fit into u8".to_string(),
+                location: Location {
+                    offset: 2,
+                    char_offset: 2,
+                    line: 1,
+                    column: 3,
+                },
+                context: vec![],
+                expected: vec![],
+                cause: None,
+                at_end: false,
+            })
+        );
+        let parsed = run(unsigned::<u32>(), "-1".to_string());
+        assert_eq!(
+            parsed.map_err(|e| e.desc),
+            Err("expected an unsigned integer".to_string())
+        );
+    }
+
+    #[test]
+    fn float_test() {
+        let number = || float::<f64>().then_ignore(prefix(";"));
+        assert_eq!(run(number(), "-1.5e3;".to_string()), Ok(-1500.0));
+        assert_eq!(run(number(), ".5;".to_string()), Ok(0.5));
+        assert_eq!(run(number(), "2.;".to_string()), Ok(2.0));
+        assert_eq!(run(number(), "1E-2;".to_string()), Ok(0.01));
+        // an `e` without exponent digits is not part of the number
+        let parser = float::<f64>().then(prefix("e"));
+        assert_eq!(run(parser, "3e".to_string()), Ok((3.0, "e")));
+        assert!(run(float::<f64>(), ".".to_string()).is_err());
+    }
+
+    #[test]
+    fn int_literal_test() {
+        let parse = |text: &str| run(int_literal::<i64>(), text.to_string());
+        assert_eq!(parse("1_000_000"), Ok(1_000_000));
+        assert_eq!(parse("-0xFF_ff"), Ok(-0xffff));
+        assert_eq!(parse("+0o17"), Ok(0o17));
+        assert_eq!(parse("0b1010_"), Ok(10));
+        assert_eq!(parse("-0x8000_0000_0000_0000"), Ok(i64::MIN));
+        assert_eq!(run(int_literal::<u8>(), "0b1111_1111".to_string()), Ok(255));
+        let max = u128::MAX.to_string();
+        assert_eq!(run(int_literal::<u128>(), max), Ok(u128::MAX));
+        // digits of another radix end the literal
+        let parser = int_literal::<u32>().then(prefix("2"));
+        assert_eq!(run(parser, "0b12".to_string()), Ok((1, "2")));
+
+        let error = |text: &str| {
+            let e = run(int_literal::<i8>(), text.to_string()).unwrap_err();
+            (e.location.offset, e.desc)
+        };
+        assert_eq!(error("0x80"), (0, "0x80 does not fit into i8".to_string()));
+        assert!(run(int_literal::<i8>(), "-0x80".to_string()).is_ok());
+        assert_eq!(error("0x_1"), (2, "expected hex digits".to_string()));
+        assert_eq!(error("-"), (0, "expected an integer".to_string()));
+        let parsed = run(int_literal::<u64>(), "-1".to_string()).map_err(|e| e.desc);
+        assert_eq!(parsed, Err("-1 does not fit into u64".to_string()));
+        let huge = "9".repeat(40);
+        assert!(run(int_literal::<u128>(), huge).is_err());
+    }
+}
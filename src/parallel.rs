@@ -0,0 +1,148 @@
+//! Parsing the records of a large input on several threads at once.
+//!
+//! Needs the `sync` feature, parsers being shared between the threads.
+//!
+//! ```
+//! use parcoom::chars::digits1;
+//! use parcoom::parallel::parse_records;
+//!
+//! let parsed = parse_records(&digits1(), "1\n22\n333\n", "\n");
+//! let lens: Vec<usize> = parsed.into_iter().map(|r| r.unwrap().len()).collect();
+//! assert_eq!(lens, vec![1, 2, 3]);
+//! ```
+
+use std::thread;
+
+use crate::{run, Location, Parser, ParserError};
+
+/// Runs `p` on each of the records of `input`, which `boundary` separates,
+/// on as many threads as the machine runs in parallel. See
+/// [`parse_records_on`].
+pub fn parse_records<A: Send>(
+    p: &Parser<A>,
+    input: &str,
+    boundary: &str,
+) -> Vec<Result<A, ParserError>> {
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    parse_records_on(threads, p, input, boundary)
+}
+
+/// Runs `p` on each of the records of `input`, which `boundary` separates,
+/// on `threads` threads. A boundary at the very end doesn't start another
+/// record.
+///
+/// The results are in the order of the records, and the locations of the
+/// errors are in `input` rather than in their record. Each record is parsed
+/// as with [`run`], so `p` doesn't have to reach its end.
+pub fn parse_records_on<A: Send>(
+    threads: usize,
+    p: &Parser<A>,
+    input: &str,
+    boundary: &str,
+) -> Vec<Result<A, ParserError>> {
+    let records = records(input, boundary);
+    if records.is_empty() {
+        return Vec::new();
+    }
+    let per_thread = records.len().div_ceil(threads.max(1));
+    thread::scope(|scope| {
+        let workers: Vec<_> = records
+            .chunks(per_thread)
+            .map(|chunk| {
+                scope.spawn(move || {
+                    let parse = |&(text, start): &(&str, Location)| {
+                        run(p.clone(), text.into()).map_err(|e| relocate(e, start))
+                    };
+                    chunk.iter().map(parse).collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().unwrap())
+            .collect()
+    })
+}
+
+/// The records of `input` with where they start.
+fn records<'s>(input: &'s str, boundary: &str) -> Vec<(&'s str, Location)> {
+    let mut records = Vec::new();
+    let mut start = Location {
+        offset: 0,
+        char_offset: 0,
+        line: 1,
+        column: 1,
+    };
+    let mut rest = input;
+    while !rest.is_empty() {
+        let (record, next) = match (boundary.is_empty(), rest.find(boundary)) {
+            (false, Some(end)) => (&rest[..end], &rest[end + boundary.len()..]),
+            _ => (rest, ""),
+        };
+        records.push((record, start));
+        let skipped = &rest[..rest.len() - next.len()];
+        start.offset += skipped.len();
+        start.char_offset += skipped.chars().count();
+        match skipped.rfind('\n') {
+            Some(i) => {
+                start.line += skipped.matches('\n').count();
+                start.column = skipped[i + 1..].chars().count() + 1;
+            }
+            None => start.column += skipped.chars().count(),
+        }
+        rest = next;
+    }
+    records
+}
+
+/// `e` and its causes with their locations moved from their record, which
+/// starts at `start`, to the whole input.
+fn relocate(mut e: ParserError, start: Location) -> ParserError {
+    let location = &mut e.location;
+    if location.line == 1 {
+        location.column += start.column - 1;
+    }
+    location.offset += start.offset;
+    location.char_offset += start.char_offset;
+    location.line += start.line - 1;
+    e.cause = e.cause.map(|cause| Box::new(relocate(*cause, start)));
+    e
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chars::digits1;
+    use crate::prefix;
+
+    #[test]
+    fn parse_records_test() {
+        let pair = digits1() << prefix(",") << digits1();
+        let input = "1,2\n3,4\n5;6\n7,8\n";
+        let parsed = parse_records_on(3, &pair, input, "\n");
+        assert_eq!(parsed.len(), 4);
+        assert_eq!(parsed[3].as_deref(), Ok("7"));
+        assert_eq!(
+            parsed[2].clone().unwrap_err().location,
+            Location {
+                offset: 9,
+                char_offset: 9,
+                line: 3,
+                column: 2,
+            }
+        );
+
+        // records within a line
+        let parsed = parse_records_on(2, &digits1(), "1;é;x", ";");
+        assert_eq!(
+            parsed[2].clone().unwrap_err().location,
+            Location {
+                offset: 5,
+                char_offset: 4,
+                line: 1,
+                column: 5,
+            }
+        );
+        assert!(parse_records(&digits1(), "", "\n").is_empty());
+    }
+}
@@ -0,0 +1,118 @@
+//! Types that know how to parse themselves, see [`Parsable`].
+
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::ident::identifier;
+use crate::number::{float, integer};
+use crate::{MaybeSync, Parser};
+
+/// A type with a parser of its own. The parser of a struct or enum can be
+/// derived with `#[derive(Parse)]`, see the `derive` feature.
+///
+/// Integers and floats parse as decimal numbers, `String` as an
+/// [`identifier`], `Option`, `Vec` and `Box` as whatever parses their
+/// contents, optional, zero or more times and boxed.
+pub trait Parsable: Sized {
+    fn parser<'a>() -> Parser<'a, Self>;
+}
+
+macro_rules! numbers {
+    ($parser:ident: $($t:ty)*) => {
+        $(
+            impl Parsable for $t {
+                fn parser<'a>() -> Parser<'a, Self> {
+                    $parser()
+                }
+            }
+        )*
+    };
+}
+
+numbers!(integer: i8 i16 i32 i64 i128 isize u8 u16 u32 u64 u128 usize);
+numbers!(float: f32 f64);
+
+impl Parsable for String {
+    fn parser<'a>() -> Parser<'a, Self> {
+        identifier()
+    }
+}
+
+impl<T: Parsable + 'static> Parsable for Option<T> {
+    fn parser<'a>() -> Parser<'a, Self> {
+        lazy(T::parser).optional()
+    }
+}
+
+impl<T: Parsable + 'static> Parsable for Vec<T> {
+    fn parser<'a>() -> Parser<'a, Self> {
+        lazy(T::parser).many()
+    }
+}
+
+impl<T: Parsable + MaybeSync + 'static> Parsable for Box<T> {
+    fn parser<'a>() -> Parser<'a, Self> {
+        lazy(T::parser).map(Box::new)
+    }
+}
+
+/// The `Vec` fields of derived parsers with a separator, items and
+/// separators being [`padded`](crate::padded) if `padded`.
+pub trait Separated: Sized {
+    fn sep_by<'a>(sep: &'static str, padded: bool) -> Parser<'a, Self>;
+}
+
+impl<T: Parsable + 'static> Separated for Vec<T> {
+    fn sep_by<'a>(sep: &'static str, padded: bool) -> Parser<'a, Self> {
+        let (item, sep) = (lazy(T::parser), crate::prefix(sep));
+        match padded {
+            true => item.padded().sep_by(sep.padded()),
+            false => item.sep_by(sep),
+        }
+    }
+}
+
+/// The parser `f` builds, built when it first runs. The parsers of
+/// recursive types would build forever otherwise.
+pub fn lazy<'a, T: 'a>(f: fn() -> Parser<'a, T>) -> Parser<'a, T> {
+    #[cfg(not(feature = "sync"))]
+    use core::cell::OnceCell;
+    #[cfg(feature = "sync")]
+    use std::sync::OnceLock as OnceCell;
+
+    let cell = OnceCell::new();
+    Parser {
+        run: crate::Ptr::new(move |input| {
+            crate::limits::nested(input, |input| (cell.get_or_init(f).run)(input))
+        }),
+        shape: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::run;
+
+    #[derive(Debug, PartialEq)]
+    struct List(Vec<List>);
+
+    impl Parsable for List {
+        fn parser<'a>() -> Parser<'a, Self> {
+            (crate::prefix("(") >> Vec::parser() << crate::prefix(")")).map(List)
+        }
+    }
+
+    #[test]
+    fn parsable_test() {
+        assert_eq!(run(i64::parser(), "-12".to_string()), Ok(-12));
+        assert_eq!(run(Option::<u8>::parser(), "x".to_string()), Ok(None));
+        let list = |items| List(items);
+        let parsed = run(List::parser(), "(()(()))".to_string());
+        assert_eq!(
+            parsed,
+            Ok(list(vec![list(vec![]), list(vec![list(vec![])])]))
+        );
+    }
+}
@@ -0,0 +1,107 @@
+//! Matching parsers in any order, see [`permutation`].
+
+use crate::{Failure, Parser, ParserInput, Ptr};
+
+/// Tuples of parsers [`permutation`] takes, of up to 8 of them.
+pub trait Permutation<'a> {
+    type Output;
+
+    fn permutation(self) -> Parser<'a, Self::Output>;
+}
+
+macro_rules! permutations {
+    ($($n:literal: $(($i:tt $t:ident $x:ident))+;)+) => {
+        $(
+            impl<'a, $($t: 'a),+> Permutation<'a> for ($(Parser<'a, $t>,)+) {
+                type Output = ($($t,)+);
+
+                fn permutation(self) -> Parser<'a, Self::Output> {
+                    Parser {
+                        run: Ptr::new(move |input| {
+                            let mut input = input;
+                            $(let mut $x = None;)+
+                            for _ in 0..$n {
+                                let mut failure: Option<(ParserInput, Failure)> = None;
+                                let mut matched = false;
+                                $(
+                                    if !matched && $x.is_none() {
+                                        failure = match ((self.$i.run)(input.clone()), failure) {
+                                            ((input_, Ok(x)), failure) => {
+                                                $x = Some(x);
+                                                input = input_;
+                                                matched = true;
+                                                failure
+                                            }
+                                            ((input_, Err(e)), _) if e.fatal => return (input_, Err(e)),
+                                            ((input_, Err(e)), Some((last, e_))) if last.pos == input_.pos => {
+                                                Some((input_, e_.merge(e)))
+                                            }
+                                            ((input_, Err(e)), _) => Some((input_, e)),
+                                        };
+                                    }
+                                )+
+                                if !matched {
+                                    let (input_, e) = failure.unwrap();
+                                    return (input_, Err(e));
+                                }
+                            }
+                            (input, Ok(($($x.unwrap(),)+)))
+                        }),
+                        shape: None,
+                    }
+                }
+            }
+        )+
+    };
+}
+
+permutations! {
+    2: (0 A a) (1 B b);
+    3: (0 A a) (1 B b) (2 C c);
+    4: (0 A a) (1 B b) (2 C c) (3 D d);
+    5: (0 A a) (1 B b) (2 C c) (3 D d) (4 E e);
+    6: (0 A a) (1 B b) (2 C c) (3 D d) (4 E e) (5 F f);
+    7: (0 A a) (1 B b) (2 C c) (3 D d) (4 E e) (5 F f) (6 G g);
+    8: (0 A a) (1 B b) (2 C c) (3 D d) (4 E e) (5 F f) (6 G g) (7 H h);
+}
+
+/// Matches each of a tuple of parsers exactly once, in any order, yielding
+/// their values in the order of the tuple. At each step the first parser
+/// not matched yet that matches is taken.
+pub fn permutation<'a, P: Permutation<'a>>(parsers: P) -> Parser<'a, P::Output> {
+    parsers.permutation()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chars::digits1;
+    use crate::{prefix, run, Location, ParserError};
+
+    #[test]
+    fn permutation_test() {
+        let flag = |name| prefix(name).padded();
+        let parser = || permutation((flag("-a"), flag("-b"), prefix("-n") >> digits1().to_owned()));
+        let expected = Ok(("-a", "-b", "3".to_string()));
+        assert_eq!(run(parser(), "-n3 -b -a".to_string()), expected);
+        assert_eq!(run(parser(), "-a -n3 -b".to_string()), expected);
+
+        let parsed = run(parser(), "-b -a -b".to_string());
+        assert_eq!(
+            parsed,
+            Err(ParserError {
+                desc: "expected -n".to_string(),
+                location: Location {
+                    offset: 6,
+                    char_offset: 6,
+                    line: 1,
+                    column: 7,
+                },
+                context: vec![],
+                expected: vec!["\"-n\"".to_string()],
+                cause: None,
+                at_end: false,
+            })
+        );
+    }
+}
@@ -0,0 +1,162 @@
+//! Counting what the [named](Parser::named) rules of a grammar do, to find
+//! where a slow parse spends its time.
+
+use alloc::collections::BTreeMap;
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::fmt;
+use core::time::Duration;
+
+use crate::{finish, make_input, Parse, Parser, ParserError, ParserInput, Ptr};
+
+/// What a rule did during a [`run_profiled`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RuleStats {
+    pub calls: usize,
+    pub failures: usize,
+    /// The failures that got past the start of the rule before giving up,
+    /// the input between being parsed again by whatever came next.
+    pub backtracks: usize,
+    /// The time spent in the rule, including the rules it ran. Only
+    /// measured with the `std` feature, zero otherwise.
+    pub time: Duration,
+}
+
+/// The [`RuleStats`] of each rule that ran, by name. Displays as a table,
+/// the rules that took longest first.
+#[derive(Debug, Clone, Default)]
+pub struct Profile {
+    rules: BTreeMap<String, RuleStats>,
+}
+
+impl Profile {
+    pub fn get(&self, rule: &str) -> Option<&RuleStats> {
+        self.rules.get(rule)
+    }
+
+    /// The rules in the order of their names.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &RuleStats)> {
+        self.rules
+            .iter()
+            .map(|(rule, stats)| (rule.as_str(), stats))
+    }
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut rules: Vec<_> = self.iter().collect();
+        rules.sort_by_key(|(_, stats)| core::cmp::Reverse(stats.time));
+        let width = rules.iter().map(|(rule, _)| rule.len()).max().unwrap_or(0);
+        let width = width.max("rule".len());
+        writeln!(
+            f,
+            "{:width$}  {:>8}  {:>8}  {:>10}  time",
+            "rule", "calls", "failures", "backtracks"
+        )?;
+        for (rule, stats) in rules {
+            writeln!(
+                f,
+                "{rule:width$}  {:>8}  {:>8}  {:>10}  {:?}",
+                stats.calls, stats.failures, stats.backtracks, stats.time
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// `p` counting into the profile of the run as `name`, if it has one.
+pub(crate) fn profiled<'a, T: 'a>(name: String, p: Parser<'a, T>) -> Parser<'a, T> {
+    let shape = p.shape.clone();
+    Parser {
+        run: Ptr::new(move |input| {
+            let Some(profile) = input.profile.clone() else {
+                return (p.run)(input);
+            };
+            let start = input.pos;
+            #[cfg(feature = "std")]
+            let clock = std::time::Instant::now();
+            let (input_, result) = (p.run)(input);
+            let mut profile = profile.borrow_mut();
+            let stats = profile.rules.entry(name.clone()).or_default();
+            stats.calls += 1;
+            if let Err(e) = &result {
+                stats.failures += 1;
+                if !e.fatal && input_.pos > start {
+                    stats.backtracks += 1;
+                }
+            }
+            #[cfg(feature = "std")]
+            {
+                stats.time += clock.elapsed();
+            }
+            (input_, result)
+        }),
+        shape,
+    }
+}
+
+/// Runs `p` on `input` like [`run`](crate::run), and returns what its
+/// [named](Parser::named) rules did along with the result.
+///
+/// ```
+/// use parcoom::profile::run_profiled;
+/// use parcoom::{chars, prefix};
+///
+/// let number = chars::digits1().named("number");
+/// let call = (chars::alpha() >> prefix("(") >> number.clone() << prefix(")")).named("call");
+/// let expr = call | chars::alpha() >> number;
+/// let (parsed, profile) = run_profiled(expr, "f12".to_string());
+/// assert_eq!(parsed.as_deref(), Ok("12"));
+/// assert_eq!(profile.get("call").unwrap().failures, 1);
+/// assert_eq!(profile.get("number").unwrap().calls, 1);
+/// ```
+pub fn run_profiled<A>(p: impl Parse<A>, input: String) -> (Result<A, ParserError>, Profile) {
+    let profile = Rc::new(RefCell::new(Profile::default()));
+    let input = ParserInput {
+        profile: Some(profile.clone()),
+        ..make_input(input)
+    };
+    let result = finish(p.parse(input));
+    (result, profile.take())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chars::{alpha, digits1};
+    use crate::{prefix, recursive, run};
+    use alloc::string::ToString;
+
+    #[test]
+    fn profile_test() {
+        // lists are tried as pairs first, which fail after the first atom
+        let item = recursive(|item| {
+            let atom = digits1().named("atom").map(|_| 1);
+            let items = item.sep_by(prefix(",")).named("items");
+            let list = items.delimited_by(prefix("["), prefix("]"));
+            let pair = (prefix("[") >> atom.clone() << prefix(";") << atom.clone()).named("pair");
+            pair | list.map(|xs: Vec<usize>| xs.iter().sum()) | atom
+        });
+        let (parsed, profile) = run_profiled(item.clone(), "[1,[2,3]]".to_string());
+        assert_eq!(parsed, Ok(3));
+        let pair = profile.get("pair").unwrap();
+        assert_eq!((pair.calls, pair.failures, pair.backtracks), (2, 2, 2));
+        assert_eq!(profile.get("atom").unwrap().calls, 5);
+        assert_eq!(profile.get("items").unwrap().calls, 2);
+        assert_eq!(profile.iter().count(), 3);
+
+        let table = profile.to_string();
+        assert!(table.starts_with("rule   "));
+        assert_eq!(table.lines().count(), 4);
+        assert!(table
+            .lines()
+            .any(|line| line.starts_with("pair          2")));
+
+        // unprofiled runs count nothing
+        assert_eq!(run(item, "[1;2]".to_string()), Ok(1));
+        let (_, profile) = run_profiled(alpha(), "a".to_string());
+        assert_eq!(profile.iter().count(), 0);
+    }
+}
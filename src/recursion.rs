@@ -0,0 +1,171 @@
+//! Parsers that refer to themselves.
+
+use alloc::string::ToString;
+#[cfg(not(feature = "sync"))]
+use {alloc::rc::Weak, core::cell::OnceCell};
+#[cfg(feature = "sync")]
+use {alloc::sync::Weak, std::sync::OnceLock as OnceCell};
+
+use crate::limits::nested;
+use crate::memo::Memo;
+use crate::shape::{Shape, ShapeCell};
+use crate::{next_id, Failure, Parser, ParserInput, Ptr};
+
+/// A parser standing in for the one being defined, which is only known once
+/// the definition is done. It must not outlive the parser it stands for.
+fn forward<'a, T: 'a>(
+    cell: Weak<OnceCell<Parser<'a, T>>>,
+    shape: &Ptr<ShapeCell>,
+) -> Parser<'a, T> {
+    Parser {
+        run: Ptr::new(move |input| {
+            let cell = cell
+                .upgrade()
+                .expect("recursive parser used after its definition was dropped");
+            let p = cell
+                .get()
+                .expect("recursive parser used while being defined");
+            nested(input, |input| (p.run)(input))
+        }),
+        shape: Some(Ptr::new(Shape::Recurse(Ptr::downgrade(shape)))),
+    }
+}
+
+/// Defines a parser that refers to itself, such as a JSON value that may
+/// contain arrays of values. `f` is handed the parser being defined to build
+/// its body from.
+///
+/// The body must consume input before recursing, use [`left_rec`] for
+/// left-recursive grammars.
+pub fn recursive<'a, T: 'a>(f: impl FnOnce(Parser<'a, T>) -> Parser<'a, T>) -> Parser<'a, T> {
+    let cell = Ptr::new(OnceCell::new());
+    let shape = Ptr::new(ShapeCell::new());
+    let body = f(forward(Ptr::downgrade(&cell), &shape));
+    let _ = shape.set(body.shape.clone());
+    let _ = cell.set(body);
+    Parser {
+        run: Ptr::new(move |input| (cell.get().unwrap().run)(input)),
+        shape: Some(Ptr::new(Shape::Rule(shape))),
+    }
+}
+
+/// Defines a directly left-recursive parser such as
+/// `expr = expr "+" term | term`. `f` is handed the parser being defined to
+/// build its body from.
+///
+/// At each position the body is first run with the recursive reference
+/// failing, then again and again with it yielding the previous result, for as
+/// long as that consumes more input (Warth et al.'s seed growing). Indirect
+/// left recursion through other rules is not supported.
+pub fn left_rec<'a, T: Clone + 'static>(
+    f: impl FnOnce(Parser<'a, T>) -> Parser<'a, T>,
+) -> Parser<'a, T> {
+    let cell = Ptr::new(OnceCell::new());
+    let shape = Ptr::new(ShapeCell::new());
+    let body = f(forward(Ptr::downgrade(&cell), &shape));
+    let _ = shape.set(body.shape.clone());
+    let id = next_id();
+    let p = Parser {
+        run: Ptr::new(move |input| {
+            let key = (id, input.pos);
+            if let Some(output) = Memo::lookup(&input.memo, key, &input) {
+                return output;
+            }
+            let before = input.diagnostics.len();
+            let memo = input.memo.clone();
+
+            let seed = (
+                input.clone(),
+                Err(Failure::from("left recursion".to_string())),
+            );
+            Memo::store::<T>(&memo, key, before, &seed);
+            let mut best: Option<(ParserInput, _)> = None;
+            loop {
+                let output = (body.run)(input.clone());
+                let grew = match (&output, &best) {
+                    ((_, Err(_)), _) => false,
+                    (_, None) => true,
+                    ((input_, Ok(_)), Some((best_input, _))) => input_.pos > best_input.pos,
+                };
+                if !grew {
+                    let output = best.unwrap_or(output);
+                    Memo::store(&memo, key, before, &output);
+                    return output;
+                }
+                Memo::store(&memo, key, before, &output);
+                best = Some(output);
+            }
+        }),
+        shape: None,
+    };
+    let _ = cell.set(p);
+    // only the parser handed out keeps the definition alive, the references
+    // inside of it are weak so that they don't form a cycle
+    Parser {
+        run: Ptr::new(move |input| (cell.get().unwrap().run)(input)),
+        shape: Some(Ptr::new(Shape::Rule(shape))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{chars, prefix, run};
+
+    fn number<'a>() -> Parser<'a, i64> {
+        chars::digits1().map(|x| x.parse().unwrap())
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum Value {
+        Number(i64),
+        List(Vec<Value>),
+    }
+
+    #[test]
+    fn recursive_test() {
+        let value = recursive(|value| {
+            let list = value
+                .sep_by(prefix(","))
+                .delimited_by(prefix("["), prefix("]"));
+            number().map(Value::Number) | list.map(Value::List)
+        });
+
+        let parsed = run(value.clone(), "[1,[2,[]],3]".to_string());
+        assert_eq!(
+            parsed,
+            Ok(Value::List(vec![
+                Value::Number(1),
+                Value::List(vec![Value::Number(2), Value::List(vec![])]),
+                Value::Number(3),
+            ]))
+        );
+        assert!(run(value, "[1,[2]".to_string()).is_err());
+    }
+
+    #[test]
+    fn left_rec_test() {
+        let expr = left_rec(|expr| {
+            let add = (expr.clone() << prefix("+")) + number();
+            let sub = (expr << prefix("-")) + number();
+            add.map(|(a, b)| a + b) | sub.map(|(a, b)| a - b) | number()
+        });
+
+        assert_eq!(run(expr.clone(), "1+2+3".to_string()), Ok(6));
+        // left associative
+        assert_eq!(run(expr.clone(), "8-3-2".to_string()), Ok(3));
+        assert_eq!(run(expr.clone(), "8".to_string()), Ok(8));
+        assert!(run(expr, "+1".to_string()).is_err());
+    }
+
+    #[test]
+    fn left_rec_nested_test() {
+        // the recursive reference can also appear away from the left edge
+        let expr = left_rec(|expr| {
+            let atom = number() | (prefix("(") >> expr.clone() << prefix(")"));
+            ((expr << prefix("-")) + atom.clone()).map(|(a, b)| a - b) | atom
+        });
+
+        assert_eq!(run(expr, "10-(4-1)-2".to_string()), Ok(5));
+    }
+}
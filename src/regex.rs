@@ -0,0 +1,654 @@
+//! Parsers matching a regular expression at the current position.
+//!
+//! The engine is a small Pike VM supporting literals, `.`, classes like
+//! `[a-z_]`, `[^"]`, `\d`, `\w` and `\s`, groups `(...)` and `(?:...)`,
+//! alternatives `|`, the quantifiers `*`, `+`, `?`, `{n}`, `{n,}` and `{n,m}`
+//! (made lazy with a trailing `?`), `^` for the start and `$` for the end of
+//! input. Matches are anchored at the current position, and take time linear
+//! in the length of the text, whatever the pattern.
+
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::limits::counted;
+use crate::{input_sub, Failure, Needed, Parser, Ptr};
+
+#[derive(Debug)]
+enum Node {
+    Char(char),
+    Any,
+    Class {
+        ranges: Vec<(char, char)>,
+        negated: bool,
+    },
+    Start,
+    End,
+    Group(Box<Node>, Option<usize>),
+    Concat(Vec<Node>),
+    Alt(Vec<Node>),
+    Repeat {
+        node: Box<Node>,
+        min: usize,
+        max: Option<usize>,
+        greedy: bool,
+    },
+}
+
+/// Capture group spans, as byte ranges of the text.
+type Captures = Vec<Option<(usize, usize)>>;
+
+struct Compiler<'p> {
+    chars: core::iter::Peekable<core::str::Chars<'p>>,
+    groups: usize,
+}
+
+const DIGIT: &[(char, char)] = &[('0', '9')];
+const WORD: &[(char, char)] = &[('0', '9'), ('A', 'Z'), ('_', '_'), ('a', 'z')];
+const SPACE: &[(char, char)] = &[('\t', '\r'), (' ', ' ')];
+
+impl Compiler<'_> {
+    fn alt(&mut self) -> Result<Node, String> {
+        let mut alts = vec![self.concat()?];
+        while self.chars.next_if_eq(&'|').is_some() {
+            alts.push(self.concat()?);
+        }
+        Ok(if alts.len() == 1 {
+            alts.pop().unwrap()
+        } else {
+            Node::Alt(alts)
+        })
+    }
+
+    fn concat(&mut self) -> Result<Node, String> {
+        let mut nodes = Vec::new();
+        while let Some(&c) = self.chars.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+            let atom = self.atom()?;
+            nodes.push(self.quantified(atom)?);
+        }
+        Ok(Node::Concat(nodes))
+    }
+
+    fn number(&mut self) -> Option<usize> {
+        let mut n = None;
+        while let Some(d) = self.chars.peek().and_then(|c| c.to_digit(10)) {
+            self.chars.next();
+            n = Some(n.unwrap_or(0) * 10 + d as usize);
+        }
+        n
+    }
+
+    fn quantified(&mut self, node: Node) -> Result<Node, String> {
+        let (min, max) = match self.chars.peek() {
+            Some('*') => (0, None),
+            Some('+') => (1, None),
+            Some('?') => (0, Some(1)),
+            Some('{') => {
+                self.chars.next();
+                let min = self.number().ok_or("expected a number after {")?;
+                let max = if self.chars.next_if_eq(&',').is_some() {
+                    self.number()
+                } else {
+                    Some(min)
+                };
+                if self.chars.peek() != Some(&'}') {
+                    return Err("expected } closing a repetition".to_string());
+                }
+                if max.is_some_and(|max| max < min) {
+                    return Err(format!("invalid repetition {{{min},{}}}", max.unwrap()));
+                }
+                (min, max)
+            }
+            _ => return Ok(node),
+        };
+        self.chars.next();
+        let greedy = self.chars.next_if_eq(&'?').is_none();
+        let node = Box::new(node);
+        Ok(Node::Repeat {
+            node,
+            min,
+            max,
+            greedy,
+        })
+    }
+
+    fn atom(&mut self) -> Result<Node, String> {
+        match self.chars.next().unwrap() {
+            '(' => {
+                let index = if self.chars.next_if_eq(&'?').is_some() {
+                    if self.chars.next() != Some(':') {
+                        return Err("expected : after (?".to_string());
+                    }
+                    None
+                } else {
+                    self.groups += 1;
+                    Some(self.groups)
+                };
+                let node = self.alt()?;
+                if self.chars.next() != Some(')') {
+                    return Err("unclosed group".to_string());
+                }
+                Ok(Node::Group(Box::new(node), index))
+            }
+            '[' => self.class(),
+            '.' => Ok(Node::Any),
+            '^' => Ok(Node::Start),
+            '$' => Ok(Node::End),
+            '\\' => {
+                let c = self.chars.next().ok_or("trailing backslash")?;
+                Ok(match escape_class(c) {
+                    Some((ranges, negated)) => Node::Class {
+                        ranges: ranges.to_vec(),
+                        negated,
+                    },
+                    None => Node::Char(escape_char(c)),
+                })
+            }
+            c @ ('*' | '+' | '?' | '{') => Err(format!("nothing to repeat before {c}")),
+            c => Ok(Node::Char(c)),
+        }
+    }
+
+    fn class(&mut self) -> Result<Node, String> {
+        let negated = self.chars.next_if_eq(&'^').is_some();
+        let mut ranges = Vec::new();
+        let mut first = true;
+        loop {
+            let c = match self.chars.next() {
+                None => return Err("unclosed class".to_string()),
+                Some(']') if !first => break,
+                Some('\\') => {
+                    let c = self.chars.next().ok_or("trailing backslash")?;
+                    match escape_class(c) {
+                        Some((class, false)) => {
+                            ranges.extend_from_slice(class);
+                            first = false;
+                            continue;
+                        }
+                        Some((_, true)) => {
+                            return Err(format!("\\{c} is not supported in a class"))
+                        }
+                        None => escape_char(c),
+                    }
+                }
+                Some(c) => c,
+            };
+            first = false;
+            let is_range = self.chars.peek() == Some(&'-') && {
+                let mut ahead = self.chars.clone();
+                ahead.next();
+                ahead.peek().is_some_and(|&c| c != ']')
+            };
+            if is_range {
+                self.chars.next();
+                let end = match self.chars.next().unwrap() {
+                    '\\' => escape_char(self.chars.next().ok_or("trailing backslash")?),
+                    end => end,
+                };
+                if end < c {
+                    return Err(format!("invalid range {c}-{end}"));
+                }
+                ranges.push((c, end));
+            } else {
+                ranges.push((c, c));
+            }
+        }
+        Ok(Node::Class { ranges, negated })
+    }
+}
+
+fn escape_class(c: char) -> Option<(&'static [(char, char)], bool)> {
+    match c {
+        'd' => Some((DIGIT, false)),
+        'D' => Some((DIGIT, true)),
+        'w' => Some((WORD, false)),
+        'W' => Some((WORD, true)),
+        's' => Some((SPACE, false)),
+        'S' => Some((SPACE, true)),
+        _ => None,
+    }
+}
+
+fn escape_char(c: char) -> char {
+    match c {
+        'n' => '\n',
+        'r' => '\r',
+        't' => '\t',
+        c => c,
+    }
+}
+
+/// An instruction of a compiled pattern.
+#[derive(Debug)]
+enum Inst {
+    Char(char),
+    Any,
+    Class {
+        ranges: Vec<(char, char)>,
+        negated: bool,
+    },
+    Start,
+    End,
+    /// Records the position in a capture slot.
+    Save(usize),
+    /// Goes on at both, preferring the first.
+    Split(usize, usize),
+    Jump(usize),
+    Match,
+}
+
+impl Inst {
+    /// Whether the instruction consumes `c`.
+    fn accepts(&self, c: char) -> bool {
+        match self {
+            Inst::Char(x) => c == *x,
+            Inst::Any => c != '\n',
+            Inst::Class { ranges, negated } => {
+                ranges.iter().any(|&(lo, hi)| lo <= c && c <= hi) != *negated
+            }
+            _ => false,
+        }
+    }
+}
+
+fn emit(node: &Node, prog: &mut Vec<Inst>) {
+    match node {
+        Node::Char(c) => prog.push(Inst::Char(*c)),
+        Node::Any => prog.push(Inst::Any),
+        Node::Class { ranges, negated } => prog.push(Inst::Class {
+            ranges: ranges.clone(),
+            negated: *negated,
+        }),
+        Node::Start => prog.push(Inst::Start),
+        Node::End => prog.push(Inst::End),
+        Node::Group(node, None) => emit(node, prog),
+        Node::Group(node, Some(i)) => {
+            prog.push(Inst::Save(2 * i));
+            emit(node, prog);
+            prog.push(Inst::Save(2 * i + 1));
+        }
+        Node::Concat(nodes) => nodes.iter().for_each(|node| emit(node, prog)),
+        Node::Alt(alts) => {
+            let mut jumps = Vec::new();
+            for (i, alt) in alts.iter().enumerate() {
+                if i + 1 == alts.len() {
+                    emit(alt, prog);
+                    break;
+                }
+                let split = prog.len();
+                prog.push(Inst::Split(split + 1, 0));
+                emit(alt, prog);
+                jumps.push(prog.len());
+                prog.push(Inst::Jump(0));
+                prog[split] = Inst::Split(split + 1, prog.len());
+            }
+            for jump in jumps {
+                prog[jump] = Inst::Jump(prog.len());
+            }
+        }
+        Node::Repeat {
+            node,
+            min,
+            max,
+            greedy,
+        } => {
+            let split = |body, exit| match greedy {
+                true => Inst::Split(body, exit),
+                false => Inst::Split(exit, body),
+            };
+            for _ in 0..*min {
+                emit(node, prog);
+            }
+            match max {
+                None => {
+                    let start = prog.len();
+                    prog.push(Inst::Jump(0));
+                    emit(node, prog);
+                    prog.push(Inst::Jump(start));
+                    prog[start] = split(start + 1, prog.len());
+                }
+                // Failing to repeat once more skips the rest.
+                Some(max) => {
+                    let mut splits = Vec::new();
+                    for _ in *min..*max {
+                        splits.push(prog.len());
+                        prog.push(Inst::Jump(0));
+                        emit(node, prog);
+                    }
+                    let exit = prog.len();
+                    for at in splits {
+                        prog[at] = split(at + 1, exit);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Capture slots, the start and end of each group.
+type Slots = Vec<Option<usize>>;
+
+/// A compiled pattern.
+struct Regex {
+    prog: Vec<Inst>,
+    groups: usize,
+}
+
+impl Regex {
+    fn new(pattern: &str) -> Result<Regex, String> {
+        let mut compiler = Compiler {
+            chars: pattern.chars().peekable(),
+            groups: 0,
+        };
+        let node = compiler.alt()?;
+        if compiler.chars.next().is_some() {
+            return Err("unmatched )".to_string());
+        }
+        let mut prog = Vec::new();
+        emit(&node, &mut prog);
+        prog.push(Inst::Match);
+        let groups = compiler.groups;
+        Ok(Regex { prog, groups })
+    }
+
+    /// The captures of the first match at the start of `text`, the whole
+    /// match being group 0, trying the alternatives in order. `at_start` is
+    /// whether `text` starts the input, for `^`, and `partial` whether more
+    /// of it may follow, which the match may then depend on.
+    ///
+    /// All the ways of matching are followed at once, in order of
+    /// preference, one char of the text at a time.
+    fn captures(&self, text: &str, at_start: bool, partial: bool) -> Search {
+        let mut threads = Threads::new(self.prog.len());
+        let mut next = Threads::new(self.prog.len());
+        let mut slots = vec![None; 2 * self.groups + 2];
+        slots[0] = Some(0);
+        let at = (text, at_start, partial);
+        self.add(&mut threads, 0, slots, 0, at);
+        let mut found = None;
+        let mut more = false;
+        let mut chars = text.char_indices();
+        loop {
+            let c = chars.next();
+            let end = c.map_or(text.len(), |(i, c)| i + c.len_utf8());
+            for (pc, mut slots) in threads.list.drain(..) {
+                match (&self.prog[pc], c) {
+                    (Inst::Match, _) => {
+                        slots[1] = Some(c.map_or(text.len(), |(i, _)| i));
+                        found = Some(slots);
+                        // The threads left are less preferred.
+                        break;
+                    }
+                    (inst, Some((_, c))) if inst.accepts(c) => {
+                        self.add(&mut next, pc + 1, slots, end, at);
+                    }
+                    // preferred to any match, and waiting for the text
+                    (_, None) => more = partial,
+                    _ => {}
+                }
+            }
+            if next.list.is_empty() || c.is_none() {
+                break;
+            }
+            core::mem::swap(&mut threads, &mut next);
+            next.clear();
+        }
+        match found {
+            _ if more => Search::More,
+            Some(slots) => {
+                let pairs = slots.chunks(2).map(|pair| pair[0].zip(pair[1]));
+                Search::Found(pairs.collect())
+            }
+            None => Search::Failed,
+        }
+    }
+
+    /// Adds the thread at `pc`, following the instructions that consume
+    /// nothing. Each instruction gets at most one thread per position, the
+    /// most preferred one. A `$` at the end of partial text waits for the
+    /// rest like the instructions consuming chars.
+    fn add(
+        &self,
+        threads: &mut Threads,
+        pc: usize,
+        slots: Slots,
+        pos: usize,
+        (text, at_start, partial): (&str, bool, bool),
+    ) {
+        let mut stack = vec![(pc, slots)];
+        while let Some((pc, mut slots)) = stack.pop() {
+            if threads.seen[pc] {
+                continue;
+            }
+            threads.seen[pc] = true;
+            match self.prog[pc] {
+                Inst::Jump(to) => stack.push((to, slots)),
+                Inst::Split(first, second) => {
+                    stack.push((second, slots.clone()));
+                    stack.push((first, slots));
+                }
+                Inst::Save(i) => {
+                    slots[i] = Some(pos);
+                    stack.push((pc + 1, slots));
+                }
+                Inst::Start if pos == 0 && at_start => stack.push((pc + 1, slots)),
+                Inst::End if pos == text.len() && !partial => stack.push((pc + 1, slots)),
+                Inst::End if pos == text.len() => threads.list.push((pc, slots)),
+                Inst::Start | Inst::End => {}
+                _ => threads.list.push((pc, slots)),
+            }
+        }
+    }
+}
+
+/// How matching a text went.
+enum Search {
+    Found(Captures),
+    Failed,
+    /// More text might still match, or match more.
+    More,
+}
+
+/// The threads at a position of the text, in order of preference.
+struct Threads {
+    list: Vec<(usize, Slots)>,
+    seen: Vec<bool>,
+}
+
+impl Threads {
+    fn new(len: usize) -> Threads {
+        Threads {
+            list: Vec::new(),
+            seen: vec![false; len],
+        }
+    }
+
+    fn clear(&mut self) {
+        self.list.clear();
+        self.seen.iter_mut().for_each(|seen| *seen = false);
+    }
+}
+
+fn compile(pattern: &str) -> Regex {
+    Regex::new(pattern).unwrap_or_else(|e| panic!("invalid regex /{pattern}/: {e}"))
+}
+
+fn captures_parser<'a, A>(
+    pattern: &str,
+    f: impl Fn(&str, Captures) -> A + crate::MaybeSync + 'a,
+) -> Parser<'a, A> {
+    let regex = compile(pattern);
+    let expected = format!("/{pattern}/");
+    Parser {
+        run: Ptr::new(move |input| {
+            match regex.captures(input.text(), input.pos == 0, input.partial) {
+                Search::Found(caps) => {
+                    let end = caps[0].unwrap().1;
+                    let x = f(input.text(), caps);
+                    (input_sub(end, &input), Ok(x))
+                }
+                Search::More => (input, Err(Failure::incomplete(Needed::Unknown))),
+                Search::Failed => {
+                    let desc = format!("expected a match of {expected}");
+                    (input, Err(Failure::expecting(expected.clone(), desc)))
+                }
+            }
+        }),
+        shape: None,
+    }
+}
+
+/// The text matching `pattern`, e.g. `regex(r"[0-9]{4}-[0-9]{2}")`.
+///
+/// Panics if `pattern` is not a valid regular expression.
+pub fn regex<'a>(pattern: &str) -> Parser<'a, String> {
+    counted(captures_parser(pattern, |text, caps| {
+        let (start, end) = caps[0].unwrap();
+        text[start..end].to_string()
+    }))
+}
+
+/// The capture groups of the text matching `pattern`, the whole match first.
+/// Groups that did not take part in the match are `None`.
+///
+/// Panics if `pattern` is not a valid regular expression.
+pub fn regex_captures<'a>(pattern: &str) -> Parser<'a, Vec<Option<String>>> {
+    captures_parser(pattern, |text, caps| {
+        let group = |span: Option<(usize, usize)>| span.map(|(s, e)| text[s..e].to_string());
+        caps.into_iter().map(group).collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{prefix, run, run_streaming, Location, ParserError, StreamResult};
+
+    #[test]
+    fn regex_test() {
+        let date = regex(r"[0-9]{4}-[0-9]{2}");
+        assert_eq!(
+            run(date, "2024-05-17".to_string()),
+            Ok("2024-05".to_string())
+        );
+
+        let parser = regex(r"\w+") << prefix("=");
+        assert_eq!(run(parser, "key_1=".to_string()), Ok("key_1".to_string()));
+
+        let parser = regex(r#""([^"\\]|\\.)*""#);
+        let parsed = run(parser, r#""a\"b" rest"#.to_string());
+        assert_eq!(parsed, Ok(r#""a\"b""#.to_string()));
+
+        let parser = regex("a+?b|a+");
+        assert_eq!(run(parser, "aaa".to_string()), Ok("aaa".to_string()));
+        assert_eq!(
+            run(regex("(?:ab)*$"), "abab".to_string()),
+            Ok("abab".to_string())
+        );
+        assert!(run(regex("(?:ab)*$"), "aba".to_string()).is_err());
+    }
+
+    #[test]
+    fn long_input_test() {
+        let text = "a".repeat(200_000);
+        let parsed = run(regex("a*"), text.clone());
+        assert_eq!(parsed.map(|m| m.len()), Ok(200_000));
+        let parsed = run(regex("(a|b)*?$"), text.clone());
+        assert_eq!(parsed.map(|m| m.len()), Ok(200_000));
+
+        // no exponential backtracking
+        let text = "a".repeat(5_000) + "c";
+        assert!(run(regex("(a*)*b"), text.clone()).is_err());
+        assert!(run(regex("(a|aa)+$"), text).is_err());
+    }
+
+    #[test]
+    fn regex_streaming_test() {
+        let stream = |pattern: &str, text: &str| run_streaming(regex(pattern), text.to_string());
+        assert_eq!(
+            stream("[0-9]+", "12"),
+            StreamResult::Incomplete(Needed::Unknown)
+        );
+        assert_eq!(
+            stream("[0-9]+", "12;"),
+            StreamResult::Ok("12".to_string(), 2)
+        );
+        assert_eq!(
+            stream("ab|a", "a"),
+            StreamResult::Incomplete(Needed::Unknown)
+        );
+        // the preferred alternative is done, more text changes nothing
+        assert_eq!(stream("a|ab", "a"), StreamResult::Ok("a".to_string(), 1));
+        assert_eq!(stream("a$", "a"), StreamResult::Incomplete(Needed::Unknown));
+        assert!(matches!(stream("a", "b"), StreamResult::Err(_)));
+    }
+
+    #[test]
+    fn anchor_test() {
+        assert_eq!(run(regex("^a+"), "aab".to_string()), Ok("aa".to_string()));
+        assert!(run(prefix("b") >> regex("^a"), "ba".to_string()).is_err());
+        assert!(run(regex("a^"), "a".to_string()).is_err());
+        assert_eq!(run(regex("(a|^b)+"), "ab".to_string()), Ok("a".to_string()));
+    }
+
+    #[test]
+    fn captures_test() {
+        let parser = regex_captures(r"(\d+)\.(\d+)(-(\w+))?");
+        let parsed = run(parser.clone(), "1.20-rc".to_string());
+        let some = |s: &str| Some(s.to_string());
+        assert_eq!(
+            parsed,
+            Ok(vec![
+                some("1.20-rc"),
+                some("1"),
+                some("20"),
+                some("-rc"),
+                some("rc")
+            ])
+        );
+        let parsed = run(parser, "1.2".to_string());
+        assert_eq!(
+            parsed,
+            Ok(vec![some("1.2"), some("1"), some("2"), None, None])
+        );
+    }
+
+    #[test]
+    fn regex_error_test() {
+        let parsed = run(prefix("v") >> regex(r"\d"), "vx".to_string());
+        assert_eq!(
+            parsed,
+            Err(ParserError {
+                desc: "expected a match of /\\d/".to_string(),
+                location: Location {
+                    offset: 1,
+                    char_offset: 1,
+                    line: 1,
+                    column: 2,
+                },
+                context: vec![],
+                expected: vec!["/\\d/".to_string()],
+                cause: None,
+                at_end: false,
+            })
+        );
+    }
+
+    #[test]
+    fn invalid_repetition_test() {
+        let e = Regex::new("a{3,2}").err();
+        assert_eq!(e, Some("invalid repetition {3,2}".to_string()));
+        assert!(Regex::new("a{2,2}").is_ok());
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid regex /[a-/: unclosed class")]
+    fn invalid_regex_test() {
+        regex("[a-");
+    }
+}
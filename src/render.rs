@@ -0,0 +1,92 @@
+//! Human-friendly rendering of [`ParserError`]s against their source.
+
+use crate::{Location, ParserError};
+use alloc::format;
+use alloc::string::{String, ToString};
+
+impl ParserError {
+    /// Renders the error with the offending line of `source` and a caret
+    /// underline below the text the error points at:
+    ///
+    /// ```text
+    /// error: expected =
+    ///  --> 2:5
+    ///   |
+    /// 2 | key ? value
+    ///   |     ^
+    ///   = in entry > value
+    /// ```
+    pub fn render(&self, source: &str) -> String {
+        self.render_as("error", None, source)
+    }
+
+    /// Like [`render`](ParserError::render), but with the location prefixed
+    /// by `name`, the name of the file `source` was read from, as in
+    /// ` --> config.ini:2:5`.
+    pub fn render_in(&self, name: &str, source: &str) -> String {
+        self.render_as("error", Some(name), source)
+    }
+
+    /// The rendering headed by `severity` instead of `error`.
+    pub(crate) fn render_as(&self, severity: &str, name: Option<&str>, source: &str) -> String {
+        let Location { line, column, .. } = self.location;
+        let text = source.lines().nth(line - 1).unwrap_or("");
+        let number = line.to_string();
+        let gutter = " ".repeat(number.len());
+
+        // keep tabs so the caret lines up with the source above it
+        let indent: String = text
+            .chars()
+            .take(column - 1)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let width = text
+            .chars()
+            .skip(column - 1)
+            .take_while(|c| !c.is_whitespace())
+            .count()
+            .max(1);
+
+        let mut out = format!("{severity}: {}\n", self.desc);
+        match name {
+            Some(name) => out += &format!("{gutter}--> {name}:{line}:{column}\n"),
+            None => out += &format!("{gutter}--> {line}:{column}\n"),
+        }
+        out += &format!("{gutter} |\n");
+        out += &format!("{number} | {text}\n");
+        out += &format!("{gutter} | {indent}{}\n", "^".repeat(width));
+        if !self.context.is_empty() {
+            out += &format!("{gutter} = in {}\n", self.context.join(" > "));
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chars::{CharClass, ALPHABETIC};
+    use crate::{between, many, none_of, parse_while, prefix, run};
+
+    #[test]
+    fn render_test() {
+        let source = "a = 1\nkey ? value";
+        let line = parse_while(CharClass::except('\n')) << prefix("\n");
+        let parser = line >> parse_while(ALPHABETIC) >> prefix(" ") >> prefix("=");
+
+        let error = run(parser, source.to_string()).unwrap_err();
+        assert_eq!(
+            error.render(source),
+            "error: expected =\n --> 2:5\n  |\n2 | key ? value\n  |     ^\n"
+        );
+
+        let source = "x =\t\"abc";
+        let string = between(prefix("\""), many(none_of("\"")), prefix("\"")).context("string");
+        let parser = prefix("x =\t") >> string.context("value");
+
+        let error = run(parser, source.to_string()).unwrap_err();
+        assert_eq!(
+            error.render(source),
+            "error: missing closing delimiter: expected \"\n --> 1:9\n  |\n1 | x =\t\"abc\n  |    \t    ^\n  = in value > string\n"
+        );
+    }
+}
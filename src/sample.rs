@@ -0,0 +1,203 @@
+//! Generating inputs from parsers, for fuzzing and property tests.
+
+use alloc::string::String;
+
+use crate::shape::Shape;
+use crate::Parser;
+
+/// A source of random numbers for [`Parser::sample`]. Closures returning
+/// `u64`s are ones, so are the generators of most random number crates with
+/// a little wrapping.
+pub trait SampleRng {
+    fn next_u64(&mut self) -> u64;
+}
+
+impl<F: FnMut() -> u64> SampleRng for F {
+    fn next_u64(&mut self) -> u64 {
+        self()
+    }
+}
+
+/// A small, fast and seedable [`SampleRng`], not fit for cryptography.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SplitMix64(pub u64);
+
+impl SampleRng for SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+/// How many more items than their minimum unbounded repetitions generate at
+/// most.
+const EXTRA_ITEMS: usize = 3;
+
+impl<'a, T: 'a> Parser<'a, T> {
+    /// A random input of the parser's structure, recursive parsers nested no
+    /// more than `max_depth` deep. Once that deep repetitions only generate
+    /// as many items as they must and alternatives that would recurse further
+    /// are left out.
+    ///
+    /// Only parsers built from literals ([`prefix`](crate::prefix),
+    /// [`one_of`](crate::one_of), ...) with sequences, alternatives,
+    /// repetitions and [`recursive`](crate::recursive) have a structure to
+    /// generate from, `None` is returned for others and when nothing fits
+    /// within `max_depth`. Empty parsers such as [`eof`](crate::eof)
+    /// generate nothing, and mapping or labelling keeps the structure.
+    ///
+    /// Samples follow the structure, not the semantics of ordered choice, so
+    /// a sample taking a later alternative may still not parse when an
+    /// earlier one matches a prefix of it.
+    ///
+    /// ```
+    /// use parcoom::{one_of, prefix, recursive, run, SplitMix64};
+    ///
+    /// let list = recursive(|list| {
+    ///     let item = one_of("xyz").ignored() | list;
+    ///     item.sep_by(prefix(",")).delimited_by(prefix("["), prefix("]")).ignored()
+    /// });
+    /// let mut rng = SplitMix64(7);
+    /// let text = list.sample(&mut rng, 4).unwrap();
+    /// assert!(text.starts_with('['));
+    /// assert_eq!(run(list, text), Ok(()));
+    /// ```
+    pub fn sample(&self, rng: &mut impl SampleRng, max_depth: usize) -> Option<String> {
+        let mut out = String::new();
+        generate(self.shape.as_ref()?, rng, max_depth, &mut out).then_some(out)
+    }
+}
+
+/// A random number below `n`, which must not be zero.
+fn below(rng: &mut impl SampleRng, n: usize) -> usize {
+    (rng.next_u64() % n as u64) as usize
+}
+
+/// Appends a sample of `shape` to `out`, returning whether there is one.
+/// `out` may have been appended to when there is not.
+fn generate(shape: &Shape, rng: &mut impl SampleRng, depth: usize, out: &mut String) -> bool {
+    match shape {
+        Shape::Literal(text) | Shape::Caseless(text) => {
+            out.push_str(text);
+            true
+        }
+        Shape::OneOf(chars) => match chars.chars().count() {
+            0 => false,
+            n => {
+                out.extend(chars.chars().nth(below(rng, n)));
+                true
+            }
+        },
+        Shape::Seq(items) => items.iter().all(|item| generate(item, rng, depth, out)),
+        Shape::Alt(alternatives) => {
+            if alternatives.is_empty() {
+                return false;
+            }
+            let start = below(rng, alternatives.len());
+            let len = out.len();
+            for i in 0..alternatives.len() {
+                let alternative = &alternatives[(start + i) % alternatives.len()];
+                if generate(alternative, rng, depth, out) {
+                    return true;
+                }
+                out.truncate(len);
+            }
+            false
+        }
+        Shape::Repeat { item, min, max } => {
+            let extra = max.map_or(EXTRA_ITEMS, |max| max.saturating_sub(*min));
+            let times = match depth {
+                0 => *min,
+                _ => min + below(rng, extra.min(EXTRA_ITEMS) + 1),
+            };
+            (0..times).all(|_| generate(item, rng, depth, out))
+        }
+        Shape::Named(_, shape) | Shape::Reporting(shape) => generate(shape, rng, depth, out),
+        // What follows is left to the next parser.
+        Shape::Ahead { .. } => true,
+        Shape::Described(_) | Shape::Opaque => false,
+        Shape::Rule(cell) => match (depth, cell.get()) {
+            (0, _) | (_, None | Some(None)) => false,
+            (_, Some(Some(shape))) => generate(shape, rng, depth - 1, out),
+        },
+        Shape::Recurse(cell) => match (depth, cell.upgrade()) {
+            (0, _) | (_, None) => false,
+            (_, Some(cell)) => match cell.get() {
+                Some(Some(shape)) => generate(shape, rng, depth - 1, out),
+                _ => false,
+            },
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{chars, choice, eof, keyword, one_of, prefix, recursive, run};
+    use alloc::vec;
+
+    #[test]
+    fn sample_test() {
+        let mut rng = SplitMix64(1);
+        let greeting = (prefix("hello") | prefix("hi")) >> prefix(" ") >> one_of("ab").many1();
+        let parser = greeting.then_ignore(eof());
+        for _ in 0..50 {
+            let text = parser.sample(&mut rng, 0).unwrap();
+            assert!(run(parser.clone(), text.clone()).is_ok(), "{text:?}");
+        }
+
+        let digits = prefix("7").repeated(2..=4).label("digits");
+        for _ in 0..20 {
+            let text = digits.sample(&mut rng, 3).unwrap();
+            assert!((2..=4).contains(&text.len()), "{text:?}");
+        }
+        let words = choice(vec![keyword("let"), keyword("in")]).sep_by1(prefix(" "));
+        assert!(run(words.clone(), words.sample(&mut rng, 2).unwrap()).is_ok());
+
+        let mut n = 0;
+        let mut counter = || {
+            n += 1;
+            n
+        };
+        assert_eq!(one_of("abc").sample(&mut counter, 0), Some("b".to_string()));
+
+        assert_eq!(chars::digits1().sample(&mut rng, 3), None);
+        assert_eq!((prefix("a") >> chars::digits1()).sample(&mut rng, 3), None);
+        assert_eq!(eof().sample(&mut rng, 3), Some(String::new()));
+    }
+
+    #[test]
+    fn sample_recursive_test() {
+        let mut rng = SplitMix64(42);
+        let value = recursive(|value| {
+            let list = value.many().delimited_by(prefix("("), prefix(")"));
+            list.ignored() | prefix("x").ignored()
+        });
+        let depth = |text: &str| {
+            text.chars()
+                .scan(0i32, |open, c| {
+                    *open += match c {
+                        '(' => 1,
+                        ')' => -1,
+                        _ => 0,
+                    };
+                    Some(*open)
+                })
+                .max()
+                .unwrap_or(0)
+        };
+        for _ in 0..50 {
+            let text = value.sample(&mut rng, 5).unwrap();
+            assert!(depth(&text) <= 5, "{text:?}");
+            assert_eq!(run(value.clone(), text), Ok(()));
+        }
+
+        // nothing fits without recursing
+        let nested = recursive(|nested| (prefix("(") >> nested << prefix(")")).ignored());
+        assert_eq!(nested.sample(&mut rng, 10), None);
+        assert_eq!(value.sample(&mut rng, 0), None);
+    }
+}
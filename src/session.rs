@@ -0,0 +1,83 @@
+//! Parsing one buffer piece by piece, see [`ParseSession`].
+
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+use crate::{finish, make_input, Checkpoint, Parse, ParserError, ParserInput};
+
+/// A buffer parsed piece by piece: each [`ParseSession::parse`] goes on
+/// where the last successful one stopped.
+#[derive(Debug, Clone)]
+pub struct ParseSession {
+    input: ParserInput,
+}
+
+impl ParseSession {
+    pub fn new(input: String) -> Self {
+        ParseSession {
+            input: make_input(input),
+        }
+    }
+
+    /// Runs `p` from where the session is. On success the session moves past
+    /// what `p` consumed, on failure it stays where it was.
+    pub fn parse<A>(&mut self, p: impl Parse<A>) -> Result<A, ParserError> {
+        // Each parse starts afresh, errors and cached results are not shared.
+        let input = ParserInput {
+            diagnostics: Arc::new(Vec::new()),
+            memo: Rc::default(),
+            ..self.input.clone()
+        };
+        let (input, result) = p.parse(input).into();
+        let after = input.clone();
+        let result = finish((input, result));
+        if result.is_ok() {
+            self.input = after;
+        }
+        result
+    }
+
+    /// How far into the buffer the session is, in bytes.
+    pub fn offset(&self) -> usize {
+        self.input.pos
+    }
+
+    /// The part of the buffer not parsed yet.
+    pub fn remaining(&self) -> &str {
+        self.input.text()
+    }
+
+    pub fn checkpoint(&self) -> Checkpoint {
+        self.input.checkpoint()
+    }
+
+    /// Goes back to `checkpoint`, which must come from this session.
+    pub fn rewind(&mut self, checkpoint: Checkpoint) {
+        self.input.rewind(checkpoint);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chars::{digits1, rest_of_line};
+    use crate::prefix;
+
+    #[test]
+    fn session_test() {
+        let mut session = ParseSession::new("len: 12\nbody".to_string());
+        let header = prefix("len: ") >> digits1() << prefix("\n");
+        assert_eq!(session.parse(&header).as_deref(), Ok("12"));
+        assert_eq!((session.offset(), session.remaining()), (8, "body"));
+
+        let checkpoint = session.checkpoint();
+        assert!(session.parse(&header).is_err());
+        assert_eq!(session.offset(), 8);
+        assert_eq!(session.parse(rest_of_line()).as_deref(), Ok("body"));
+        assert_eq!(session.remaining(), "");
+        session.rewind(checkpoint);
+        assert_eq!(session.remaining(), "body");
+    }
+}
@@ -0,0 +1,170 @@
+//! What parsers are made of, as far as the combinators they were built with
+//! tell, for generating input from them.
+
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+#[cfg(not(feature = "sync"))]
+use {alloc::rc::Weak, core::cell::OnceCell};
+#[cfg(feature = "sync")]
+use {alloc::sync::Weak, std::sync::OnceLock as OnceCell};
+
+use crate::{Parser, Ptr};
+
+/// The shape of a recursive parser, set once its definition is done.
+pub(crate) type ShapeCell = OnceCell<Option<Ptr<Shape>>>;
+
+/// The structure of a parser, as far as it is known. Parsers built
+/// otherwise, from closures or predicates, have none and are opaque.
+#[derive(Debug)]
+pub(crate) enum Shape {
+    /// Exactly this text.
+    Literal(String),
+    /// This text ignoring case, in some way.
+    Caseless(String),
+    /// One of these chars.
+    OneOf(String),
+    Seq(Vec<Ptr<Shape>>),
+    Alt(Vec<Ptr<Shape>>),
+    Repeat {
+        item: Ptr<Shape>,
+        min: usize,
+        max: Option<usize>,
+    },
+    /// `shape` without consuming it, or anything but it when `negated`,
+    /// see [`peek`](crate::peek) and [`not`](crate::not).
+    Ahead {
+        shape: Ptr<Shape>,
+        negated: bool,
+    },
+    /// A rule of the grammar, see [`Parser::named`].
+    Named(String, Ptr<Shape>),
+    /// `shape` failing otherwise than it would by itself, such as with a
+    /// [`Parser::label`].
+    Reporting(Ptr<Shape>),
+    /// An opaque parser with a description, such as "digits".
+    Described(String),
+    Opaque,
+    /// A recursive parser, see [`recursive`](crate::recursive).
+    Rule(Ptr<ShapeCell>),
+    /// A reference to the recursive parser being defined.
+    Recurse(Weak<ShapeCell>),
+}
+
+pub(crate) fn empty() -> Shape {
+    Shape::Seq(Vec::new())
+}
+
+impl<'a, T> Parser<'a, T> {
+    pub(crate) fn with_shape(mut self, shape: Shape) -> Self {
+        self.shape = Some(Ptr::new(shape));
+        self
+    }
+}
+
+/// The shape of `p`, opaque when it has none.
+pub(crate) fn of<A>(p: &Parser<A>) -> Ptr<Shape> {
+    known(&p.shape)
+}
+
+fn known(shape: &Option<Ptr<Shape>>) -> Ptr<Shape> {
+    shape.clone().unwrap_or_else(|| Ptr::new(Shape::Opaque))
+}
+
+/// The shape of `p` wrapped to fail in its own way, see
+/// [`Shape::Reporting`].
+pub(crate) fn reporting<A>(p: &Parser<A>) -> Option<Ptr<Shape>> {
+    let shape = p.shape.clone()?;
+    Some(Ptr::new(Shape::Reporting(shape)))
+}
+
+pub(crate) fn ahead<A>(p: &Parser<A>, negated: bool) -> Shape {
+    let shape = of(p);
+    Shape::Ahead { shape, negated }
+}
+
+pub(crate) fn seq<A, B>(a: &Parser<A>, b: &Parser<B>) -> Shape {
+    seq_of(&[&a.shape, &b.shape])
+}
+
+/// The shapes one after another. Nested sequences are flattened.
+pub(crate) fn seq_of(shapes: &[&Option<Ptr<Shape>>]) -> Shape {
+    let mut items = Vec::new();
+    for shape in shapes {
+        let shape = known(shape);
+        match &*shape {
+            Shape::Seq(inner) => items.extend(inner.iter().cloned()),
+            _ => items.push(shape),
+        }
+    }
+    Shape::Seq(items)
+}
+
+/// The shapes of `parsers` as alternatives. Nested alternatives are
+/// flattened.
+pub(crate) fn alt<A>(parsers: &[&Parser<A>]) -> Shape {
+    let mut alternatives = Vec::new();
+    for p in parsers {
+        let shape = of(p);
+        match &*shape {
+            Shape::Alt(inner) => alternatives.extend(inner.iter().cloned()),
+            _ => alternatives.push(shape),
+        }
+    }
+    Shape::Alt(alternatives)
+}
+
+pub(crate) fn repeat<A>(p: &Parser<A>, min: usize, max: Option<usize>) -> Shape {
+    let item = of(p);
+    Shape::Repeat { item, min, max }
+}
+
+/// `p`, then `sep` and `p` any number of times.
+pub(crate) fn sep_by1<A, S>(p: &Parser<A>, sep: &Parser<S>) -> Shape {
+    let item = of(p);
+    let rest = Shape::Repeat {
+        item: Ptr::new(Shape::Seq(vec![of(sep), item.clone()])),
+        min: 0,
+        max: None,
+    };
+    Shape::Seq(vec![item, Ptr::new(rest)])
+}
+
+/// The chars inputs of `shape` start with, sorted, `None` when they aren't
+/// known or when it also matches nothing.
+pub(crate) fn first(shape: &Shape) -> Option<Vec<char>> {
+    first_within(shape, 8)
+}
+
+/// [`first`], looking into recursive parsers `depth` deep.
+fn first_within(shape: &Shape, depth: usize) -> Option<Vec<char>> {
+    let mut chars = match shape {
+        Shape::Literal(text) => vec![text.chars().next()?],
+        Shape::OneOf(chars) => chars.chars().collect(),
+        Shape::Seq(items) => return first_within(items.first()?, depth),
+        Shape::Alt(items) => {
+            let mut chars = Vec::new();
+            for item in items {
+                chars.extend(first_within(item, depth)?);
+            }
+            chars
+        }
+        Shape::Repeat { item, min, .. } if *min > 0 => return first_within(item, depth),
+        Shape::Named(_, shape) | Shape::Reporting(shape) => return first_within(shape, depth),
+        Shape::Ahead {
+            shape,
+            negated: false,
+        } => return first_within(shape, depth),
+        Shape::Rule(cell) if depth > 0 => {
+            return first_within(cell.get()?.as_ref()?, depth - 1);
+        }
+        Shape::Recurse(cell) if depth > 0 => {
+            let cell = cell.upgrade()?;
+            return first_within(cell.get()?.as_ref()?, depth - 1);
+        }
+        _ => return None,
+    };
+    chars.sort_unstable();
+    chars.dedup();
+    Some(chars)
+}
@@ -0,0 +1,159 @@
+//! User state threaded through a parse.
+//!
+//! The state is part of the input, so it is rewound along with the position
+//! whenever a parser backtracks. Updates clone the state, keep it cheap to
+//! clone (e.g. a persistent stack or an `Rc` of a table) if it is big.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::sync::Arc;
+use core::any::type_name;
+
+use crate::{finish, make_input, Failure, MaybeSync, Parser, ParserError, ParserInput, Ptr};
+
+/// The state, failing when there is none, outside of [`run_with_state`]
+/// and [`with_state`], or when it isn't an `S`.
+fn state<S: Clone + MaybeSync + 'static>(input: &ParserInput) -> Result<S, Failure> {
+    match &input.state {
+        None => Err(Failure::from(String::from("no state installed"))),
+        Some(state) => match state.downcast_ref::<S>() {
+            Some(s) => Ok(s.clone()),
+            None => Err(Failure::from(format!(
+                "parser state is not a {}",
+                type_name::<S>()
+            ))),
+        },
+    }
+}
+
+#[allow(clippy::arc_with_non_send_sync)]
+fn with<S: 'static>(input: ParserInput, s: S) -> ParserInput {
+    ParserInput {
+        state: Some(Arc::new(s)),
+        ..input
+    }
+}
+
+/// Yields a copy of the current state without consuming input.
+pub fn get_state<'a, S: Clone + MaybeSync + 'static>() -> Parser<'a, S> {
+    Parser {
+        run: Ptr::new(|input| {
+            let s = state::<S>(&input);
+            (input, s)
+        }),
+        shape: None,
+    }
+}
+
+pub fn set_state<'a, S: Clone + MaybeSync + 'static>(s: S) -> Parser<'a, ()> {
+    Parser {
+        run: Ptr::new(move |input| (with(input, s.clone()), Ok(()))),
+        shape: None,
+    }
+}
+
+pub fn modify_state<'a, S: Clone + MaybeSync + 'static>(
+    f: impl Fn(&mut S) + MaybeSync + 'a,
+) -> Parser<'a, ()> {
+    Parser {
+        run: Ptr::new(move |input| match state::<S>(&input) {
+            Ok(mut s) => {
+                f(&mut s);
+                (with(input, s), Ok(()))
+            }
+            Err(e) => (input, Err(e)),
+        }),
+        shape: None,
+    }
+}
+
+/// Runs `p` with its own state starting out as `init`, and puts the state of
+/// the surrounding parser back afterwards.
+pub fn with_state<'a, S: Clone + MaybeSync + 'static, A: 'a>(
+    init: S,
+    p: Parser<'a, A>,
+) -> Parser<'a, A> {
+    Parser {
+        run: Ptr::new(move |input| {
+            let outer = input.state.clone();
+            let (input_, result) = (p.run)(with(input, init.clone()));
+            (
+                ParserInput {
+                    state: outer,
+                    ..input_
+                },
+                result,
+            )
+        }),
+        shape: None,
+    }
+}
+
+/// Runs `p` on `input` starting with `init` as the state, and returns the
+/// state it ended up with along with the result.
+pub fn run_with_state<A, S: Clone + MaybeSync + 'static>(
+    p: Parser<A>,
+    input: String,
+    init: S,
+) -> Result<(A, S), ParserError> {
+    let (input, result) = (p.run)(with(make_input(input), init));
+    let s = state::<S>(&input);
+    let x = finish((input.clone(), result))?;
+    s.map(|s| (x, s)).map_err(|e| e.at(&input))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{chars, prefix, run};
+
+    #[test]
+    fn state_test() {
+        // count the words and remember the longest one
+        let word = chars::alpha().many1().map(String::from_iter);
+        let counted = word.and_then(|w| {
+            modify_state(move |s: &mut (usize, String)| {
+                s.0 += 1;
+                if w.len() > s.1.len() {
+                    s.1 = w.clone();
+                }
+            })
+        });
+        let parser = counted.sep_by(prefix(" "));
+
+        let parsed = run_with_state(parser, "a bcd ef".to_string(), (0usize, String::new()));
+        assert_eq!(parsed, Ok((vec![(), (), ()], (3, "bcd".to_string()))));
+    }
+
+    #[test]
+    fn backtracking_state_test() {
+        // the state changes of a failed alternative are undone
+        let bump = modify_state(|n: &mut i32| *n += 1);
+        let parser =
+            (bump.clone() >> prefix("a") >> bump.clone() >> prefix("x")) | (bump >> prefix("ab"));
+        let parsed = run_with_state(parser.clone() >> get_state::<i32>(), "ab".to_string(), 0);
+        assert_eq!(parsed, Ok((1, 1)));
+    }
+
+    #[test]
+    fn with_state_test() {
+        let depth = set_state(1u8) >> get_state::<u8>();
+        let parser = set_state(0u8) >> (with_state(5u8, depth) + get_state::<u8>());
+        assert_eq!(run(parser, "".to_string()), Ok((1, 0)));
+    }
+
+    #[test]
+    fn no_state_test() {
+        let e = run(get_state::<u8>(), "".to_string()).unwrap_err();
+        assert_eq!(e.desc, "no state installed");
+        let e = run(
+            prefix("a") >> modify_state(|n: &mut u8| *n += 1),
+            "a".to_string(),
+        )
+        .unwrap_err();
+        assert_eq!(e.desc, "no state installed");
+        assert_eq!(e.location.offset, 1);
+        let parsed = run_with_state(get_state::<i8>(), "".to_string(), 0u8);
+        assert_eq!(parsed.unwrap_err().desc, "parser state is not a i8");
+    }
+}
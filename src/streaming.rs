@@ -0,0 +1,500 @@
+//! Parsing input that may not have fully arrived yet.
+
+use crate::{finish, make_input, Failure, Parser, ParserError, ParserInput};
+#[cfg(feature = "std")]
+use crate::{input_from, Source};
+use alloc::string::String;
+#[cfg(feature = "std")]
+use alloc::vec::Vec;
+#[cfg(feature = "async")]
+use core::pin::Pin;
+#[cfg(feature = "async")]
+use core::task::Context;
+#[cfg(feature = "std")]
+use core::task::Poll;
+
+/// How much more input a streaming parser wants before it can decide.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Needed {
+    Unknown,
+    /// At least this many more bytes.
+    Size(usize),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StreamResult<T> {
+    /// The value and how many bytes of the input it was parsed from.
+    Ok(T, usize),
+    Err(ParserError),
+    /// The input ended in the middle of a match.
+    Incomplete(Needed),
+}
+
+/// Runs `p` on `input`, treating it as the start of a stream that may have
+/// more data to come. Primitives that hit the end of `input` before they can
+/// tell whether they match report [`StreamResult::Incomplete`] instead of
+/// failing, and alternatives don't backtrack past that.
+pub fn run_streaming<A>(p: Parser<A>, input: String) -> StreamResult<A> {
+    stream(&p, make_input(input))
+}
+
+/// Runs `p` on `input` as the start of a stream, see [`run_streaming`].
+pub(crate) fn stream<A>(p: &Parser<A>, mut input: ParserInput) -> StreamResult<A> {
+    input.partial = true;
+    match (p.run)(input) {
+        (
+            _,
+            Err(Failure {
+                needed: Some(needed),
+                ..
+            }),
+        ) => StreamResult::Incomplete(needed),
+        (input, result) => {
+            let consumed = input.pos;
+            match finish((input, result)) {
+                Ok(x) => StreamResult::Ok(x, consumed),
+                Err(e) => StreamResult::Err(e),
+            }
+        }
+    }
+}
+
+/// How many bytes are read at a time.
+#[cfg(feature = "std")]
+const CHUNK: usize = 8 * 1024;
+
+/// A value and how many bytes of the stream it was parsed from.
+#[cfg(feature = "std")]
+type Parsed<A> = Result<(A, usize), ParserError>;
+
+/// Parses values one after another from the text a reader yields, reading
+/// only as much of it as they need, see [`run_streaming`].
+///
+/// What was read past a value is kept for the next one, and all of it when
+/// parsing failed, see [`StreamReader::buffered`]. A value that takes more
+/// than one read is parsed anew from its start as the bytes come in, once
+/// they have doubled, or sooner when the reader had no more right away, so
+/// a value of `n` bytes takes `O(n)` to parse either way. Fails with
+/// `InvalidData` on invalid UTF-8.
+///
+/// ```
+/// use parcoom::{chars::ALPHABETIC, parse_while, prefix, StreamReader};
+///
+/// let word = parse_while(ALPHABETIC).to_owned() << prefix(";");
+/// let mut reader = StreamReader::new("ab;cd;".as_bytes());
+/// assert_eq!(reader.parse(&word).unwrap(), Ok(("ab".to_string(), 3)));
+/// assert_eq!(reader.parse(&word).unwrap(), Ok(("cd".to_string(), 3)));
+/// ```
+#[cfg(feature = "std")]
+pub struct StreamReader<R> {
+    reader: R,
+    feed: Feed,
+}
+
+#[cfg(feature = "std")]
+impl<R> StreamReader<R> {
+    pub fn new(reader: R) -> Self {
+        StreamReader {
+            reader,
+            feed: Feed::default(),
+        }
+    }
+
+    /// The bytes read and not parsed yet.
+    pub fn buffered(&self) -> &[u8] {
+        &self.feed.bytes
+    }
+
+    /// The reader, dropping the [`buffered`](StreamReader::buffered) bytes.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: std::io::Read> StreamReader<R> {
+    /// Parses the next value, reading more for as long as `p` reports
+    /// [`StreamResult::Incomplete`].
+    pub fn parse<A>(&mut self, p: &Parser<A>) -> std::io::Result<Parsed<A>> {
+        let mut ready = true;
+        loop {
+            if ready || self.feed.enough() {
+                if let Some(parsed) = self.feed.attempt(p, false) {
+                    return Ok(parsed);
+                }
+            }
+            let reader = &mut self.reader;
+            let Poll::Ready(n) = self.feed.fill(|buf| Poll::Ready(reader.read(buf))) else {
+                unreachable!()
+            };
+            match n? {
+                0 => return Ok(self.feed.finish(p)),
+                n => ready = n < CHUNK,
+            }
+        }
+    }
+}
+
+/// The bytes read and not parsed yet, the UTF-8 text they start with, and
+/// how much of it is wanted before parsing again.
+#[cfg(feature = "std")]
+#[derive(Default)]
+struct Feed {
+    bytes: Vec<u8>,
+    valid: usize,
+    wanted: usize,
+}
+
+#[cfg(feature = "std")]
+impl Feed {
+    /// Appends what `read` reads into the buffer it is given, checking that
+    /// it is text; only a char that is still arriving may be cut short.
+    fn fill(
+        &mut self,
+        read: impl FnOnce(&mut [u8]) -> Poll<std::io::Result<usize>>,
+    ) -> Poll<std::io::Result<usize>> {
+        use std::io::{Error, ErrorKind};
+
+        let len = self.bytes.len();
+        self.bytes.resize(len + CHUNK, 0);
+        let result = read(&mut self.bytes[len..]);
+        let n = match &result {
+            Poll::Ready(Ok(n)) => *n,
+            _ => 0,
+        };
+        self.bytes.truncate(len + n);
+        if let Poll::Ready(Ok(n)) = result {
+            let eof = n == 0;
+            match core::str::from_utf8(&self.bytes[self.valid..]) {
+                Ok(_) => self.valid = self.bytes.len(),
+                Err(e) if e.error_len().is_none() && !eof => self.valid += e.valid_up_to(),
+                Err(e) => return Poll::Ready(Err(Error::new(ErrorKind::InvalidData, e))),
+            }
+        }
+        result
+    }
+
+    /// Whether as much text arrived as the last attempt asked for.
+    fn enough(&self) -> bool {
+        self.valid >= self.wanted
+    }
+
+    fn text(&self) -> ParserInput {
+        let text = core::str::from_utf8(&self.bytes[..self.valid]).unwrap();
+        input_from(Source::Text(text.into()))
+    }
+
+    /// Runs `p` on the text so far as the start of a stream, `None` meaning
+    /// it needs more.
+    fn attempt<A>(&mut self, p: &Parser<A>, eof: bool) -> Option<Parsed<A>> {
+        if eof {
+            let (input, result) = (p.run)(self.text());
+            let consumed = input.pos;
+            return Some(self.used(finish((input, result)).map(|x| (x, consumed))));
+        }
+        match stream(p, self.text()) {
+            StreamResult::Ok(x, consumed) => Some(self.used(Ok((x, consumed)))),
+            StreamResult::Err(e) => Some(self.used(Err(e))),
+            StreamResult::Incomplete(needed) => {
+                // at least double, so that parsing again stays linear
+                let needed = match needed {
+                    Needed::Size(n) => n,
+                    Needed::Unknown => 1,
+                };
+                self.wanted = (self.valid + needed).max(2 * self.valid);
+                None
+            }
+        }
+    }
+
+    /// Runs `p` on the text so far as all of it.
+    fn finish<A>(&mut self, p: &Parser<A>) -> Parsed<A> {
+        self.attempt(p, true).unwrap()
+    }
+
+    /// Drops the bytes the value was parsed from, none on an error.
+    fn used<A>(&mut self, parsed: Parsed<A>) -> Parsed<A> {
+        if let Ok((_, consumed)) = &parsed {
+            self.bytes.drain(..consumed);
+            self.valid -= consumed;
+        }
+        self.wanted = 0;
+        parsed
+    }
+}
+
+/// A source of bytes that may have to wait for them, such as a socket.
+///
+/// It has the shape of the `AsyncRead` traits of `futures` and tokio, so
+/// that their readers take a few lines to wrap, but doesn't tie the crate
+/// to one:
+///
+/// ```text
+/// struct Compat<R>(R);
+///
+/// impl<R: tokio::io::AsyncRead + Unpin> parcoom::AsyncRead for Compat<R> {
+///     fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+///         let mut buf = tokio::io::ReadBuf::new(buf);
+///         ready!(Pin::new(&mut self.get_mut().0).poll_read(cx, &mut buf))?;
+///         Poll::Ready(Ok(buf.filled().len()))
+///     }
+/// }
+/// ```
+///
+/// The bytes are read straight into the buffer of the [`StreamReader`].
+#[cfg(feature = "async")]
+pub trait AsyncRead {
+    /// Reads into `buf`, returning how many bytes it read, none at the end.
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<std::io::Result<usize>>;
+}
+
+#[cfg(feature = "async")]
+impl<R: AsyncRead + Unpin + ?Sized> AsyncRead for &mut R {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<std::io::Result<usize>> {
+        Pin::new(&mut **self.get_mut()).poll_read(cx, buf)
+    }
+}
+
+#[cfg(feature = "async")]
+impl<R: AsyncRead + Unpin> StreamReader<R> {
+    /// Like [`StreamReader::parse`], awaiting more bytes from the reader
+    /// for as long as `p` reports [`StreamResult::Incomplete`].
+    pub async fn parse_async<A>(&mut self, p: &Parser<'_, A>) -> std::io::Result<Parsed<A>> {
+        loop {
+            if let Some(parsed) = self.feed.attempt(p, false) {
+                return Ok(parsed);
+            }
+            // reads until there are enough bytes, or none more for now
+            let mut fresh = 0;
+            let n = core::future::poll_fn(|cx| loop {
+                let reader = Pin::new(&mut self.reader);
+                match self.feed.fill(|buf| reader.poll_read(cx, buf)) {
+                    Poll::Ready(Ok(n)) if n > 0 && !self.feed.enough() => fresh += n,
+                    Poll::Pending if fresh > 0 => return Poll::Ready(Ok(fresh)),
+                    poll => return poll,
+                }
+            })
+            .await?;
+            if n == 0 {
+                return Ok(self.feed.finish(p));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chars::ALPHABETIC;
+    #[cfg(feature = "std")]
+    use crate::Ptr;
+    use crate::{any_char, many_exact, parse_while, prefix};
+    #[cfg(feature = "std")]
+    use core::sync::atomic::{AtomicUsize, Ordering};
+    #[cfg(feature = "std")]
+    use std::io::Read;
+
+    /// Reads at most `n` bytes at a time, counting how many were read.
+    #[cfg(feature = "std")]
+    struct Chunks<'a>(&'a [u8], usize, &'a mut usize);
+
+    #[cfg(feature = "std")]
+    impl Read for Chunks<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let n = self.0.len().min(self.1).min(buf.len());
+            buf[..n].copy_from_slice(&self.0[..n]);
+            self.0 = &self.0[n..];
+            *self.2 += n;
+            Ok(n)
+        }
+    }
+
+    /// `p`, counting how many times it runs.
+    #[cfg(feature = "std")]
+    fn counted<'a, A: 'a>(p: Parser<'a, A>, runs: &Ptr<AtomicUsize>) -> Parser<'a, A> {
+        let runs = runs.clone();
+        Parser {
+            run: Ptr::new(move |input| {
+                runs.fetch_add(1, Ordering::Relaxed);
+                (p.run)(input)
+            }),
+            shape: None,
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn stream_reader_test() {
+        let source = "héllo;world;".as_bytes();
+        let word = parse_while(ALPHABETIC).to_owned() << prefix(";");
+        let mut read = 0;
+        let mut reader = StreamReader::new(Chunks(source, 2, &mut read));
+        let parsed = reader.parse(&word).unwrap();
+        assert_eq!(parsed, Ok(("héllo".to_string(), 7)));
+        // what was read past it is kept for the next one
+        let parsed = reader.parse(&word).unwrap();
+        assert_eq!(parsed, Ok(("world".to_string(), 6)));
+        assert_eq!(reader.buffered(), b"");
+        drop(reader);
+        assert_eq!(read, source.len());
+
+        // and all of it on an error
+        let mut reader = StreamReader::new(&b"ab,c"[..]);
+        let parsed = reader.parse(&word).unwrap();
+        assert_eq!(parsed.map_err(|e| e.desc), Err("expected ;".to_string()));
+        assert_eq!(reader.buffered(), b"ab,c");
+
+        let mut reader = StreamReader::new(&[0xff, b'a'][..]);
+        let error = reader.parse(&any_char()).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn stream_reader_lookahead_test() {
+        // `abc` is looked for across reads of one byte, `a` matches
+        let p = prefix("abc") | prefix("a");
+        let mut read = 0;
+        let mut reader = StreamReader::new(Chunks(b"abd", 1, &mut read));
+        assert_eq!(reader.parse(&p).unwrap(), Ok(("a", 1)));
+        assert_eq!(reader.buffered(), b"bd");
+        assert_eq!(reader.parse(&prefix("bd")).unwrap(), Ok(("bd", 2)));
+
+        // a char split across reads
+        let mut reader = StreamReader::new(Chunks("é;".as_bytes(), 1, &mut read));
+        let parsed = reader.parse(&(any_char() << prefix(";"))).unwrap();
+        assert_eq!(parsed, Ok(('é', 3)));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn stream_reader_retries_test() {
+        // a long value is parsed anew only as the bytes double
+        let runs = Ptr::new(AtomicUsize::new(0));
+        let word = counted(parse_while(ALPHABETIC).to_owned() << prefix(";"), &runs);
+        let source = "a".repeat(100 * 1024) + ";";
+        let mut reader = StreamReader::new(source.as_bytes());
+        let parsed = reader.parse(&word).unwrap().map(|(_, n)| n);
+        assert_eq!(parsed, Ok(source.len()));
+        assert!(runs.load(Ordering::Relaxed) <= 6, "{runs:?}");
+
+        // but after each read when the reader has no more right away
+        runs.store(0, Ordering::Relaxed);
+        let mut read = 0;
+        let mut reader = StreamReader::new(Chunks(b"abc;", 1, &mut read));
+        assert!(reader.parse(&word).unwrap().is_ok());
+        assert_eq!(runs.load(Ordering::Relaxed), 5);
+    }
+
+    #[test]
+    fn prefix_streaming_test() {
+        let parser = prefix("HELLO") + prefix(" ");
+        assert_eq!(
+            run_streaming(parser.clone(), "HEL".to_string()),
+            StreamResult::Incomplete(Needed::Size(2))
+        );
+        assert_eq!(
+            run_streaming(parser.clone(), "HELLO ".to_string()),
+            StreamResult::Ok(("HELLO", " "), 6)
+        );
+        assert!(matches!(
+            run_streaming(parser, "HELP".to_string()),
+            StreamResult::Err(_)
+        ));
+    }
+
+    #[test]
+    fn any_char_streaming_test() {
+        let parser = many_exact(3, any_char());
+        assert_eq!(
+            run_streaming(parser.clone(), "ab".to_string()),
+            StreamResult::Incomplete(Needed::Size(1))
+        );
+        assert_eq!(
+            run_streaming(parser, "abcd".to_string()),
+            StreamResult::Ok(vec!['a', 'b', 'c'], 3)
+        );
+    }
+
+    #[test]
+    fn alternatives_streaming_test() {
+        // the first alternative might still match once more input arrives
+        let parser = prefix("GET") | prefix("G");
+        assert_eq!(
+            run_streaming(parser, "GE".to_string()),
+            StreamResult::Incomplete(Needed::Size(1))
+        );
+
+        // a word can only end once something else follows it
+        let parser = parse_while(ALPHABETIC).to_owned() << prefix(";");
+        assert_eq!(
+            run_streaming(parser.clone(), "abc".to_string()),
+            StreamResult::Incomplete(Needed::Size(1))
+        );
+        assert_eq!(
+            run_streaming(parser, "abc;".to_string()),
+            StreamResult::Ok("abc".to_string(), 4)
+        );
+    }
+
+    /// Has a byte every other poll, waking its task in between.
+    #[cfg(feature = "async")]
+    struct Trickle(&'static [u8], bool);
+
+    #[cfg(feature = "async")]
+    impl AsyncRead for Trickle {
+        fn poll_read(
+            self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut [u8],
+        ) -> Poll<std::io::Result<usize>> {
+            let this = self.get_mut();
+            this.1 = !this.1;
+            if this.1 {
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+            let n = this.0.len().min(1);
+            buf[..n].copy_from_slice(&this.0[..n]);
+            this.0 = &this.0[n..];
+            Poll::Ready(Ok(n))
+        }
+    }
+
+    #[cfg(feature = "async")]
+    fn block_on<F: core::future::Future>(future: F) -> F::Output {
+        let mut future = core::pin::pin!(future);
+        let mut cx = Context::from_waker(core::task::Waker::noop());
+        loop {
+            if let Poll::Ready(x) = future.as_mut().poll(&mut cx) {
+                return x;
+            }
+        }
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn parse_async_test() {
+        let word = parse_while(ALPHABETIC).to_owned() << prefix(";");
+        let mut reader = StreamReader::new(Trickle(b"ab;cd", false));
+        let parsed = block_on(reader.parse_async(&word));
+        assert_eq!(parsed.unwrap(), Ok(("ab".to_string(), 3)));
+        let parsed = block_on(reader.parse_async(&word));
+        let parsed = parsed.unwrap().map_err(|e| e.desc);
+        assert_eq!(parsed, Err("expected ;".to_string()));
+        assert_eq!(reader.buffered(), b"cd");
+
+        let p = prefix("abc") | prefix("a");
+        let mut reader = StreamReader::new(Trickle(b"abd", false));
+        assert_eq!(block_on(reader.parse_async(&p)).unwrap(), Ok(("a", 1)));
+        assert_eq!(reader.buffered(), b"bd");
+    }
+}
@@ -0,0 +1,183 @@
+//! Slices of the source that share it instead of copying, see [`Substr`].
+//!
+//! The char parsers taking text off the input, such as
+//! [`parse_while`](crate::parse_while), [`chars::take_while`] or
+//! [`chars::digits1`], yield them. [`Parser::to_owned`] turns their output
+//! into a `String`.
+//!
+//! [`chars::take_while`]: crate::chars::take_while
+//! [`chars::digits1`]: crate::chars::digits1
+
+use alloc::string::String;
+use alloc::sync::Arc;
+use core::borrow::Borrow;
+use core::fmt;
+use core::hash::{Hash, Hasher};
+use core::ops::{Deref, Range};
+
+use crate::limits::counted;
+use crate::{Failure, Parser, ParserInput, Ptr, Source};
+
+/// A part of the source being parsed. It keeps the source alive rather than
+/// copying the text, so it costs no allocation. Derefs to `str`; `to_string`
+/// gets an owned copy.
+#[derive(Clone)]
+pub struct Substr {
+    source: Arc<str>,
+    range: Range<usize>,
+}
+
+impl Substr {
+    /// `range` of `source`, unless it cuts through a char.
+    pub(crate) fn new(source: Arc<str>, range: Range<usize>) -> Option<Substr> {
+        source.get(range.clone())?;
+        Some(Substr { source, range })
+    }
+
+    /// The next `n` bytes of the text of `input`, `n` being at most the
+    /// length of [`ParserInput::text`]. Empty and of no source when the
+    /// input has no text.
+    pub(crate) fn take(input: &ParserInput, n: usize) -> Substr {
+        let substr = match &input.source {
+            Source::Text(source) => Substr::new(source.clone(), input.pos..input.pos + n),
+            Source::Bytes(_) | Source::Tokens(..) => None,
+        };
+        substr.unwrap_or_else(|| Substr {
+            source: Arc::from(""),
+            range: 0..0,
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.source[self.range.clone()]
+    }
+
+    /// The byte range of the source it covers.
+    pub fn span(&self) -> Range<usize> {
+        self.range.clone()
+    }
+}
+
+impl Deref for Substr {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl AsRef<str> for Substr {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl fmt::Debug for Substr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl fmt::Display for Substr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self.as_str(), f)
+    }
+}
+
+impl PartialEq for Substr {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for Substr {}
+
+impl PartialEq<str> for Substr {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for Substr {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl PartialEq<String> for Substr {
+    fn eq(&self, other: &String) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl Hash for Substr {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state)
+    }
+}
+
+impl Borrow<str> for Substr {
+    fn borrow(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl From<Substr> for String {
+    fn from(s: Substr) -> String {
+        String::from(s.as_str())
+    }
+}
+
+impl<'a, T: 'a> Parser<'a, T> {
+    /// Like [`Parser::recognize`], but sharing the source instead of copying
+    /// the text. Only for parsers of text: it fails on bytes, tokens, or
+    /// part of a char.
+    pub fn substr(self) -> Parser<'a, Substr> {
+        Parser {
+            run: Ptr::new(move |input| {
+                let start = input.pos;
+                match (self.run)(input) {
+                    (input_, Ok(_)) => {
+                        let substr = match &input_.source {
+                            Source::Text(source) => Substr::new(source.clone(), start..input_.pos),
+                            Source::Bytes(_) | Source::Tokens(..) => None,
+                        };
+                        match substr {
+                            Some(substr) => (input_, Ok(substr)),
+                            None => {
+                                let desc = String::from("substr needs a text source");
+                                (input_, Err(Failure::from(desc)))
+                            }
+                        }
+                    }
+                    (input_, Err(e)) => (input_, Err(e)),
+                }
+            }),
+            shape: None,
+        }
+    }
+}
+
+impl<'a> Parser<'a, Substr> {
+    /// Copies the text into a `String`, for keeping it past the source.
+    pub fn to_owned(&self) -> Parser<'a, String> {
+        counted(self.clone().map(String::from))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chars::ALPHABETIC;
+    use crate::{parse_while, prefix, run};
+
+    #[test]
+    fn substr_test() {
+        let word = || parse_while(ALPHABETIC).substr();
+        let parser = word().sep_by(prefix(" "));
+        let words = run(parser, "ab çd".to_string()).unwrap();
+        assert_eq!(words, ["ab", "çd"]);
+        assert_eq!(words[1].span(), 3..6);
+        assert_eq!(words[1].len(), 3);
+        assert_eq!(String::from(words[0].clone()), "ab");
+    }
+}
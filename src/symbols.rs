@@ -0,0 +1,184 @@
+//! Operators and keywords registered once and matched by maximal munch.
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::cmp::Reverse;
+
+use crate::shape::Shape;
+use crate::{expected_desc, input_sub, Failure, MaybeSync, Needed, Parser, Ptr};
+
+/// Builds a parser of the longest registered symbol the input starts with,
+/// yielding its value, so `+` never matches the start of `+=` when both are
+/// registered, whatever order they were in. Keywords only match as whole
+/// words, as with [`keyword`](crate::keyword), and leave the rest to the
+/// other symbols: `in` doesn't match the start of `int` unless `int` is a
+/// keyword too.
+///
+/// ```
+/// use parcoom::{run, SymbolTable};
+///
+/// #[derive(Debug, Clone, PartialEq)]
+/// enum Symbol {
+///     Plus,
+///     PlusEq,
+///     Arrow,
+///     In,
+/// }
+///
+/// let symbol = SymbolTable::new()
+///     .operator("+", Symbol::Plus)
+///     .operator("+=", Symbol::PlusEq)
+///     .operator("->", Symbol::Arrow)
+///     .keyword("in", Symbol::In)
+///     .build();
+/// assert_eq!(run(symbol.clone(), "+=".to_string()), Ok(Symbol::PlusEq));
+/// assert!(run(symbol, "int".to_string()).is_err());
+/// ```
+#[derive(Debug, Clone)]
+pub struct SymbolTable<T> {
+    /// Each symbol, whether it is a keyword, and its value.
+    symbols: Vec<(&'static str, bool, T)>,
+}
+
+impl<T> Default for SymbolTable<T> {
+    fn default() -> Self {
+        SymbolTable::new()
+    }
+}
+
+impl<T> SymbolTable<T> {
+    pub fn new() -> Self {
+        SymbolTable {
+            symbols: Vec::new(),
+        }
+    }
+
+    /// `value` wherever the source reads `operator`, unless a longer symbol
+    /// matches there.
+    pub fn operator(mut self, operator: &'static str, value: T) -> Self {
+        self.symbols.push((operator, false, value));
+        self
+    }
+
+    /// `value` wherever the source reads `keyword` as a whole word, unless a
+    /// longer symbol matches there.
+    pub fn keyword(mut self, keyword: &'static str, value: T) -> Self {
+        self.symbols.push((keyword, true, value));
+        self
+    }
+
+    pub fn build<'a>(self) -> Parser<'a, T>
+    where
+        T: Clone + MaybeSync + 'a,
+    {
+        let is_word = |c: char| c.is_alphanumeric() || c == '_';
+        let expected: Vec<String> = self
+            .symbols
+            .iter()
+            .map(|(symbol, _, _)| format!("{symbol:?}"))
+            .collect();
+        let shape = Shape::Alt(
+            self.symbols
+                .iter()
+                .map(|(symbol, _, _)| Ptr::new(Shape::Literal(symbol.to_string())))
+                .collect(),
+        );
+        let mut symbols = self.symbols;
+        // stable, so the first registered wins among the same symbols
+        symbols.sort_by_key(|(symbol, _, _)| Reverse(symbol.len()));
+        Parser {
+            run: Ptr::new(move |input| {
+                let text = input.text();
+                for (symbol, keyword, value) in &symbols {
+                    if input.partial && symbol.starts_with(text) && symbol.len() > text.len() {
+                        let needed = Needed::Size(symbol.len() - text.len());
+                        return (input, Err(Failure::incomplete(needed)));
+                    }
+                    if !text.starts_with(symbol) {
+                        continue;
+                    }
+                    match text[symbol.len()..].chars().next() {
+                        Some(c) if *keyword && is_word(c) => continue,
+                        None if *keyword && input.partial => {
+                            return (input, Err(Failure::incomplete(Needed::Size(1))))
+                        }
+                        _ => return (input_sub(symbol.len(), &input), Ok(value.clone())),
+                    }
+                }
+                let truncated =
+                    |symbol: &&str| symbol.len() > text.len() && symbol.starts_with(text);
+                let e = Failure {
+                    expected: expected.clone(),
+                    truncated: symbols.iter().any(|(symbol, _, _)| truncated(symbol)),
+                    ..Failure::from(expected_desc(&expected))
+                };
+                (input, Err(e))
+            }),
+            shape: Some(Ptr::new(shape)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chars::alpha;
+    use crate::{prefix, run, run_streaming, StreamResult};
+    use alloc::vec;
+
+    #[derive(Debug, Clone, PartialEq)]
+    enum Token {
+        Plus,
+        PlusEq,
+        Eq,
+        FatArrow,
+        Arrow,
+        Minus,
+        If,
+        In,
+        Int,
+    }
+
+    fn table() -> SymbolTable<Token> {
+        SymbolTable::new()
+            .operator("+", Token::Plus)
+            .operator("=", Token::Eq)
+            .operator("-", Token::Minus)
+            .operator("=>", Token::FatArrow)
+            .operator("+=", Token::PlusEq)
+            .operator("->", Token::Arrow)
+            .keyword("if", Token::If)
+            .keyword("in", Token::In)
+            .keyword("int", Token::Int)
+    }
+
+    #[test]
+    fn symbol_table_test() {
+        let tokens = (table().build() << prefix(" ").optional()).many();
+        let parsed = run(tokens.clone(), "+=+ -> - = => in int if".to_string());
+        use Token::*;
+        let expected = vec![PlusEq, Plus, Arrow, Minus, Eq, FatArrow, In, Int, If];
+        assert_eq!(parsed, Ok(expected));
+        assert_eq!(run(tokens, "==>".to_string()), Ok(vec![Eq, FatArrow]));
+
+        // keywords give way to longer words
+        let ident = alpha().many1().map(|_| Token::Minus);
+        let parser = table().build() | ident;
+        assert_eq!(run(parser.clone(), "iffy".to_string()), Ok(Token::Minus));
+        assert_eq!(run(parser, "if(".to_string()), Ok(Token::If));
+        let e = run(table().build(), "*".to_string()).unwrap_err();
+        assert_eq!(e.expected.len(), 9);
+        assert!(e.desc.starts_with("expected \"+\", \"=\""));
+    }
+
+    #[test]
+    fn symbol_table_streaming_test() {
+        let streamed = run_streaming(table().build(), "+".to_string());
+        assert_eq!(streamed, StreamResult::Incomplete(Needed::Size(1)));
+        let streamed = run_streaming(table().build(), "if".to_string());
+        assert_eq!(streamed, StreamResult::Incomplete(Needed::Size(1)));
+        let streamed = run_streaming(table().build(), "+ ".to_string());
+        assert_eq!(streamed, StreamResult::Ok(Token::Plus, 1));
+    }
+}
@@ -0,0 +1,188 @@
+//! Assertions for testing grammars, failing with the rendered error.
+//!
+//! ```
+//! use parcoom::testing::{assert_fails_at, assert_parses};
+//! use parcoom::{chars, prefix};
+//!
+//! let pair = (chars::digits1().to_owned() << prefix(",")) + chars::digits1().to_owned();
+//! assert_parses(&pair, "1,23", ("1".to_string(), "23".to_string()));
+//! assert_fails_at(&pair, "1,x", 2);
+//! ```
+//!
+//! For property tests, [`samples`] generates inputs of a parser's structure
+//! to feed to whichever framework is at hand. There are no proptest
+//! strategies, so that the crate keeps no dependencies: a seed strategy
+//! mapped through `samples` makes one, and proptest then shrinks the seed
+//! rather than the input.
+//!
+//! ```text
+//! proptest! {
+//!     #[test]
+//!     fn round_trips(input in any::<u64>().prop_map(|seed| samples(&p, seed, 8).next().unwrap())) {
+//!         prop_assert!(run_complete(p.clone(), input).is_ok());
+//!     }
+//! }
+//! ```
+//!
+//! [`assert_round_trips`] checks a printer against the parser the same way.
+
+use alloc::string::String;
+use core::fmt::Debug;
+
+use crate::sample::{SampleRng, SplitMix64};
+use crate::{run_complete, Parser, ParserError};
+
+/// Asserts that `p` parses all of `input` into `expected`.
+#[track_caller]
+pub fn assert_parses<'a, T: PartialEq + Debug + 'a>(p: &Parser<'a, T>, input: &str, expected: T) {
+    match run_complete(p.clone(), input.into()) {
+        Ok(value) => assert_eq!(value, expected, "parsing {input:?}"),
+        Err(e) => panic!("expected {input:?} to parse\n{}", e.render(input)),
+    }
+}
+
+/// Asserts that `p` fails on `input`, or on its rest, at byte offset
+/// `offset`. The error is returned for checking further.
+#[track_caller]
+pub fn assert_fails_at<'a, T: Debug + 'a>(
+    p: &Parser<'a, T>,
+    input: &str,
+    offset: usize,
+) -> ParserError {
+    match run_complete(p.clone(), input.into()) {
+        Ok(value) => panic!("expected {input:?} to fail at {offset}, parsed {value:?}"),
+        Err(e) if e.location.offset != offset => panic!(
+            "expected {input:?} to fail at {offset}, failed at {}\n{}",
+            e.location.offset,
+            e.render(input)
+        ),
+        Err(e) => e,
+    }
+}
+
+/// An endless run of samples of `p` from a generator seeded with `seed`, see
+/// [`Parser::sample`]. Ends right away when that returns `None`.
+pub fn samples<'p, 'a, T: 'a>(
+    p: &'p Parser<'a, T>,
+    seed: u64,
+    max_depth: usize,
+) -> impl Iterator<Item = String> + 'p {
+    let mut rng = SplitMix64(seed);
+    core::iter::from_fn(move || p.sample(&mut rng, max_depth))
+}
+
+/// Asserts that `p` parses `count` samples of itself, which holds for
+/// grammars free of ambiguity between their alternatives.
+#[track_caller]
+pub fn assert_accepts_samples<'a, T: Debug + 'a>(
+    p: &Parser<'a, T>,
+    rng: &mut impl SampleRng,
+    count: usize,
+    max_depth: usize,
+) {
+    for _ in 0..count {
+        let Some(input) = p.sample(rng, max_depth) else {
+            panic!("no sample within a depth of {max_depth}");
+        };
+        if let Err(e) = run_complete(p.clone(), input.clone()) {
+            panic!("expected sample {input:?} to parse\n{}", e.render(&input));
+        }
+    }
+}
+
+/// Asserts that `count` samples of `p` parse, and that their values parse
+/// back from what `print` makes of them, into the same values.
+#[track_caller]
+pub fn assert_round_trips<'a, T: PartialEq + Debug + 'a>(
+    p: &Parser<'a, T>,
+    print: impl Fn(&T) -> String,
+    rng: &mut impl SampleRng,
+    count: usize,
+    max_depth: usize,
+) {
+    for _ in 0..count {
+        let Some(input) = p.sample(rng, max_depth) else {
+            panic!("no sample within a depth of {max_depth}");
+        };
+        let value = match run_complete(p.clone(), input.clone()) {
+            Ok(value) => value,
+            Err(e) => panic!("expected sample {input:?} to parse\n{}", e.render(&input)),
+        };
+        let printed = print(&value);
+        match run_complete(p.clone(), printed.clone()) {
+            Ok(again) => assert_eq!(again, value, "parsing {printed:?}, printed from {input:?}"),
+            Err(e) => panic!(
+                "expected {printed:?}, printed from {input:?}, to parse\n{}",
+                e.render(&printed)
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{chars, one_of, prefix, recursive};
+    use alloc::vec::Vec;
+
+    fn list<'a>() -> Parser<'a, usize> {
+        recursive(|list| {
+            let item = one_of("ab").to(1) | list;
+            let items = item
+                .sep_by(prefix(","))
+                .delimited_by(prefix("["), prefix("]"));
+            items.map(|items| items.iter().sum())
+        })
+    }
+
+    #[test]
+    fn assert_test() {
+        assert_parses(&list(), "[a,[b,a]]", 3);
+        let e = assert_fails_at(&list(), "[a,b", 4);
+        assert_eq!(e.desc, "missing closing delimiter: expected ]");
+        assert_fails_at(&list(), "[a]]", 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "expected \"[a,b\" to fail at 1, failed at 4")]
+    fn assert_fails_at_test() {
+        assert_fails_at(&list(), "[a,b", 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "expected \"[a,b\" to parse\nerror: missing closing delimiter")]
+    fn assert_parses_test() {
+        assert_parses(&list(), "[a,b", 2);
+    }
+
+    #[test]
+    fn samples_test() {
+        let texts: Vec<_> = samples(&list(), 3, 4).take(20).collect();
+        assert_eq!(texts.len(), 20);
+        assert_eq!(texts, samples(&list(), 3, 4).take(20).collect::<Vec<_>>());
+        for text in &texts {
+            assert!(run_complete(list(), text.clone()).is_ok(), "{text}");
+        }
+        assert_eq!(samples(&chars::digits1(), 3, 4).next(), None);
+
+        assert_accepts_samples(&list(), &mut SplitMix64(5), 50, 4);
+    }
+
+    fn words<'a>() -> Parser<'a, Vec<String>> {
+        let word = one_of("ab").many1().map(|cs| cs.into_iter().collect());
+        word.sep_by(prefix(" "))
+    }
+
+    #[test]
+    fn round_trip_test() {
+        let print = |words: &Vec<String>| words.join(" ");
+        assert_round_trips(&words(), print, &mut SplitMix64(7), 50, 4);
+    }
+
+    #[test]
+    #[should_panic(expected = "parsing \"\"")]
+    fn round_trip_fails_test() {
+        let print = |_: &Vec<String>| String::new();
+        assert_round_trips(&words(), print, &mut SplitMix64(7), 50, 4);
+    }
+}
@@ -0,0 +1,128 @@
+//! Parsers over the tokens of a separate lexer, run with [`run_tokens`].
+//!
+//! Tokens can be any `Clone` type. Positions in errors are token indices.
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::fmt::Debug;
+
+use crate::{finish, input_from, Failure, MaybeSync, Parse, Parser, ParserError};
+use crate::{ParserInput, Ptr, Source};
+
+/// Runs `p` on `tokens`, like [`run`](crate::run) does on text.
+#[allow(clippy::arc_with_non_send_sync)]
+pub fn run_tokens<T: 'static, A>(p: impl Parse<A>, tokens: Vec<T>) -> Result<A, ParserError> {
+    let len = tokens.len();
+    finish(p.parse(input_from(Source::Tokens(Arc::new(tokens), len))))
+}
+
+/// The not yet consumed tokens, none when parsing text or bytes.
+fn rest<T: 'static>(input: &ParserInput) -> &[T] {
+    match &input.source {
+        Source::Tokens(tokens, _) => {
+            let tokens: &Vec<T> = tokens
+                .downcast_ref()
+                .expect("token parser run on tokens of another type");
+            &tokens[input.pos..]
+        }
+        Source::Text(_) | Source::Bytes(_) => &[],
+    }
+}
+
+fn satisfy_token_or<'a, T: Clone + Debug + 'static>(
+    expected: String,
+    f: impl Fn(&T) -> bool + MaybeSync + 'a,
+) -> Parser<'a, T> {
+    Parser {
+        run: Ptr::new(move |input| match rest::<T>(&input).first() {
+            Some(t) if f(t) => {
+                let t = t.clone();
+                let pos = input.pos + 1;
+                (ParserInput { pos, ..input }, Ok(t))
+            }
+            Some(t) => {
+                let desc = format!("expected {expected}, got {t:?}");
+                (input, Err(Failure::expecting(expected.clone(), desc)))
+            }
+            None => {
+                let desc = format!("expected {expected}, got end of input");
+                (input, Err(Failure::expecting(expected.clone(), desc)))
+            }
+        }),
+        shape: None,
+    }
+}
+
+/// One token equal to `token`.
+pub fn token<'a, T: Clone + PartialEq + Debug + MaybeSync + 'static>(token: T) -> Parser<'a, T> {
+    satisfy_token_or(format!("{token:?}"), move |t| *t == token)
+}
+
+/// One token accepted by `f`, e.g. `satisfy_token(|t| matches!(t, Token::Ident(_)))`.
+pub fn satisfy_token<'a, T: Clone + Debug + 'static>(
+    f: impl Fn(&T) -> bool + MaybeSync + 'a,
+) -> Parser<'a, T> {
+    satisfy_token_or("a matching token".to_string(), f)
+}
+
+pub fn any_token<'a, T: Clone + Debug + 'static>() -> Parser<'a, T> {
+    satisfy_token_or("any token".to_string(), |_| true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{recursive, Location};
+
+    #[derive(Debug, Clone, PartialEq)]
+    enum Token {
+        Num(i64),
+        Ident(String),
+        Plus,
+        LParen,
+        RParen,
+    }
+
+    fn sum<'a>() -> Parser<'a, i64> {
+        recursive(|sum| {
+            let num = satisfy_token(|t| matches!(t, Token::Num(_))).map(|t| match t {
+                Token::Num(n) => n,
+                _ => unreachable!(),
+            });
+            let atom = num | sum.delimited_by(token(Token::LParen), token(Token::RParen));
+            atom.sep_by1(token(Token::Plus))
+                .map(|xs| xs.into_iter().sum())
+        })
+    }
+
+    #[test]
+    fn token_test() {
+        use Token::*;
+        let tokens = vec![Num(1), Plus, LParen, Num(2), Plus, Num(3), RParen];
+        assert_eq!(run_tokens(sum(), tokens), Ok(6));
+    }
+
+    #[test]
+    fn token_error_test() {
+        use Token::*;
+        let tokens = vec![LParen, Num(1), Ident("x".to_string())];
+        assert_eq!(
+            run_tokens(sum(), tokens),
+            Err(ParserError {
+                desc: "missing closing delimiter: expected RParen, got Ident(\"x\")".to_string(),
+                location: Location {
+                    offset: 2,
+                    char_offset: 2,
+                    line: 1,
+                    column: 3,
+                },
+                context: vec![],
+                expected: vec!["RParen".to_string()],
+                cause: None,
+                at_end: false,
+            })
+        );
+    }
+}
@@ -0,0 +1,92 @@
+//! Sequencing parsers into flat tuples, see [`tuple`].
+
+use crate::{shape, Parser, Ptr};
+
+/// Tuples of parsers [`tuple`] takes, of up to 12 of them.
+pub trait Sequence<'a> {
+    type Output;
+
+    fn sequence(self) -> Parser<'a, Self::Output>;
+}
+
+macro_rules! sequences {
+    ($($(($i:tt $t:ident $x:ident))+;)+) => {
+        $(
+            impl<'a, $($t: 'a),+> Sequence<'a> for ($(Parser<'a, $t>,)+) {
+                type Output = ($($t,)+);
+
+                fn sequence(self) -> Parser<'a, Self::Output> {
+                    let shape = shape::seq_of(&[$(&self.$i.shape),+]);
+                    Parser {
+                        run: Ptr::new(move |input| {
+                            $(
+                                let (input, $x) = match (self.$i.run)(input) {
+                                    (input_, Ok(x)) => (input_, x),
+                                    (input_, Err(e)) => return (input_, Err(e)),
+                                };
+                            )+
+                            (input, Ok(($($x,)+)))
+                        }),
+                        shape: Some(Ptr::new(shape)),
+                    }
+                }
+            }
+        )+
+    };
+}
+
+sequences! {
+    (0 A a);
+    (0 A a) (1 B b);
+    (0 A a) (1 B b) (2 C c);
+    (0 A a) (1 B b) (2 C c) (3 D d);
+    (0 A a) (1 B b) (2 C c) (3 D d) (4 E e);
+    (0 A a) (1 B b) (2 C c) (3 D d) (4 E e) (5 F f);
+    (0 A a) (1 B b) (2 C c) (3 D d) (4 E e) (5 F f) (6 G g);
+    (0 A a) (1 B b) (2 C c) (3 D d) (4 E e) (5 F f) (6 G g) (7 H h);
+    (0 A a) (1 B b) (2 C c) (3 D d) (4 E e) (5 F f) (6 G g) (7 H h) (8 I i);
+    (0 A a) (1 B b) (2 C c) (3 D d) (4 E e) (5 F f) (6 G g) (7 H h) (8 I i) (9 J j);
+    (0 A a) (1 B b) (2 C c) (3 D d) (4 E e) (5 F f) (6 G g) (7 H h) (8 I i) (9 J j)
+        (10 K k);
+    (0 A a) (1 B b) (2 C c) (3 D d) (4 E e) (5 F f) (6 G g) (7 H h) (8 I i) (9 J j)
+        (10 K k) (11 L l);
+}
+
+/// Runs a tuple of parsers one after the other, yielding the flat tuple of
+/// their values, where `+` would nest pairs.
+pub fn tuple<'a, P: Sequence<'a>>(parsers: P) -> Parser<'a, P::Output> {
+    parsers.sequence()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chars::digits1;
+    use crate::{prefix, run, Location, ParserError};
+
+    #[test]
+    fn tuple_test() {
+        let parser = || tuple((digits1(), prefix(":"), digits1(), prefix(":"), digits1()));
+        let parsed = run(parser(), "12:30:05".to_string());
+        assert_eq!(
+            parsed.map(|(h, _, m, _, s)| (h.to_string(), m.to_string(), s.to_string())),
+            Ok(("12".to_string(), "30".to_string(), "05".to_string()))
+        );
+        assert_eq!(
+            run(parser(), "12:30".to_string()),
+            Err(ParserError {
+                desc: "expected :".to_string(),
+                location: Location {
+                    offset: 5,
+                    char_offset: 5,
+                    line: 1,
+                    column: 6,
+                },
+                context: vec![],
+                expected: vec!["\":\"".to_string()],
+                cause: None,
+                at_end: true,
+            })
+        );
+    }
+}
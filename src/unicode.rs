@@ -0,0 +1,338 @@
+//! Unicode identifier classes and grapheme clusters. Needs the `unicode`
+//! feature.
+//!
+//! The identifier classes are those of Unicode 14.0. The crate has no
+//! dependencies, so its other tables are smaller than Unicode's: they cover
+//! the combining marks and emoji of the scripts in common use, and
+//! [`any_grapheme`] follows the rules of UAX #29 that matter for them, not
+//! all of them. Spans always end on char boundaries either way.
+//!
+//! ```
+//! use parcoom::unicode::any_grapheme;
+//! use parcoom::run;
+//!
+//! let clusters = any_grapheme().many();
+//! let parsed = run(clusters, "e\u{301}👍🏽🇫🇷\r\n".to_string()).unwrap();
+//! assert_eq!(parsed, ["e\u{301}", "👍🏽", "🇫🇷", "\r\n"]);
+//! ```
+
+use alloc::string::{String, ToString};
+
+use crate::limits::counted;
+use crate::{input_sub, Failure, Needed, Parser, Ptr};
+
+mod tables;
+
+/// Combining marks, nonspacing, spacing and enclosing ones, and variation
+/// selectors, as sorted ranges.
+const MARKS: &[(u32, u32)] = &[
+    (0x0300, 0x036f),
+    (0x0483, 0x0489),
+    (0x0591, 0x05bd),
+    (0x05bf, 0x05bf),
+    (0x05c1, 0x05c2),
+    (0x05c4, 0x05c5),
+    (0x05c7, 0x05c7),
+    (0x0610, 0x061a),
+    (0x064b, 0x065f),
+    (0x0670, 0x0670),
+    (0x06d6, 0x06dc),
+    (0x06df, 0x06e4),
+    (0x06e7, 0x06e8),
+    (0x06ea, 0x06ed),
+    (0x0711, 0x0711),
+    (0x0730, 0x074a),
+    (0x07a6, 0x07b0),
+    (0x07eb, 0x07f3),
+    (0x0816, 0x0819),
+    (0x081b, 0x0823),
+    (0x0825, 0x0827),
+    (0x0829, 0x082d),
+    (0x0859, 0x085b),
+    (0x08d3, 0x08e1),
+    (0x08e3, 0x0903),
+    (0x093a, 0x093c),
+    (0x093e, 0x094f),
+    (0x0951, 0x0957),
+    (0x0962, 0x0963),
+    (0x0981, 0x0983),
+    (0x09bc, 0x09bc),
+    (0x09be, 0x09c4),
+    (0x09c7, 0x09c8),
+    (0x09cb, 0x09cd),
+    (0x09d7, 0x09d7),
+    (0x09e2, 0x09e3),
+    (0x0a01, 0x0a03),
+    (0x0a3c, 0x0a3c),
+    (0x0a3e, 0x0a42),
+    (0x0a47, 0x0a48),
+    (0x0a4b, 0x0a4d),
+    (0x0a70, 0x0a71),
+    (0x0a81, 0x0a83),
+    (0x0abc, 0x0abc),
+    (0x0abe, 0x0ac5),
+    (0x0ac7, 0x0ac9),
+    (0x0acb, 0x0acd),
+    (0x0b01, 0x0b03),
+    (0x0b3c, 0x0b3c),
+    (0x0b3e, 0x0b44),
+    (0x0b47, 0x0b48),
+    (0x0b4b, 0x0b4d),
+    (0x0b82, 0x0b82),
+    (0x0bbe, 0x0bc2),
+    (0x0bc6, 0x0bc8),
+    (0x0bca, 0x0bcd),
+    (0x0bd7, 0x0bd7),
+    (0x0c00, 0x0c04),
+    (0x0c3e, 0x0c44),
+    (0x0c46, 0x0c48),
+    (0x0c4a, 0x0c4d),
+    (0x0c55, 0x0c56),
+    (0x0c81, 0x0c83),
+    (0x0cbc, 0x0cbc),
+    (0x0cbe, 0x0cc4),
+    (0x0cc6, 0x0cc8),
+    (0x0cca, 0x0ccd),
+    (0x0d00, 0x0d03),
+    (0x0d3e, 0x0d44),
+    (0x0d46, 0x0d48),
+    (0x0d4a, 0x0d4d),
+    (0x0d57, 0x0d57),
+    (0x0dca, 0x0dca),
+    (0x0dcf, 0x0dd4),
+    (0x0dd6, 0x0dd6),
+    (0x0dd8, 0x0ddf),
+    (0x0e31, 0x0e31),
+    (0x0e34, 0x0e3a),
+    (0x0e47, 0x0e4e),
+    (0x0eb1, 0x0eb1),
+    (0x0eb4, 0x0ebc),
+    (0x0ec8, 0x0ecd),
+    (0x0f18, 0x0f19),
+    (0x0f35, 0x0f35),
+    (0x0f37, 0x0f37),
+    (0x0f39, 0x0f39),
+    (0x0f3e, 0x0f3f),
+    (0x0f71, 0x0f84),
+    (0x0f86, 0x0f87),
+    (0x0f8d, 0x0fbc),
+    (0x0fc6, 0x0fc6),
+    (0x102b, 0x103e),
+    (0x1056, 0x1059),
+    (0x135d, 0x135f),
+    (0x1712, 0x1714),
+    (0x17b4, 0x17d3),
+    (0x17dd, 0x17dd),
+    (0x180b, 0x180d),
+    (0x1ab0, 0x1aff),
+    (0x1b00, 0x1b04),
+    (0x1b34, 0x1b44),
+    (0x1dc0, 0x1dff),
+    (0x20d0, 0x20f0),
+    (0x2cef, 0x2cf1),
+    (0x2de0, 0x2dff),
+    (0x302a, 0x302f),
+    (0x3099, 0x309a),
+    (0xa66f, 0xa672),
+    (0xa674, 0xa67d),
+    (0xa69e, 0xa69f),
+    (0xa8e0, 0xa8f1),
+    (0xfb1e, 0xfb1e),
+    (0xfe00, 0xfe0f),
+    (0xfe20, 0xfe2f),
+    (0x1d165, 0x1d169),
+    (0x1d16d, 0x1d172),
+    (0x1d17b, 0x1d182),
+    (0x1d185, 0x1d18b),
+    (0x1d1aa, 0x1d1ad),
+    (0xe0100, 0xe01ef),
+];
+
+/// Pictographs that emoji sequences are made of, as sorted ranges.
+const PICTOGRAPHS: &[(u32, u32)] = &[
+    (0x00a9, 0x00a9),
+    (0x00ae, 0x00ae),
+    (0x203c, 0x203c),
+    (0x2049, 0x2049),
+    (0x2122, 0x2122),
+    (0x2139, 0x2139),
+    (0x2194, 0x21aa),
+    (0x2300, 0x23ff),
+    (0x24c2, 0x24c2),
+    (0x25aa, 0x25fe),
+    (0x2600, 0x27bf),
+    (0x2934, 0x2935),
+    (0x2b05, 0x2b55),
+    (0x3030, 0x3030),
+    (0x303d, 0x303d),
+    (0x3297, 0x3297),
+    (0x3299, 0x3299),
+    (0x1f000, 0x1f0ff),
+    (0x1f10d, 0x1f1ad),
+    (0x1f201, 0x1f3fa),
+    (0x1f400, 0x1faff),
+];
+
+fn within(ranges: &[(u32, u32)], c: char) -> bool {
+    let c = u32::from(c);
+    let i = ranges.partition_point(|&(_, end)| end < c);
+    ranges.get(i).is_some_and(|&(start, _)| start <= c)
+}
+
+/// A combining mark, which goes with the char before it, or a variation
+/// selector.
+pub fn is_mark(c: char) -> bool {
+    within(MARKS, c)
+}
+
+/// Punctuation joining words, such as `_` and `‿`.
+pub fn is_connector_punctuation(c: char) -> bool {
+    matches!(
+        c,
+        '_' | '\u{203f}' | '\u{2040}' | '\u{2054}' | '\u{fe33}' | '\u{fe34}' | '\u{fe4d}'
+            ..='\u{fe4f}' | '\u{ff3f}'
+    )
+}
+
+/// A char identifiers may start with, `XID_Start`: letters, including
+/// letter numbers such as `Ⅻ`.
+pub fn is_xid_start(c: char) -> bool {
+    within(tables::XID_START, c)
+}
+
+/// A char identifiers may go on with, `XID_Continue`: those they may start
+/// with, digits, combining marks and connector punctuation, and a few
+/// others such as `·`.
+pub fn is_xid_continue(c: char) -> bool {
+    within(tables::XID_CONTINUE, c)
+}
+
+fn is_regional_indicator(c: char) -> bool {
+    ('\u{1f1e6}'..='\u{1f1ff}').contains(&c)
+}
+
+/// Chars that extend the cluster they follow: marks, emoji skin tones and
+/// the tags of subdivision flags.
+fn is_extend(c: char) -> bool {
+    is_mark(c)
+        || ('\u{1f3fb}'..='\u{1f3ff}').contains(&c)
+        || ('\u{e0020}'..='\u{e007f}').contains(&c)
+}
+
+/// Hangul jamo vowels and trailing consonants, which extend the syllable
+/// before them.
+fn is_jamo_extend(c: char) -> bool {
+    matches!(c, '\u{1160}'..='\u{11ff}' | '\u{d7b0}'..='\u{d7ff}')
+}
+
+fn is_hangul(c: char) -> bool {
+    matches!(c, '\u{1100}'..='\u{11ff}' | '\u{ac00}'..='\u{d7a3}' | '\u{a960}'..='\u{a97f}')
+}
+
+/// The length in bytes of the grapheme cluster `text` starts with, and
+/// whether it may go on past the end of `text`.
+fn cluster(text: &str) -> (usize, bool) {
+    let mut chars = text.char_indices().peekable();
+    let Some((_, first)) = chars.next() else {
+        return (0, true);
+    };
+    match first {
+        '\r' if text[1..].starts_with('\n') => return (2, false),
+        '\r' => return (1, text.len() == 1),
+        c if c.is_control() => return (c.len_utf8(), false),
+        _ => {}
+    }
+    if is_regional_indicator(first) {
+        // Flags are pairs of indicators.
+        chars.next_if(|&(_, c)| is_regional_indicator(c));
+    }
+    let mut previous = first;
+    while let Some(&(_, c)) = chars.peek() {
+        let joined = previous == '\u{200d}' && within(PICTOGRAPHS, c);
+        let jamo = is_hangul(previous) && is_jamo_extend(c);
+        if !(is_extend(c) || c == '\u{200d}' || joined || jamo) {
+            break;
+        }
+        previous = c;
+        chars.next();
+    }
+    chars
+        .peek()
+        .map_or((text.len(), true), |&(i, _)| (i, false))
+}
+
+/// A grapheme cluster, what a reader takes for one char: a char and the
+/// marks following it, an emoji sequence, a flag or `\r\n`.
+pub fn any_grapheme<'a>() -> Parser<'a, String> {
+    counted(Parser {
+        run: Ptr::new(|input| {
+            let text = input.text();
+            match cluster(text) {
+                (0, _) if input.partial => (input, Err(Failure::incomplete(Needed::Size(1)))),
+                (0, _) => {
+                    let desc = "expected a grapheme, found the end of the input".to_string();
+                    (
+                        input,
+                        Err(Failure::expecting("a grapheme".to_string(), desc)),
+                    )
+                }
+                (_, true) if input.partial => (input, Err(Failure::incomplete(Needed::Unknown))),
+                (n, _) => (input_sub(n, &input), Ok(text[..n].to_string())),
+            }
+        }),
+        shape: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ident::Identifier;
+    use crate::{run, run_streaming, StreamResult};
+    use alloc::vec::Vec;
+
+    #[test]
+    fn grapheme_test() {
+        let clusters = |text: &str| run(any_grapheme().many(), text.to_string()).unwrap();
+        // a family, joined with ZWJs, and a flag of a subdivision with tags
+        let family = "👨\u{200d}👩\u{200d}👧";
+        let wales = "🏴\u{e0067}\u{e0062}\u{e0077}\u{e006c}\u{e0073}\u{e007f}";
+        let text = [
+            "a",
+            "ñ",
+            "\u{915}\u{94d}",
+            family,
+            wales,
+            "\u{1100}\u{1161}",
+            "\n",
+        ];
+        assert_eq!(clusters(&text.concat()), text);
+        // a third indicator starts the next flag
+        assert_eq!(clusters("🇫🇷🇮"), ["🇫🇷", "🇮"]);
+        assert_eq!(clusters("\r\r\n"), ["\r", "\r\n"]);
+        assert!(run(any_grapheme(), "".to_string()).is_err());
+
+        let parsed = run_streaming(any_grapheme(), "e".to_string());
+        assert!(matches!(parsed, StreamResult::Incomplete(_)));
+        let parsed = run_streaming(any_grapheme(), "e\u{301}x".to_string());
+        assert!(matches!(parsed, StreamResult::Ok(ref e, 3) if e == "e\u{301}"));
+    }
+
+    #[test]
+    fn xid_test() {
+        let starts: Vec<_> = "aÉλ字Ⅻ_1\u{301}·".chars().map(is_xid_start).collect();
+        let expected = [true, true, true, true, true, false, false, false, false];
+        assert_eq!(starts, expected);
+        assert!("a1_\u{301}\u{94d}‿".chars().all(is_xid_continue));
+        assert!(!"-€ ".chars().any(is_xid_continue));
+        // by the tables rather than the general categories
+        assert!(is_xid_continue('·') && !is_xid_start('·'));
+        assert!(!is_xid_continue('½') && is_xid_continue('\u{1d7ce}'));
+        // a letter, but pattern syntax
+        assert!('\u{2e2f}'.is_alphabetic() && !is_xid_start('\u{2e2f}'));
+
+        let ident = Identifier::new().xid().build();
+        let parsed = run(ident, "cafe\u{301}_2 = 1".to_string());
+        assert_eq!(parsed, Ok("cafe\u{301}_2".to_string()));
+    }
+}
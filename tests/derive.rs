@@ -0,0 +1,73 @@
+use parcoom::{run, Location, Parsable, ParserError};
+use parcoom_derive::Parse;
+
+#[derive(Debug, PartialEq, Parse)]
+#[parcoom(suffix = ";", padded)]
+struct Entry {
+    key: String,
+    #[parcoom(prefix = "=", padded)]
+    value: Value,
+}
+
+#[derive(Debug, PartialEq, Parse)]
+enum Value {
+    #[parcoom(prefix = "true")]
+    True,
+    False,
+    Number(i64),
+    #[parcoom(prefix = "[", suffix = "]")]
+    List(#[parcoom(sep = ",", padded)] Vec<Value>),
+    #[parcoom(prefix = "-")]
+    Neg(Box<Value>),
+}
+
+#[derive(Debug, PartialEq, Parse)]
+struct Config {
+    entries: Vec<Entry>,
+}
+
+#[test]
+fn derive_test() {
+    let parsed = run(
+        Config::parser(),
+        "a = 1; b=[true , False,[ ]];\nc = -true;".to_string(),
+    );
+    let entry = |key: &str, value| Entry {
+        key: key.to_string(),
+        value,
+    };
+    assert_eq!(
+        parsed,
+        Ok(Config {
+            entries: vec![
+                entry("a", Value::Number(1)),
+                entry(
+                    "b",
+                    Value::List(vec![Value::True, Value::False, Value::List(vec![])])
+                ),
+                entry("c", Value::Neg(Box::new(Value::True))),
+            ],
+        })
+    );
+}
+
+#[test]
+fn derive_error_test() {
+    let parsed = run(Entry::parser(), "a = 1".to_string());
+    assert_eq!(
+        parsed,
+        Err(ParserError {
+            desc: "expected ;".to_string(),
+            location: Location {
+                offset: 5,
+                char_offset: 5,
+                line: 1,
+                column: 6,
+            },
+            context: vec![],
+            expected: vec!["\";\"".to_string()],
+            cause: None,
+            at_end: true,
+        })
+    );
+}